@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+
+/// How the tiling layout of a workspace places its windows.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TilingStrategy {
+    /// Windows split the focused window, the tree is shaped manually
+    #[default]
+    Split,
+    /// A column of master windows next to a column of the remaining windows
+    MasterStack,
+    /// Every window splits the remaining space in half, alternating the direction
+    Dwindle,
+    /// Like `Dwindle`, but the splits wind inwards around the center
+    Spiral,
+    /// Rows of equally sized windows
+    Grid,
+}
+
+impl TilingStrategy {
+    /// The strategy following this one, wrapping around
+    pub fn next(self) -> TilingStrategy {
+        match self {
+            TilingStrategy::Split => TilingStrategy::MasterStack,
+            TilingStrategy::MasterStack => TilingStrategy::Dwindle,
+            TilingStrategy::Dwindle => TilingStrategy::Spiral,
+            TilingStrategy::Spiral => TilingStrategy::Grid,
+            TilingStrategy::Grid => TilingStrategy::Split,
+        }
+    }
+}
+
+/// Behaviors provided by cosmic-comp, which are not part of the
+/// shortcuts managed by cosmic-settings.
+///
+/// They are bound under the `compositor_shortcuts` key, with key bindings
+/// in the format of the cosmic-settings shortcuts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompAction {
+    /// Switches the active workspace to the next tiling strategy
+    CycleTilingStrategy,
+    SetTilingStrategy(TilingStrategy),
+    /// Switches the active workspace between the tiling tree and scrollable columns
+    ToggleScrolling,
+    GrowMaster,
+    ShrinkMaster,
+    IncreaseMasterCount,
+    DecreaseMasterCount,
+    /// Saves the tiling tree of the active workspace as the named layout preset
+    SaveLayoutPreset(String),
+    /// Rearranges the tiled windows of the active workspace after the named layout preset
+    ApplyLayoutPreset(String),
+    /// Hides the focused window in the scratchpad
+    SendToScratchpad,
+    /// Shows or hides the scratchpad window with the given app_id
+    ToggleScratchpad(String),
+    /// Replaces the shown scratchpad window with the next one
+    CycleScratchpad,
+}
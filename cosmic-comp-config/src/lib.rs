@@ -6,9 +6,12 @@ use std::collections::HashMap;
 
 use crate::input::TouchpadOverride;
 
+pub mod actions;
 pub mod input;
 #[cfg(feature = "output")]
 pub mod output;
+pub mod presets;
+pub mod window_rules;
 pub mod workspace;
 
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -102,6 +105,18 @@ pub struct CosmicCompConfig {
     /// Hide the cursor after this many seconds of pointer inactivity (None disables)
     pub cursor_hide_timeout: Option<u32>,
     pub activation_policy: ActivationPolicy,
+    /// Rules applied to windows when they are mapped
+    pub window_rules: Vec<window_rules::WindowRule>,
+    /// Tiling arrangements saved by name, to be applied to workspaces
+    pub layout_presets: Vec<presets::LayoutPreset>,
+    /// App-ids of terminals, whose tiles are taken over by windows of apps launched from them
+    pub swallow_terminals: Vec<String>,
+    /// How to respond to clients ringing the system bell
+    pub system_bell: BellConfig,
+    /// Scheduled warmer color temperature of all outputs
+    pub night_light: NightLightConfig,
+    /// Scaling and source of outputs mirroring another one, by connector name
+    pub mirrors: HashMap<String, MirrorConfig>,
 }
 
 impl Default for CosmicCompConfig {
@@ -140,6 +155,12 @@ impl Default for CosmicCompConfig {
             appearance_settings: AppearanceConfig::default(),
             cursor_hide_timeout: None,
             activation_policy: ActivationPolicy::default(),
+            window_rules: Vec::new(),
+            layout_presets: Vec::new(),
+            swallow_terminals: Vec::new(),
+            system_bell: BellConfig::default(),
+            night_light: NightLightConfig::default(),
+            mirrors: HashMap::new(),
         }
     }
 }
@@ -214,6 +235,99 @@ pub enum ZoomMovement {
     Continuously,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BellConfig {
+    /// Emit a D-Bus signal, for a sound to be played
+    pub audible: bool,
+    /// Flash the ringing window or its output
+    pub visual: VisualBell,
+    /// Mark the ringing window and its workspace as urgent, unless it is focused
+    pub urgent: bool,
+}
+
+impl Default for BellConfig {
+    fn default() -> Self {
+        BellConfig {
+            audible: true,
+            visual: VisualBell::None,
+            urgent: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum VisualBell {
+    #[default]
+    None,
+    Window,
+    Output,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NightLightConfig {
+    pub enabled: bool,
+    /// Color temperature in Kelvin during the night
+    pub temperature: u32,
+    /// Local time as (hour, minute), when the night starts
+    pub start: (u8, u8),
+    /// Local time as (hour, minute), when the night ends
+    pub end: (u8, u8),
+    /// Minutes to fade in after the start and out before the end
+    pub transition: u32,
+}
+
+impl Default for NightLightConfig {
+    fn default() -> Self {
+        NightLightConfig {
+            enabled: false,
+            temperature: 4000,
+            start: (20, 0),
+            end: (7, 0),
+            transition: 30,
+        }
+    }
+}
+
+/// How the content of a mirrored output is fit onto a mirror of a different size
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorScaling {
+    /// Keep the aspect ratio and fill the remaining space with black bars
+    #[default]
+    Letterbox,
+    /// Fill the mirror, distorting the aspect ratio
+    Stretch,
+    /// Keep the aspect ratio and cut off what doesn't fit
+    Crop,
+}
+
+/// What part of the mirrored output is shown
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorSource {
+    /// The whole output
+    #[default]
+    Output,
+    /// A region in logical coordinates, relative to the mirrored output
+    Region {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    /// The first window with the given app id on the active workspace of the mirrored output
+    Window { app_id: String },
+}
+
+/// Settings of an output, that is mirroring another one
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MirrorConfig {
+    #[serde(default)]
+    pub scaling: MirrorScaling,
+    #[serde(default)]
+    pub source: MirrorSource,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub struct XwaylandEavesdropping {
     pub keyboard: EavesdroppingKeyboardMode,
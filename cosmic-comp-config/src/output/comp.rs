@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fs::OpenOptions, path::Path};
 use tracing::{error, warn};
 
+use crate::MirrorConfig;
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum OutputState {
@@ -26,6 +28,10 @@ pub enum AdaptiveSync {
     #[serde(rename = "false")]
     Disabled,
     Force,
+    /// Enabled while a fullscreen surface marked as game or video is shown
+    ///
+    /// Output management protocols can't set this and report it as automatic, like `Enabled`.
+    Automatic,
 }
 
 fn default_sync() -> AdaptiveSync {
@@ -35,6 +41,155 @@ fn default_sync() -> AdaptiveSync {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OutputsConfig {
     pub config: HashMap<Vec<OutputInfo>, Vec<OutputConfig>>,
+    /// Layouts for combinations of outputs without a config of their own,
+    /// the first matching profile is used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<OutputProfile>,
+}
+
+impl OutputsConfig {
+    /// The first profile matching all of `outputs`, given with their serial numbers,
+    /// and the profile output assigned to each of them, in the same order.
+    pub fn match_profile(
+        &self,
+        outputs: &[(OutputInfo, String)],
+    ) -> Option<(&OutputProfile, Vec<ProfileOutput>)> {
+        self.profiles.iter().find_map(|profile| {
+            let assignment = profile.assign(outputs)?;
+            let mut assigned = assignment
+                .iter()
+                .map(|idx| profile.outputs[*idx].clone())
+                .collect::<Vec<_>>();
+
+            // connectors differ between docks, so mirroring targets are patterns
+            for (idx, output) in assigned.iter_mut().enumerate() {
+                if let OutputState::Mirroring(pattern) = output.config.enabled.clone() {
+                    output.config.enabled =
+                        match outputs.iter().enumerate().find(|(i, (info, _))| {
+                            *i != idx && glob_match(&pattern, &info.connector)
+                        }) {
+                            Some((_, (info, _))) => OutputState::Mirroring(info.connector.clone()),
+                            None => {
+                                warn!(
+                                    profile = %profile.name,
+                                    "Invalid Mirroring tag, overriding with `Enabled` instead"
+                                );
+                                OutputState::Enabled
+                            }
+                        };
+                }
+            }
+
+            Some((profile, assigned))
+        })
+    }
+}
+
+/// Layout of a set of outputs, identified by patterns instead of their connectors
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OutputProfile {
+    pub name: String,
+    /// Matches only if every connected output matches exactly one of these
+    pub outputs: Vec<ProfileOutput>,
+}
+
+impl OutputProfile {
+    /// Index of the profile output for each of `outputs`, if all of them can be matched.
+    fn assign(&self, outputs: &[(OutputInfo, String)]) -> Option<Vec<usize>> {
+        fn assign_from(
+            profile: &OutputProfile,
+            outputs: &[(OutputInfo, String)],
+            assignment: &mut Vec<usize>,
+        ) -> bool {
+            let Some((info, serial)) = outputs.get(assignment.len()) else {
+                return true;
+            };
+            for (idx, output) in profile.outputs.iter().enumerate() {
+                if !assignment.contains(&idx) && output.matcher.matches(info, serial) {
+                    assignment.push(idx);
+                    if assign_from(profile, outputs, assignment) {
+                        return true;
+                    }
+                    assignment.pop();
+                }
+            }
+            false
+        }
+
+        if outputs.len() != self.outputs.len() {
+            return None;
+        }
+        let mut assignment = Vec::with_capacity(outputs.len());
+        assign_from(self, outputs, &mut assignment).then_some(assignment)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ProfileOutput {
+    #[serde(rename = "match")]
+    pub matcher: OutputMatcher,
+    /// `Mirroring` takes a pattern of the connector to mirror
+    pub config: OutputConfig,
+    /// Make this the active output of all seats, when the profile is applied
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// Patterns matched against the properties of an output, unset ones match anything.
+///
+/// `*` matches any sequence of characters, `?` matches a single one.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct OutputMatcher {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connector: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub make: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+}
+
+impl OutputMatcher {
+    pub fn matches(&self, info: &OutputInfo, serial: &str) -> bool {
+        [
+            (&self.connector, info.connector.as_str()),
+            (&self.make, info.make.as_str()),
+            (&self.model, info.model.as_str()),
+            (&self.serial, serial),
+        ]
+        .into_iter()
+        .all(|(pattern, value)| pattern.as_ref().is_none_or(|p| glob_match(p, value)))
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // position after the last `*` and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -51,6 +206,15 @@ pub struct OutputConfig {
     pub max_bpc: Option<u32>,
     #[serde(default)]
     pub xwayland_primary: bool,
+    /// Honor requests of fullscreen surfaces to be presented without vsync
+    #[serde(default)]
+    pub allow_tearing: bool,
+    /// Drive the output with HDR10 (PQ, BT.2020), if its EDID supports it
+    #[serde(default)]
+    pub hdr: bool,
+    /// Used if `enabled` is [`OutputState::Mirroring`], set from `CosmicCompConfig::mirrors`
+    #[serde(skip)]
+    pub mirror: MirrorConfig,
 }
 
 impl Default for OutputConfig {
@@ -64,6 +228,9 @@ impl Default for OutputConfig {
             enabled: OutputState::Enabled,
             max_bpc: None,
             xwayland_primary: false,
+            allow_tearing: false,
+            hdr: false,
+            mirror: MirrorConfig::default(),
         }
     }
 }
@@ -121,6 +288,7 @@ pub fn load_outputs(path: Option<impl AsRef<Path>>) -> OutputsConfig {
 
     OutputsConfig {
         config: HashMap::new(),
+        profiles: Vec::new(),
     }
 }
 
@@ -135,3 +303,83 @@ pub enum TransformDef {
     Flipped180,
     Flipped270,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn output(connector: &str, model: &str, serial: &str) -> (OutputInfo, String) {
+        let info = OutputInfo {
+            connector: connector.into(),
+            make: "Dell Inc.".into(),
+            model: model.into(),
+        };
+        (info, serial.into())
+    }
+
+    fn profile(models: &[&str]) -> OutputProfile {
+        OutputProfile {
+            name: "desk".into(),
+            outputs: models
+                .iter()
+                .map(|model| ProfileOutput {
+                    matcher: OutputMatcher {
+                        model: Some(model.to_string()),
+                        ..Default::default()
+                    },
+                    config: OutputConfig::default(),
+                    primary: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("DP-1", "DP-1"));
+        assert!(!glob_match("DP-1", "DP-10"));
+        assert!(glob_match("DP-?", "DP-2"));
+        assert!(!glob_match("DP-?", "DP-10"));
+        assert!(glob_match("HDMI-*", "HDMI-A-1"));
+        assert!(glob_match("*-1", "HDMI-A-1"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "DP-1"));
+        assert!(!glob_match("dell*", "Dell Inc."));
+    }
+
+    #[test]
+    fn assign_backtracks_to_specific_matchers() {
+        let profile = profile(&["*", "U2720Q"]);
+        assert_eq!(
+            profile.assign(&[output("DP-1", "U2720Q", ""), output("DP-2", "P2419H", "")]),
+            Some(vec![1, 0])
+        );
+        assert_eq!(
+            profile.assign(&[output("DP-1", "P2419H", ""), output("DP-2", "U2720Q", "")]),
+            Some(vec![0, 1])
+        );
+    }
+
+    #[test]
+    fn assign_matches_every_output_once() {
+        let twins = profile(&["U2720Q", "U2720Q"]);
+        let outputs = [output("DP-1", "U2720Q", ""), output("DP-2", "U2720Q", "")];
+        assert_eq!(twins.assign(&outputs), Some(vec![0, 1]));
+        assert_eq!(twins.assign(&outputs[..1]), None);
+        assert_eq!(
+            twins.assign(&[output("DP-1", "U2720Q", ""), output("DP-2", "P2419H", "")]),
+            None
+        );
+
+        let mut by_serial = profile(&["*"]);
+        by_serial.outputs[0].matcher.serial = Some("ABC*".into());
+        assert_eq!(
+            by_serial.assign(&[output("DP-1", "U2720Q", "ABC123")]),
+            Some(vec![0])
+        );
+        assert_eq!(by_serial.assign(&[output("DP-1", "U2720Q", "XYZ")]), None);
+    }
+}
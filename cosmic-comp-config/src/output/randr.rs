@@ -66,7 +66,8 @@ impl From<CompList> for cosmic_randr_shell::List {
                 modes,
                 current: Some(current),
                 adaptive_sync: Some(match output.vrr {
-                    crate::output::comp::AdaptiveSync::Enabled => AdaptiveSyncState::Auto,
+                    crate::output::comp::AdaptiveSync::Enabled
+                    | crate::output::comp::AdaptiveSync::Automatic => AdaptiveSyncState::Auto,
                     crate::output::comp::AdaptiveSync::Disabled => AdaptiveSyncState::Disabled,
                     crate::output::comp::AdaptiveSync::Force => AdaptiveSyncState::Always,
                 }),
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+
+/// A named arrangement of the tiling tree of a workspace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutPreset {
+    pub name: String,
+    pub root: PresetNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PresetNode {
+    Split {
+        orientation: SplitOrientation,
+        /// Share of every child of the length of the split
+        ratios: Vec<f64>,
+        children: Vec<PresetNode>,
+    },
+    /// Space for one window or stack, reserved for windows with the given app_id
+    Slot { app_id: Option<String> },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitOrientation {
+    /// Children are placed on top of each other
+    Horizontal,
+    /// Children are placed next to each other
+    Vertical,
+}
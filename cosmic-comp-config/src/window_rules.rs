@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+
+/// A rule applied to matching windows, when they are mapped.
+///
+/// All rules matching a window are applied in order,
+/// so later rules override properties set by earlier ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowRule {
+    #[serde(rename = "match")]
+    pub matches: WindowMatch,
+    /// Name of the output to open the window on
+    pub output: Option<String>,
+    /// Index of the workspace (starting at 0) to open the window on
+    pub workspace: Option<usize>,
+    /// Force the window into the floating (`true`) or tiling (`false`) layer
+    pub floating: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub maximized: Option<bool>,
+    pub sticky: Option<bool>,
+    pub minimized: Option<bool>,
+    /// Initial size of the window, if floating
+    pub size: Option<(i32, i32)>,
+    /// Initial position of the window relative to its output, if floating
+    pub position: Option<(i32, i32)>,
+    /// Regex of an app_id, the window joins the first stack
+    /// containing a matching window on its workspace
+    pub join_stack: Option<String>,
+    /// Disables server-side decorations and asks the client to not draw any
+    pub decorations: Option<bool>,
+    pub shadow: Option<bool>,
+}
+
+/// Conditions a window has to fulfill for a rule to apply.
+///
+/// Unset fields match any window.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowMatch {
+    /// Regex matched against the app_id
+    pub app_id: Option<String>,
+    /// Regex matched against the title
+    pub title: Option<String>,
+    /// Regex matched against the X11 class, never matches wayland clients
+    pub x11_class: Option<String>,
+    /// Whether the window belongs to an XWayland client
+    pub xwayland: Option<bool>,
+}
@@ -0,0 +1,903 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    config::ScreenFilter,
+    shell::{CosmicMapped, Devices, SeatExt},
+    state::{BackendData, Common},
+    utils::prelude::*,
+    wayland::handlers::image_copy_capture::FrameHolder,
+};
+use anyhow::{Context, Result, anyhow};
+use calloop::{
+    channel::{self, Channel, Sender},
+    timer::{TimeoutAction, Timer},
+};
+use cosmic_comp_config::output::comp::OutputConfig;
+use smithay::{
+    backend::{
+        allocator::{Fourcc, format::get_transparent},
+        input::{
+            AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+            DeviceCapability, Event, InputBackend, InputEvent, KeyState, KeyboardKeyEvent,
+            PointerAxisEvent, PointerButtonEvent, UnusedEvent,
+        },
+        renderer::{
+            Bind, BufferType, ExportMem, Offscreen, buffer_dimensions, buffer_type,
+            damage::{OutputDamageTracker, RenderOutputResult},
+            element::{
+                Kind,
+                surface::{WaylandSurfaceRenderElement, render_elements_from_surface_tree},
+            },
+            glow::GlowRenderer,
+            pixman::{PixmanRenderer, PixmanTarget},
+        },
+    },
+    desktop::{PopupManager, layer_map_for_output, space::SpaceElement},
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+    reexports::{
+        calloop::{EventLoop, LoopHandle, RegistrationToken, ping},
+        pixman::Image,
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_server::{
+            DisplayHandle,
+            protocol::{wl_buffer::WlBuffer, wl_surface::WlSurface},
+        },
+    },
+    utils::{Buffer as BufferCoords, Logical, Physical, Point, Rectangle, Size, Transform},
+    wayland::{
+        image_copy_capture::CaptureFailureReason,
+        presentation::Refresh,
+        seat::WaylandFocus,
+        shell::wlr_layer::Layer,
+        shm::{shm_format_to_fourcc, with_buffer_contents_mut},
+    },
+};
+use std::{cell::RefCell, fmt, path::PathBuf, time::Duration};
+use tracing::{error, info, warn};
+use xkbcommon::xkb::Keycode;
+
+use super::render::CLEAR_COLOR;
+
+/// Size, scale and refresh rate (in mHz) of a virtual output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputSpec {
+    pub size: Size<i32, Physical>,
+    pub scale: f64,
+    pub refresh: i32,
+}
+
+impl Default for OutputSpec {
+    fn default() -> Self {
+        OutputSpec {
+            size: (1920, 1080).into(),
+            scale: 1.0,
+            refresh: 60_000,
+        }
+    }
+}
+
+impl OutputSpec {
+    /// Parses `<width>x<height>[@<scale>]`, e.g. `2560x1440@1.5`
+    pub fn parse(spec: &str) -> Option<OutputSpec> {
+        let spec = spec.trim();
+        let (size, scale) = match spec.split_once('@') {
+            Some((size, scale)) => (size, scale.parse::<f64>().ok()?),
+            None => (spec, 1.0),
+        };
+        let (w, h) = size.split_once('x')?;
+        let (w, h) = (w.parse::<i32>().ok()?, h.parse::<i32>().ok()?);
+        if w <= 0 || h <= 0 || scale <= 0.0 {
+            return None;
+        }
+
+        Some(OutputSpec {
+            size: (w, h).into(),
+            scale,
+            ..Default::default()
+        })
+    }
+
+    /// Creates an output of this size, scale and refresh rate named `name`.
+    pub fn create_output(&self, name: String) -> Output {
+        let props = PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: Subpixel::Unknown,
+            make: "COSMIC".to_string(),
+            model: name.clone(),
+            serial_number: "Unknown".to_string(),
+        };
+        let mode = Mode {
+            size: self.size,
+            refresh: self.refresh,
+        };
+        let output = Output::new(name, props);
+        output.add_mode(mode);
+        output.set_preferred(mode);
+        output.change_current_state(
+            Some(mode),
+            Some(Transform::Normal),
+            Some(Scale::Fractional(self.scale)),
+            Some((0, 0).into()),
+        );
+        output.user_data().insert_if_missing(|| {
+            RefCell::new(OutputConfig {
+                mode: ((self.size.w, self.size.h), Some(self.refresh as u32)),
+                scale: self.scale,
+                ..Default::default()
+            })
+        });
+        output
+    }
+
+    fn from_env() -> Vec<OutputSpec> {
+        let Ok(value) = std::env::var("COSMIC_HEADLESS_OUTPUTS") else {
+            return vec![OutputSpec::default()];
+        };
+
+        let specs = value
+            .split(',')
+            .filter_map(|spec| {
+                let parsed = OutputSpec::parse(spec);
+                if parsed.is_none() {
+                    warn!("Failed to parse headless output {:?}. Skipping", spec);
+                }
+                parsed
+            })
+            .collect::<Vec<_>>();
+        if specs.is_empty() {
+            vec![OutputSpec::default()]
+        } else {
+            specs
+        }
+    }
+}
+
+/// Input events that can be injected into the headless backend
+#[derive(Debug, Clone)]
+pub enum HeadlessInputEvent {
+    Key {
+        keycode: Keycode,
+        state: KeyState,
+    },
+    /// Absolute pointer motion in logical coordinates relative to the named output
+    PointerMotion {
+        output: String,
+        position: Point<f64, Logical>,
+    },
+    PointerButton {
+        button: u32,
+        state: ButtonState,
+    },
+    PointerAxis {
+        horizontal: f64,
+        vertical: f64,
+    },
+}
+
+pub struct HeadlessState {
+    renderer: PixmanRenderer,
+    // Mesa llvmpipe renderer used for screen capture, if available
+    pub software_renderer: Option<GlowRenderer>,
+    surfaces: Vec<Surface>,
+    input: Sender<HeadlessInputEvent>,
+}
+
+struct Surface {
+    output: Output,
+    buffer: Image<'static, 'static>,
+    damage_tracker: OutputDamageTracker,
+    render: ping::Ping,
+    token: RegistrationToken,
+    rendered: bool,
+    dirty: bool,
+    pending: bool,
+}
+
+impl fmt::Debug for HeadlessState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeadlessState")
+            .field("renderer", &self.renderer)
+            .field("software_renderer", &self.software_renderer)
+            .field(
+                "outputs",
+                &self.surfaces.iter().map(|s| &s.output).collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl HeadlessState {
+    /// Sender to inject synthetic input into the compositor
+    pub fn input_sender(&self) -> Sender<HeadlessInputEvent> {
+        self.input.clone()
+    }
+
+    pub fn add_output(
+        &mut self,
+        spec: OutputSpec,
+        handle: &LoopHandle<'static, State>,
+    ) -> Result<Output> {
+        let name = (1..)
+            .map(|idx| format!("HEADLESS-{idx}"))
+            .find(|name| !self.surfaces.iter().any(|s| s.output.name() == *name))
+            .unwrap();
+        let output = spec.create_output(name);
+
+        let buffer: Image<'static, 'static> = self
+            .renderer
+            .create_buffer(
+                Fourcc::Xrgb8888,
+                Size::<i32, BufferCoords>::from((spec.size.w, spec.size.h)),
+            )
+            .with_context(|| "Failed to allocate output buffer")?;
+
+        let output_ref = output.clone();
+        let (ping, source) =
+            ping::make_ping().with_context(|| "Failed to create output event loop source")?;
+        let token = handle
+            .insert_source(source, move |_, _, state| {
+                let headless_state = state.backend.headless();
+                if let Some(surface) = headless_state
+                    .surfaces
+                    .iter_mut()
+                    .find(|s| s.output == output_ref)
+                {
+                    if let Err(err) =
+                        surface.render_output(&mut headless_state.renderer, &mut state.common)
+                    {
+                        error!(?err, "Error rendering.");
+                    }
+                    surface.dirty = false;
+                    surface.pending = true;
+
+                    // emulate a vblank
+                    let output = output_ref.clone();
+                    let refresh = Duration::from_secs_f64(1_000.0 / spec.refresh as f64);
+                    let timer = state.common.event_loop_handle.insert_source(
+                        Timer::from_duration(refresh),
+                        move |_, _, state| {
+                            if let Some(surface) = state
+                                .backend
+                                .headless()
+                                .surfaces
+                                .iter_mut()
+                                .find(|s| s.output == output)
+                            {
+                                if surface.dirty {
+                                    surface.render.ping();
+                                } else {
+                                    surface.pending = false;
+                                }
+                            }
+                            TimeoutAction::Drop
+                        },
+                    );
+                    if timer.is_err() {
+                        warn!("Failed to schedule vblank timer");
+                        surface.pending = false;
+                    }
+                }
+            })
+            .with_context(|| "Failed to add output to event loop")?;
+
+        self.surfaces.push(Surface {
+            output: output.clone(),
+            buffer,
+            damage_tracker: OutputDamageTracker::from_output(&output),
+            render: ping.clone(),
+            token,
+            rendered: false,
+            dirty: false,
+            pending: true,
+        });
+
+        // schedule first render
+        ping.ping();
+        Ok(output)
+    }
+
+    /// Removes an output added with [`HeadlessState::add_output`].
+    pub fn remove_output(
+        &mut self,
+        output: &Output,
+        handle: &LoopHandle<'static, State>,
+    ) -> Result<()> {
+        let idx = self
+            .surfaces
+            .iter()
+            .position(|s| s.output == *output)
+            .with_context(|| format!("{} is not a headless output", output.name()))?;
+        let surface = self.surfaces.remove(idx);
+        handle.remove(surface.token);
+        Ok(())
+    }
+
+    pub fn schedule_render(&mut self, output: &Output) {
+        if let Some(surface) = self.surfaces.iter_mut().find(|s| s.output == *output) {
+            surface.dirty = true;
+            if !surface.pending {
+                surface.render.ping();
+            }
+        }
+    }
+
+    pub fn all_outputs(&self) -> Vec<Output> {
+        self.surfaces.iter().map(|s| s.output.clone()).collect()
+    }
+
+    pub fn apply_config_for_outputs(&mut self, test_only: bool) -> Result<(), anyhow::Error> {
+        for surface in &mut self.surfaces {
+            let mode = surface.output.current_mode().unwrap();
+            let mut config = surface
+                .output
+                .user_data()
+                .get::<RefCell<OutputConfig>>()
+                .unwrap()
+                .borrow_mut();
+
+            // virtual outputs have a fixed size, scale and position may change
+            if config.mode.0 != (mode.size.w, mode.size.h) {
+                if !test_only {
+                    config.mode = ((mode.size.w, mode.size.h), Some(mode.refresh as u32));
+                }
+                return Err(anyhow!("Cannot change size of headless output"));
+            }
+            if !test_only {
+                surface.damage_tracker = OutputDamageTracker::from_output(&surface.output);
+                surface.rendered = false;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn update_screen_filter(&mut self, screen_filter: &ScreenFilter) -> Result<()> {
+        if !screen_filter.is_noop() {
+            warn!("Screen filters are not supported by the headless backend");
+        }
+        Ok(())
+    }
+}
+
+impl Surface {
+    fn render_output(&mut self, renderer: &mut PixmanRenderer, state: &mut Common) -> Result<()> {
+        let elements = output_elements(renderer, &state.shell.read(), &self.output);
+        let age = if self.rendered { 1 } else { 0 };
+        let mut fb = renderer
+            .bind(&mut self.buffer)
+            .with_context(|| "Failed to bind buffer")?;
+
+        match self
+            .damage_tracker
+            .render_output(renderer, &mut fb, age, &elements, CLEAR_COLOR)
+        {
+            Ok(RenderOutputResult { damage, states, .. }) => {
+                self.rendered = true;
+                for (_session, frame) in self.output.take_pending_frames() {
+                    if let Err(err) = copy_to_shm(renderer, &fb, &frame.buffer()) {
+                        warn!(?err, "Failed to copy output into capture buffer");
+                        frame.fail(CaptureFailureReason::BufferConstraints);
+                        continue;
+                    }
+                    frame.success(self.output.current_transform(), None, state.clock.now());
+                }
+                state.send_frames(&self.output, None);
+                state.update_primary_output(&self.output, &states);
+                if damage.is_some() {
+                    let mut output_presentation_feedback = state
+                        .shell
+                        .read()
+                        .take_presentation_feedback(&self.output, &states);
+                    output_presentation_feedback.presented(
+                        state.clock.now(),
+                        self.output
+                            .current_mode()
+                            .map(|mode| {
+                                Refresh::Fixed(Duration::from_secs_f64(
+                                    1_000.0 / mode.refresh as f64,
+                                ))
+                            })
+                            .unwrap_or(Refresh::Unknown),
+                        0,
+                        wp_presentation_feedback::Kind::empty(),
+                    );
+                }
+            }
+            Err(err) => {
+                self.rendered = false;
+                anyhow::bail!("Rendering failed: {:?}", err);
+            }
+        };
+
+        Ok(())
+    }
+}
+
+/// Copies the contents of a rendered output into the shm buffer of an image copy frame.
+///
+/// The pixman renderer has no dmabuf support, so that is the only kind of buffer
+/// output captures can use with the headless backend.
+fn copy_to_shm(
+    renderer: &mut PixmanRenderer,
+    fb: &PixmanTarget<'_>,
+    buffer: &WlBuffer,
+) -> Result<()> {
+    if !matches!(buffer_type(buffer), Some(BufferType::Shm)) {
+        anyhow::bail!("Only shm buffers can be used for headless captures");
+    }
+    let size = buffer_dimensions(buffer).with_context(|| "Failed to get buffer size")?;
+
+    with_buffer_contents_mut(buffer, |ptr, len, data| -> Result<()> {
+        let format = shm_format_to_fourcc(data.format)
+            .with_context(|| format!("Unsupported shm format {:?}", data.format))?;
+        let format = get_transparent(format).unwrap_or(format);
+        let mapping = renderer
+            .copy_framebuffer(fb, Rectangle::from_size(size), format)
+            .with_context(|| "Failed to copy framebuffer")?;
+        let pixels = renderer
+            .map_texture(&mapping)
+            .with_context(|| "Failed to map framebuffer copy")?;
+
+        let row = data.width as usize * 4;
+        for i in 0..data.height as usize {
+            let src = row * i;
+            let dst = data.offset as usize + data.stride as usize * i;
+            if src + row > pixels.len() || dst + row > len {
+                anyhow::bail!("Buffer too small for {}x{}", data.width, data.height);
+            }
+            unsafe {
+                std::ptr::copy_nonoverlapping(pixels.as_ptr().add(src), ptr.add(dst), row);
+            }
+        }
+        Ok(())
+    })
+    .with_context(|| "Failed to access shm buffer")?
+}
+
+/// Client surfaces and their popups visible on `output` in front-to-back order.
+///
+/// Server-side decorations, shadows and other effects rely on GL shaders
+/// and are not drawn by the pixman renderer.
+fn output_elements(
+    renderer: &mut PixmanRenderer,
+    shell: &Shell,
+    output: &Output,
+) -> Vec<WaylandSurfaceRenderElement<PixmanRenderer>> {
+    let scale = Scale::from(output.current_scale().fractional_scale());
+    let mut elements = Vec::new();
+    // `location` is the origin of the surface, `geometry` the offset of its window geometry
+    // popups are positioned relative to
+    let mut push_surface =
+        |surface: &WlSurface, location: Point<i32, Logical>, geometry: Point<i32, Logical>| {
+            let popups = PopupManager::popups_for_surface(surface).collect::<Vec<_>>();
+            for (popup, popup_offset) in popups.into_iter().rev() {
+                let popup_location = location + geometry + popup_offset - popup.geometry().loc;
+                elements.extend(render_elements_from_surface_tree(
+                    renderer,
+                    popup.wl_surface(),
+                    popup_location.to_f64().to_physical(scale).to_i32_round(),
+                    scale,
+                    1.0,
+                    Kind::Unspecified,
+                ));
+            }
+            elements.extend(render_elements_from_surface_tree(
+                renderer,
+                surface,
+                location.to_f64().to_physical(scale).to_i32_round(),
+                scale,
+                1.0,
+                Kind::Unspecified,
+            ));
+        };
+
+    if let Some(session_lock) = shell.session_lock.as_ref() {
+        if let Some(lock_surface) = session_lock.surfaces.get(output) {
+            push_surface(
+                lock_surface.wl_surface(),
+                Point::default(),
+                Point::default(),
+            );
+        }
+        return elements;
+    }
+
+    let layer_map = layer_map_for_output(output);
+    let mut push_layers =
+        |layer: Layer,
+         push: &mut dyn FnMut(&WlSurface, Point<i32, Logical>, Point<i32, Logical>)| {
+            for surface in layer_map.layers_on(layer).rev() {
+                if let Some(geometry) = layer_map.layer_geometry(surface) {
+                    push(surface.wl_surface(), geometry.loc, Point::default());
+                }
+            }
+        };
+    push_layers(Layer::Overlay, &mut push_surface);
+
+    if let Some(workspace) = shell.active_space(output) {
+        let fullscreen = workspace
+            .get_fullscreen_surfaces()
+            .find(|f| f.ended_at.is_none());
+        if let Some(fullscreen) = fullscreen {
+            let geometry = workspace.fullscreen_geometry_for(fullscreen);
+            if let Some(surface) = fullscreen.surface.wl_surface() {
+                push_surface(
+                    &surface,
+                    geometry.loc.as_logical() - fullscreen.surface.geometry().loc,
+                    fullscreen.surface.geometry().loc,
+                );
+            }
+            return elements;
+        }
+
+        push_layers(Layer::Top, &mut push_surface);
+
+        let mut push_mapped = |mapped: &CosmicMapped, location: Point<i32, Local>| {
+            let window = mapped.active_window();
+            if let Some(surface) = window.wl_surface() {
+                push_surface(
+                    &surface,
+                    location.as_logical() + mapped.active_window_offset() - window.geometry().loc,
+                    window.geometry().loc,
+                );
+            }
+        };
+        if let Some(sticky_layer) = shell
+            .workspaces
+            .sets
+            .get(output)
+            .map(|set| &set.sticky_layer)
+        {
+            for mapped in sticky_layer.mapped() {
+                if let Some(geometry) = sticky_layer.element_geometry(mapped) {
+                    push_mapped(mapped, geometry.loc);
+                }
+            }
+        }
+        for mapped in workspace.floating_layer.mapped() {
+            if let Some(geometry) = workspace.floating_layer.element_geometry(mapped) {
+                push_mapped(mapped, geometry.loc);
+            }
+        }
+        for (mapped, geometry) in workspace.tiling_layer.mapped() {
+            push_mapped(mapped, geometry.loc);
+        }
+    } else {
+        push_layers(Layer::Top, &mut push_surface);
+    }
+
+    push_layers(Layer::Bottom, &mut push_surface);
+    push_layers(Layer::Background, &mut push_surface);
+
+    elements
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeadlessVirtualDevice;
+
+impl Device for HeadlessVirtualDevice {
+    fn id(&self) -> String {
+        String::from("headless")
+    }
+
+    fn name(&self) -> String {
+        String::from("headless virtual device")
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(
+            capability,
+            DeviceCapability::Keyboard | DeviceCapability::Pointer
+        )
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct HeadlessInput;
+
+#[derive(Debug, Clone)]
+pub struct HeadlessKeyboardEvent {
+    time: u64,
+    keycode: Keycode,
+    state: KeyState,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeadlessMotionAbsoluteEvent {
+    time: u64,
+    position: Point<f64, Logical>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeadlessButtonEvent {
+    time: u64,
+    button: u32,
+    state: ButtonState,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeadlessAxisEvent {
+    time: u64,
+    horizontal: f64,
+    vertical: f64,
+}
+
+macro_rules! headless_event {
+    ($event:ty) => {
+        impl Event<HeadlessInput> for $event {
+            fn time(&self) -> u64 {
+                self.time
+            }
+
+            fn device(&self) -> HeadlessVirtualDevice {
+                HeadlessVirtualDevice
+            }
+        }
+    };
+}
+
+headless_event!(HeadlessKeyboardEvent);
+headless_event!(HeadlessMotionAbsoluteEvent);
+headless_event!(HeadlessButtonEvent);
+headless_event!(HeadlessAxisEvent);
+
+impl KeyboardKeyEvent<HeadlessInput> for HeadlessKeyboardEvent {
+    fn key_code(&self) -> Keycode {
+        self.keycode
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        match self.state {
+            KeyState::Pressed => 1,
+            KeyState::Released => 0,
+        }
+    }
+}
+
+impl AbsolutePositionEvent<HeadlessInput> for HeadlessMotionAbsoluteEvent {
+    fn x(&self) -> f64 {
+        self.position.x
+    }
+
+    fn y(&self) -> f64 {
+        self.position.y
+    }
+
+    // positions are already in the logical space of the output
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.position.x.clamp(0.0, width as f64)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.position.y.clamp(0.0, height as f64)
+    }
+}
+
+impl smithay::backend::input::PointerMotionAbsoluteEvent<HeadlessInput>
+    for HeadlessMotionAbsoluteEvent
+{
+}
+
+impl PointerButtonEvent<HeadlessInput> for HeadlessButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+impl PointerAxisEvent<HeadlessInput> for HeadlessAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        match axis {
+            Axis::Horizontal => Some(self.horizontal),
+            Axis::Vertical => Some(self.vertical),
+        }
+        .filter(|amount| *amount != 0.0)
+    }
+
+    fn amount_v120(&self, _axis: Axis) -> Option<f64> {
+        None
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Continuous
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+impl InputBackend for HeadlessInput {
+    type Device = HeadlessVirtualDevice;
+    type KeyboardKeyEvent = HeadlessKeyboardEvent;
+    type PointerAxisEvent = HeadlessAxisEvent;
+    type PointerButtonEvent = HeadlessButtonEvent;
+    type PointerMotionEvent = UnusedEvent;
+    type PointerMotionAbsoluteEvent = HeadlessMotionAbsoluteEvent;
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
+    type SpecialEvent = UnusedEvent;
+}
+
+pub fn init_backend(
+    dh: &DisplayHandle,
+    event_loop: &mut EventLoop<State>,
+    state: &mut State,
+) -> Result<()> {
+    init_backend_with_outputs(dh, event_loop, state, OutputSpec::from_env())
+}
+
+/// Initializes the headless backend with a fixed set of outputs instead of
+/// reading them from `COSMIC_HEADLESS_OUTPUTS`.
+pub fn init_backend_with_outputs(
+    _dh: &DisplayHandle,
+    event_loop: &mut EventLoop<State>,
+    state: &mut State,
+    specs: Vec<OutputSpec>,
+) -> Result<()> {
+    let renderer = PixmanRenderer::new().with_context(|| "Failed to initialize pixman renderer")?;
+    let software_renderer = match super::kms::software_renderer() {
+        Ok(renderer) => Some(renderer),
+        Err(err) => {
+            info!(
+                ?err,
+                "No software EGL renderer, screen capture is unavailable."
+            );
+            None
+        }
+    };
+
+    let (input, channel): (_, Channel<HeadlessInputEvent>) = channel::channel();
+    state.backend = BackendData::Headless(HeadlessState {
+        renderer,
+        software_renderer,
+        surfaces: Vec::new(),
+        input: input.clone(),
+    });
+
+    let mut outputs = Vec::new();
+    for spec in specs {
+        outputs.push(
+            state
+                .backend
+                .headless()
+                .add_output(spec, &event_loop.handle())
+                .with_context(|| "Failed to create wl_output")?,
+        );
+    }
+    state
+        .common
+        .output_configuration_state
+        .add_heads(outputs.iter());
+    {
+        for output in &outputs {
+            state.common.add_output(output);
+        }
+        if let Err(err) = state.common.config.read_outputs(
+            &mut state.common.output_configuration_state,
+            &mut state.backend,
+            &state.common.shell,
+            &state.common.event_loop_handle,
+            &mut state.common.workspace_state.update(),
+            &state.common.xdg_activation_state,
+            state.common.startup_done.clone(),
+            &state.common.clock,
+        ) {
+            error!("Unrecoverable output configuration error: {}", err);
+        }
+        state.common.refresh();
+    }
+
+    event_loop
+        .handle()
+        .insert_source(channel, |event, _, state| {
+            if let channel::Event::Msg(event) = event {
+                state.process_headless_event(event);
+            }
+        })
+        .map_err(|_| anyhow!("Failed to insert headless input source into event loop"))?;
+    // processed once the initial seat exists
+    state.common.event_loop_handle.insert_idle(|state| {
+        state.process_input_event(InputEvent::<HeadlessInput>::DeviceAdded {
+            device: HeadlessVirtualDevice,
+        });
+    });
+
+    if state.common.with_xwayland {
+        state.launch_xwayland(None);
+    } else {
+        state.notify_ready();
+    }
+
+    Ok(())
+}
+
+impl State {
+    pub fn process_headless_event(&mut self, event: HeadlessInputEvent) {
+        let time = Duration::from(self.common.clock.now()).as_micros() as u64;
+        let event = match event {
+            HeadlessInputEvent::Key { keycode, state } => InputEvent::Keyboard {
+                event: HeadlessKeyboardEvent {
+                    time,
+                    keycode,
+                    state,
+                },
+            },
+            HeadlessInputEvent::PointerMotion { output, position } => {
+                let Some(output) = self
+                    .common
+                    .shell
+                    .read()
+                    .outputs()
+                    .find(|o| o.name() == output)
+                    .cloned()
+                else {
+                    warn!(output, "Pointer motion on unknown headless output");
+                    return;
+                };
+                for seat in self.common.shell.read().seats.iter() {
+                    let devices = seat.user_data().get::<Devices>().unwrap();
+                    if devices.has_device(&HeadlessVirtualDevice) {
+                        seat.set_active_output(&output);
+                        break;
+                    }
+                }
+                InputEvent::PointerMotionAbsolute {
+                    event: HeadlessMotionAbsoluteEvent { time, position },
+                }
+            }
+            HeadlessInputEvent::PointerButton { button, state } => InputEvent::PointerButton {
+                event: HeadlessButtonEvent {
+                    time,
+                    button,
+                    state,
+                },
+            },
+            HeadlessInputEvent::PointerAxis {
+                horizontal,
+                vertical,
+            } => InputEvent::PointerAxis {
+                event: HeadlessAxisEvent {
+                    time,
+                    horizontal,
+                    vertical,
+                },
+            },
+        };
+
+        self.process_input_event::<HeadlessInput>(event);
+        for output in self.common.shell.read().outputs() {
+            self.backend.headless().schedule_render(output);
+        }
+    }
+}
@@ -346,3 +346,127 @@ pub fn panel_orientation(dev: &impl ControlDevice, conn: connector::Handle) -> R
         _ => Err(anyhow!("panel orientation has wrong value type")),
     }
 }
+
+/// Luminance range of a display supporting HDR10, in cd/m²
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrCapabilities {
+    pub max_luminance: f32,
+    pub max_frame_average_luminance: f32,
+    pub min_luminance: f32,
+}
+
+/// Luminance assumed for displays not reporting their range
+const DEFAULT_HDR_MAX_LUMINANCE: f32 = 1000.;
+
+/// Reads the HDR static metadata block of the EDID, `None` if the display doesn't accept PQ.
+pub fn hdr_capabilities(
+    device: &impl ControlDevice,
+    connector: connector::Handle,
+) -> Result<Option<HdrCapabilities>> {
+    let info = edid_info(device, connector)?;
+    let metadata = info.hdr_static_metadata();
+    if !metadata.pq {
+        return Ok(None);
+    }
+
+    // zero means the value is not reported
+    let max_luminance = Some(metadata.desired_content_max_luminance)
+        .filter(|val| *val > 0.)
+        .unwrap_or(DEFAULT_HDR_MAX_LUMINANCE);
+    Ok(Some(HdrCapabilities {
+        max_luminance,
+        max_frame_average_luminance: Some(metadata.desired_content_max_frame_avg_luminance)
+            .filter(|val| *val > 0.)
+            .unwrap_or(max_luminance),
+        min_luminance: metadata.desired_content_min_luminance,
+    }))
+}
+
+// `struct hdr_metadata_infoframe` of the kernel uapi
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct HdrMetadataInfoframe {
+    eotf: u8,
+    metadata_type: u8,
+    display_primaries: [[u16; 2]; 3],
+    white_point: [u16; 2],
+    max_display_mastering_luminance: u16,
+    min_display_mastering_luminance: u16,
+    max_cll: u16,
+    max_fall: u16,
+}
+
+// `struct hdr_output_metadata` of the kernel uapi
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct HdrOutputMetadata {
+    metadata_type: u32,
+    hdmi_metadata_type1: HdrMetadataInfoframe,
+}
+
+/// SMPTE ST 2084 in the EOTF field of the infoframe
+const HDMI_EOTF_SMPTE_ST2084: u8 = 2;
+/// Chromaticities of BT.2020 in units of 0.00002, red, green and blue
+const BT2020_PRIMARIES: [[u16; 2]; 3] = [[35400, 14600], [8500, 39850], [6550, 2300]];
+/// D65 in units of 0.00002
+const D65_WHITE_POINT: [u16; 2] = [15635, 16450];
+
+/// Switches the connector between HDR10 signaling and the default colorspace.
+///
+/// Returns the blob of the new metadata infoframe, the caller has to destroy it once replaced.
+pub fn set_hdr_output(
+    dev: &impl ControlDevice,
+    conn: connector::Handle,
+    hdr: Option<&HdrCapabilities>,
+) -> Result<Option<u64>> {
+    let colorspace = get_prop(dev, conn, "Colorspace")?;
+    let property::ValueType::Enum(values) = dev.get_property(colorspace)?.value_type() else {
+        anyhow::bail!("Colorspace has wrong value type");
+    };
+    let name = if hdr.is_some() {
+        "BT2020_RGB"
+    } else {
+        "Default"
+    };
+    let value = values
+        .values()
+        .1
+        .iter()
+        .find(|val| val.name().to_str() == Ok(name))
+        .ok_or(anyhow!("Colorspace {} not supported", name))?
+        .value();
+
+    let metadata = get_prop(dev, conn, "HDR_OUTPUT_METADATA")?;
+    let blob = match hdr {
+        Some(caps) => {
+            let luminance = |val: f32| val.round().clamp(0., u16::MAX as f32) as u16;
+            let blob = dev.create_property_blob(&HdrOutputMetadata {
+                metadata_type: 0,
+                hdmi_metadata_type1: HdrMetadataInfoframe {
+                    eotf: HDMI_EOTF_SMPTE_ST2084,
+                    metadata_type: 0,
+                    display_primaries: BT2020_PRIMARIES,
+                    white_point: D65_WHITE_POINT,
+                    max_display_mastering_luminance: luminance(caps.max_luminance),
+                    // in units of 0.0001 cd/m²
+                    min_display_mastering_luminance: luminance(caps.min_luminance * 10000.),
+                    max_cll: luminance(caps.max_luminance),
+                    max_fall: luminance(caps.max_frame_average_luminance),
+                },
+            })?;
+            Some(blob.into())
+        }
+        None => None,
+    };
+
+    let res = dev
+        .set_property(conn, colorspace, value)
+        .and_then(|_| dev.set_property(conn, metadata, blob.unwrap_or(0)));
+    if let Err(err) = res {
+        if let Some(blob) = blob {
+            let _ = dev.destroy_property_blob(blob);
+        }
+        return Err(err.into());
+    }
+    Ok(blob)
+}
@@ -55,9 +55,13 @@ mod drm_helpers;
 pub mod render;
 mod socket;
 mod surface;
+mod virtual_output;
 use device::*;
 pub(crate) use surface::Surface;
-pub use surface::Timings;
+#[cfg(test)]
+pub(crate) use surface::mirror_area;
+pub use surface::{Histogram, SurfaceStats, Timings};
+pub(crate) use virtual_output::VirtualOutput;
 
 use super::render::{CLEAR_COLOR, CursorMode, output_elements};
 
@@ -69,6 +73,7 @@ pub struct KmsState {
     // Mesa llvmpipe renderer, if supported and there are no render nodes
     pub software_renderer: Option<GlowRenderer>,
     pub api: GpuManager<GbmGlowBackend<DrmDeviceFd>>,
+    virtual_outputs: Vec<VirtualOutput>,
 
     pub session: LibSeatSession,
     libinput: Libinput,
@@ -80,6 +85,7 @@ pub struct KmsGuard<'a> {
     pub drm_devices: IndexMap<DrmNode, LockedDevice<'a>>,
     pub primary_node: Arc<RwLock<Option<DrmNode>>>,
     api: &'a mut GpuManager<GbmGlowBackend<DrmDeviceFd>>,
+    virtual_outputs: &'a mut Vec<VirtualOutput>,
     session: &'a LibSeatSession,
 }
 
@@ -126,6 +132,7 @@ pub fn init_backend(
         primary_node: Arc::new(RwLock::new(None)),
         software_renderer: None,
         api: GpuManager::new(GbmGlowBackend::new()).context("Failed to initialize gpu backend")?,
+        virtual_outputs: Vec::new(),
 
         session,
         libinput: libinput_context,
@@ -274,7 +281,7 @@ fn determine_primary_gpu(
 }
 
 /// Create `GlowRenderer` for `EGL_MESA_device_software` device, if present
-fn software_renderer() -> anyhow::Result<GlowRenderer> {
+pub(super) fn software_renderer() -> anyhow::Result<GlowRenderer> {
     let mut devices = EGLDevice::enumerate()?;
     let device = devices
         .find(|device| {
@@ -597,6 +604,13 @@ impl KmsState {
         {
             surface.schedule_render();
         }
+        if let Some(virtual_output) = self
+            .virtual_outputs
+            .iter_mut()
+            .find(|v| v.output == *output)
+        {
+            virtual_output.schedule_render();
+        }
     }
 
     pub fn target_node_for_output(&self, output: &Output) -> Option<DrmNode> {
@@ -613,6 +627,9 @@ impl KmsState {
                 surface.set_screen_filter(screen_filter.clone());
             }
         }
+        for virtual_output in &mut self.virtual_outputs {
+            virtual_output.set_screen_filter(screen_filter);
+        }
 
         // We don't expect this to fail in a meaningful way.
         // The shader is already compiled at this point and we don't rely on any features,
@@ -622,6 +639,25 @@ impl KmsState {
         Ok(())
     }
 
+    pub fn update_gamma(&mut self, output: &Output) {
+        for device in self.drm_devices.values_mut() {
+            for surface in device.inner.surfaces.values_mut() {
+                if surface.output == *output {
+                    surface.update_gamma(device.drm.device());
+                }
+            }
+        }
+    }
+
+    /// Render statistics of `output`, if it is driven by a CRTC
+    pub fn surface_stats(&self, output: &Output) -> Option<SurfaceStats> {
+        self.drm_devices
+            .values()
+            .flat_map(|device| device.inner.surfaces.values())
+            .find(|surface| surface.output == *output)
+            .map(|surface| surface.stats())
+    }
+
     fn clear_used_devices(&mut self) -> Result<()> {
         let primary_node = self.primary_node.read().unwrap();
         let empty_devices = HashSet::new();
@@ -686,6 +722,7 @@ impl KmsState {
                 .collect(),
             primary_node: self.primary_node.clone(),
             api: &mut self.api,
+            virtual_outputs: &mut self.virtual_outputs,
             session: &self.session,
         }
     }
@@ -701,6 +738,13 @@ impl KmsGuard<'_> {
         {
             surface.schedule_render();
         }
+        if let Some(virtual_output) = self
+            .virtual_outputs
+            .iter_mut()
+            .find(|v| v.output == *output)
+        {
+            virtual_output.schedule_render();
+        }
     }
 
     pub fn refresh_used_devices(&mut self) -> Result<()> {
@@ -756,6 +800,7 @@ impl KmsGuard<'_> {
                     })
                     .map(|(_, output)| output.clone())
             })
+            .chain(self.virtual_outputs.iter().map(|v| v.output.clone()))
             .collect()
     }
 
@@ -768,6 +813,10 @@ impl KmsGuard<'_> {
         startup_done: Arc<AtomicBool>,
         clock: &Clock<Monotonic>,
     ) -> Result<(), anyhow::Error> {
+        for virtual_output in self.virtual_outputs.iter_mut() {
+            virtual_output.apply_config(test_only)?;
+        }
+
         if !self.session.is_active() {
             return Ok(());
         }
@@ -1014,7 +1063,12 @@ impl KmsGuard<'_> {
                         }
 
                         let vrr = output_config.0.vrr;
+                        let allow_tearing = output_config.0.allow_tearing;
+                        let hdr = output_config.0.hdr;
                         std::mem::drop(output_config);
+                        surface.allow_tearing(allow_tearing);
+                        surface.set_hdr(drm.device(), hdr);
+                        surface.update_gamma(drm.device());
 
                         let compositor_ref = drm.compositors().get(crtc).unwrap().lock().unwrap();
                         let vrr_support = compositor_ref
@@ -1041,7 +1095,12 @@ impl KmsGuard<'_> {
 
                         surface.output.set_adaptive_sync_support(vrr_support);
                         if match vrr_support {
-                            Some(VrrSupport::RequiresModeset) if vrr == AdaptiveSync::Enabled => {
+                            Some(VrrSupport::RequiresModeset)
+                                if matches!(
+                                    vrr,
+                                    AdaptiveSync::Enabled | AdaptiveSync::Automatic
+                                ) =>
+                            {
                                 false
                             }
                             Some(VrrSupport::NotSupported) => false,
@@ -1056,11 +1115,19 @@ impl KmsGuard<'_> {
                         }
                     } else {
                         let vrr = output_config.0.vrr;
+                        let allow_tearing = output_config.0.allow_tearing;
+                        let hdr = output_config.0.hdr;
                         std::mem::drop(output_config);
+                        surface.allow_tearing(allow_tearing);
+                        surface.set_hdr(drm.device(), hdr);
+                        surface.update_gamma(drm.device());
                         if vrr != surface.output.adaptive_sync() {
                             if match surface.output.adaptive_sync_support() {
                                 Some(VrrSupport::RequiresModeset)
-                                    if vrr == AdaptiveSync::Enabled =>
+                                    if matches!(
+                                        vrr,
+                                        AdaptiveSync::Enabled | AdaptiveSync::Automatic
+                                    ) =>
                                 {
                                     true
                                 }
@@ -1167,8 +1234,12 @@ impl KmsGuard<'_> {
                         None
                     };
 
-                if !test_only && mirrored_output != surface.output.mirroring() {
-                    surface.set_mirroring(mirrored_output.clone());
+                let mirror_config = surface.output.config().mirror.clone();
+                if !test_only
+                    && (mirrored_output != surface.output.mirroring()
+                        || mirror_config != *surface.mirror_config())
+                {
+                    surface.set_mirroring(mirrored_output.clone(), mirror_config);
                 }
             }
         }
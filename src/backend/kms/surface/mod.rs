@@ -3,23 +3,30 @@
 use crate::{
     backend::render::{
         CLEAR_COLOR, CursorMode, GlMultiError, GlMultiRenderer, PostprocessOutputConfig,
-        PostprocessShader, PostprocessState,
+        PostprocessShader, PostprocessState, SDR_WHITE_LEVEL,
         element::{CosmicElement, DamageElement},
+        gamma::{self, GammaCurve, GammaRamp},
         init_shaders, output_elements,
+        wayland::color_conversion::{LinearLight, LinearLightElement, decode_srgb_color},
     },
     config::ScreenFilter,
     shell::Shell,
     state::SurfaceDmabufFeedback,
     utils::prelude::*,
-    wayland::handlers::{
-        compositor::recursive_frame_time_estimation,
-        image_copy_capture::{FrameHolder, PendingImageCopyData, SessionData, submit_buffer},
+    wayland::{
+        handlers::{
+            compositor::recursive_frame_time_estimation,
+            content_type::surface_content_type,
+            image_copy_capture::{FrameHolder, PendingImageCopyData, SessionData, submit_buffer},
+            tearing_control::wants_tearing,
+        },
+        protocols::color_management::{ColorParams, set_output_image_description},
     },
 };
 
 use anyhow::{Context, Result};
 use calloop::channel::Channel;
-use cosmic_comp_config::output::comp::AdaptiveSync;
+use cosmic_comp_config::{MirrorConfig, MirrorScaling, MirrorSource, output::comp::AdaptiveSync};
 use smithay::{
     backend::{
         allocator::{
@@ -51,9 +58,10 @@ use smithay::{
                 },
             },
             gles::{
-                GlesRenderbuffer, GlesRenderer, GlesTexture, Uniform, element::TextureShaderElement,
+                GlesFrame, GlesRenderbuffer, GlesRenderer, GlesTexture, Uniform,
+                element::TextureShaderElement,
             },
-            glow::GlowRenderer,
+            glow::{GlowFrame, GlowRenderer},
             multigpu::{ApiDevice, Error as MultiError, GpuManager},
             sync::SyncPoint,
             utils::with_renderer_surface_state,
@@ -67,14 +75,15 @@ use smithay::{
             channel::{Event, Sender, channel},
             timer::{TimeoutAction, Timer},
         },
-        drm::control::{connector, crtc},
+        drm::control::{Device as ControlDevice, connector, crtc},
         wayland_protocols::wp::{
+            content_type::v1::server::wp_content_type_v1::Type as ContentType,
             linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1,
             presentation_time::server::wp_presentation_feedback,
         },
         wayland_server::protocol::wl_surface::WlSurface,
     },
-    utils::{Clock, Monotonic, Physical, Point, Rectangle, Transform},
+    utils::{Clock, Logical, Monotonic, Physical, Point, Rectangle, Size, Transform},
     wayland::{
         dmabuf::{DmabufFeedbackBuilder, get_dmabuf},
         image_copy_capture::{
@@ -92,7 +101,7 @@ use std::{
     collections::{HashMap, HashSet, hash_map},
     mem,
     sync::{
-        Arc, RwLock,
+        Arc, Mutex, RwLock,
         atomic::{AtomicBool, Ordering},
         mpsc::{Receiver, SyncSender},
     },
@@ -100,10 +109,15 @@ use std::{
     time::Duration,
 };
 
+mod stats;
 mod timings;
+pub use self::stats::{Histogram, SurfaceStats};
 pub use self::timings::Timings;
 
-use super::{drm_helpers, render::gles::GbmGlowBackend};
+use super::{
+    drm_helpers::{self, HdrCapabilities},
+    render::gles::GbmGlowBackend,
+};
 
 #[cfg(feature = "debug")]
 use smithay_egui::EguiState;
@@ -116,6 +130,7 @@ pub struct Surface {
     known_nodes: HashSet<DrmNode>,
 
     active: Arc<AtomicBool>,
+    stats: Arc<Mutex<SurfaceStats>>,
     pub feedback: HashMap<DrmNode, SurfaceDmabufFeedback>,
     pub(super) primary_plane_formats: FormatSet,
     overlay_plane_formats: Option<FormatSet>,
@@ -126,6 +141,9 @@ pub struct Surface {
     thread: Option<JoinHandle<()>>,
 
     dpms: bool,
+    /// Capabilities the output is driven in HDR10 with, and the blob of its metadata infoframe
+    hdr: Option<(HdrCapabilities, u64)>,
+    mirror_config: MirrorConfig,
 }
 
 pub struct SurfaceThreadState {
@@ -136,15 +154,24 @@ pub struct SurfaceThreadState {
     active: Arc<AtomicBool>,
     vrr_mode: AdaptiveSync,
     frame_flags: FrameFlags,
+    allow_tearing: bool,
+    /// Whether the last queued frame was scanned out directly from a client buffer
+    direct_scanout: bool,
+    /// Whether the output is driven with HDR10, encoding everything through the postprocess pass
+    hdr: bool,
+    /// Gamma ramp to apply in the postprocess pass, if the CRTC can't
+    gamma: Option<GammaCurve>,
     compositor: Option<GbmDrmOutput>,
 
     state: QueueState,
     timings: Timings,
+    stats: Arc<Mutex<SurfaceStats>>,
     frame_callback_seq: usize,
     thread_sender: Sender<SurfaceCommand>,
 
     output: Output,
     mirroring: Option<Output>,
+    mirror_config: MirrorConfig,
     screen_filter: ScreenFilter,
     postprocess_textures: HashMap<DrmNode, PostprocessState>,
 
@@ -212,13 +239,16 @@ pub enum ThreadCommand {
         node: DrmNode,
         sync: SyncSender<()>,
     },
-    UpdateMirroring(Option<Output>),
+    UpdateMirroring(Option<Output>, MirrorConfig),
     UpdateScreenFilter(ScreenFilter),
     VBlank(Option<DrmEventMetadata>),
     ScheduleRender,
     AdaptiveSyncAvailable(SyncSender<Result<VrrSupport>>),
     UseAdaptiveSync(AdaptiveSync),
     AllowFrameFlags(bool, FrameFlags),
+    AllowTearing(bool),
+    UseHdr(bool),
+    UpdateGamma(Option<GammaCurve>),
     End,
     DpmsOff,
 }
@@ -235,6 +265,8 @@ struct PrePostprocessData {
     texture: Option<GlesTexture>,
     cursor_texture: Option<GlesTexture>,
     cursor_geometry: Option<Rectangle<i32, Physical>>,
+    /// Whether `texture` holds linear light, which the postprocess pass encodes
+    linear: bool,
 }
 
 impl Surface {
@@ -253,8 +285,10 @@ impl Surface {
         let (tx, rx) = channel::<ThreadCommand>();
         let (tx2, rx2) = channel::<SurfaceCommand>();
         let active = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(Mutex::new(SurfaceStats::default()));
 
         let active_clone = active.clone();
+        let stats_clone = stats.clone();
         let output_clone = output.clone();
 
         let thread = std::thread::Builder::new()
@@ -266,6 +300,7 @@ impl Surface {
                     target_node,
                     shell,
                     active_clone,
+                    stats_clone,
                     screen_filter,
                     tx2,
                     rx,
@@ -339,6 +374,7 @@ impl Surface {
             output: output.clone(),
             known_nodes: HashSet::new(),
             active,
+            stats,
             feedback: HashMap::new(),
             primary_plane_formats: FormatSet::default(),
             overlay_plane_formats: None,
@@ -347,6 +383,8 @@ impl Surface {
             thread_token,
             thread: Some(thread),
             dpms: true,
+            hdr: None,
+            mirror_config: MirrorConfig::default(),
         })
     }
 
@@ -358,6 +396,11 @@ impl Surface {
         self.active.load(Ordering::SeqCst)
     }
 
+    /// Render statistics since the surface was created
+    pub fn stats(&self) -> SurfaceStats {
+        self.stats.lock().unwrap().clone()
+    }
+
     pub fn add_node(&mut self, node: DrmNode, gbm: GbmAllocator<DrmDeviceFd>, egl: EGLContext) {
         self.known_nodes.insert(node);
         let (tx, rx) = std::sync::mpsc::sync_channel(1);
@@ -392,10 +435,15 @@ impl Surface {
         }
     }
 
-    pub fn set_mirroring(&mut self, output: Option<Output>) {
+    pub fn mirror_config(&self) -> &MirrorConfig {
+        &self.mirror_config
+    }
+
+    pub fn set_mirroring(&mut self, output: Option<Output>, config: MirrorConfig) {
+        self.mirror_config = config.clone();
         let _ = self
             .thread_command
-            .send(ThreadCommand::UpdateMirroring(output));
+            .send(ThreadCommand::UpdateMirroring(output, config));
     }
 
     pub fn set_screen_filter(&mut self, config: ScreenFilter) {
@@ -424,6 +472,103 @@ impl Surface {
             .send(ThreadCommand::AllowFrameFlags(flag, flags));
     }
 
+    pub fn allow_tearing(&mut self, allow: bool) {
+        let _ = self.thread_command.send(ThreadCommand::AllowTearing(allow));
+    }
+
+    /// Drives the output with HDR10, if `hdr` is set and the display supports it.
+    pub fn set_hdr(&mut self, device: &impl ControlDevice, hdr: bool) {
+        if !hdr && self.hdr.is_none() {
+            return;
+        }
+
+        let mut caps = None;
+        if hdr {
+            match drm_helpers::hdr_capabilities(device, self.connector) {
+                Ok(Some(hdr_caps)) => caps = Some(hdr_caps),
+                Ok(None) => warn!("Output {} does not support HDR10", self.output.name()),
+                Err(err) => warn!(
+                    ?err,
+                    "Failed to read HDR capabilities of {}",
+                    self.output.name()
+                ),
+            }
+        }
+        if self.hdr.map(|(current, _)| current) == caps {
+            return;
+        }
+
+        let blob = match drm_helpers::set_hdr_output(device, self.connector, caps.as_ref()) {
+            Ok(blob) => blob,
+            Err(err) => {
+                warn!(?err, "Failed to set HDR mode of {}", self.output.name());
+                return;
+            }
+        };
+        if let Some((_, previous)) = self.hdr.take()
+            && let Err(err) = device.destroy_property_blob(previous)
+        {
+            warn!(
+                ?err,
+                "Failed to destroy HDR metadata of {}",
+                self.output.name()
+            );
+        }
+        self.hdr = caps.zip(blob);
+
+        set_output_image_description(
+            &self.output,
+            match self.hdr {
+                Some((caps, _)) => ColorParams::hdr10(
+                    caps.min_luminance,
+                    caps.max_luminance,
+                    caps.max_frame_average_luminance,
+                ),
+                None => ColorParams::srgb(),
+            },
+        );
+        let _ = self
+            .thread_command
+            .send(ThreadCommand::UseHdr(self.hdr.is_some()));
+    }
+
+    /// Applies the gamma ramp of the output.
+    ///
+    /// Uses the gamma LUT of the CRTC, if it has one, and the postprocess shader otherwise.
+    /// HDR10 output is always adjusted in the shader, as the LUT would apply to the encoded signal.
+    pub fn update_gamma(&mut self, device: &impl ControlDevice) {
+        let lut_size = match device.get_crtc(self.crtc) {
+            Ok(info) => info.gamma_length() as usize,
+            Err(err) => {
+                warn!(?err, "Failed to read gamma size of {}", self.output.name());
+                0
+            }
+        };
+        gamma::set_gamma_size(
+            &self.output,
+            if lut_size > 0 {
+                lut_size
+            } else {
+                gamma::SHADER_GAMMA_SIZE
+            },
+        );
+
+        let ramp = gamma::output_gamma(&self.output);
+        let mut curve = ramp.as_ref().map(GammaCurve::fit);
+        if lut_size > 0 {
+            let lut = match ramp.as_ref().filter(|_| self.hdr.is_none()) {
+                Some(ramp) => ramp.resample(lut_size),
+                None => GammaRamp::for_temperature(lut_size, gamma::NEUTRAL_TEMPERATURE),
+            };
+            match device.set_gamma(self.crtc, &lut.red, &lut.green, &lut.blue) {
+                Ok(()) if self.hdr.is_none() => curve = None,
+                Ok(()) => {}
+                Err(err) => warn!(?err, "Failed to set gamma LUT of {}", self.output.name()),
+            }
+        }
+        let _ = self.thread_command.send(ThreadCommand::UpdateGamma(curve));
+    }
+
     pub fn suspend(&mut self) {
         let (tx, rx) = std::sync::mpsc::sync_channel(1);
         let _ = self.thread_command.send(ThreadCommand::Suspend(tx));
@@ -495,6 +640,7 @@ fn surface_thread(
     target_node: DrmNode,
     shell: Arc<parking_lot::RwLock<Shell>>,
     active: Arc<AtomicBool>,
+    stats: Arc<Mutex<SurfaceStats>>,
     screen_filter: ScreenFilter,
     thread_sender: Sender<SurfaceCommand>,
     thread_receiver: Channel<ThreadCommand>,
@@ -536,14 +682,20 @@ fn surface_thread(
         compositor: None,
         frame_flags: FrameFlags::DEFAULT,
         vrr_mode: AdaptiveSync::Disabled,
+        allow_tearing: false,
+        direct_scanout: false,
+        hdr: false,
+        gamma: None,
 
         state: QueueState::Idle,
         timings: Timings::new(None, None, false, target_node),
+        stats,
         frame_callback_seq: 0,
         thread_sender,
 
         output,
         mirroring: None,
+        mirror_config: MirrorConfig::default(),
         screen_filter,
         postprocess_textures: HashMap::new(),
 
@@ -594,8 +746,8 @@ fn surface_thread(
 
                 state.queue_redraw(false);
             }
-            Event::Msg(ThreadCommand::UpdateMirroring(mirroring_output)) => {
-                state.update_mirroring(mirroring_output);
+            Event::Msg(ThreadCommand::UpdateMirroring(mirroring_output, config)) => {
+                state.update_mirroring(mirroring_output, config);
             }
             Event::Msg(ThreadCommand::UpdateScreenFilter(filter_config)) => {
                 state.update_screen_filter(filter_config);
@@ -654,6 +806,22 @@ fn surface_thread(
                     state.frame_flags.remove(flags);
                 }
             }
+            Event::Msg(ThreadCommand::UseHdr(hdr)) => {
+                if state.hdr != hdr {
+                    state.hdr = hdr;
+                    state.postprocess_textures.clear();
+                }
+            }
+            Event::Msg(ThreadCommand::UpdateGamma(gamma)) => {
+                if state.gamma != gamma {
+                    state.gamma = gamma;
+                    state.postprocess_textures.clear();
+                    state.queue_redraw(true);
+                }
+            }
+            Event::Msg(ThreadCommand::AllowTearing(allow)) => {
+                state.allow_tearing = allow;
+            }
             Event::Closed | Event::Msg(ThreadCommand::End) => {
                 signal.stop();
                 signal.wakeup();
@@ -861,7 +1029,17 @@ impl SurfaceThreadState {
 
             feedback.presented(clock, refresh, sequence as u64, flags);
 
+            let frame_pending = self.timings.pending_frame.is_some();
             self.timings.presented(clock);
+            if frame_pending && let Some(frame) = self.timings.previous_frames.back() {
+                self.stats.lock().unwrap().frame_presented(
+                    frame.frame_time(),
+                    frame.render_time(),
+                    estimated_presentation_time,
+                    presentation_time,
+                    self.timings.refresh_interval(),
+                );
+            }
 
             while let Ok(pending_image_copy_data) = frames.recv() {
                 pending_image_copy_data.send_success_when_ready(
@@ -1019,7 +1197,13 @@ impl SurfaceThreadState {
         let mut additional_frame_flags = FrameFlags::empty();
         let mut remove_frame_flags = FrameFlags::empty();
 
-        let (has_active_fullscreen, fullscreen_drives_refresh_rate, animations_going) = {
+        let (
+            has_active_fullscreen,
+            fullscreen_drives_refresh_rate,
+            fullscreen_wants_tearing,
+            fullscreen_content_type,
+            animations_going,
+        ) = {
             let shell = self.shell.read();
             let animations_going = shell.animations_going();
             let output = self.mirroring.as_ref().unwrap_or(&self.output);
@@ -1036,13 +1220,22 @@ impl SurfaceThreadState {
                                 recursive_frame_time_estimation(&self.clock, &surface)
                                     .is_some_and(|dur| dur <= _30_FPS)
                             }),
+                        fullscreen_surface
+                            .surface
+                            .wl_surface()
+                            .is_some_and(|surface| wants_tearing(&surface)),
+                        fullscreen_surface
+                            .surface
+                            .wl_surface()
+                            .map(|surface| surface_content_type(&surface))
+                            .unwrap_or(ContentType::None),
                         animations_going,
                     )
                 } else {
-                    (false, false, animations_going)
+                    (false, false, false, ContentType::None, animations_going)
                 }
             } else {
-                (false, false, animations_going)
+                (false, false, false, ContentType::None, animations_going)
             }
         };
 
@@ -1056,7 +1249,15 @@ impl SurfaceThreadState {
         if self.vrr_mode == AdaptiveSync::Enabled {
             vrr = has_active_fullscreen;
         }
+        if self.vrr_mode == AdaptiveSync::Automatic {
+            vrr = matches!(
+                fullscreen_content_type,
+                ContentType::Game | ContentType::Video
+            );
+        }
 
+        // HDR10 outputs composite in linear light, see `color_conversion`
+        LinearLight::set(&renderer, self.hdr);
         let mut elements = output_elements(
             Some(&render_node),
             &mut renderer,
@@ -1079,6 +1280,7 @@ impl SurfaceThreadState {
             additional_frame_flags |= FrameFlags::SKIP_CURSOR_ONLY_UPDATES;
         };
         self.timings.set_vrr(vrr);
+        self.timings.set_content_type(fullscreen_content_type);
         self.timings.elements_done(&self.clock);
 
         // we can't use the elements after `compositor.render_frame`,
@@ -1094,11 +1296,18 @@ impl SurfaceThreadState {
         let source_output = self
             .mirroring
             .as_ref()
-            .or((!self.screen_filter.is_noop()).then_some(&self.output))
+            .or(
+                (!self.screen_filter.is_noop() || self.hdr || self.gamma.is_some())
+                    .then_some(&self.output),
+            )
             .filter(|output| {
                 PostprocessOutputConfig::for_output_untransformed(output)
                     != PostprocessOutputConfig::for_output(&self.output)
                     || !self.screen_filter.is_noop()
+                    || self.hdr
+                    || self.gamma.is_some()
+                    || (self.mirroring.is_some()
+                        && self.mirror_config.source != MirrorSource::Output)
             });
 
         let mut pre_postprocess_data = PrePostprocessData::default();
@@ -1106,14 +1315,22 @@ impl SurfaceThreadState {
         let res = if let Some(source_output) = source_output {
             let offscreen_output_config =
                 PostprocessOutputConfig::for_output_untransformed(source_output);
+            // HDR content is composited beyond sRGB white, which needs a float format to survive
+            let offscreen_format = if self.hdr {
+                Fourcc::Abgr16161616f
+            } else {
+                compositor.format()
+            };
             let postprocess_state = match self.postprocess_textures.entry(self.target_node) {
                 hash_map::Entry::Occupied(occupied) => {
                     let postprocess_state = occupied.into_mut();
-                    // If output config is different, re-create offscreen state
-                    if postprocess_state.output_config != offscreen_output_config {
+                    // If output config or format is different, re-create offscreen state
+                    if postprocess_state.output_config != offscreen_output_config
+                        || postprocess_state.format != offscreen_format
+                    {
                         *postprocess_state = PostprocessState::new_with_renderer(
                             &mut renderer,
-                            compositor.format(),
+                            offscreen_format,
                             offscreen_output_config,
                         )?
                     }
@@ -1122,7 +1339,7 @@ impl SurfaceThreadState {
                 hash_map::Entry::Vacant(vacant) => {
                     vacant.insert(PostprocessState::new_with_renderer(
                         &mut renderer,
-                        compositor.format(),
+                        offscreen_format,
                         offscreen_output_config,
                     )?)
                 }
@@ -1220,6 +1437,17 @@ impl SurfaceThreadState {
                 postprocess_state.remove_cursor();
             }
 
+            let offscreen_elements = elements
+                .drain(..)
+                .map(|elem| LinearLightElement::new(&renderer, elem))
+                .collect::<Vec<_>>();
+            let clear_color = if self.hdr {
+                decode_srgb_color(CLEAR_COLOR)
+            } else {
+                CLEAR_COLOR
+            };
+            pre_postprocess_data.linear = self.hdr;
+
             postprocess_state
                 .texture
                 .render()
@@ -1233,8 +1461,8 @@ impl SurfaceThreadState {
                         &mut renderer,
                         &mut fb,
                         1,
-                        &elements,
-                        CLEAR_COLOR,
+                        &offscreen_elements,
+                        clear_color,
                     ) {
                         Ok(res) => res,
                         Err(RenderError::Rendering(err)) => return Err(err),
@@ -1269,12 +1497,31 @@ impl SurfaceThreadState {
 
             renderer = self.api.single_renderer(&self.target_node).unwrap();
 
+            let (scaling, area) = match self.mirroring.as_ref() {
+                Some(mirrored) => (
+                    self.mirror_config.scaling,
+                    mirror_area(
+                        &self.shell.read(),
+                        mirrored,
+                        &self.mirror_config.source,
+                        &postprocess_state.output_config,
+                    ),
+                ),
+                None => (
+                    MirrorScaling::Letterbox,
+                    Rectangle::from_size(postprocess_state.output_config.size),
+                ),
+            };
             elements = postprocess_elements(
                 &mut renderer,
                 &self.output,
                 &pre_postprocess_data,
                 postprocess_state,
                 &self.screen_filter,
+                self.hdr,
+                self.gamma.unwrap_or(GammaCurve::IDENTITY),
+                scaling,
+                area,
             );
 
             if let Err(err) = compositor.with_compositor(|c| c.use_vrr(vrr)) {
@@ -1325,9 +1572,33 @@ impl SurfaceThreadState {
                     elem.sync.wait()?;
                 }
 
+                // Async flips may only exchange the framebuffer of an unchanged plane,
+                // so the frame before has to be scanned out directly as well.
+                let direct_scanout = matches!(
+                    frame_result.primary_element,
+                    PrimaryPlaneElement::Element(_)
+                );
+                let tearing = self.allow_tearing
+                    && self.mirroring.is_none()
+                    && fullscreen_wants_tearing
+                    && direct_scanout
+                    && self.direct_scanout;
+                self.direct_scanout = direct_scanout;
+                if let Err(err) = compositor.with_compositor(|c| c.use_async_page_flip(tearing)) {
+                    warn!("Unable to use async page flips, disabling tearing: {}", err);
+                    self.allow_tearing = false;
+                }
+
                 match compositor.queue_frame(feedback) {
                     x @ Ok(()) | x @ Err(FrameError::EmptyFrame) => {
                         self.timings.submitted_for_presentation(&self.clock);
+                        if x.is_ok() {
+                            self.stats.lock().unwrap().frame_queued(
+                                direct_scanout,
+                                frame_result.overlay_elements.len(),
+                                self.timings.vrr(),
+                            );
+                        }
 
                         // Update `state` after `queue_frame`, before any early return from errors
                         if x.is_ok() {
@@ -1445,8 +1716,9 @@ impl SurfaceThreadState {
         self.state = QueueState::WaitingForEstimatedVBlank(token);
     }
 
-    fn update_mirroring(&mut self, mirroring_output: Option<Output>) {
+    fn update_mirroring(&mut self, mirroring_output: Option<Output>, config: MirrorConfig) {
         self.mirroring = mirroring_output;
+        self.mirror_config = config;
         self.postprocess_textures.clear();
     }
 
@@ -1711,6 +1983,7 @@ fn send_screencopy_result<'a>(
             .texture
             .as_ref()
             .is_some_and(|tex| tex.format() == Some(format))
+            && !pre_postprocess_data.linear
             && (!session.draw_cursor() || pre_postprocess_data.cursor_texture.is_none())
         {
             None
@@ -1764,18 +2037,63 @@ fn send_screencopy_result<'a>(
             .collect::<Vec<_>>();
 
         if let Some(tex) = pre_postprocess_data.texture.as_mut() {
-            let tex_fb = renderer
-                .bind(tex)
-                .map_err(RenderError::<<GlMultiRenderer as RendererSuper>::Error>::Rendering)?;
-
             if let Some(fb) = fb.as_mut() {
-                for rect in adjusted.iter().copied() {
-                    // TODO: On Vulkan, may need to combine sync points instead of just using latest?
-                    sync = renderer
-                        .blit(&tex_fb, fb, rect, rect, TextureFilter::Linear)
+                if pre_postprocess_data.linear {
+                    // a blit can't encode linear light, so draw through the postprocess shader
+                    let postprocess_shader = Borrow::<GlesRenderer>::borrow(renderer.as_ref())
+                        .egl_context()
+                        .user_data()
+                        .get::<PostprocessShader>()
+                        .expect("OffscreenShader should be available through `init_shaders`")
+                        .0
+                        .clone();
+                    let uniforms = vec![
+                        Uniform::new("invert", 0.),
+                        Uniform::new("color_mode", 0.),
+                        Uniform::new("linear", 1.),
+                        Uniform::new("hdr", 0.),
+                        Uniform::new("sdr_white", SDR_WHITE_LEVEL),
+                        Uniform::new("gamma_gain", GammaCurve::IDENTITY.gain),
+                        Uniform::new("gamma_exponent", GammaCurve::IDENTITY.exponent),
+                    ];
+                    let tex_size = tex.size();
+                    let size = Size::from((tex_size.w, tex_size.h));
+                    let mut frame = renderer.render(fb, size, Transform::Normal).map_err(
+                        RenderError::<<GlMultiRenderer as RendererSuper>::Error>::Rendering,
+                    )?;
+                    let glow_frame: &mut GlowFrame<'_, '_> = frame.as_mut();
+                    BorrowMut::<GlesFrame>::borrow_mut(glow_frame)
+                        .override_default_tex_program(postprocess_shader, uniforms);
+                    glow_frame
+                        .render_texture_from_to(
+                            tex,
+                            Rectangle::from_size(tex_size.to_f64()),
+                            Rectangle::from_size(size),
+                            &adjusted,
+                            &[],
+                            Transform::Normal,
+                            1.0,
+                        )
+                        .map_err(GlMultiError::Render)
                         .map_err(
                             RenderError::<<GlMultiRenderer as RendererSuper>::Error>::Rendering,
                         )?;
+                    BorrowMut::<GlesFrame>::borrow_mut(glow_frame).clear_tex_program_override();
+                    sync = frame.finish().map_err(
+                        RenderError::<<GlMultiRenderer as RendererSuper>::Error>::Rendering,
+                    )?;
+                } else {
+                    let tex_fb = renderer.bind(tex).map_err(
+                        RenderError::<<GlMultiRenderer as RendererSuper>::Error>::Rendering,
+                    )?;
+                    for rect in adjusted.iter().copied() {
+                        // TODO: On Vulkan, may need to combine sync points instead of just using latest?
+                        sync = renderer
+                            .blit(&tex_fb, fb, rect, rect, TextureFilter::Linear)
+                            .map_err(
+                                RenderError::<<GlMultiRenderer as RendererSuper>::Error>::Rendering,
+                            )?;
+                    }
                 }
                 if let Some(cursor_geometry) = pre_postprocess_data
                     .cursor_geometry
@@ -1820,7 +2138,9 @@ fn send_screencopy_result<'a>(
                     )?;
                 }
             } else {
-                fb = Some(tex_fb);
+                fb = Some(renderer.bind(tex).map_err(
+                    RenderError::<<GlMultiRenderer as RendererSuper>::Error>::Rendering,
+                )?);
             }
         } else {
             sync = frame_result
@@ -1867,12 +2187,65 @@ fn send_screencopy_result<'a>(
     Ok(())
 }
 
+/// Area of `mirrored` shown by a mirror, in coordinates of its offscreen texture.
+///
+/// Falls back to the whole output, if the region is empty or the window isn't
+/// on the active workspace of `mirrored`.
+pub(crate) fn mirror_area(
+    shell: &Shell,
+    mirrored: &Output,
+    source: &MirrorSource,
+    output_config: &PostprocessOutputConfig,
+) -> Rectangle<i32, Physical> {
+    let full = Rectangle::from_size(output_config.size);
+    let area = match source {
+        MirrorSource::Output => None,
+        MirrorSource::Region {
+            x,
+            y,
+            width,
+            height,
+        } => Some(Rectangle::<i32, Logical>::new(
+            (*x, *y).into(),
+            (*width, *height).into(),
+        )),
+        MirrorSource::Window { app_id } => shell
+            .workspaces
+            .active(mirrored)
+            .and_then(|(_, workspace)| {
+                workspace
+                    .get_fullscreen_surfaces()
+                    .find(|fullscreen| fullscreen.surface.app_id() == *app_id)
+                    .map(|fullscreen| workspace.fullscreen_geometry_for(fullscreen))
+                    .or_else(|| {
+                        workspace
+                            .mapped()
+                            .find(|mapped| mapped.active_window().app_id() == *app_id)
+                            .and_then(|mapped| workspace.element_geometry(mapped))
+                    })
+            })
+            .map(|geometry| geometry.as_logical()),
+    };
+
+    area.map(|area| {
+        area.to_f64()
+            .to_physical_precise_round(output_config.fractional_scale)
+    })
+    .and_then(|area| area.intersection(full))
+    .filter(|area| !area.is_empty())
+    .unwrap_or(full)
+}
+
 fn postprocess_elements<'a>(
     renderer: &mut GlMultiRenderer<'a>,
     output: &Output,
     pre_postprocess_data: &PrePostprocessData,
     postprocess_state: &PostprocessState,
     screen_filter: &ScreenFilter,
+    hdr: bool,
+    gamma: GammaCurve,
+    scaling: MirrorScaling,
+    area: Rectangle<i32, Physical>,
 ) -> Vec<CosmicElement<GlMultiRenderer<'a>>> {
     let postprocess_texture_shader = Borrow::<GlesRenderer>::borrow(renderer.as_ref())
         .egl_context()
@@ -1880,11 +2253,26 @@ fn postprocess_elements<'a>(
         .get::<PostprocessShader>()
         .expect("OffscreenShader should be available through `init_shaders`");
 
+    let mut uniforms = vec![
+        Uniform::new("invert", if screen_filter.inverted { 1. } else { 0. }),
+        Uniform::new(
+            "color_mode",
+            screen_filter
+                .color_filter
+                .map(|val| val as u8 as f32)
+                .unwrap_or(0.),
+        ),
+        Uniform::new("hdr", if hdr { 1. } else { 0. }),
+        Uniform::new("sdr_white", SDR_WHITE_LEVEL),
+        Uniform::new("gamma_gain", gamma.gain),
+        Uniform::new("gamma_exponent", gamma.exponent),
+    ];
+
     let mut elements: [Option<TextureShaderElement>; 2] = [None, None];
     if let Some(cursor_texture) = postprocess_state.cursor_texture.as_ref() {
         let cursor_geometry = pre_postprocess_data.cursor_geometry.unwrap();
         let texture_elem = TextureRenderElement::from_texture_render_buffer(
-            cursor_geometry.loc.to_f64(),
+            (cursor_geometry.loc - area.loc).to_f64(),
             cursor_texture,
             None,
             Some(Rectangle::new(
@@ -1901,19 +2289,13 @@ fn postprocess_elements<'a>(
             Kind::Cursor,
         );
 
+        // the cursor is always composited into an 8-bit sRGB buffer
+        let mut uniforms = uniforms.clone();
+        uniforms.push(Uniform::new("linear", 0.));
         elements[0] = Some(TextureShaderElement::new(
             texture_elem,
             postprocess_texture_shader.0.clone(),
-            vec![
-                Uniform::new("invert", if screen_filter.inverted { 1. } else { 0. }),
-                Uniform::new(
-                    "color_mode",
-                    screen_filter
-                        .color_filter
-                        .map(|val| val as u8 as f32)
-                        .unwrap_or(0.),
-                ),
-            ],
+            uniforms,
         ));
     }
 
@@ -1921,33 +2303,23 @@ fn postprocess_elements<'a>(
         (0., 0.),
         &postprocess_state.texture,
         None,
-        Some(Rectangle::new(
-            Point::from((0., 0.)),
-            postprocess_state.output_config.size.to_logical(1).to_f64(),
-        )),
+        Some(area.to_logical(1).to_f64()),
         Some(
-            postprocess_state
-                .output_config
-                .size
+            area.size
                 .to_f64()
                 .to_logical(postprocess_state.output_config.fractional_scale)
                 .to_i32_round(),
         ),
         Kind::Unspecified,
     );
+    uniforms.push(Uniform::new(
+        "linear",
+        if pre_postprocess_data.linear { 1. } else { 0. },
+    ));
     elements[1] = Some(TextureShaderElement::new(
         texture_elem,
         postprocess_texture_shader.0.clone(),
-        vec![
-            Uniform::new("invert", if screen_filter.inverted { 1. } else { 0. }),
-            Uniform::new(
-                "color_mode",
-                screen_filter
-                    .color_filter
-                    .map(|val| val as u8 as f32)
-                    .unwrap_or(0.),
-            ),
-        ],
+        uniforms,
     ));
 
     constrain_render_elements(
@@ -1960,8 +2332,12 @@ fn postprocess_elements<'a>(
                 .as_logical()
                 .to_physical_precise_round(output.current_scale().fractional_scale()),
         ),
-        Rectangle::new(Point::from((0, 0)), postprocess_state.output_config.size),
-        ConstrainScaleBehavior::Fit,
+        Rectangle::from_size(area.size),
+        match scaling {
+            MirrorScaling::Letterbox => ConstrainScaleBehavior::Fit,
+            MirrorScaling::Stretch => ConstrainScaleBehavior::Stretch,
+            MirrorScaling::Crop => ConstrainScaleBehavior::Zoom,
+        },
         ConstrainAlign::CENTER,
         postprocess_state.output_config.fractional_scale,
     )
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Render statistics of a surface thread.
+//!
+//! Unlike [`Timings`](super::Timings), which keeps a window of recent frames for
+//! scheduling, these are cumulative counters since the output was connected. They
+//! are cheap enough to collect in release builds and are exported through the IPC socket.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Upper bounds of the buckets of frame time histograms, in milliseconds
+const FRAME_TIME_BOUNDS_MS: [f64; 10] = [2., 4., 6., 8., 12., 16.7, 25., 33.4, 50., 100.];
+
+/// Histogram of durations, in the style of prometheus but not cumulative
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// Upper bounds of the buckets, in milliseconds
+    pub bounds_ms: Vec<f64>,
+    /// Observations per bucket, with an additional last bucket for everything above
+    pub counts: Vec<u64>,
+    pub sum_ms: f64,
+}
+
+impl Histogram {
+    fn new(bounds_ms: &[f64]) -> Histogram {
+        Histogram {
+            bounds_ms: bounds_ms.to_vec(),
+            counts: vec![0; bounds_ms.len() + 1],
+            sum_ms: 0.,
+        }
+    }
+
+    pub fn observe(&mut self, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.;
+        let bucket = self
+            .bounds_ms
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(self.bounds_ms.len());
+        self.counts[bucket] += 1;
+        self.sum_ms += ms;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurfaceStats {
+    /// Time from the start of rendering to the presentation of a frame
+    pub frame_time: Histogram,
+    /// Time spent collecting elements and drawing them
+    pub render_time: Histogram,
+    /// Frames queued to the CRTC
+    pub frames: u64,
+    /// Frames the primary plane scanned out a client buffer directly
+    pub direct_scanout_frames: u64,
+    /// Frames that put at least one element on an overlay plane
+    pub overlay_plane_frames: u64,
+    /// Overlay planes used by the last frame
+    pub overlay_planes: usize,
+    /// Refresh cycles presentation happened later than estimated
+    pub missed_vblanks: u64,
+    pub vrr: bool,
+}
+
+impl Default for SurfaceStats {
+    fn default() -> Self {
+        SurfaceStats {
+            frame_time: Histogram::new(&FRAME_TIME_BOUNDS_MS),
+            render_time: Histogram::new(&FRAME_TIME_BOUNDS_MS),
+            frames: 0,
+            direct_scanout_frames: 0,
+            overlay_plane_frames: 0,
+            overlay_planes: 0,
+            missed_vblanks: 0,
+            vrr: false,
+        }
+    }
+}
+
+impl SurfaceStats {
+    pub fn frame_queued(&mut self, direct_scanout: bool, overlay_planes: usize, vrr: bool) {
+        self.frames += 1;
+        if direct_scanout {
+            self.direct_scanout_frames += 1;
+        }
+        if overlay_planes > 0 {
+            self.overlay_plane_frames += 1;
+        }
+        self.overlay_planes = overlay_planes;
+        self.vrr = vrr;
+    }
+
+    /// Records a presented frame, `estimated` and `presented` being monotonic timestamps.
+    pub fn frame_presented(
+        &mut self,
+        frame_time: Duration,
+        render_time: Duration,
+        estimated: Duration,
+        presented: Option<Duration>,
+        refresh_interval: Duration,
+    ) {
+        self.frame_time.observe(frame_time);
+        self.render_time.observe(render_time);
+
+        // without an estimate or a fixed refresh rate there is nothing to miss
+        if let Some(presented) = presented
+            && !estimated.is_zero()
+            && !refresh_interval.is_zero()
+            && !self.vrr
+        {
+            let late = presented.saturating_sub(estimated);
+            self.missed_vblanks +=
+                (late.as_secs_f64() / refresh_interval.as_secs_f64()).round() as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::SurfaceStats;
+
+    #[test]
+    fn late_presentation_counts_missed_vblanks() {
+        let interval = Duration::from_micros(16_667);
+        let mut stats = SurfaceStats::default();
+        let estimated = Duration::from_secs(1);
+
+        stats.frame_presented(
+            Duration::from_millis(10),
+            Duration::from_millis(3),
+            estimated,
+            Some(estimated + Duration::from_millis(1)),
+            interval,
+        );
+        assert_eq!(stats.missed_vblanks, 0);
+
+        stats.frame_presented(
+            Duration::from_millis(40),
+            Duration::from_millis(30),
+            estimated,
+            Some(estimated + interval * 2),
+            interval,
+        );
+        assert_eq!(stats.missed_vblanks, 2);
+        assert_eq!(stats.frame_time.count(), 2);
+        assert_eq!(stats.render_time.counts[1], 1);
+        assert_eq!(stats.render_time.counts[7], 1);
+    }
+}
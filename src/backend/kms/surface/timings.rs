@@ -2,11 +2,16 @@ use std::{collections::VecDeque, num::NonZeroU64, time::Duration};
 
 use smithay::{
     backend::drm::DrmNode,
+    reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::Type as ContentType,
     utils::{Clock, Monotonic, Time},
 };
 use tracing::{debug, error};
 
 const BASE_SAFETY_MARGIN: Duration = Duration::from_millis(3);
+/// Games trade the occasional missed frame for lower latency
+const GAME_SAFETY_MARGIN: Duration = Duration::from_millis(1);
+/// Missed frames are more noticeable in videos than latency
+const VIDEO_SAFETY_MARGIN: Duration = Duration::from_millis(5);
 const SAMPLE_TIME_WINDOW: usize = 5;
 
 pub struct Timings {
@@ -14,6 +19,7 @@ pub struct Timings {
     min_refresh_interval_ns: Option<NonZeroU64>,
     vrr: bool,
     vendor: Option<u32>,
+    safety_margin: Duration,
 
     pub pending_frame: Option<PendingFrame>,
     pub previous_frames: VecDeque<Frame>,
@@ -37,7 +43,7 @@ pub struct Frame {
 }
 
 impl Frame {
-    fn render_time(&self) -> Duration {
+    pub fn render_time(&self) -> Duration {
         self.render_duration_elements + self.render_duration_draw
     }
 
@@ -45,7 +51,7 @@ impl Frame {
         Time::elapsed(&self.render_start, self.presentation_submitted)
     }
 
-    fn frame_time(&self) -> Duration {
+    pub fn frame_time(&self) -> Duration {
         Time::elapsed(&self.render_start, self.presentation_presented)
     }
 }
@@ -87,6 +93,7 @@ impl Timings {
             min_refresh_interval_ns,
             vrr,
             vendor,
+            safety_margin: BASE_SAFETY_MARGIN,
 
             pending_frame: None,
             previous_frames: VecDeque::new(),
@@ -122,6 +129,15 @@ impl Timings {
         self.vrr
     }
 
+    /// Adjusts the safety margin of frame scheduling to the content shown fullscreen.
+    pub fn set_content_type(&mut self, content_type: ContentType) {
+        self.safety_margin = match content_type {
+            ContentType::Game => GAME_SAFETY_MARGIN,
+            ContentType::Video => VIDEO_SAFETY_MARGIN,
+            _ => BASE_SAFETY_MARGIN,
+        };
+    }
+
     pub fn start_render(&mut self, clock: &Clock<Monotonic>) {
         self.pending_frame = Some(PendingFrame {
             render_start: clock.now(),
@@ -357,7 +373,7 @@ impl Timings {
                 avg_submittime
             } else {
                 baseline
-            } + BASE_SAFETY_MARGIN,
+            } + self.safety_margin,
         );
 
         now >= deadline
@@ -382,10 +398,10 @@ impl Timings {
         }
 
         let Some(avg_submittime) = self.avg_submittime(SAMPLE_TIME_WINDOW) else {
-            return estimated_presentation_time.saturating_sub(baseline + BASE_SAFETY_MARGIN);
+            return estimated_presentation_time.saturating_sub(baseline + self.safety_margin);
         };
 
-        let margin = avg_submittime + BASE_SAFETY_MARGIN;
+        let margin = avg_submittime + self.safety_margin;
         estimated_presentation_time.saturating_sub(margin)
     }
 }
@@ -0,0 +1,319 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Outputs without a connector, created at runtime.
+//!
+//! Virtual outputs take part in the layout like any monitor, but are only rendered
+//! offscreen on the primary gpu. Their content is consumed through image-copy-capture,
+//! e.g. to stream a second desktop to another device over VNC or RDP.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        drm::DrmNode,
+        renderer::{
+            Bind, ContextId, Offscreen,
+            damage::{OutputDamageTracker, RenderOutputResult},
+            element::RenderElement,
+            gles::{GlesRenderbuffer, GlesTexture},
+        },
+    },
+    output::Output,
+    reexports::{
+        calloop::{
+            LoopHandle, RegistrationToken, ping,
+            timer::{TimeoutAction, Timer},
+        },
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+    },
+    utils::{Buffer as BufferCoords, Size},
+    wayland::{image_copy_capture::CaptureFailureReason, presentation::Refresh},
+};
+use tracing::{error, warn};
+
+use crate::{
+    backend::{
+        headless::OutputSpec,
+        render::{
+            self, CursorMode, ScreenFilterStorage,
+            element::{AsGlowRenderer, CosmicElement},
+        },
+    },
+    config::ScreenFilter,
+    shell::{CosmicMappedRenderElement, WorkspaceRenderElement},
+    state::{BackendData, Common, State},
+    utils::prelude::*,
+    wayland::handlers::image_copy_capture::SessionHolder,
+};
+
+use super::KmsState;
+
+#[derive(Debug)]
+pub struct VirtualOutput {
+    pub output: Output,
+    spec: OutputSpec,
+    damage_tracker: OutputDamageTracker,
+    screen_filter: ScreenFilterStorage,
+    buffer: Option<(ContextId<GlesTexture>, GlesRenderbuffer)>,
+    render: ping::Ping,
+    token: RegistrationToken,
+    loop_handle: LoopHandle<'static, State>,
+    rendered: bool,
+    dirty: bool,
+    pending: bool,
+}
+
+impl VirtualOutput {
+    pub(crate) fn new(
+        name: String,
+        spec: OutputSpec,
+        screen_filter: &ScreenFilter,
+        loop_handle: &LoopHandle<'static, State>,
+    ) -> Result<VirtualOutput> {
+        let output = spec.create_output(name);
+
+        let output_ref = output.clone();
+        let (ping, source) =
+            ping::make_ping().with_context(|| "Failed to create output event loop source")?;
+        let token = loop_handle
+            .insert_source(source, move |_, _, state| {
+                // only the kms backend keeps track of virtual outputs
+                let BackendData::Kms(kms) = &mut state.backend else {
+                    return;
+                };
+                if let Err(err) = kms.render_virtual_output(&output_ref, &mut state.common) {
+                    error!(?err, "Error rendering virtual output.");
+                }
+            })
+            .with_context(|| "Failed to add output to event loop")?;
+
+        // schedule first render
+        ping.ping();
+        Ok(VirtualOutput {
+            damage_tracker: OutputDamageTracker::from_output(&output),
+            output,
+            spec,
+            screen_filter: ScreenFilterStorage {
+                filter: screen_filter.clone(),
+                state: None,
+            },
+            buffer: None,
+            render: ping,
+            token,
+            loop_handle: loop_handle.clone(),
+            rendered: false,
+            dirty: false,
+            pending: true,
+        })
+    }
+
+    pub fn schedule_render(&mut self) {
+        self.dirty = true;
+        if !self.pending {
+            self.render.ping();
+        }
+    }
+
+    pub fn set_screen_filter(&mut self, screen_filter: &ScreenFilter) {
+        self.screen_filter.filter = screen_filter.clone();
+    }
+
+    /// Virtual outputs have a fixed size, scale and position may change
+    pub fn apply_config(&mut self, test_only: bool) -> Result<()> {
+        let mut config = self.output.config_mut();
+        let size = self.spec.size;
+        if config.mode.0 != (size.w, size.h) {
+            if !test_only {
+                config.mode = ((size.w, size.h), Some(self.spec.refresh as u32));
+            }
+            anyhow::bail!("Cannot change size of virtual output");
+        }
+        std::mem::drop(config);
+
+        if !test_only {
+            self.damage_tracker = OutputDamageTracker::from_output(&self.output);
+            self.rendered = false;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn render<R>(
+        &mut self,
+        node: Option<&DrmNode>,
+        renderer: &mut R,
+        common: &mut Common,
+    ) -> Result<()>
+    where
+        R: AsGlowRenderer + Bind<GlesRenderbuffer>,
+        R::TextureId: Send + Clone + 'static,
+        R::Error: Send + Sync + 'static,
+        CosmicElement<R>: RenderElement<R>,
+        CosmicMappedRenderElement<R>: RenderElement<R>,
+        WorkspaceRenderElement<R>: RenderElement<R>,
+    {
+        let context = renderer.glow_renderer().context_id();
+        self.buffer
+            .take_if(|(context_id, _)| *context_id != context);
+        if self.buffer.is_none() {
+            let buffer = Offscreen::<GlesRenderbuffer>::create_buffer(
+                renderer,
+                Fourcc::Abgr8888,
+                Size::<i32, BufferCoords>::from((self.spec.size.w, self.spec.size.h)),
+            )
+            .with_context(|| "Failed to allocate output buffer")?;
+            self.buffer = Some((context, buffer));
+            self.rendered = false;
+        }
+
+        let (_, buffer) = self.buffer.as_mut().unwrap();
+        let mut fb = renderer
+            .bind(buffer)
+            .with_context(|| "Failed to bind buffer")?;
+        let age = if self.rendered { 1 } else { 0 };
+        match render::render_output(
+            node,
+            renderer,
+            &mut fb,
+            &mut self.damage_tracker,
+            age,
+            &common.shell,
+            common.clock.now(),
+            &self.output,
+            CursorMode::All,
+            &mut self.screen_filter,
+            &common.event_loop_handle,
+        ) {
+            Ok(RenderOutputResult { damage, states, .. }) => {
+                self.rendered = true;
+                common.send_frames(&self.output, None);
+                common.update_primary_output(&self.output, &states);
+                if damage.is_some() {
+                    let mut output_presentation_feedback = common
+                        .shell
+                        .read()
+                        .take_presentation_feedback(&self.output, &states);
+                    output_presentation_feedback.presented(
+                        common.clock.now(),
+                        Refresh::Fixed(Duration::from_secs_f64(1_000.0 / self.spec.refresh as f64)),
+                        0,
+                        wp_presentation_feedback::Kind::empty(),
+                    );
+                }
+            }
+            Err(err) => {
+                self.rendered = false;
+                self.fail_captures();
+                anyhow::bail!("Rendering failed: {:?}", err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails capture frames that won't be rendered, instead of leaving clients waiting forever.
+    fn fail_captures(&self) {
+        for (_session, frame) in self.output.take_pending_frames() {
+            frame.fail(CaptureFailureReason::Unknown);
+        }
+    }
+
+    /// Emulates a vblank, rendering again afterwards if anything changed in the meantime.
+    fn schedule_vblank(&mut self) {
+        let output = self.output.clone();
+        let refresh = Duration::from_secs_f64(1_000.0 / self.spec.refresh as f64);
+        let timer =
+            self.loop_handle
+                .insert_source(Timer::from_duration(refresh), move |_, _, state| {
+                    if let Some(virtual_output) = state
+                        .backend
+                        .kms()
+                        .virtual_outputs
+                        .iter_mut()
+                        .find(|v| v.output == output)
+                    {
+                        if virtual_output.dirty {
+                            virtual_output.render.ping();
+                        } else {
+                            virtual_output.pending = false;
+                        }
+                    }
+                    TimeoutAction::Drop
+                });
+        if timer.is_err() {
+            warn!("Failed to schedule vblank timer");
+            self.pending = false;
+        }
+    }
+}
+
+impl Drop for VirtualOutput {
+    fn drop(&mut self) {
+        self.loop_handle.remove(self.token);
+    }
+}
+
+impl KmsState {
+    /// Creates a virtual output, named after the first free `VIRTUAL-<n>`, so it picks up
+    /// the stored configuration of a previous one.
+    pub fn create_virtual_output(
+        &mut self,
+        spec: OutputSpec,
+        screen_filter: &ScreenFilter,
+        loop_handle: &LoopHandle<'static, State>,
+    ) -> Result<Output> {
+        let name = (1..)
+            .map(|idx| format!("VIRTUAL-{idx}"))
+            .find(|name| {
+                !self
+                    .virtual_outputs
+                    .iter()
+                    .any(|v| v.output.name() == *name)
+            })
+            .unwrap();
+        let virtual_output = VirtualOutput::new(name, spec, screen_filter, loop_handle)?;
+        let output = virtual_output.output.clone();
+        self.virtual_outputs.push(virtual_output);
+        Ok(output)
+    }
+
+    pub fn remove_virtual_output(&mut self, output: &Output) -> Result<()> {
+        let idx = self
+            .virtual_outputs
+            .iter()
+            .position(|v| v.output == *output)
+            .with_context(|| format!("{} is not a virtual output", output.name()))?;
+        self.virtual_outputs.remove(idx);
+        Ok(())
+    }
+
+    fn render_virtual_output(&mut self, output: &Output, common: &mut Common) -> Result<()> {
+        let Some(virtual_output) = self
+            .virtual_outputs
+            .iter_mut()
+            .find(|v| v.output == *output)
+        else {
+            return Ok(());
+        };
+
+        let node = *self.primary_node.read().unwrap();
+        let res = match node {
+            Some(node) if self.session.is_active() => self
+                .api
+                .single_renderer(&node)
+                .with_context(|| "Failed to create renderer")
+                .and_then(|mut renderer| virtual_output.render(Some(&node), &mut renderer, common)),
+            // nothing to render with, clients have to capture again once the session is back
+            _ => {
+                virtual_output.fail_captures();
+                Ok(())
+            }
+        };
+
+        virtual_output.dirty = false;
+        virtual_output.pending = true;
+        virtual_output.schedule_vblank();
+        res
+    }
+}
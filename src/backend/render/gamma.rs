@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Gamma ramps of outputs.
+//!
+//! Ramps are either set by clients through wlr-gamma-control or derived from the
+//! color temperature of the night light, with client ramps taking precedence.
+//! Backends apply them through the gamma LUT of the CRTC, or approximate them in
+//! the postprocess shader as a [`GammaCurve`], if there is no LUT to use.
+
+use std::{sync::Mutex, time::Duration};
+
+use calloop::{
+    LoopHandle,
+    timer::{TimeoutAction, Timer},
+};
+use cosmic_comp_config::NightLightConfig;
+use smithay::output::Output;
+use tracing::error;
+
+use crate::state::State;
+
+/// Ramp size advertised for outputs, which apply ramps in the postprocess shader
+pub const SHADER_GAMMA_SIZE: usize = 256;
+/// Color temperature of the sRGB white point
+pub const NEUTRAL_TEMPERATURE: u32 = 6500;
+/// How often the night light is updated
+const NIGHT_LIGHT_INTERVAL: Duration = Duration::from_secs(60);
+const MINUTES_PER_DAY: i32 = 24 * 60;
+
+/// Per channel lookup table from input levels to output levels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GammaRamp {
+    pub red: Vec<u16>,
+    pub green: Vec<u16>,
+    pub blue: Vec<u16>,
+}
+
+impl GammaRamp {
+    /// Reads a ramp of `size` entries per channel, as sent through wlr-gamma-control:
+    /// all red values first, then green, then blue, in native byte order.
+    pub fn from_bytes(bytes: &[u8], size: usize) -> Option<GammaRamp> {
+        if size == 0 || bytes.len() != size * 3 * 2 {
+            return None;
+        }
+        let mut values = bytes
+            .chunks_exact(2)
+            .map(|chunk| u16::from_ne_bytes([chunk[0], chunk[1]]));
+        let mut channel = || values.by_ref().take(size).collect::<Vec<_>>();
+        Some(GammaRamp {
+            red: channel(),
+            green: channel(),
+            blue: channel(),
+        })
+    }
+
+    /// Linear ramp of `size` entries, shifting white to the color of `temperature`.
+    pub fn for_temperature(size: usize, temperature: u32) -> GammaRamp {
+        let whitepoint = whitepoint(temperature);
+        let channel = |gain: f32| {
+            (0..size)
+                .map(|idx| {
+                    let level = idx as f32 / (size.max(2) - 1) as f32;
+                    (level * gain * u16::MAX as f32).round() as u16
+                })
+                .collect()
+        };
+        GammaRamp {
+            red: channel(whitepoint[0]),
+            green: channel(whitepoint[1]),
+            blue: channel(whitepoint[2]),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.red.len()
+    }
+
+    /// Linearly interpolates the ramp to `size` entries.
+    pub fn resample(&self, size: usize) -> GammaRamp {
+        if size == self.len() {
+            return self.clone();
+        }
+        let channel = |values: &[u16]| {
+            (0..size)
+                .map(|idx| sample(values, idx as f32 / (size.max(2) - 1) as f32))
+                .collect()
+        };
+        GammaRamp {
+            red: channel(&self.red),
+            green: channel(&self.green),
+            blue: channel(&self.blue),
+        }
+    }
+}
+
+/// Value of the channel at `level` in `0.0..=1.0`
+fn sample(values: &[u16], level: f32) -> u16 {
+    let Some(last) = values.len().checked_sub(1) else {
+        return 0;
+    };
+    let pos = level.clamp(0., 1.) * last as f32;
+    let (low, high) = (pos.floor() as usize, pos.ceil() as usize);
+    let fract = pos - low as f32;
+    (values[low] as f32 * (1. - fract) + values[high] as f32 * fract).round() as u16
+}
+
+/// Per channel `gain * level^exponent`, approximating a [`GammaRamp`] in shaders
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GammaCurve {
+    pub gain: [f32; 3],
+    pub exponent: [f32; 3],
+}
+
+impl GammaCurve {
+    pub const IDENTITY: GammaCurve = GammaCurve {
+        gain: [1.; 3],
+        exponent: [1.; 3],
+    };
+
+    /// Fits the curve through the end and the middle of every channel of `ramp`.
+    pub fn fit(ramp: &GammaRamp) -> GammaCurve {
+        let channel = |values: &[u16]| {
+            let gain = sample(values, 1.) as f32 / u16::MAX as f32;
+            let mid = sample(values, 0.5) as f32 / u16::MAX as f32;
+            let exponent = if gain > 0. && mid > 0. {
+                (mid / gain).ln() / 0.5f32.ln()
+            } else {
+                1.
+            };
+            (gain, exponent)
+        };
+        let (red, green, blue) = (
+            channel(&ramp.red),
+            channel(&ramp.green),
+            channel(&ramp.blue),
+        );
+        GammaCurve {
+            gain: [red.0, green.0, blue.0],
+            exponent: [red.1, green.1, blue.1],
+        }
+    }
+}
+
+/// Channel gains turning sRGB white into the color of a black body at `temperature` Kelvin
+pub fn whitepoint(temperature: u32) -> [f32; 3] {
+    // approximation of the planckian locus by Tanner Helland, normalized to 6500K
+    fn rgb(temperature: u32) -> [f32; 3] {
+        let t = temperature.clamp(1000, 40000) as f32 / 100.;
+        let red = if t <= 66. {
+            255.
+        } else {
+            329.698_73 * (t - 60.).powf(-0.133_204_76)
+        };
+        let green = if t <= 66. {
+            99.470_8 * t.ln() - 161.119_57
+        } else {
+            288.122_17 * (t - 60.).powf(-0.075_514_85)
+        };
+        let blue = if t >= 66. {
+            255.
+        } else if t <= 19. {
+            0.
+        } else {
+            138.517_73 * (t - 10.).ln() - 305.044_8
+        };
+        [red, green, blue].map(|val| val.clamp(0., 255.) / 255.)
+    }
+
+    let color = rgb(temperature);
+    let neutral = rgb(NEUTRAL_TEMPERATURE);
+    [0, 1, 2].map(|idx| (color[idx] / neutral[idx]).min(1.))
+}
+
+/// Color temperature the night light asks for at `now`, `None` during the day.
+///
+/// The night may span midnight. If it starts when it ends, it is empty.
+pub fn night_light_temperature(config: &NightLightConfig, now: jiff::civil::Time) -> Option<u32> {
+    if !config.enabled {
+        return None;
+    }
+
+    let minutes = |(hour, minute): (u8, u8)| hour as i32 % 24 * 60 + minute as i32 % 60;
+    let start = minutes(config.start);
+    let length = (minutes(config.end) - start).rem_euclid(MINUTES_PER_DAY);
+    let elapsed =
+        (now.hour() as i32 * 60 + now.minute() as i32 - start).rem_euclid(MINUTES_PER_DAY);
+    if elapsed >= length {
+        return None;
+    }
+
+    // fade in after the start and out before the end
+    let transition = (config.transition as i32).min(length / 2);
+    let strength = if transition > 0 {
+        (elapsed.min(length - elapsed) as f32 / transition as f32).min(1.)
+    } else {
+        1.
+    };
+    let neutral = NEUTRAL_TEMPERATURE as f32;
+    let temperature = neutral + (config.temperature as f32 - neutral) * strength;
+    Some(temperature.round() as u32).filter(|temperature| *temperature != NEUTRAL_TEMPERATURE)
+}
+
+#[derive(Debug, Default)]
+struct OutputGamma(Mutex<OutputGammaState>);
+
+#[derive(Debug, Default)]
+struct OutputGammaState {
+    /// Size of the gamma LUT, zero if the output can't apply ramps
+    size: usize,
+    client: Option<GammaRamp>,
+    temperature: Option<u32>,
+}
+
+fn with_gamma<T>(output: &Output, f: impl FnOnce(&mut OutputGammaState) -> T) -> T {
+    let gamma = output
+        .user_data()
+        .get_or_insert_threadsafe(OutputGamma::default);
+    f(&mut gamma.0.lock().unwrap())
+}
+
+/// Ramp size clients have to use for `output`, if it supports gamma ramps at all
+pub fn gamma_size(output: &Output) -> Option<usize> {
+    with_gamma(output, |gamma| Some(gamma.size).filter(|size| *size > 0))
+}
+
+pub fn set_gamma_size(output: &Output, size: usize) {
+    with_gamma(output, |gamma| gamma.size = size);
+}
+
+/// Sets the ramp of a client, or resets it.
+pub fn set_client_gamma(output: &Output, ramp: Option<GammaRamp>) {
+    with_gamma(output, |gamma| gamma.client = ramp);
+}
+
+/// Sets the color temperature of the night light, returns if it changed.
+fn set_night_light(output: &Output, temperature: Option<u32>) -> bool {
+    with_gamma(output, |gamma| {
+        std::mem::replace(&mut gamma.temperature, temperature) != temperature
+    })
+}
+
+/// The ramp to apply to `output`, `None` for the identity.
+pub fn output_gamma(output: &Output) -> Option<GammaRamp> {
+    with_gamma(output, |gamma| {
+        gamma.client.clone().or_else(|| {
+            gamma.temperature.map(|temperature| {
+                GammaRamp::for_temperature(
+                    Some(gamma.size)
+                        .filter(|size| *size > 0)
+                        .unwrap_or(SHADER_GAMMA_SIZE),
+                    temperature,
+                )
+            })
+        })
+    })
+}
+
+/// Applies the current night light temperature to all outputs.
+pub fn update_night_light(state: &mut State) {
+    let temperature = night_light_temperature(
+        &state.common.config.cosmic_conf.night_light,
+        jiff::Zoned::now().time(),
+    );
+    let outputs = state
+        .common
+        .shell
+        .read()
+        .outputs()
+        .cloned()
+        .collect::<Vec<_>>();
+    for output in outputs {
+        if set_night_light(&output, temperature) {
+            state.backend.update_gamma(&output);
+        }
+    }
+}
+
+/// Periodically updates the night light.
+pub fn schedule_night_light(handle: &LoopHandle<'static, State>) {
+    if let Err(err) = handle.insert_source(Timer::immediate(), |_, _, state| {
+        update_night_light(state);
+        TimeoutAction::ToDuration(NIGHT_LIGHT_INTERVAL)
+    }) {
+        error!(?err, "Failed to schedule night light");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use jiff::civil::time;
+
+    use super::*;
+
+    fn night(start: (u8, u8), end: (u8, u8), transition: u32) -> NightLightConfig {
+        NightLightConfig {
+            enabled: true,
+            temperature: 4000,
+            start,
+            end,
+            transition,
+        }
+    }
+
+    #[test]
+    fn night_crossing_midnight() {
+        let config = night((20, 0), (7, 0), 30);
+        let at = |hour, minute| night_light_temperature(&config, time(hour, minute, 0, 0));
+        assert_eq!(at(19, 59), None);
+        assert_eq!(at(20, 0), None);
+        assert_eq!(at(20, 15), Some(5250));
+        assert_eq!(at(20, 30), Some(4000));
+        assert_eq!(at(0, 0), Some(4000));
+        assert_eq!(at(6, 45), Some(5250));
+        assert_eq!(at(7, 0), None);
+        assert_eq!(at(12, 0), None);
+
+        let disabled = NightLightConfig {
+            enabled: false,
+            ..config
+        };
+        assert_eq!(night_light_temperature(&disabled, time(0, 0, 0, 0)), None);
+    }
+
+    #[test]
+    fn transition_longer_than_half_the_night() {
+        // fades in and out within half an hour each, reaching the temperature in between
+        let config = night((22, 0), (23, 0), 60);
+        let at = |minute| night_light_temperature(&config, time(22, minute, 0, 0));
+        assert_eq!(at(15), Some(5250));
+        assert_eq!(at(30), Some(4000));
+        assert_eq!(at(45), Some(5250));
+    }
+
+    #[test]
+    fn empty_night() {
+        let config = night((22, 0), (22, 0), 0);
+        for hour in [0, 10, 21, 22, 23] {
+            assert_eq!(night_light_temperature(&config, time(hour, 0, 0, 0)), None);
+        }
+    }
+}
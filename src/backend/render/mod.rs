@@ -21,6 +21,9 @@ use crate::{
                 SurfaceRenderElement,
                 blur_effect::BlurShaders,
                 clipped_surface::{CLIPPING_SHADER, ClippingShader},
+                color_conversion::{
+                    LINEAR_TEXTURE_SHADER, LinearLight, LinearTextureShader, decode_srgb,
+                },
                 push_render_elements_from_surface_tree,
             },
         },
@@ -88,6 +91,7 @@ use smithay_egui::EguiState;
 pub mod animations;
 pub mod cursor;
 pub mod element;
+pub mod gamma;
 pub mod shadow;
 pub mod wayland;
 use self::element::{AsGlowRenderer, CosmicElement};
@@ -127,8 +131,11 @@ pub static CLEAR_COLOR: Color32F = Color32F::new(0.153, 0.161, 0.165, 1.0);
 pub static OUTLINE_SHADER: &str = include_str!("./shaders/rounded_outline.frag");
 pub static RECTANGLE_SHADER: &str = include_str!("./shaders/rounded_rectangle.frag");
 pub static POSTPROCESS_SHADER: &str = include_str!("./shaders/offscreen.frag");
+/// Luminance of sRGB white on HDR outputs in cd/m², after ITU-R BT.2408
+pub static SDR_WHITE_LEVEL: f32 = 203.;
 pub static GROUP_COLOR: [f32; 3] = [0.788, 0.788, 0.788];
 pub static ACTIVE_GROUP_COLOR: [f32; 3] = [0.58, 0.922, 0.922];
+pub static MODAL_BACKDROP_ALPHA: f32 = 0.5;
 
 pub struct IndicatorShader(pub GlesPixelProgram);
 
@@ -141,6 +148,8 @@ pub enum Usage {
     PotentialGroupIndicator,
     SnappingIndicator,
     Border,
+    ModalBackdrop,
+    Bell,
 }
 
 #[derive(Clone)]
@@ -190,6 +199,7 @@ struct IndicatorSettings {
     alpha: f32,
     color: [f32; 3],
     scale: f64,
+    linear: bool,
 }
 type IndicatorCache = RefCell<HashMap<Key, (IndicatorSettings, PixelShaderElement)>>;
 
@@ -241,12 +251,14 @@ impl IndicatorShader {
         scale: f64,
         color: [f32; 3],
     ) -> PixelShaderElement {
+        let linear = LinearLight::get(renderer);
         let settings = IndicatorSettings {
             thickness,
             outer_radius,
             alpha,
             scale,
             color,
+            linear,
         };
 
         let user_data = Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
@@ -269,6 +281,7 @@ impl IndicatorShader {
         {
             let thickness: f32 = ((thickness as f64 * scale).ceil() / scale) as f32;
             let shader = Self::get(renderer);
+            let color = if linear { decode_srgb(color) } else { color };
 
             let elem = PixelShaderElement::new(
                 shader,
@@ -312,6 +325,7 @@ struct BackdropSettings {
     radius: f32,
     alpha: f32,
     color: [f32; 3],
+    linear: bool,
 }
 type BackdropCache = RefCell<HashMap<Key, (BackdropSettings, PixelShaderElement)>>;
 
@@ -334,10 +348,12 @@ impl BackdropShader {
         alpha: f32,
         color: [f32; 3],
     ) -> PixelShaderElement {
+        let linear = LinearLight::get(renderer);
         let settings = BackdropSettings {
             radius,
             alpha,
             color,
+            linear,
         };
 
         let user_data = Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
@@ -359,6 +375,7 @@ impl BackdropShader {
             .is_none()
         {
             let shader = Self::get(renderer);
+            let color = if linear { decode_srgb(color) } else { color };
 
             let elem = PixelShaderElement::new(
                 shader,
@@ -419,6 +436,11 @@ pub fn init_shaders(renderer: &mut GlesRenderer) -> Result<(), GlesError> {
         &[
             UniformName::new("invert", UniformType::_1f),
             UniformName::new("color_mode", UniformType::_1f),
+            UniformName::new("linear", UniformType::_1f),
+            UniformName::new("hdr", UniformType::_1f),
+            UniformName::new("sdr_white", UniformType::_1f),
+            UniformName::new("gamma_gain", UniformType::_3f),
+            UniformName::new("gamma_exponent", UniformType::_3f),
         ],
     )?;
     let clipping_shader = renderer.compile_custom_texture_shader(
@@ -428,8 +450,14 @@ pub fn init_shaders(renderer: &mut GlesRenderer) -> Result<(), GlesError> {
             UniformName::new("corner_radius", UniformType::_4f),
             UniformName::new("input_to_geo", UniformType::Matrix3x3),
             UniformName::new("noise", UniformType::_1f),
+            UniformName::new("transfer", UniformType::_1f),
+            UniformName::new("to_srgb_primaries", UniformType::Matrix3x3),
+            UniformName::new("luminance_scale", UniformType::_1f),
+            UniformName::new("linear", UniformType::_1f),
         ],
     )?;
+    let linear_texture_shader =
+        renderer.compile_custom_texture_shader(LINEAR_TEXTURE_SHADER, &[])?;
     let shadow_shader = renderer.compile_custom_pixel_shader(
         SHADOW_SHADER,
         &[
@@ -462,6 +490,9 @@ pub fn init_shaders(renderer: &mut GlesRenderer) -> Result<(), GlesError> {
         .user_data()
         .insert_if_missing(|| ShadowShader(shadow_shader));
     egl_context.user_data().insert_if_missing(|| blur_shaders);
+    egl_context
+        .user_data()
+        .insert_if_missing(|| LinearTextureShader(linear_texture_shader));
 
     Ok(())
 }
@@ -818,6 +849,13 @@ where
         )
     };
 
+    shell.render_flashes(renderer, output, &mut |elem| {
+        if let Some(elem) = crop_to_output(Into::<CosmicMappedRenderElement<R>>::into(elem).into())
+        {
+            elements.push(elem.into())
+        }
+    });
+
     render_input_order::<()>(&shell, output, previous, current, element_filter, |stage| {
         match stage {
             Stage::ZoomUI => {
@@ -1106,6 +1144,7 @@ pub struct PostprocessState {
     pub cursor_texture: Option<TextureRenderBuffer<GlesTexture>>,
     pub cursor_damage_tracker: Option<OutputDamageTracker>,
     pub output_config: PostprocessOutputConfig,
+    pub format: Fourcc,
 }
 
 impl PostprocessState {
@@ -1137,6 +1176,7 @@ impl PostprocessState {
             cursor_texture: None,
             cursor_damage_tracker: None,
             output_config,
+            format,
         })
     }
 
@@ -1363,6 +1403,11 @@ where
                                 .map(|val| val as u8 as f32)
                                 .unwrap_or(0.),
                         ),
+                        Uniform::new("linear", 0.),
+                        Uniform::new("hdr", 0.),
+                        Uniform::new("sdr_white", SDR_WHITE_LEVEL),
+                        Uniform::new("gamma_gain", gamma::GammaCurve::IDENTITY.gain),
+                        Uniform::new("gamma_exponent", gamma::GammaCurve::IDENTITY.exponent),
                     ],
                 );
                 constrain_render_elements(
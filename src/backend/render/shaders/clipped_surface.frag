@@ -27,6 +27,16 @@ uniform vec4 corner_radius;
 uniform mat3 input_to_geo;
 uniform float noise;
 
+// transfer function to decode tagged content with, 0.0 to leave it as is:
+// 1.0 sRGB, 2.0 gamma 2.2, 3.0 BT.1886, 4.0 linear, 5.0 PQ
+uniform float transfer;
+// linear conversion into BT.709 primaries
+uniform mat3 to_srgb_primaries;
+// luminance of a decoded 1.0 relative to sRGB white
+uniform float luminance_scale;
+// 1.0 to leave decoded content in linear light, when the output composites in it
+uniform float linear;
+
 float rounding_alpha(vec2 coords, vec2 size) {
     vec2 center;
     float radius;
@@ -52,6 +62,37 @@ float rounding_alpha(vec2 coords, vec2 size) {
     return 1.0 - smoothstep(radius - half_px, radius + half_px, dist);
 }
 
+vec3 pq_decode(vec3 color) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+
+    vec3 p = pow(clamp(color, 0.0, 1.0), vec3(1.0 / m2));
+    return pow(max(p - c1, 0.0) / (c2 - c3 * p), vec3(1.0 / m1));
+}
+
+vec3 decode(vec3 color) {
+    if (transfer == 1.0) {
+        return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+    } else if (transfer == 2.0) {
+        return pow(color, vec3(2.2));
+    } else if (transfer == 3.0) {
+        return pow(color, vec3(2.4));
+    } else if (transfer == 5.0) {
+        return pq_decode(color);
+    }
+    return color;
+}
+
+// sRGB, extended beyond the unit range by mirroring and continuing the curve
+vec3 srgb_encode_extended(vec3 color) {
+    vec3 value = abs(color);
+    vec3 encoded = mix(value * 12.92, 1.055 * pow(value, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, value));
+    return sign(color) * encoded;
+}
+
 float hash(vec2 p) {
     vec3 p3 = fract(vec3(p.xyx) * 727.727);
     p3 += dot(p3, p3.xyz + 33.33);
@@ -71,6 +112,16 @@ void main() {
         discard;
     }
 
+    if (transfer > 0.0) {
+        vec3 decoded = decode(max(color.rgb / color.a, 0.0));
+        vec3 converted = to_srgb_primaries * (decoded * luminance_scale);
+        if (linear == 1.0) {
+            color.rgb = converted * color.a;
+        } else {
+            color.rgb = srgb_encode_extended(converted) * color.a;
+        }
+    }
+
     vec3 coords_geo = input_to_geo * vec3(v_coords, 1.0);
     if (coords_geo.x < 0.0 || 1.0 < coords_geo.x || coords_geo.y < 0.0 || 1.0 < coords_geo.y) {
         // Clip outside geometry.
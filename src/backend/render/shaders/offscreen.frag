@@ -22,6 +22,45 @@ uniform float tint;
 
 uniform float invert;
 uniform float color_mode;
+// 1.0 if the texture holds linear light, as composited for HDR10 outputs
+uniform float linear;
+// 1.0 to encode for HDR10 outputs
+uniform float hdr;
+// luminance of sRGB white in cd/m²
+uniform float sdr_white;
+// gamma ramp approximated as `gain * color^exponent`
+uniform vec3 gamma_gain;
+uniform vec3 gamma_exponent;
+
+// BT.709 to BT.2020 primaries, in linear light
+const mat3 BT709_TO_BT2020 = mat3(
+    0.6274, 0.0691, 0.0164,
+    0.3293, 0.9195, 0.0880,
+    0.0433, 0.0114, 0.8956
+);
+
+// sRGB, extended beyond the unit range by mirroring and continuing the curve
+vec3 srgb_to_linear(vec3 color) {
+    vec3 value = abs(color);
+    return sign(color) * mix(value / 12.92, pow((value + 0.055) / 1.055, vec3(2.4)), step(0.04045, value));
+}
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 value = abs(color);
+    return sign(color) * mix(value * 12.92, 1.055 * pow(value, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, value));
+}
+
+// SMPTE ST 2084, `color` normalized to 10000 cd/m²
+vec3 pq_encode(vec3 color) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+
+    vec3 p = pow(clamp(color, 0.0, 1.0), vec3(m1));
+    return pow((c1 + c2 * p) / (1.0 + c3 * p), vec3(m2));
+}
 
 void main() {
     vec4 color = texture2D(tex, v_coords);
@@ -35,6 +74,11 @@ void main() {
     // un-multiply
     color.rgb /= color.a;
 
+    // filters and gamma ramps apply to encoded values
+    if (linear == 1.0) {
+        color.rgb = linear_to_srgb(color.rgb);
+    }
+
     // First invert then filter
 
     if (invert == 1.0) {
@@ -83,6 +127,15 @@ void main() {
         color.rgb += correction;
     }
 
+    // values beyond the unit range only reach this on HDR10 outputs, which composite
+    // highlights and wide gamut colors of tagged surfaces in linear light into a float buffer
+    color.rgb = sign(color.rgb) * gamma_gain * pow(abs(color.rgb), gamma_exponent);
+
+    if (hdr == 1.0) {
+        vec3 linear = BT709_TO_BT2020 * srgb_to_linear(color.rgb);
+        color.rgb = pq_encode(linear * sdr_white / 10000.0);
+    }
+
     // re-multiply
     color.rgb *= color.a;
 
@@ -27,7 +27,13 @@ use smithay::{
 use tracing::trace;
 
 use crate::{
-    backend::render::{element::AsGlowRenderer, wayland::clipped_surface::ClippingShader},
+    backend::render::{
+        element::AsGlowRenderer,
+        wayland::{
+            clipped_surface::ClippingShader,
+            color_conversion::{ColorConversion, LinearLight},
+        },
+    },
     wayland::handlers::background_effect::ComputedBlurRegionCachedState,
 };
 
@@ -157,6 +163,8 @@ pub struct BlurElement {
     offset: f64,
     passes: usize,
     uniforms: Vec<Uniform<'static>>,
+    /// Format of the captured framebuffer, a float one when compositing in linear light
+    format: Fourcc,
 }
 
 impl BlurElement {
@@ -242,7 +250,7 @@ impl BlurElement {
         };
         let input_to_geo = Mat3::from(geo_scale * geo_translation);
 
-        let uniforms = vec![
+        let mut uniforms = vec![
             Uniform::new("geo_size", (geometry.size.w as f32, geometry.size.h as f32)),
             Uniform::new(
                 "corner_radius",
@@ -262,6 +270,13 @@ impl BlurElement {
             ),
             Uniform::new("noise", UniformValue::_1f(NOISE)),
         ];
+        // the captured framebuffer is in the compositing space already
+        uniforms.extend(ColorConversion::uniforms(None, false));
+        let format = if LinearLight::get(renderer) {
+            Fourcc::Abgr16161616f
+        } else {
+            Fourcc::Abgr8888
+        };
 
         let geometry = extended_geo.to_logical(output_scale);
         let extended_offset = Point::<f64, Physical>::new(radius, radius).to_logical(output_scale);
@@ -298,6 +313,7 @@ impl BlurElement {
             render_shader: ClippingShader::get(renderer),
             offset: state.offset,
             passes: state.passes,
+            format,
             region: region
                 .iter()
                 .cloned()
@@ -386,6 +402,7 @@ where
         let mut texture_entry = texture_ref.lock().unwrap();
         if texture_entry.as_ref().is_some_and(|tex| {
             tex.size() != tex_size
+                || tex.format() != Some(self.format)
                 || R::tex_to_gl(
                     &renderer.as_ref().context_id(),
                     texture_entry.as_ref().unwrap(),
@@ -397,7 +414,7 @@ where
         if texture_entry.is_none() {
             let gl_texture = renderer
                 .as_mut()
-                .create_buffer(Fourcc::Abgr8888, tex_size)
+                .create_buffer(self.format, tex_size)
                 .map_err(R::from_gles_error)?;
             *texture_entry = Some(R::tex_from_gl(&renderer.as_ref().context_id(), gl_texture));
         }
@@ -409,7 +426,7 @@ where
         .unwrap();
         let mut off_texture = renderer
             .as_mut()
-            .create_buffer(Fourcc::Abgr8888, tex_size)
+            .create_buffer(self.format, tex_size)
             .map_err(R::from_gles_error)?;
         std::mem::drop(renderer);
 
@@ -488,6 +505,7 @@ fn blit_from_active_fb(
     to_texture: &mut GlesTexture,
 ) -> Result<SyncPoint, GlesError> {
     let tex_size = to_texture.size();
+    let format = to_texture.format().unwrap_or(Fourcc::Abgr8888);
     let tex_size_phys = tex_size.to_logical(1, Transform::Normal).to_physical(1);
     let fb_size = frame.output_size();
 
@@ -514,9 +532,7 @@ fn blit_from_active_fb(
         let dst_buffer = dst_phys
             .to_logical(1)
             .to_buffer(1, Transform::Normal, &Size::default());
-        let mut tmp_texture = renderer
-            .as_mut()
-            .create_buffer(Fourcc::Abgr8888, dst_buffer.size)?;
+        let mut tmp_texture = renderer.as_mut().create_buffer(format, dst_buffer.size)?;
         let mut fb_tmp = renderer.as_mut().bind(&mut tmp_texture)?;
         std::mem::drop(renderer);
         frame.wait(&sync)?;
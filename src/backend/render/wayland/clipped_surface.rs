@@ -17,7 +17,10 @@ use smithay::{
     utils::user_data::UserDataMap,
 };
 
-use crate::backend::render::element::AsGlowRenderer;
+use crate::backend::render::{
+    element::AsGlowRenderer,
+    wayland::color_conversion::{ColorConversion, LinearLight},
+};
 
 pub static CLIPPING_SHADER: &str = include_str!("../shaders/clipped_surface.frag");
 pub struct ClippingShader(pub GlesTexProgram);
@@ -53,6 +56,7 @@ where
         scale: Scale<f64>,
         geometry: Rectangle<f64, Logical>,
         radius: [u8; 4],
+        conversion: Option<&ColorConversion>,
     ) -> Self
     where
         R: AsGlowRenderer,
@@ -94,7 +98,7 @@ where
             transform_matrix * geo_scale * geo_translation * buf_scale * buf_translation,
         );
 
-        let uniforms = vec![
+        let mut uniforms = vec![
             Uniform::new("geo_size", (geometry.size.w as f32, geometry.size.h as f32)),
             Uniform::new(
                 "corner_radius",
@@ -114,6 +118,10 @@ where
             ),
             Uniform::new("noise", UniformValue::_1f(0.0)),
         ];
+        uniforms.extend(ColorConversion::uniforms(
+            conversion,
+            LinearLight::get(renderer),
+        ));
 
         Self {
             inner: elem,
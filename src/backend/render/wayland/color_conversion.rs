@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Decoding of surfaces tagged through `wp_color_management_v1`.
+//!
+//! Content is composited with BT.709 primaries and sRGB white at 1.0, sRGB
+//! encoded on most outputs. HDR10 outputs composite in linear light into a
+//! float buffer instead, which their postprocess pass encodes with PQ.
+//! Renderers of those outputs are marked with [`LinearLight`], so textures and
+//! colors get decoded on the way in, see [`LinearLightElement`].
+//!
+//! Tagged surfaces are converted into the compositing space by the surface
+//! shader, with values beyond the unit range for highlights and wide gamut
+//! colors. Those survive on HDR10 outputs and get clipped on all others.
+
+use std::{
+    borrow::{Borrow, BorrowMut},
+    cell::Cell,
+};
+
+use glam::{Mat3, Vec3};
+use smithay::{
+    backend::renderer::{
+        Color32F, Renderer,
+        element::{Element, Id, Kind, RenderElement, UnderlyingStorage},
+        gles::{GlesFrame, GlesRenderer, GlesTexProgram, Uniform, UniformValue},
+        utils::{CommitCounter, DamageSet, OpaqueRegions},
+    },
+    reexports::wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::{
+        Primaries, TransferFunction,
+    },
+    utils::{Buffer, Physical, Point, Rectangle, Scale, Transform, user_data::UserDataMap},
+    wayland::compositor::SurfaceData,
+};
+
+use crate::{
+    backend::render::element::AsGlowRenderer,
+    wayland::protocols::color_management::{
+        Chromaticities, ColorManagementCachedState, ColorParams, SRGB_PRIMARIES,
+    },
+};
+
+pub static LINEAR_TEXTURE_SHADER: &str = include_str!("../shaders/linear_texture.frag");
+/// Texture shader decoding sRGB content into linear light
+pub struct LinearTextureShader(pub GlesTexProgram);
+
+/// Marks a renderer compositing in linear light, stored in the user data of its EGL context.
+///
+/// Each KMS output renders with its own context, so this is set per output.
+#[derive(Debug, Default)]
+pub struct LinearLight(Cell<bool>);
+
+impl LinearLight {
+    pub fn get<R: AsGlowRenderer>(renderer: &R) -> bool {
+        Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
+            .egl_context()
+            .user_data()
+            .get::<LinearLight>()
+            .is_some_and(|linear| linear.0.get())
+    }
+
+    pub fn set<R: AsGlowRenderer>(renderer: &R, linear: bool) {
+        let user_data = Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
+            .egl_context()
+            .user_data();
+        user_data.insert_if_missing(LinearLight::default);
+        user_data.get::<LinearLight>().unwrap().0.set(linear);
+    }
+}
+
+/// Luminance of a PQ encoded 1.0 in cd/m²
+const PQ_MAX_LUMINANCE: f32 = 10000.;
+
+/// Conversion of a surface into the compositing space
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorConversion {
+    /// Transfer function to decode, numbered like in the surface shader
+    transfer: f32,
+    /// Linear light conversion into BT.709 primaries
+    to_srgb_primaries: Mat3,
+    /// Luminance of a decoded 1.0 relative to sRGB white
+    luminance_scale: f32,
+}
+
+impl ColorConversion {
+    /// Conversion of the image description committed for a surface, `None` for sRGB content.
+    pub fn for_surface(states: &SurfaceData) -> Option<ColorConversion> {
+        let description = states
+            .cached_state
+            .get::<ColorManagementCachedState>()
+            .current()
+            .image_description
+            .clone()?;
+        ColorConversion::new(&description.params)
+    }
+
+    pub fn new(params: &ColorParams) -> Option<ColorConversion> {
+        if params.primaries == Primaries::Srgb
+            && matches!(
+                params.tf,
+                TransferFunction::Srgb | TransferFunction::Gamma22
+            )
+        {
+            return None;
+        }
+
+        let transfer = match params.tf {
+            TransferFunction::Srgb => 1.,
+            TransferFunction::Gamma22 => 2.,
+            TransferFunction::Bt1886 => 3.,
+            TransferFunction::ExtLinear => 4.,
+            TransferFunction::St2084Pq => 5.,
+            _ => return None,
+        };
+        let luminances = params.luminances();
+        let max_luminance = if params.tf == TransferFunction::St2084Pq {
+            PQ_MAX_LUMINANCE
+        } else {
+            luminances.max as f32
+        };
+
+        Some(ColorConversion {
+            transfer,
+            to_srgb_primaries: rgb_to_xyz(SRGB_PRIMARIES).inverse()
+                * rgb_to_xyz(params.primaries()),
+            luminance_scale: max_luminance / luminances.reference.max(1) as f32,
+        })
+    }
+
+    /// Uniforms of the surface shader for content tagged with `conversion`.
+    ///
+    /// Untagged content is left as is, unless it has to be decoded for compositing in linear light.
+    pub fn uniforms(conversion: Option<&ColorConversion>, linear: bool) -> [Uniform<'static>; 4] {
+        let (transfer, matrix, scale) = match conversion {
+            Some(conversion) => (
+                conversion.transfer,
+                conversion.to_srgb_primaries,
+                conversion.luminance_scale,
+            ),
+            None if linear => (1., Mat3::IDENTITY, 1.),
+            None => (0., Mat3::IDENTITY, 1.),
+        };
+        [
+            Uniform::new("transfer", transfer),
+            Uniform::new(
+                "to_srgb_primaries",
+                UniformValue::Matrix3x3 {
+                    matrices: vec![matrix.to_cols_array()],
+                    transpose: false,
+                },
+            ),
+            Uniform::new("luminance_scale", scale),
+            Uniform::new("linear", if linear { 1. } else { 0. }),
+        ]
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Decodes an sRGB color into linear light.
+pub fn decode_srgb(color: [f32; 3]) -> [f32; 3] {
+    color.map(srgb_to_linear)
+}
+
+/// Decodes an sRGB clear color into linear light.
+pub fn decode_srgb_color(color: Color32F) -> Color32F {
+    let [r, g, b] = decode_srgb([color.r(), color.g(), color.b()]);
+    Color32F::new(r, g, b, color.a())
+}
+
+/// Element drawn by a renderer that might be compositing in linear light.
+///
+/// If the renderer is marked with [`LinearLight`], textures drawn with the default
+/// program get decoded from sRGB. Elements with their own programs handle it themselves.
+pub struct LinearLightElement<E> {
+    inner: E,
+    program: Option<GlesTexProgram>,
+}
+
+impl<E> LinearLightElement<E> {
+    pub fn new<R: AsGlowRenderer>(renderer: &R, inner: E) -> Self {
+        let program = LinearLight::get(renderer).then(|| {
+            Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
+                .egl_context()
+                .user_data()
+                .get::<LinearTextureShader>()
+                .expect("Custom Shaders not initialized")
+                .0
+                .clone()
+        });
+        LinearLightElement { inner, program }
+    }
+}
+
+impl<E: Element> Element for LinearLightElement<E> {
+    fn id(&self) -> &Id {
+        self.inner.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner.current_commit()
+    }
+
+    fn location(&self, scale: Scale<f64>) -> Point<i32, Physical> {
+        self.inner.location(scale)
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner.geometry(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner.src()
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner.transform()
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+    ) -> DamageSet<i32, Physical> {
+        self.inner.damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        self.inner.opaque_regions(scale)
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+
+    fn is_framebuffer_effect(&self) -> bool {
+        self.inner.is_framebuffer_effect()
+    }
+}
+
+impl<R, E> RenderElement<R> for LinearLightElement<E>
+where
+    R: AsGlowRenderer + Renderer,
+    E: RenderElement<R>,
+{
+    fn draw(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+        cache: Option<&UserDataMap>,
+    ) -> Result<(), R::Error> {
+        let Some(program) = self.program.as_ref() else {
+            return self
+                .inner
+                .draw(frame, src, dst, damage, opaque_regions, cache);
+        };
+
+        BorrowMut::<GlesFrame>::borrow_mut(<R as AsGlowRenderer>::glow_frame_mut(frame))
+            .override_default_tex_program(program.clone(), Vec::new());
+        let res = self
+            .inner
+            .draw(frame, src, dst, damage, opaque_regions, cache);
+        BorrowMut::<GlesFrame>::borrow_mut(<R as AsGlowRenderer>::glow_frame_mut(frame))
+            .clear_tex_program_override();
+        res
+    }
+
+    fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
+        self.inner.underlying_storage(renderer)
+    }
+
+    fn capture_framebuffer(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        cache: &UserDataMap,
+    ) -> Result<(), R::Error> {
+        self.inner.capture_framebuffer(frame, src, dst, cache)
+    }
+}
+
+/// Matrix from linear RGB with the given primaries to CIE XYZ, normalized to Y = 1 for white.
+fn rgb_to_xyz(chromaticities: Chromaticities) -> Mat3 {
+    let xyz = |(x, y): (i32, i32)| {
+        let (x, y) = (x as f32 / 1_000_000., y as f32 / 1_000_000.);
+        Vec3::new(x / y, 1., (1. - x - y) / y)
+    };
+    let [red, green, blue, white] = chromaticities.map(xyz);
+    let primaries = Mat3::from_cols(red, green, blue);
+    let scale = primaries.inverse() * white;
+    Mat3::from_cols(red * scale.x, green * scale.y, blue * scale.z)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn srgb_params(primaries: Primaries, tf: TransferFunction) -> ColorParams {
+        ColorParams {
+            primaries,
+            tf,
+            ..ColorParams::srgb()
+        }
+    }
+
+    #[test]
+    fn srgb_is_left_as_is() {
+        assert_eq!(ColorConversion::new(&ColorParams::srgb()), None);
+        assert_eq!(
+            ColorConversion::new(&srgb_params(Primaries::Srgb, TransferFunction::Srgb)),
+            None
+        );
+    }
+
+    #[test]
+    fn bt2020_is_converted_to_bt709() {
+        let conversion =
+            ColorConversion::new(&srgb_params(Primaries::Bt2020, TransferFunction::Gamma22))
+                .unwrap();
+        // ITU-R BT.2087, inverted
+        let expected = Mat3::from_cols(
+            Vec3::new(1.6605, -0.1246, -0.0182),
+            Vec3::new(-0.5876, 1.1329, -0.1006),
+            Vec3::new(-0.0728, -0.0083, 1.1187),
+        );
+        assert!(
+            conversion.to_srgb_primaries.abs_diff_eq(expected, 0.001),
+            "{:?}",
+            conversion.to_srgb_primaries
+        );
+        assert_eq!(conversion.luminance_scale, 1.);
+    }
+
+    #[test]
+    fn srgb_decodes_to_linear_light() {
+        let [black, grey, white] = decode_srgb([0., 0.5, 1.]);
+        assert_eq!(black, 0.);
+        assert!((grey - 0.214).abs() < 0.001, "{grey}");
+        assert!((white - 1.).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn pq_is_relative_to_reference_white() {
+        let conversion = ColorConversion::new(&ColorParams::hdr10(0.005, 1000., 400.)).unwrap();
+        assert_eq!(conversion.transfer, 5.);
+        assert_eq!(conversion.luminance_scale, 10000. / 203.);
+    }
+}
@@ -1,7 +1,10 @@
 use smithay::{
     backend::renderer::{
         ImportAll, Renderer,
-        element::surface::{KindEvaluation, WaylandSurfaceRenderElement},
+        element::{
+            Element,
+            surface::{KindEvaluation, WaylandSurfaceRenderElement},
+        },
         utils::RendererSurfaceStateUserData,
     },
     reexports::wayland_server::protocol::wl_surface,
@@ -13,11 +16,15 @@ use tracing::warn;
 
 use crate::backend::render::{
     element::AsGlowRenderer,
-    wayland::{blur_effect::BlurElement, clipped_surface::ClippedSurfaceRenderElement},
+    wayland::{
+        blur_effect::BlurElement, clipped_surface::ClippedSurfaceRenderElement,
+        color_conversion::ColorConversion,
+    },
 };
 
 pub mod blur_effect;
 pub mod clipped_surface;
+pub mod color_conversion;
 
 render_elements! {
     pub SurfaceRenderElement<R> where R: AsGlowRenderer + ImportAll, R::TextureId: Send;
@@ -98,13 +105,31 @@ pub fn push_render_elements_from_surface_tree<R>(
                                 radii,
                                 blur_strength,
                             );
+                            let conversion = ColorConversion::for_surface(states);
                             let elem: SurfaceRenderElement<R> = if radii.iter().any(|r| *r != 0)
                                 && should_clip
                                 && ClippedSurfaceRenderElement::will_clip(
                                     &surface, scale, geometry, radii,
                                 ) {
                                 ClippedSurfaceRenderElement::new(
-                                    renderer, surface, scale, geometry, radii,
+                                    renderer,
+                                    surface,
+                                    scale,
+                                    geometry,
+                                    radii,
+                                    conversion.as_ref(),
+                                )
+                                .into()
+                            } else if conversion.is_some() {
+                                // clipping to its own bounds only applies the conversion
+                                let bounds = surface.geometry(scale).to_f64().to_logical(scale);
+                                ClippedSurfaceRenderElement::new(
+                                    renderer,
+                                    surface,
+                                    scale,
+                                    bounds,
+                                    [0; 4],
+                                    conversion.as_ref(),
                                 )
                                 .into()
                             } else {
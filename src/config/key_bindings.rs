@@ -1,6 +1,13 @@
+use std::collections::BTreeMap;
+
+use cosmic_comp_config::actions::CompAction;
 use cosmic_settings_config::shortcuts::State as KeyState;
 use cosmic_settings_config::shortcuts::{self, Modifiers};
 use smithay::input::keyboard::ModifiersState;
+use tracing::warn;
+
+/// Default bindings of compositor actions, in the format of the cosmic-settings shortcuts
+const DEFAULT_COMPOSITOR_SHORTCUTS: &str = include_str!("../../data/compositor-shortcuts.ron");
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Action {
@@ -8,6 +15,20 @@ pub enum Action {
     Private(PrivateAction),
     /// Behaviors managed via cosmic-settings.
     Shortcut(shortcuts::Action),
+    /// Behaviors bound in the cosmic-comp config.
+    Compositor(CompAction),
+}
+
+impl From<shortcuts::Action> for Action {
+    fn from(action: shortcuts::Action) -> Self {
+        Action::Shortcut(action)
+    }
+}
+
+impl From<CompAction> for Action {
+    fn from(action: CompAction) -> Self {
+        Action::Compositor(action)
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -54,3 +75,38 @@ pub fn cosmic_modifiers_from_smithay(value: ModifiersState) -> Modifiers {
         logo: value.logo,
     }
 }
+
+/// Bindings of compositor actions, the defaults overridden by the `compositor_shortcuts` key.
+pub fn compositor_shortcuts(
+    config: &cosmic_config::Config,
+) -> BTreeMap<shortcuts::Binding, CompAction> {
+    let mut shortcuts = default_compositor_shortcuts();
+    match config.get::<BTreeMap<shortcuts::Binding, CompAction>>("compositor_shortcuts") {
+        Ok(custom) => shortcuts.extend(custom),
+        Err(err) if err.is_err() => warn!(?err, "Failed to read compositor shortcuts"),
+        Err(_) => {}
+    }
+    shortcuts
+}
+
+fn default_compositor_shortcuts() -> BTreeMap<shortcuts::Binding, CompAction> {
+    ron::from_str(DEFAULT_COMPOSITOR_SHORTCUTS).expect("Invalid default compositor shortcuts")
+}
+
+#[cfg(test)]
+mod test {
+    use cosmic_comp_config::actions::CompAction;
+
+    use super::default_compositor_shortcuts;
+
+    #[test]
+    fn default_compositor_shortcuts_parse() {
+        let shortcuts = default_compositor_shortcuts();
+        assert!(
+            shortcuts
+                .values()
+                .any(|action| *action == CompAction::ToggleScrolling)
+        );
+        assert!(shortcuts.keys().all(|binding| binding.key.is_some()));
+    }
+}
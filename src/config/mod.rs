@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::{
-    shell::Shell,
+    shell::{
+        Shell,
+        layout::restore::{LayoutSnapshot, SessionSnapshots},
+    },
     state::{BackendData, State},
-    utils::prelude::OutputExt,
+    utils::prelude::{OutputExt, SeatExt},
     wayland::protocols::{
         output_configuration::OutputConfigurationState, workspace::WorkspaceUpdateGuard,
     },
@@ -36,7 +39,7 @@ use std::{
     path::PathBuf,
     sync::{Arc, atomic::AtomicBool},
 };
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 mod input_config;
 pub mod key_bindings;
@@ -45,12 +48,16 @@ mod types;
 use cosmic::config::CosmicTk;
 pub use cosmic_comp_config::EdidProduct;
 use cosmic_comp_config::{
-    ActivationPolicy, AppearanceConfig, CosmicCompConfig, KeyboardConfig, TileBehavior, XkbConfig,
-    XwaylandDescaling, XwaylandEavesdropping, ZoomConfig,
+    ActivationPolicy, AppearanceConfig, BellConfig, CosmicCompConfig, KeyboardConfig, MirrorConfig,
+    NightLightConfig, TileBehavior, XkbConfig, XwaylandDescaling, XwaylandEavesdropping,
+    ZoomConfig,
+    actions::CompAction,
     input::{DeviceState as InputDeviceState, InputConfig, TouchpadOverride},
     output::comp::{
         OutputConfig, OutputInfo, OutputState, OutputsConfig, TransformDef, load_outputs,
     },
+    presets::LayoutPreset,
+    window_rules::WindowRule,
     workspace::WorkspaceConfig,
 };
 pub use key_bindings::{Action, PrivateAction};
@@ -70,6 +77,8 @@ pub struct Config {
     pub tiling_exceptions: Vec<ApplicationException>,
     /// System actions from `com.system76.CosmicSettings.Shortcuts`
     pub system_actions: BTreeMap<shortcuts::action::System, String>,
+    /// Key bindings of compositor actions, including the defaults
+    pub compositor_shortcuts: BTreeMap<shortcuts::Binding, CompAction>,
 }
 
 #[derive(Debug)]
@@ -77,6 +86,8 @@ pub struct DynamicConfig {
     outputs: (Option<PathBuf>, OutputsConfig),
     numlock: (Option<PathBuf>, NumlockStateConfig),
     accessibility_filter: (Option<PathBuf>, ScreenFilter),
+    layout: (Option<PathBuf>, LayoutSnapshot),
+    sessions: (Option<PathBuf>, SessionSnapshots),
 }
 
 #[derive(Default, Debug, Deserialize, Serialize)]
@@ -167,16 +178,44 @@ pub enum ColorFilter {
     Tritanopia = 4,
 }
 
+#[cfg(test)]
+thread_local! {
+    /// Directory replacing the config and state homes of compositors created on this thread,
+    /// so tests running in parallel don't share their configuration.
+    pub static TEST_HOME: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// The cosmic-config handle and base directories the configuration is stored in
+fn config_locations() -> (cosmic_config::Config, xdg::BaseDirectories) {
+    #[cfg(test)]
+    if let Some(home) = TEST_HOME.with_borrow(Clone::clone) {
+        let config = cosmic_config::Config::with_custom_path(
+            "com.system76.CosmicComp",
+            1,
+            home.join("config"),
+        )
+        .unwrap();
+        let mut xdg = xdg::BaseDirectories::new();
+        xdg.config_home = Some(home.join("config"));
+        xdg.state_home = Some(home.join("state"));
+        return (config, xdg);
+    }
+
+    (
+        cosmic_config::Config::new("com.system76.CosmicComp", 1).unwrap(),
+        xdg::BaseDirectories::new(),
+    )
+}
+
 impl Config {
     pub fn load(loop_handle: &LoopHandle<'_, State>) -> Config {
-        let config = cosmic_config::Config::new("com.system76.CosmicComp", 1).unwrap();
+        let (config, xdg) = config_locations();
         let source = cosmic_config::calloop::ConfigWatchSource::new(&config).unwrap();
         loop_handle
             .insert_source(source, |(config, keys), (), state| {
                 config_changed(config, keys, state);
             })
             .expect("Failed to add cosmic-config to the event loop");
-        let xdg = xdg::BaseDirectories::new();
 
         let cosmic_comp_config =
             CosmicCompConfig::get_entry(&config).unwrap_or_else(|(errs, c)| {
@@ -323,6 +362,8 @@ impl Config {
                 .set_screen_filter(filter_conf.color_filter);
         });
 
+        let compositor_shortcuts = key_bindings::compositor_shortcuts(&config);
+
         Config {
             dynamic_conf: Self::load_dynamic(&xdg),
             cosmic_conf: cosmic_comp_config,
@@ -331,6 +372,7 @@ impl Config {
             shortcuts,
             system_actions,
             tiling_exceptions,
+            compositor_shortcuts,
         }
     }
 
@@ -345,10 +387,18 @@ impl Config {
             .ok();
         let filter = Self::load_filter_state(&filter_path);
 
+        let layout_path = xdg.place_state_file("cosmic-comp/layout.ron").ok();
+        let layout = Self::load_layout(&layout_path);
+
+        let sessions_path = xdg.place_state_file("cosmic-comp/sessions.ron").ok();
+        let sessions = Self::load_sessions(&sessions_path);
+
         DynamicConfig {
             outputs: (output_path, outputs),
             numlock: (numlock_path, numlock),
             accessibility_filter: (filter_path, filter),
+            layout: (layout_path, layout),
+            sessions: (sessions_path, sessions),
         }
     }
 
@@ -393,6 +443,45 @@ impl Config {
         }
     }
 
+    fn load_layout(path: &Option<PathBuf>) -> LayoutSnapshot {
+        path.as_deref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                ron::de::from_reader::<_, LayoutSnapshot>(
+                    OpenOptions::new().read(true).open(path).unwrap(),
+                )
+                .map_err(|err| {
+                    warn!(?err, "Failed to read layout.ron, resetting..");
+                    if let Err(err) = std::fs::remove_file(path) {
+                        error!(?err, "Failed to remove layout.ron.");
+                    }
+                })
+                .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    fn load_sessions(path: &Option<PathBuf>) -> SessionSnapshots {
+        let mut sessions = path
+            .as_deref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                ron::de::from_reader::<_, SessionSnapshots>(
+                    OpenOptions::new().read(true).open(path).unwrap(),
+                )
+                .map_err(|err| {
+                    warn!(?err, "Failed to read sessions.ron, resetting..");
+                    if let Err(err) = std::fs::remove_file(path) {
+                        error!(?err, "Failed to remove sessions.ron.");
+                    }
+                })
+                .ok()
+            })
+            .unwrap_or_default();
+        sessions.expire();
+        sessions
+    }
+
     pub fn shortcut_for_action(&self, action: &shortcuts::Action) -> Option<String> {
         self.shortcuts.shortcut_for_action(action)
     }
@@ -417,29 +506,37 @@ impl Config {
             .collect::<Vec<_>>();
         infos.sort();
 
-        if let Some(configs) = self
-            .dynamic_conf
-            .outputs()
-            .config
-            .get(&infos)
-            .filter(|configs| {
-                if configs
-                    .iter()
-                    .all(|config| config.enabled == OutputState::Disabled)
-                {
-                    if !configs.is_empty() {
-                        error!(
-                            "Broken config, all outputs disabled. Resetting... {:?}",
-                            configs
-                        );
-                    }
-                    false
-                } else {
-                    true
+        // a config for exactly these outputs takes precedence over profiles,
+        // which are resolved again every time instead of being stored as one
+        let mut from_profile = false;
+        let mut primary = None;
+        let configs = match self.dynamic_conf.outputs().config.get(&infos) {
+            Some(configs) => Some(configs.clone()),
+            None => self
+                .profile_configs(&outputs, &infos)
+                .map(|(configs, output)| {
+                    from_profile = true;
+                    primary = output;
+                    configs
+                }),
+        };
+
+        if let Some(configs) = configs.filter(|configs| {
+            if configs
+                .iter()
+                .all(|config| config.enabled == OutputState::Disabled)
+            {
+                if !configs.is_empty() {
+                    error!(
+                        "Broken config, all outputs disabled. Resetting... {:?}",
+                        configs
+                    );
                 }
-            })
-            .cloned()
-        {
+                false
+            } else {
+                true
+            }
+        }) {
             let known_good_configs = outputs
                 .iter()
                 .map(|output| {
@@ -453,9 +550,10 @@ impl Config {
                 .collect::<Vec<_>>();
 
             let mut found_outputs = Vec::new();
-            for (name, output_config) in infos.iter().map(|o| &o.connector).zip(configs) {
+            for (name, mut output_config) in infos.iter().map(|o| &o.connector).zip(configs) {
                 let output = outputs.iter().find(|o| &o.name() == name).unwrap().clone();
                 let enabled = output_config.enabled.clone();
+                output_config.mirror = self.mirror_config(&output);
                 *output
                     .user_data()
                     .get::<RefCell<OutputConfig>>()
@@ -515,10 +613,20 @@ impl Config {
                         output_state.disable_head(&output);
                     }
                 }
+
+                if let Some(primary) =
+                    primary.filter(|o| o.config().enabled == OutputState::Enabled)
+                {
+                    for seat in shell.read().seats.iter() {
+                        seat.set_active_output(&primary);
+                    }
+                }
             }
 
             output_state.update();
-            self.write_outputs(output_state.outputs());
+            if !from_profile {
+                self.write_outputs(output_state.outputs());
+            }
         } else {
             if outputs
                 .iter()
@@ -544,6 +652,9 @@ impl Config {
                 }
                 w += output.geometry().size.w as u32;
             }
+            for output in &outputs {
+                output.config_mut().mirror = self.mirror_config(output);
+            }
 
             let mut backend = backend.lock();
             backend
@@ -580,6 +691,46 @@ impl Config {
         Ok(())
     }
 
+    /// Scaling and source of `output`, if it mirrors another one
+    fn mirror_config(&self, output: &Output) -> MirrorConfig {
+        self.cosmic_conf
+            .mirrors
+            .get(&output.name())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Configs of the first profile matching `outputs`, in the order of `infos`,
+    /// and the primary output of the profile.
+    fn profile_configs(
+        &self,
+        outputs: &[Output],
+        infos: &[OutputInfo],
+    ) -> Option<(Vec<OutputConfig>, Option<Output>)> {
+        let identities = infos
+            .iter()
+            .map(|info| {
+                let serial = outputs
+                    .iter()
+                    .find(|o| o.name() == info.connector)
+                    .map(|o| o.physical_properties().serial_number)
+                    .unwrap_or_default();
+                (info.clone(), serial)
+            })
+            .collect::<Vec<_>>();
+
+        let (profile, assigned) = self.dynamic_conf.outputs().match_profile(&identities)?;
+        info!(profile = %profile.name, "Using output profile");
+        let primary = infos
+            .iter()
+            .zip(assigned.iter())
+            .find(|(_, output)| output.primary)
+            .and_then(|(info, _)| outputs.iter().find(|o| o.name() == info.connector))
+            .cloned();
+        let configs = assigned.into_iter().map(|output| output.config).collect();
+        Some((configs, primary))
+    }
+
     pub fn write_outputs(
         &mut self,
         outputs: impl Iterator<Item = impl std::borrow::Borrow<Output>>,
@@ -730,6 +881,22 @@ impl DynamicConfig {
             &mut self.accessibility_filter.1,
         )
     }
+
+    pub fn layout(&self) -> &LayoutSnapshot {
+        &self.layout.1
+    }
+
+    pub fn layout_mut(&mut self) -> PersistenceGuard<'_, LayoutSnapshot> {
+        PersistenceGuard(self.layout.0.clone(), &mut self.layout.1)
+    }
+
+    pub fn sessions(&self) -> &SessionSnapshots {
+        &self.sessions.1
+    }
+
+    pub fn sessions_mut(&mut self) -> PersistenceGuard<'_, SessionSnapshots> {
+        PersistenceGuard(self.sessions.0.clone(), &mut self.sessions.1)
+    }
 }
 
 pub fn xkb_config_to_wl(config: &XkbConfig) -> WlXkbConfig<'_> {
@@ -752,6 +919,27 @@ fn get_config<T: Default + serde::de::DeserializeOwned>(
     })
 }
 
+/// Applies changed mirror configs to all outputs.
+fn update_mirrors(state: &mut State) {
+    for output in state.common.output_configuration_state.outputs() {
+        output.config_mut().mirror = state.common.config.mirror_config(&output);
+    }
+
+    let mut backend = state.backend.lock();
+    if let Err(err) = backend.apply_config_for_outputs(
+        false,
+        &state.common.event_loop_handle,
+        state.common.config.dynamic_conf.screen_filter(),
+        state.common.shell.clone(),
+        &mut state.common.workspace_state.update(),
+        &state.common.xdg_activation_state,
+        state.common.startup_done.clone(),
+        &state.common.clock,
+    ) {
+        warn!(?err, "Failed to apply mirror config");
+    }
+}
+
 fn update_input(state: &mut State) {
     if let BackendData::Kms(kms_state) = &mut state.backend {
         for device in kms_state.input_devices.values_mut() {
@@ -883,6 +1071,32 @@ fn config_changed(config: cosmic_config::Config, keys: Vec<String>, state: &mut
                     );
                 }
             }
+            "window_rules" => {
+                let new = get_config::<Vec<WindowRule>>(&config, "window_rules");
+                if new != state.common.config.cosmic_conf.window_rules {
+                    state.common.config.cosmic_conf.window_rules = new;
+                    state
+                        .common
+                        .shell
+                        .write()
+                        .update_window_rules(state.common.config.cosmic_conf.window_rules.iter());
+                }
+            }
+            "compositor_shortcuts" => {
+                state.common.config.compositor_shortcuts =
+                    key_bindings::compositor_shortcuts(&config);
+            }
+            "layout_presets" => {
+                state.common.config.cosmic_conf.layout_presets =
+                    get_config::<Vec<LayoutPreset>>(&config, "layout_presets");
+            }
+            "swallow_terminals" => {
+                let new = get_config::<Vec<String>>(&config, "swallow_terminals");
+                if new != state.common.config.cosmic_conf.swallow_terminals {
+                    state.common.config.cosmic_conf.swallow_terminals = new.clone();
+                    state.common.shell.write().update_swallow_terminals(new);
+                }
+            }
             "active_hint" => {
                 let new = get_config::<bool>(&config, "active_hint");
                 if new != state.common.config.cosmic_conf.active_hint {
@@ -972,6 +1186,26 @@ fn config_changed(config: cosmic_config::Config, keys: Vec<String>, state: &mut
                     state.common.config.cosmic_conf.activation_policy = new;
                 }
             }
+            "system_bell" => {
+                let new = get_config::<BellConfig>(&config, "system_bell");
+                if new != state.common.config.cosmic_conf.system_bell {
+                    state.common.config.cosmic_conf.system_bell = new;
+                }
+            }
+            "night_light" => {
+                let new = get_config::<NightLightConfig>(&config, "night_light");
+                if new != state.common.config.cosmic_conf.night_light {
+                    state.common.config.cosmic_conf.night_light = new;
+                    crate::backend::render::gamma::update_night_light(state);
+                }
+            }
+            "mirrors" => {
+                let new = get_config::<HashMap<String, MirrorConfig>>(&config, "mirrors");
+                if new != state.common.config.cosmic_conf.mirrors {
+                    state.common.config.cosmic_conf.mirrors = new;
+                    update_mirrors(state);
+                }
+            }
             _ => {}
         }
     }
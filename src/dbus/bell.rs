@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Forwards the system bell to the session bus, where e.g. a sound server may play it.
+
+use zbus::object_server::SignalEmitter;
+
+const PATH: &str = "/com/system76/CosmicComp/Bell";
+
+pub struct Bell;
+
+#[zbus::interface(name = "com.system76.CosmicComp.Bell")]
+impl Bell {
+    /// Emitted whenever a client rings the bell, with the app-id of its window, if any.
+    #[zbus(signal)]
+    async fn ring(ctx: SignalEmitter<'_>, app_id: &str) -> zbus::Result<()>;
+}
+
+pub async fn init(conn: &zbus::Connection) -> zbus::Result<()> {
+    conn.object_server().at(PATH, Bell).await?;
+    Ok(())
+}
+
+pub async fn ring(conn: &zbus::Connection, app_id: &str) -> zbus::Result<()> {
+    let ctx = SignalEmitter::new(conn, PATH)?;
+    Bell::ring(ctx, app_id).await
+}
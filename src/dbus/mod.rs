@@ -14,6 +14,7 @@ use tracing::{error, warn};
 
 pub mod a11y_keyboard_monitor;
 use a11y_keyboard_monitor::A11yKeyboardMonitorState;
+mod bell;
 #[cfg(feature = "logind")]
 pub mod logind;
 mod name_owners;
@@ -65,6 +66,20 @@ impl DBusState {
         RefMut::filter_map(self.0.a11y_keyboard_monitor.borrow_mut(), |x| x.as_mut()).ok()
     }
 
+    /// Emits the `Ring` signal of the bell interface.
+    pub fn ring_bell(&self, app_id: String) {
+        let state = self.clone();
+        self.spawn(async move {
+            let result = match state.session_conn().await {
+                Ok(conn) => bell::ring(conn, &app_id).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                warn!(?err, "Failed to forward system bell");
+            }
+        });
+    }
+
     // TODO Lazy async init when we don't have anything blocking main thread
     async fn session_conn(&self) -> zbus::Result<&zbus::Connection> {
         self.0.session_conn.as_ref().map_err(|err| err.clone())
@@ -81,6 +96,9 @@ impl DBusState {
 
 async fn init_session(state: &DBusState) -> zbus::Result<()> {
     let conn = state.session_conn().await?;
+    if let Err(err) = bell::init(conn).await {
+        warn!(?err, "Failed to initialize bell dbus interface");
+    }
     let name_owners = name_owners::NameOwners::new(conn, &state.0.executor).await?;
     let a11y_keyboard_monitor_state =
         A11yKeyboardMonitorState::new(conn, &name_owners, &state.0.executor).await?;
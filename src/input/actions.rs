@@ -5,14 +5,16 @@ use crate::{
     shell::{
         FocusResult, InvalidWorkspaceIndex, MoveResult, SeatExt, Trigger, WorkspaceDelta,
         focus::{FocusTarget, target::KeyboardFocusTarget},
-        layout::tiling::SwapWindowGrab,
+        layout::tiling::{MASTER_RATIO_STEP, SwapWindowGrab},
     },
     utils::prelude::*,
     wayland::{
         handlers::xdg_activation::ActivationContext, protocols::workspace::WorkspaceUpdateGuard,
     },
 };
-use cosmic_comp_config::{TileBehavior, workspace::WorkspaceLayout};
+use cosmic_comp_config::{
+    TileBehavior, actions::CompAction, presets::LayoutPreset, workspace::WorkspaceLayout,
+};
 use cosmic_config::ConfigSet;
 use cosmic_settings_config::shortcuts;
 use cosmic_settings_config::shortcuts::action::{Direction, FocusDirection};
@@ -72,6 +74,7 @@ impl State {
                     action, seat, serial, time, pattern, direction, propagate,
                 )
             }
+            Action::Compositor(action) => self.handle_compositor_action(action, seat),
             Action::Private(PrivateAction::Escape) => {
                 {
                     let mut shell = self.common.shell.write();
@@ -136,6 +139,123 @@ impl State {
                     &mut self.common.workspace_state.update(),
                 );
             }
+            // continuously updated while swiping
+            SwipeAction::ScrollColumns => {}
+        }
+    }
+
+    pub fn handle_compositor_action(&mut self, action: CompAction, seat: &Seat<State>) {
+        let output = seat.active_output();
+        let mut shell = self.common.shell.write();
+
+        match action {
+            CompAction::SendToScratchpad => {
+                let mapped = match seat.get_keyboard().unwrap().current_focus() {
+                    Some(KeyboardFocusTarget::Element(mapped)) => Some(mapped),
+                    // fullscreen windows have no place in a layer to be taken from
+                    Some(KeyboardFocusTarget::Fullscreen(surface)) => {
+                        match shell.unfullscreen_request(&surface, &self.common.event_loop_handle) {
+                            Some(KeyboardFocusTarget::Element(mapped)) => Some(mapped),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(mapped) = mapped {
+                    shell.send_to_scratchpad(&mapped, seat);
+                }
+            }
+            CompAction::ToggleScratchpad(app_id) => {
+                let target = shell.toggle_scratchpad(&app_id, seat);
+                std::mem::drop(shell);
+                if let Some(target) = target {
+                    Shell::set_focus(self, Some(&target), seat, None, true);
+                }
+            }
+            CompAction::CycleScratchpad => {
+                let target = shell.cycle_scratchpad(seat);
+                std::mem::drop(shell);
+                if let Some(target) = target {
+                    Shell::set_focus(self, Some(&target), seat, None, true);
+                }
+            }
+            CompAction::CycleTilingStrategy => {
+                let workspace = shell.active_space_mut(&output).unwrap();
+                let strategy = if workspace.tiling_enabled {
+                    workspace.tiling_layer.strategy().next()
+                } else {
+                    workspace.tiling_layer.strategy()
+                };
+                let mut guard = self.common.workspace_state.update();
+                workspace.set_tiling_strategy(strategy, seat, &mut guard);
+            }
+            CompAction::SetTilingStrategy(strategy) => {
+                let workspace = shell.active_space_mut(&output).unwrap();
+                let mut guard = self.common.workspace_state.update();
+                workspace.set_tiling_strategy(strategy, seat, &mut guard);
+            }
+            CompAction::ToggleScrolling => {
+                let workspace = shell.active_space_mut(&output).unwrap();
+                let scrolling = workspace.tiling_enabled && workspace.tiling_layer.is_scrolling();
+                let mut guard = self.common.workspace_state.update();
+                workspace.set_scrolling(!scrolling, seat, &mut guard);
+            }
+            CompAction::GrowMaster => shell
+                .active_space_mut(&output)
+                .unwrap()
+                .tiling_layer
+                .resize_master(MASTER_RATIO_STEP),
+            CompAction::ShrinkMaster => shell
+                .active_space_mut(&output)
+                .unwrap()
+                .tiling_layer
+                .resize_master(-MASTER_RATIO_STEP),
+            CompAction::IncreaseMasterCount => shell
+                .active_space_mut(&output)
+                .unwrap()
+                .tiling_layer
+                .change_master_count(1),
+            CompAction::DecreaseMasterCount => shell
+                .active_space_mut(&output)
+                .unwrap()
+                .tiling_layer
+                .change_master_count(-1),
+            CompAction::SaveLayoutPreset(name) => {
+                let workspace = shell.active_space_mut(&output).unwrap();
+                let Some(root) = workspace.tiling_layer.capture_preset() else {
+                    warn!(%name, "No tiled windows to save as layout preset");
+                    return;
+                };
+                let presets = &mut self.common.config.cosmic_conf.layout_presets;
+                presets.retain(|preset| preset.name != name);
+                presets.push(LayoutPreset { name, root });
+
+                let presets = presets.clone();
+                let config = self.common.config.cosmic_helper.clone();
+                thread::spawn(move || {
+                    if let Err(err) = config.set("layout_presets", presets) {
+                        error!(?err, "Failed to update layout_presets key");
+                    }
+                });
+            }
+            CompAction::ApplyLayoutPreset(name) => {
+                let Some(preset) = self
+                    .common
+                    .config
+                    .cosmic_conf
+                    .layout_presets
+                    .iter()
+                    .find(|preset| preset.name == name)
+                else {
+                    warn!(%name, "Unknown layout preset");
+                    return;
+                };
+                let workspace = shell.active_space_mut(&output).unwrap();
+                let mut guard = self.common.workspace_state.update();
+                if let Err(err) = workspace.apply_layout_preset(&preset.root, seat, &mut guard) {
+                    warn!(%name, ?err, "Failed to apply layout preset");
+                }
+            }
         }
     }
 
@@ -16,6 +16,8 @@ pub struct SwipeEvent {
 pub enum SwipeAction {
     NextWorkspace,
     PrevWorkspace,
+    /// Scrolls the columns of a scrollable tiling layout
+    ScrollColumns,
 }
 
 #[derive(Debug, Clone)]
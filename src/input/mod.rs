@@ -1100,7 +1100,18 @@ impl State {
                                 natural_scroll = natural;
                             }
                             activate_action = match gesture_state.fingers {
-                                3 => None, // TODO: 3 finger gestures
+                                3 => match gesture_state.direction {
+                                    Some(Direction::Left | Direction::Right)
+                                        if self
+                                            .common
+                                            .shell
+                                            .read()
+                                            .is_scrolling(&seat.active_output()) =>
+                                    {
+                                        Some(SwipeAction::ScrollColumns)
+                                    }
+                                    _ => None, // TODO: Other 3 finger gestures
+                                },
                                 4 => {
                                     if self.common.config.cosmic_conf.workspaces.workspace_layout
                                         == WorkspaceLayout::Horizontal
@@ -1157,6 +1168,22 @@ impl State {
                                     matches!(x, SwipeAction::NextWorkspace),
                                 )
                             }
+                            Some(SwipeAction::ScrollColumns) => {
+                                let natural_scroll = self
+                                    .common
+                                    .config
+                                    .cosmic_conf
+                                    .input_touchpad
+                                    .scroll_config
+                                    .as_ref()
+                                    .and_then(|scroll_config| scroll_config.natural_scroll)
+                                    .unwrap_or(false);
+                                let delta = event.delta().x;
+                                self.common.shell.write().scroll_columns(
+                                    &seat.active_output(),
+                                    if natural_scroll { -delta } else { delta },
+                                );
+                            }
                             _ => {}
                         }
                     } else {
@@ -1203,6 +1230,12 @@ impl State {
                                     &mut self.common.workspace_state.update(),
                                 );
                             }
+                            Some(SwipeAction::ScrollColumns) => {
+                                self.common
+                                    .shell
+                                    .write()
+                                    .end_scroll_columns(&seat.active_output());
+                            }
                             _ => {}
                         }
                         self.common.gesture_state = None;
@@ -1949,11 +1982,23 @@ impl State {
         if !shortcuts_inhibited {
             let modifiers_queue = seat.modifiers_shortcut_queue();
 
-            for (binding, action) in self.common.config.shortcuts.iter() {
-                if *action == shortcuts::Action::Disable {
-                    continue;
-                }
-
+            // compositor actions are bound like the shortcuts of cosmic-settings,
+            // which take precedence
+            let bindings = self
+                .common
+                .config
+                .shortcuts
+                .iter()
+                .filter(|(_, action)| **action != shortcuts::Action::Disable)
+                .map(|(binding, action)| (binding, Action::Shortcut(action.clone())))
+                .chain(
+                    self.common
+                        .config
+                        .compositor_shortcuts
+                        .iter()
+                        .map(|(binding, action)| (binding, Action::Compositor(action.clone()))),
+                );
+            for (binding, action) in bindings {
                 // is this a released (triggered) modifier-only binding?
                 if binding.key.is_none()
                     && event.state() == KeyState::Released
@@ -1961,10 +2006,7 @@ impl State {
                     && modifiers_queue.take(binding)
                 {
                     modifiers_queue.clear();
-                    return FilterResult::Intercept(Some((
-                        Action::Shortcut(action.clone()),
-                        binding.clone(),
-                    )));
+                    return FilterResult::Intercept(Some((action, binding.clone())));
                 }
 
                 // could this potentially become a modifier-only binding?
@@ -1984,10 +2026,7 @@ impl State {
                 {
                     modifiers_queue.clear();
                     seat.supressed_keys().add(&handle, None);
-                    return FilterResult::Intercept(Some((
-                        Action::Shortcut(action.clone()),
-                        binding.clone(),
-                    )));
+                    return FilterResult::Intercept(Some((action, binding.clone())));
                 }
             }
         }
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::actions::{CompAction, TilingStrategy};
+use cosmic_settings_config::shortcuts;
+use serde::{Deserialize, Serialize};
+use smithay::utils::Rectangle;
+
+use crate::{backend::kms::SurfaceStats, utils::prelude::Global};
+
+/// Version of the IPC protocol, bumped on every incompatible change
+pub const IPC_VERSION: u32 = 1;
+
+/// A request, sent by a client as a single line of json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "request")]
+pub enum Request {
+    Version,
+    Outputs,
+    Workspaces,
+    Windows,
+    FocusedWindow,
+    /// Render statistics of all outputs driven by a CRTC
+    Metrics,
+    /// Runs an action, as if triggered by the keybinding path
+    Action {
+        action: shortcuts::Action,
+    },
+    /// Runs an action of the compositor shortcuts of cosmic-comp
+    CompositorAction {
+        action: CompAction,
+    },
+    /// Changes how the active workspace of the active output arranges windows
+    SetTilingMode {
+        mode: TilingMode,
+    },
+    /// Creates an output without a connector, e.g. to stream a second desktop
+    CreateOutput {
+        width: i32,
+        height: i32,
+        /// Refresh rate in mHz, 60Hz if unset
+        #[serde(default)]
+        refresh: Option<i32>,
+        #[serde(default)]
+        scale: Option<f64>,
+    },
+    /// Removes an output created by `create_output`
+    DestroyOutput {
+        name: String,
+    },
+    /// Turns the connection into an event stream.
+    ///
+    /// An empty list subscribes to all events.
+    Subscribe {
+        #[serde(default)]
+        events: Vec<EventKind>,
+    },
+}
+
+/// The reply to every request, written as a single line of json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reply {
+    Ok(Response),
+    Err(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Version { version: u32, compositor: String },
+    Outputs(Vec<OutputInfo>),
+    Workspaces(Vec<WorkspaceInfo>),
+    Windows(Vec<WindowInfo>),
+    FocusedWindow(Option<WindowInfo>),
+    Metrics(Vec<OutputMetrics>),
+    OutputCreated { name: String },
+    Handled,
+    Subscribed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TilingMode {
+    Floating,
+    /// Nested horizontal and vertical splits
+    Tiling,
+    /// Columns on a horizontally scrolling strip
+    Scrolling,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Windows,
+    Focus,
+    Workspaces,
+}
+
+/// Events streamed to subscribed clients, one json object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum Event {
+    WindowMapped { window: WindowInfo },
+    WindowUnmapped { id: usize },
+    WindowFocused { id: Option<usize> },
+    WorkspaceActivated { output: String, workspace: usize },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::WindowMapped { .. } | Event::WindowUnmapped { .. } => EventKind::Windows,
+            Event::WindowFocused { .. } => EventKind::Focus,
+            Event::WorkspaceActivated { .. } => EventKind::Workspaces,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl From<Rectangle<i32, Global>> for Rect {
+    fn from(rect: Rectangle<i32, Global>) -> Self {
+        Rect {
+            x: rect.loc.x,
+            y: rect.loc.y,
+            width: rect.size.w,
+            height: rect.size.h,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputInfo {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    /// Logical geometry in the global compositor space
+    pub geometry: Rect,
+    pub scale: f64,
+    /// Refresh rate in mHz
+    pub refresh: Option<i32>,
+    pub active_workspace: usize,
+}
+
+/// Cumulative render statistics of an output since it was connected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputMetrics {
+    pub output: String,
+    #[serde(flatten)]
+    pub stats: SurfaceStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    pub output: String,
+    pub idx: usize,
+    pub name: Option<String>,
+    pub active: bool,
+    pub tiling: bool,
+    pub scrolling: bool,
+    pub strategy: TilingStrategy,
+    pub pinned: bool,
+    pub windows: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowLayer {
+    Tiling,
+    Floating,
+    Sticky,
+    Fullscreen,
+    Minimized,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowInfo {
+    /// Identifier, stable for the lifetime of the window
+    pub id: usize,
+    pub app_id: String,
+    pub title: String,
+    pub output: String,
+    /// Workspace index on `output`, `None` for sticky windows
+    pub workspace: Option<usize>,
+    pub layer: WindowLayer,
+    /// Geometry in the global compositor space, `None` while minimized
+    pub geometry: Option<Rect>,
+    pub stacked: bool,
+    pub maximized: bool,
+    pub focused: bool,
+}
@@ -0,0 +1,744 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Versioned json IPC socket to query and script the compositor.
+//!
+//! Clients connect to the socket at `COSMIC_COMP_SOCK` and send one
+//! [`Request`] per line, each answered with a single [`Reply`] line.
+//! After a `subscribe` request the connection receives [`Event`]s instead,
+//! further requests on it are ignored.
+//!
+//! Sockets are never written to blocking. Output a client doesn't accept yet is
+//! buffered and flushed once its socket becomes writable again.
+
+use smithay::{
+    input::Seat,
+    output::Output,
+    reexports::calloop::{
+        Interest, LoopHandle, Mode, PostAction, RegistrationToken, generic::Generic,
+    },
+    utils::{Rectangle, SERIAL_COUNTER},
+};
+
+use anyhow::{Context, Result};
+use cosmic_settings_config::shortcuts;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    backend::headless::OutputSpec,
+    shell::{
+        CosmicMapped, CosmicSurface, MinimizedWindow, WorkspaceSet,
+        focus::target::KeyboardFocusTarget,
+    },
+    state::State,
+    utils::prelude::*,
+};
+
+mod message;
+pub use self::message::*;
+
+crate::utils::id_gen!(next_window_id, WINDOW_ID, WINDOW_IDS);
+
+/// Requests are expected to be small, drop clients sending anything bigger
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+/// Clients not reading their replies or events are dropped once this much output is pending
+const MAX_PENDING_OUTPUT: usize = 1024 * 1024;
+
+#[derive(Debug)]
+struct IpcWindowId(usize);
+
+/// Returns the IPC identifier of a window, assigning a new one if necessary.
+pub fn window_id(surface: &CosmicSurface) -> usize {
+    surface
+        .user_data()
+        .insert_if_missing(|| IpcWindowId(next_window_id()));
+    surface.user_data().get::<IpcWindowId>().unwrap().0
+}
+
+#[derive(Debug, Default)]
+pub struct IpcState {
+    socket_path: Option<PathBuf>,
+    clients: HashMap<usize, Client>,
+    next_client: usize,
+    snapshot: Snapshot,
+}
+
+#[derive(Debug)]
+struct Client {
+    stream: UnixStream,
+    /// Replies and events the socket didn't accept yet
+    pending: Vec<u8>,
+    reader: Option<RegistrationToken>,
+    /// Source waiting for the socket to become writable, while output is pending
+    writer: Option<RegistrationToken>,
+    /// Events the client subscribed to, `None` while it sends requests
+    subscription: Option<HashSet<EventKind>>,
+}
+
+/// State last reported to subscribers, used to derive events
+#[derive(Debug, Default)]
+struct Snapshot {
+    windows: HashSet<usize>,
+    focused: Option<usize>,
+    active_workspaces: HashMap<String, usize>,
+}
+
+impl Drop for IpcState {
+    fn drop(&mut self) {
+        if let Some(path) = self.socket_path.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl IpcState {
+    pub fn socket_path(&self) -> Option<&PathBuf> {
+        self.socket_path.as_ref()
+    }
+
+    fn has_subscribers(&self) -> bool {
+        self.clients
+            .values()
+            .any(|client| client.subscription.is_some())
+    }
+
+    /// Sends out events for everything that changed since the last call.
+    pub fn refresh(state: &mut State) {
+        if !state.common.ipc_state.has_subscribers() {
+            return;
+        }
+
+        let snapshot = Snapshot::new(state);
+        let mut events = Vec::new();
+        {
+            let old = &state.common.ipc_state.snapshot;
+            let removed = old.windows.difference(&snapshot.windows).copied();
+            events.extend(removed.map(|id| Event::WindowUnmapped { id }));
+            if snapshot.windows.iter().any(|id| !old.windows.contains(id)) {
+                events.extend(
+                    windows(state)
+                        .into_iter()
+                        .filter(|window| !old.windows.contains(&window.id))
+                        .map(|window| Event::WindowMapped { window }),
+                );
+            }
+            if old.focused != snapshot.focused {
+                events.push(Event::WindowFocused {
+                    id: snapshot.focused,
+                });
+            }
+            for (output, workspace) in &snapshot.active_workspaces {
+                if old.active_workspaces.get(output) != Some(workspace) {
+                    events.push(Event::WorkspaceActivated {
+                        output: output.clone(),
+                        workspace: *workspace,
+                    });
+                }
+            }
+        }
+        state.common.ipc_state.snapshot = snapshot;
+
+        let handle = state.common.event_loop_handle.clone();
+        for event in events {
+            state.common.ipc_state.broadcast(&event, &handle);
+        }
+    }
+
+    fn broadcast(&mut self, event: &Event, handle: &LoopHandle<'static, State>) {
+        let line = match serde_json::to_string(event) {
+            Ok(json) => json + "\n",
+            Err(err) => {
+                warn!(?err, "Failed to encode IPC event");
+                return;
+            }
+        };
+        let kind = event.kind();
+        let mut dropped = Vec::new();
+        for (id, client) in self.clients.iter_mut() {
+            let Some(events) = client.subscription.as_ref() else {
+                continue;
+            };
+            if !events.is_empty() && !events.contains(&kind) {
+                continue;
+            }
+            if let Err(err) = client.send(*id, line.as_bytes(), handle) {
+                debug!(?err, "Dropping IPC subscriber");
+                dropped.push(*id);
+            }
+        }
+        for id in dropped {
+            self.remove_client(id, handle);
+        }
+    }
+
+    fn remove_client(&mut self, id: usize, handle: &LoopHandle<'static, State>) {
+        if let Some(client) = self.clients.remove(&id) {
+            for token in client.reader.into_iter().chain(client.writer) {
+                handle.remove(token);
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Queues `data` and writes as much of the pending output as the socket accepts.
+    fn send(&mut self, id: usize, data: &[u8], handle: &LoopHandle<'static, State>) -> Result<()> {
+        self.pending.extend_from_slice(data);
+        self.flush()?;
+        if self.pending.len() > MAX_PENDING_OUTPUT {
+            anyhow::bail!("Client is not reading its messages");
+        }
+
+        if !self.pending.is_empty() && self.writer.is_none() {
+            let stream = self.stream.try_clone()?;
+            let token = handle
+                .insert_source(
+                    Generic::new(stream, Interest::WRITE, Mode::Level),
+                    move |_, _, state| {
+                        let ipc_state = &mut state.common.ipc_state;
+                        let Some(client) = ipc_state.clients.get_mut(&id) else {
+                            return Ok(PostAction::Remove);
+                        };
+                        match client.flush() {
+                            Ok(()) if client.pending.is_empty() => {
+                                client.writer = None;
+                                Ok(PostAction::Remove)
+                            }
+                            Ok(()) => Ok(PostAction::Continue),
+                            Err(err) => {
+                                debug!(?err, "Error writing to IPC client");
+                                // this source is removed by returning `Remove`
+                                client.writer = None;
+                                ipc_state.remove_client(id, &state.common.event_loop_handle);
+                                Ok(PostAction::Remove)
+                            }
+                        }
+                    },
+                )
+                .map_err(|err| err.error)?;
+            self.writer = Some(token);
+        }
+        Ok(())
+    }
+
+    /// Writes pending output until the socket would block.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.pending.drain(..len);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Snapshot {
+    fn new(state: &State) -> Snapshot {
+        let focused = focused_surface(state).map(|surface| window_id(&surface));
+        let shell = state.common.shell.read();
+        Snapshot {
+            windows: shell
+                .workspaces
+                .iter()
+                .flat_map(|(_, set)| set_surfaces(set))
+                .map(|surface| window_id(&surface))
+                .collect(),
+            focused,
+            active_workspaces: shell
+                .workspaces
+                .iter()
+                .map(|(output, set)| (output.name(), set.active))
+                .collect(),
+        }
+    }
+}
+
+/// Creates the IPC socket, its path is exported to the session as `COSMIC_COMP_SOCK`.
+pub fn init_socket(state: &mut State) -> Result<()> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .with_context(|| "XDG_RUNTIME_DIR is not set")?;
+    let socket_name = state.common.socket.to_string_lossy();
+    let path = runtime_dir.join(format!("cosmic-comp-{socket_name}.sock"));
+    // a previous instance on the same wayland socket can't be running anymore
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind IPC socket at {}", path.display()))?;
+    listener
+        .set_nonblocking(true)
+        .with_context(|| "Failed to set IPC socket non-blocking")?;
+
+    state
+        .common
+        .event_loop_handle
+        .insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            |_, listener, state| {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(err) = add_client(state, stream) {
+                            warn!(?err, "Failed to add IPC client");
+                        }
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) => warn!(?err, "Failed to accept IPC client"),
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .with_context(|| "Failed to init the IPC socket source")?;
+
+    info!("IPC listening on {}", path.display());
+    state.common.ipc_state.socket_path = Some(path);
+    Ok(())
+}
+
+/// Serves requests of a client connected through `stream`.
+pub fn add_client(state: &mut State, stream: UnixStream) -> Result<()> {
+    stream.set_nonblocking(true)?;
+    let ipc_state = &mut state.common.ipc_state;
+    let id = ipc_state.next_client;
+    ipc_state.next_client += 1;
+
+    let mut buffer = Vec::new();
+    let reader = state
+        .common
+        .event_loop_handle
+        .insert_source(
+            Generic::new(stream.try_clone()?, Interest::READ, Mode::Level),
+            move |_, stream, state| {
+                let mut buf = [0u8; 4096];
+                let len = match (&**stream).read(&mut buf) {
+                    Ok(0) => return Ok(close_reader(state, id)),
+                    Ok(len) => len,
+                    Err(err)
+                        if matches!(err.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock) =>
+                    {
+                        return Ok(PostAction::Continue);
+                    }
+                    Err(err) => {
+                        debug!(?err, "Error reading from IPC client");
+                        return Ok(close_reader(state, id));
+                    }
+                };
+                match state.common.ipc_state.clients.get(&id) {
+                    Some(client) if client.subscription.is_none() => {}
+                    // the connection of a subscriber only carries events
+                    Some(_) => return Ok(PostAction::Continue),
+                    None => return Ok(PostAction::Remove),
+                }
+                buffer.extend_from_slice(&buf[..len]);
+
+                while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                    let line = buffer.drain(..=pos).collect::<Vec<_>>();
+                    let reply = match serde_json::from_slice::<Request>(&line) {
+                        Ok(Request::Subscribe { events }) => {
+                            subscribe(state, id, events);
+                            Reply::Ok(Response::Subscribed)
+                        }
+                        Ok(request) => handle_request(state, request),
+                        Err(err) => Reply::Err(format!("Invalid request: {err}")),
+                    };
+
+                    let mut json = match serde_json::to_vec(&reply) {
+                        Ok(json) => json,
+                        Err(err) => {
+                            warn!(?err, "Failed to encode IPC reply");
+                            continue;
+                        }
+                    };
+                    json.push(b'\n');
+                    let handle = state.common.event_loop_handle.clone();
+                    let Some(client) = state.common.ipc_state.clients.get_mut(&id) else {
+                        return Ok(PostAction::Remove);
+                    };
+                    if let Err(err) = client.send(id, &json, &handle) {
+                        debug!(?err, "Error writing to IPC client");
+                        return Ok(close_reader(state, id));
+                    }
+                    if client.subscription.is_some() {
+                        buffer.clear();
+                        break;
+                    }
+                }
+
+                if buffer.len() > MAX_REQUEST_SIZE {
+                    warn!("IPC request exceeds maximum size, dropping client");
+                    return Ok(close_reader(state, id));
+                }
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|err| err.error)?;
+
+    state.common.ipc_state.clients.insert(
+        id,
+        Client {
+            stream,
+            pending: Vec::new(),
+            reader: Some(reader),
+            writer: None,
+            subscription: None,
+        },
+    );
+    Ok(())
+}
+
+/// Drops a client from within its reader source, which is removed by the returned action.
+fn close_reader(state: &mut State, id: usize) -> PostAction {
+    if let Some(client) = state.common.ipc_state.clients.get_mut(&id) {
+        client.reader = None;
+    }
+    state
+        .common
+        .ipc_state
+        .remove_client(id, &state.common.event_loop_handle);
+    PostAction::Remove
+}
+
+fn subscribe(state: &mut State, id: usize, events: Vec<EventKind>) {
+    if !state.common.ipc_state.has_subscribers() {
+        // nobody was tracking changes, start from the current state
+        state.common.ipc_state.snapshot = Snapshot::new(state);
+    }
+    if let Some(client) = state.common.ipc_state.clients.get_mut(&id) {
+        client.subscription = Some(events.into_iter().collect());
+    }
+}
+
+fn handle_request(state: &mut State, request: Request) -> Reply {
+    match request {
+        Request::Version => Reply::Ok(Response::Version {
+            version: IPC_VERSION,
+            compositor: format!("cosmic-comp {}", env!("CARGO_PKG_VERSION")),
+        }),
+        Request::Outputs => Reply::Ok(Response::Outputs(outputs(state))),
+        Request::Workspaces => Reply::Ok(Response::Workspaces(workspaces(state))),
+        Request::Windows => Reply::Ok(Response::Windows(windows(state))),
+        Request::FocusedWindow => Reply::Ok(Response::FocusedWindow(
+            windows(state).into_iter().find(|window| window.focused),
+        )),
+        Request::Metrics => Reply::Ok(Response::Metrics(metrics(state))),
+        Request::Action { action } => {
+            let seat = state.common.shell.read().seats.last_active().clone();
+            run_action(state, &seat, action);
+            Reply::Ok(Response::Handled)
+        }
+        Request::CompositorAction { action } => {
+            let seat = state.common.shell.read().seats.last_active().clone();
+            state.handle_compositor_action(action, &seat);
+            Reply::Ok(Response::Handled)
+        }
+        Request::SetTilingMode { mode } => {
+            let seat = state.common.shell.read().seats.last_active().clone();
+            let output = seat.active_output();
+            let mut shell = state.common.shell.write();
+            let Some(workspace) = shell.workspaces.active_mut(&output) else {
+                return Reply::Err(format!("No active workspace on {}", output.name()));
+            };
+            let mut guard = state.common.workspace_state.update();
+            match mode {
+                TilingMode::Floating => workspace.set_tiling(false, &seat, &mut guard),
+                TilingMode::Tiling => {
+                    workspace.set_scrolling(false, &seat, &mut guard);
+                    workspace.set_tiling(true, &seat, &mut guard);
+                }
+                TilingMode::Scrolling => workspace.set_scrolling(true, &seat, &mut guard),
+            }
+            Reply::Ok(Response::Handled)
+        }
+        Request::CreateOutput {
+            width,
+            height,
+            refresh,
+            scale,
+        } => {
+            let default = OutputSpec::default();
+            let spec = OutputSpec {
+                size: (width, height).into(),
+                scale: scale.unwrap_or(default.scale),
+                refresh: refresh.unwrap_or(default.refresh),
+            };
+            if width <= 0 || height <= 0 || spec.scale <= 0.0 || spec.refresh <= 0 {
+                return Reply::Err(format!("Invalid output {width}x{height}"));
+            }
+            match state.create_virtual_output(spec) {
+                Ok(output) => Reply::Ok(Response::OutputCreated {
+                    name: output.name(),
+                }),
+                Err(err) => Reply::Err(format!("{err:#}")),
+            }
+        }
+        Request::DestroyOutput { name } => {
+            let Some(output) = state
+                .common
+                .output_configuration_state
+                .outputs()
+                .find(|output| output.name() == name)
+            else {
+                return Reply::Err(format!("No output named {name}"));
+            };
+            match state.remove_virtual_output(&output) {
+                Ok(()) => Reply::Ok(Response::Handled),
+                Err(err) => Reply::Err(format!("{err:#}")),
+            }
+        }
+        Request::Subscribe { .. } => unreachable!("Subscriptions are handled per connection"),
+    }
+}
+
+fn run_action(state: &mut State, seat: &Seat<State>, action: shortcuts::Action) {
+    let time = state.common.clock.now().as_millis();
+    state.handle_shortcut_action(
+        action,
+        seat,
+        SERIAL_COUNTER.next_serial(),
+        time,
+        shortcuts::Binding {
+            modifiers: Default::default(),
+            keycode: None,
+            key: None,
+            description: None,
+        },
+        None,
+        true,
+    );
+}
+
+fn focused_surface(state: &State) -> Option<CosmicSurface> {
+    let seat = state.common.shell.read().seats.last_active().clone();
+    match seat.get_keyboard()?.current_focus()? {
+        KeyboardFocusTarget::Element(mapped) => Some(mapped.active_window()),
+        KeyboardFocusTarget::Fullscreen(surface) => Some(surface),
+        _ => None,
+    }
+}
+
+fn outputs(state: &State) -> Vec<OutputInfo> {
+    let shell = state.common.shell.read();
+    shell
+        .workspaces
+        .iter()
+        .map(|(output, set)| {
+            let properties = output.physical_properties();
+            OutputInfo {
+                name: output.name(),
+                make: properties.make,
+                model: properties.model,
+                geometry: output.geometry().into(),
+                scale: output.current_scale().fractional_scale(),
+                refresh: output.current_mode().map(|mode| mode.refresh),
+                active_workspace: set.active,
+            }
+        })
+        .collect()
+}
+
+fn metrics(state: &State) -> Vec<OutputMetrics> {
+    // includes mirroring outputs, which the shell doesn't know about
+    state
+        .common
+        .output_configuration_state
+        .outputs()
+        .filter_map(|output| {
+            Some(OutputMetrics {
+                stats: state.backend.surface_stats(&output)?,
+                output: output.name(),
+            })
+        })
+        .collect()
+}
+
+fn workspaces(state: &State) -> Vec<WorkspaceInfo> {
+    let shell = state.common.shell.read();
+    shell
+        .workspaces
+        .iter()
+        .flat_map(|(output, set)| {
+            set.workspaces
+                .iter()
+                .enumerate()
+                .map(move |(idx, workspace)| WorkspaceInfo {
+                    output: output.name(),
+                    idx,
+                    name: workspace.name.clone(),
+                    active: idx == set.active,
+                    tiling: workspace.tiling_enabled,
+                    scrolling: workspace.tiling_layer.is_scrolling(),
+                    strategy: workspace.tiling_layer.strategy(),
+                    pinned: workspace.pinned,
+                    windows: workspace
+                        .mapped()
+                        .map(|m| m.windows().count())
+                        .sum::<usize>()
+                        + workspace.get_fullscreen_surfaces().count(),
+                })
+        })
+        .collect()
+}
+
+fn set_surfaces(set: &WorkspaceSet) -> impl Iterator<Item = CosmicSurface> + '_ {
+    let mapped = set
+        .sticky_layer
+        .mapped()
+        .chain(set.minimized_windows.iter().flat_map(|m| m.mapped()))
+        .chain(set.workspaces.iter().flat_map(|w| {
+            w.mapped()
+                .chain(w.minimized_windows.iter().flat_map(|m| m.mapped()))
+        }))
+        .flat_map(|mapped| mapped.windows().map(|(w, _)| w))
+        .collect::<Vec<_>>();
+    let fullscreen = set
+        .workspaces
+        .iter()
+        .flat_map(|w| {
+            w.get_fullscreen_surfaces()
+                .map(|f| f.surface.clone())
+                .chain(w.minimized_windows.iter().filter_map(|m| match m {
+                    MinimizedWindow::Fullscreen { surface, .. } => Some(surface.clone()),
+                    _ => None,
+                }))
+        })
+        .collect::<Vec<_>>();
+    mapped.into_iter().chain(fullscreen)
+}
+
+fn windows(state: &State) -> Vec<WindowInfo> {
+    let focused = focused_surface(state);
+    let shell = state.common.shell.read();
+    let mut windows = Vec::new();
+
+    let mut push = |surface: &CosmicSurface,
+                    output: &Output,
+                    workspace: Option<usize>,
+                    layer: WindowLayer,
+                    geometry: Option<Rectangle<i32, Global>>,
+                    mapped: Option<&CosmicMapped>| {
+        windows.push(WindowInfo {
+            id: window_id(surface),
+            app_id: surface.app_id(),
+            title: surface.title(),
+            output: output.name(),
+            workspace,
+            layer,
+            geometry: geometry.map(Into::into),
+            stacked: mapped.is_some_and(|m| m.is_stack()),
+            maximized: surface.is_maximized(false),
+            focused: focused.as_ref() == Some(surface),
+        });
+    };
+
+    for (output, set) in shell.workspaces.iter() {
+        for mapped in set.sticky_layer.mapped() {
+            let geometry = set
+                .sticky_layer
+                .element_geometry(mapped)
+                .map(|geo| geo.to_global(output));
+            for (surface, _) in mapped.windows() {
+                push(
+                    &surface,
+                    output,
+                    None,
+                    WindowLayer::Sticky,
+                    geometry,
+                    Some(mapped),
+                );
+            }
+        }
+        for mapped in set.minimized_windows.iter().flat_map(|m| m.mapped()) {
+            for (surface, _) in mapped.windows() {
+                push(
+                    &surface,
+                    output,
+                    None,
+                    WindowLayer::Minimized,
+                    None,
+                    Some(mapped),
+                );
+            }
+        }
+
+        for (idx, workspace) in set.workspaces.iter().enumerate() {
+            for fullscreen in workspace.get_fullscreen_surfaces() {
+                let geometry = workspace
+                    .fullscreen_geometry_for(fullscreen)
+                    .to_global(output);
+                push(
+                    &fullscreen.surface,
+                    output,
+                    Some(idx),
+                    WindowLayer::Fullscreen,
+                    Some(geometry),
+                    None,
+                );
+            }
+            for (mapped, geometry) in workspace.tiling_layer.mapped() {
+                for (surface, _) in mapped.windows() {
+                    push(
+                        &surface,
+                        output,
+                        Some(idx),
+                        WindowLayer::Tiling,
+                        Some(geometry.to_global(output)),
+                        Some(mapped),
+                    );
+                }
+            }
+            for mapped in workspace.floating_layer.mapped() {
+                let geometry = workspace
+                    .floating_layer
+                    .element_geometry(mapped)
+                    .map(|geo| geo.to_global(output));
+                for (surface, _) in mapped.windows() {
+                    push(
+                        &surface,
+                        output,
+                        Some(idx),
+                        WindowLayer::Floating,
+                        geometry,
+                        Some(mapped),
+                    );
+                }
+            }
+            for minimized in workspace.minimized_windows.iter() {
+                match minimized {
+                    MinimizedWindow::Fullscreen { surface, .. } => push(
+                        surface,
+                        output,
+                        Some(idx),
+                        WindowLayer::Minimized,
+                        None,
+                        None,
+                    ),
+                    MinimizedWindow::Floating { window, .. }
+                    | MinimizedWindow::Tiling { window, .. } => {
+                        for (surface, _) in window.windows() {
+                            push(
+                                &surface,
+                                output,
+                                Some(idx),
+                                WindowLayer::Minimized,
+                                None,
+                                Some(window),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    windows
+}
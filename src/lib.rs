@@ -43,12 +43,15 @@ pub mod dbus;
 pub mod debug;
 pub mod hooks;
 pub mod input;
+pub mod ipc;
 mod logger;
 pub mod session;
 pub mod shell;
 pub mod state;
 #[cfg(feature = "systemd")]
 pub mod systemd;
+#[cfg(test)]
+mod tests;
 pub mod theme;
 pub mod utils;
 pub mod wayland;
@@ -176,12 +179,19 @@ pub fn run(hooks: crate::hooks::Hooks) -> Result<(), Box<dyn Error>> {
         with_xwayland,
         kiosk_command,
     );
+    // init ipc, before the session gets notified about it
+    if let Err(err) = ipc::init_socket(&mut state) {
+        warn!(?err, "Failed to setup IPC socket");
+    }
+
     // init backend
     backend::init_backend_auto(&display, &mut event_loop, &mut state)?;
 
     if let Err(err) = theme::watch_theme(event_loop.handle()) {
         warn!(?err, "Failed to watch theme");
     }
+    shell::layout::restore::persist_layout(&event_loop.handle());
+    backend::render::gamma::schedule_night_light(&event_loop.handle());
 
     // run the event loop
     event_loop.run(None, &mut state, |state| {
@@ -338,5 +348,6 @@ fn refresh(state: &mut State) {
     OverlapNotifyState::refresh(state);
     state.common.update_x11_stacking_order();
     KeyboardLayoutState::refresh(state);
+    ipc::IpcState::refresh(state);
     state.last_refresh = LastRefresh::At(Instant::now());
 }
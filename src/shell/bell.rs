@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Visual bell, flashing the ringing window or a whole output.
+
+use std::time::{Duration, Instant};
+
+use keyframe::{ease, functions::EaseOutCubic};
+use smithay::{
+    backend::renderer::{element::Id, gles::element::PixelShaderElement},
+    output::{Output, WeakOutput},
+    utils::{IsAlive, Rectangle},
+};
+
+use super::{CosmicMapped, Shell};
+use crate::{
+    backend::render::{IndicatorShader, Key, Usage, element::AsGlowRenderer},
+    utils::prelude::*,
+};
+
+const FLASH_DURATION: Duration = Duration::from_millis(400);
+const FLASH_THICKNESS: u8 = 8;
+
+#[derive(Debug, Default)]
+pub struct Flashes(Vec<Flash>);
+
+#[derive(Debug)]
+struct Flash {
+    target: FlashTarget,
+    start: Instant,
+}
+
+#[derive(Debug)]
+enum FlashTarget {
+    Window(CosmicMapped),
+    Output(WeakOutput, Id),
+}
+
+impl Flash {
+    fn alpha(&self) -> f32 {
+        let progress =
+            Instant::now().duration_since(self.start).as_secs_f64() / FLASH_DURATION.as_secs_f64();
+        ease(EaseOutCubic, 1.0, 0.0, progress.min(1.0)) as f32
+    }
+}
+
+impl Flashes {
+    pub fn refresh(&mut self) {
+        self.0.retain(|flash| {
+            flash.start.elapsed() < FLASH_DURATION
+                && match &flash.target {
+                    FlashTarget::Window(mapped) => mapped.alive(),
+                    FlashTarget::Output(output, _) => output.upgrade().is_some(),
+                }
+        });
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.0.is_empty()
+    }
+}
+
+impl Shell {
+    pub fn flash_window(&mut self, mapped: &CosmicMapped) {
+        self.flashes.0.retain(
+            |flash| !matches!(&flash.target, FlashTarget::Window(window) if window == mapped),
+        );
+        self.flashes.0.push(Flash {
+            target: FlashTarget::Window(mapped.clone()),
+            start: Instant::now(),
+        });
+    }
+
+    pub fn flash_output(&mut self, output: &Output) {
+        self.flashes
+            .0
+            .retain(|flash| !matches!(&flash.target, FlashTarget::Output(o, _) if o == output));
+        self.flashes.0.push(Flash {
+            target: FlashTarget::Output(output.downgrade(), Id::new()),
+            start: Instant::now(),
+        });
+    }
+
+    pub fn render_flashes<R: AsGlowRenderer>(
+        &self,
+        renderer: &R,
+        output: &Output,
+        push: &mut dyn FnMut(PixelShaderElement),
+    ) {
+        let scale = output.current_scale().fractional_scale();
+        let color = crate::theme::active_window_hint(self.theme.cosmic());
+        let color = [color.red, color.green, color.blue];
+
+        for flash in &self.flashes.0 {
+            match &flash.target {
+                FlashTarget::Window(mapped) => {
+                    let visible = self.workspaces.sets.get(output).is_some_and(|set| {
+                        set.sticky_layer.mapped().any(|m| m == mapped)
+                            || self
+                                .active_space(output)
+                                .is_some_and(|workspace| workspace.mapped().any(|m| m == mapped))
+                    });
+                    let Some(geometry) = self
+                        .element_geometry(mapped)
+                        .filter(|_| visible)
+                        .map(|geometry| geometry.to_local(output))
+                    else {
+                        continue;
+                    };
+                    let radius = mapped.corner_radius(geometry.size.as_logical(), FLASH_THICKNESS);
+                    push(IndicatorShader::focus_element(
+                        renderer,
+                        Key::Window(Usage::Bell, mapped.key()),
+                        geometry,
+                        FLASH_THICKNESS,
+                        radius,
+                        flash.alpha(),
+                        scale,
+                        color,
+                    ));
+                }
+                FlashTarget::Output(o, id) if o == output => {
+                    push(IndicatorShader::element(
+                        renderer,
+                        id.clone(),
+                        Rectangle::from_size(output.geometry().size.as_local()),
+                        FLASH_THICKNESS * 2,
+                        [0; 4],
+                        flash.alpha(),
+                        scale,
+                        color,
+                    ));
+                }
+                FlashTarget::Output(..) => {}
+            }
+        }
+    }
+}
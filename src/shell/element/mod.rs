@@ -108,6 +108,8 @@ pub struct CosmicMapped {
     pub floating_tiled: Arc<Mutex<Option<TiledCorners>>>,
     //sticky
     pub previous_layer: Arc<Mutex<Option<ManagedLayer>>>,
+    //modals
+    pub has_modal: Arc<AtomicBool>,
 
     #[cfg(feature = "debug")]
     debug: Arc<Mutex<Option<smithay_egui::EguiState>>>,
@@ -123,6 +125,7 @@ impl fmt::Debug for CosmicMapped {
             .field("last_geometry", &self.last_geometry)
             .field("moved_since_mapped", &self.moved_since_mapped)
             .field("floating_tiled", &self.floating_tiled)
+            .field("has_modal", &self.has_modal)
             .finish()
     }
 }
@@ -906,6 +909,15 @@ impl CosmicMapped {
         }
     }
 
+    /// Rebuilds the decorations, e.g. after the icon of a window changed.
+    pub(crate) fn force_update(&self) {
+        match &self.element {
+            CosmicMappedInternal::Window(w) => w.force_update(),
+            CosmicMappedInternal::Stack(s) => s.force_update(),
+            CosmicMappedInternal::_GenericCatcher(_) => {}
+        }
+    }
+
     pub(crate) fn force_redraw(&self) {
         match &self.element {
             CosmicMappedInternal::Window(w) => w.force_redraw(),
@@ -1076,6 +1088,7 @@ impl From<CosmicWindow> for CosmicMapped {
             moved_since_mapped: Arc::new(AtomicBool::new(false)),
             floating_tiled: Arc::new(Mutex::new(None)),
             previous_layer: Arc::new(Mutex::new(None)),
+            has_modal: Arc::new(AtomicBool::new(false)),
             #[cfg(feature = "debug")]
             debug: Arc::new(Mutex::new(None)),
         }
@@ -1093,6 +1106,7 @@ impl From<CosmicStack> for CosmicMapped {
             moved_since_mapped: Arc::new(AtomicBool::new(false)),
             floating_tiled: Arc::new(Mutex::new(None)),
             previous_layer: Arc::new(Mutex::new(None)),
+            has_modal: Arc::new(AtomicBool::new(false)),
             #[cfg(feature = "debug")]
             debug: Arc::new(Mutex::new(None)),
         }
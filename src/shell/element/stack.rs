@@ -703,7 +703,7 @@ impl CosmicStack {
             let appearance = p.appearance_conf.lock().unwrap();
             let tiled = p.tiled.load(Ordering::Acquire);
 
-            if windows[active].is_maximized(false) {
+            if windows[active].is_maximized(false) || windows[active].shadow_disabled() {
                 return None;
             }
 
@@ -885,6 +885,10 @@ impl CosmicStack {
         self.0.force_redraw();
     }
 
+    pub(crate) fn force_update(&self) {
+        self.0.force_update();
+    }
+
     fn start_drag(&self, data: &mut State, seat: &Seat<State>, serial: Serial) {
         if let Some(dragged_out) = self
             .0
@@ -1312,12 +1316,15 @@ impl Decorations<CosmicStackInternal, Message> for DefaultDecorations {
                         user_data.insert_if_missing(Id::unique);
                         Tab::new(
                             w.title(),
-                            w.app_id(),
+                            w.icon().unwrap_or_else(|| {
+                                cosmic_widget::icon::from_name(w.app_id()).size(16).handle()
+                            }),
                             user_data.get::<Id>().unwrap().clone(),
                         )
                         .on_press(Message::PotentialTabDragStart(i))
                         .on_right_click(Message::TabMenu(i))
                         .on_close(Message::Close(i))
+                        .urgent(w.is_urgent())
                     }),
                     active,
                     windows[active].is_activated(false),
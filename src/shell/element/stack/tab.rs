@@ -13,7 +13,11 @@ use cosmic::{
         widget::{self, container::draw_background, rule::FillMode, scrollable::AbsoluteOffset},
     },
     theme,
-    widget::{Icon, icon::from_name, text},
+    widget::{
+        Icon,
+        icon::{self, from_name},
+        text,
+    },
 };
 
 #[derive(Clone, Copy)]
@@ -52,6 +56,7 @@ impl From<TabRuleTheme> for theme::Rule {
 pub(super) enum TabBackgroundTheme {
     ActiveActivated,
     ActiveDeactivated,
+    Urgent,
     Default,
 }
 
@@ -112,6 +117,22 @@ impl From<TabBackgroundTheme> for theme::Container<'_> {
                     shadow: Default::default(),
                 })
             }
+            TabBackgroundTheme::Urgent => Self::custom(move |theme| widget::container::Style {
+                snap: true,
+                icon_color: None,
+                text_color: None,
+                background: Some(Background::Color({
+                    let mut color = theme.cosmic().warning_color();
+                    color.alpha = 0.4;
+                    color.into()
+                })),
+                border: Border {
+                    radius: 0.0.into(),
+                    width: 0.0,
+                    color: Color::TRANSPARENT,
+                },
+                shadow: Default::default(),
+            }),
             TabBackgroundTheme::Default => Self::Transparent,
         }
     }
@@ -140,10 +161,10 @@ pub struct Tab<Message: TabMessage> {
 }
 
 impl<Message: TabMessage + 'static> Tab<Message> {
-    pub fn new(title: impl Into<String>, app_id: impl Into<String>, id: Id) -> Self {
+    pub fn new(title: impl Into<String>, app_icon: icon::Handle, id: Id) -> Self {
         Tab {
             id,
-            app_icon: from_name(app_id.into()).size(16).icon(),
+            app_icon: icon::icon(app_icon).size(16),
             title: title.into(),
             font: cosmic::font::default(),
             close_message: None,
@@ -170,6 +191,14 @@ impl<Message: TabMessage + 'static> Tab<Message> {
         self
     }
 
+    /// Highlights an inactive tab, e.g. because its window rang the bell
+    pub fn urgent(mut self, urgent: bool) -> Self {
+        if urgent {
+            self.background_theme = TabBackgroundTheme::Urgent;
+        }
+        self
+    }
+
     pub(super) fn font(mut self, font: Font) -> Self {
         self.font = font;
         self
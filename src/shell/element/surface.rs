@@ -9,10 +9,11 @@ use crate::{
         corner_radius::surface_corners,
     },
 };
+use cosmic::widget::icon;
 use std::{
     borrow::Cow,
     sync::{
-        Mutex,
+        Arc, Mutex,
         atomic::{AtomicBool, Ordering},
     },
     time::Duration,
@@ -45,7 +46,10 @@ use smithay::{
             },
         },
         wayland_protocols_misc::server_decoration::server::org_kde_kwin_server_decoration::Mode as KdeMode,
-        wayland_server::protocol::wl_surface::WlSurface,
+        wayland_server::{
+            DisplayHandle, Resource,
+            protocol::{wl_buffer::WlBuffer, wl_shm, wl_surface::WlSurface},
+        },
     },
     utils::{
         IsAlive, Logical, Physical, Point, Rectangle, Scale, Serial, Size, user_data::UserDataMap,
@@ -61,6 +65,8 @@ use smithay::{
         shell::xdg::{
             SurfaceCachedState, ToplevelCachedState, ToplevelSurface, XdgToplevelSurfaceData,
         },
+        shm::with_buffer_contents,
+        xdg_toplevel_icon::ToplevelIconCachedState,
     },
     xwayland::{X11Surface, xwm::X11Relatable},
 };
@@ -69,9 +75,13 @@ use tracing::trace;
 use crate::{
     state::{State, SurfaceDmabufFeedback},
     utils::prelude::*,
-    wayland::handlers::{
-        compositor::FRAME_TIME_FILTER,
-        decoration::{KdeDecorationData, PreferredDecorationMode},
+    wayland::{
+        handlers::{
+            compositor::FRAME_TIME_FILTER,
+            decoration::{KdeDecorationData, PreferredDecorationMode},
+            xdg_dialog::is_modal,
+        },
+        protocols::toplevel_info::Icon,
     },
 };
 
@@ -206,9 +216,64 @@ struct Minimized(AtomicBool);
 #[derive(Default)]
 struct Sticky(AtomicBool);
 
+#[derive(Default)]
+struct Urgent(AtomicBool);
+
+#[derive(Default)]
+struct DecorationsDisabled(AtomicBool);
+
+#[derive(Default)]
+struct ShadowDisabled(AtomicBool);
+
 #[derive(Default)]
 struct GlobalGeometry(Mutex<Option<Rectangle<i32, Global>>>);
 
+/// Last icon supplied through xdg-toplevel-icon, with what it was created from
+#[derive(Default)]
+struct ToplevelIcon(Mutex<Option<(IconSource, icon::Handle, Icon)>>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum IconSource {
+    Name(String),
+    Buffer(WlBuffer),
+}
+
+/// Copies a square argb8888 shm buffer into straight rgba pixels.
+fn icon_pixels(buffer: &WlBuffer) -> Option<(u32, Vec<u8>)> {
+    with_buffer_contents(buffer, |ptr, len, data| {
+        if data.format != wl_shm::Format::Argb8888 || data.width != data.height || data.width <= 0 {
+            return None;
+        }
+        let size = data.width as usize;
+        let stride = data.stride as usize;
+        let offset = data.offset as usize;
+        if stride < size * 4 || offset + stride * (size - 1) + size * 4 > len {
+            return None;
+        }
+
+        // SAFETY: `with_buffer_contents` guarantees `ptr` to be valid for `len` bytes
+        let contents = unsafe { std::slice::from_raw_parts(ptr, len) };
+        let mut pixels = Vec::with_capacity(size * size * 4);
+        for row in contents[offset..].chunks(stride).take(size) {
+            for pixel in row[..size * 4].chunks_exact(4) {
+                // little endian argb, with premultiplied alpha
+                let [b, g, r, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                let unmultiply = |c: u8| {
+                    if a == 0 {
+                        0
+                    } else {
+                        (c as u32 * 255 / a as u32).min(255) as u8
+                    }
+                };
+                pixels.extend_from_slice(&[unmultiply(r), unmultiply(g), unmultiply(b), a]);
+            }
+        }
+        Some((data.width as u32, pixels))
+    })
+    .ok()
+    .flatten()
+}
+
 impl CosmicSurface {
     pub fn title(&self) -> String {
         match self.0.underlying_surface() {
@@ -250,6 +315,74 @@ impl CosmicSurface {
         }
     }
 
+    /// Icon the client supplied through xdg-toplevel-icon, if any
+    pub fn icon(&self) -> Option<icon::Handle> {
+        self.cached_icon().map(|(handle, _)| handle)
+    }
+
+    /// Icon the client supplied through xdg-toplevel-icon, as forwarded by toplevel-info
+    pub fn toplevel_icon(&self) -> Option<Icon> {
+        self.cached_icon().map(|(_, icon)| icon)
+    }
+
+    fn cached_icon(&self) -> Option<(icon::Handle, Icon)> {
+        let WindowSurface::Wayland(toplevel) = self.0.underlying_surface() else {
+            return None;
+        };
+        // named icons are preferred, buffers are meant as a fallback
+        let source = with_states(toplevel.wl_surface(), |states| {
+            let mut cached = states.cached_state.get::<ToplevelIconCachedState>();
+            let current = cached.current();
+            match current.icon_name() {
+                Some(name) => Some(IconSource::Name(name.to_string())),
+                None => current
+                    .buffers()
+                    .iter()
+                    .max_by_key(|(_, scale)| *scale)
+                    .map(|(buffer, _)| IconSource::Buffer(buffer.clone())),
+            }
+        })?;
+
+        let mut cache = self
+            .user_data()
+            .get_or_insert_threadsafe(ToplevelIcon::default)
+            .0
+            .lock()
+            .unwrap();
+        if let Some((cached_source, handle, icon)) = cache.as_ref()
+            && *cached_source == source
+        {
+            return Some((handle.clone(), icon.clone()));
+        }
+
+        let (handle, icon) = match &source {
+            IconSource::Name(name) => (
+                icon::from_name(name.clone()).size(16).handle(),
+                Icon::Name(name.clone()),
+            ),
+            IconSource::Buffer(buffer) => {
+                let (size, pixels) = icon_pixels(buffer)?;
+                let handle = icon::from_raster_pixels(size, size, pixels.clone());
+                let pixels = Arc::from(pixels);
+                (handle, Icon::Pixels { size, pixels })
+            }
+        };
+        *cache = Some((source, handle.clone(), icon.clone()));
+        Some((handle, icon))
+    }
+
+    /// Process id of the client owning the window, if it can be determined
+    pub fn pid(&self, dh: &DisplayHandle) -> Option<u32> {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                let client = toplevel.wl_surface().client()?;
+                let credentials = client.get_credentials(dh).ok()?;
+                u32::try_from(credentials.pid).ok()
+            }
+            WindowSurface::X11(surface) => surface.pid(),
+        }
+    }
+
     pub fn pending_size(&self) -> Option<Size<i32, Logical>> {
         match self.0.underlying_surface() {
             WindowSurface::Wayland(toplevel) => toplevel.with_pending_state(|state| state.size),
@@ -264,6 +397,13 @@ impl CosmicSurface {
         }
     }
 
+    pub fn is_modal(&self) -> bool {
+        match self.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => is_modal(toplevel.wl_surface()),
+            WindowSurface::X11(surface) => surface.is_modal(),
+        }
+    }
+
     pub fn last_server_size(&self) -> Option<Size<i32, Logical>> {
         match self.0.underlying_surface() {
             WindowSurface::Wayland(toplevel) => with_states(toplevel.wl_surface(), |states| {
@@ -333,6 +473,9 @@ impl CosmicSurface {
     }
 
     pub fn set_activated(&self, activated: bool) {
+        if activated {
+            self.set_urgent(false);
+        }
         match self.0.underlying_surface() {
             WindowSurface::Wayland(toplevel) => toplevel.with_pending_state(|state| {
                 if activated {
@@ -549,6 +692,61 @@ impl CosmicSurface {
         }
     }
 
+    /// Whether the window rang the bell since it was last activated
+    pub fn is_urgent(&self) -> bool {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(Urgent::default)
+            .0
+            .load(Ordering::SeqCst)
+    }
+
+    pub fn set_urgent(&self, urgent: bool) {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(Urgent::default)
+            .0
+            .store(urgent, Ordering::SeqCst);
+    }
+
+    /// Whether window rules turned off decorations for this window
+    pub fn decorations_disabled(&self) -> bool {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(DecorationsDisabled::default)
+            .0
+            .load(Ordering::SeqCst)
+    }
+
+    pub fn set_decorations_disabled(&self, disabled: bool) {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(DecorationsDisabled::default)
+            .0
+            .store(disabled, Ordering::SeqCst);
+        if disabled {
+            // ask the client to not draw any decorations either
+            self.try_force_undecorated(true);
+        }
+    }
+
+    /// Whether window rules turned off the shadow for this window
+    pub fn shadow_disabled(&self) -> bool {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(ShadowDisabled::default)
+            .0
+            .load(Ordering::SeqCst)
+    }
+
+    pub fn set_shadow_disabled(&self, disabled: bool) {
+        self.0
+            .user_data()
+            .get_or_insert_threadsafe(ShadowDisabled::default)
+            .0
+            .store(disabled, Ordering::SeqCst);
+    }
+
     pub fn set_suspended(&self, suspended: bool) {
         if let WindowSurface::Wayland(window) = self.0.underlying_surface() {
             window.with_pending_state(|state| {
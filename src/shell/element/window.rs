@@ -173,7 +173,7 @@ impl CosmicWindowInternal {
 
     /// returns if the window has any current or pending server-side decorations
     pub fn has_ssd(&self, pending: bool) -> bool {
-        !self.window.is_decorated(pending)
+        !self.window.is_decorated(pending) && !self.window.decorations_disabled()
     }
 
     /// returns if the window is currently tiled
@@ -407,7 +407,7 @@ impl CosmicWindow {
             let appearance = p.appearance_conf.lock().unwrap();
             let theme = p.theme.lock().unwrap();
 
-            if p.window.is_maximized(false) {
+            if p.window.is_maximized(false) || p.window.shadow_disabled() {
                 return None;
             }
 
@@ -621,6 +621,10 @@ impl CosmicWindow {
         self.0.force_redraw();
     }
 
+    pub(crate) fn force_update(&self) {
+        self.0.force_update();
+    }
+
     pub fn min_size(&self) -> Option<Size<i32, Logical>> {
         self.0
             .with_program(|p| p.window.min_size_without_ssd())
@@ -854,7 +858,14 @@ impl Decorations<CosmicWindowInternal, Message> for DefaultDecorations {
         let sharp_corners = win.window.is_maximized(false)
             || (win.is_tiled() && !win.appearance_conf.lock().unwrap().clip_tiled_windows);
 
+        let app_icon = win.window.icon().unwrap_or_else(|| {
+            cosmic::widget::icon::from_name(win.window.app_id())
+                .size(16)
+                .handle()
+        });
+
         let mut header = cosmic::widget::header_bar()
+            .start(cosmic::widget::icon::icon(app_icon).size(16))
             .title(win.last_title.lock().unwrap().clone())
             .on_drag(Message::DragStart)
             .on_close(Message::Close)
@@ -195,6 +195,18 @@ impl Shell {
         serial: Option<Serial>,
         update_cursor: bool,
     ) {
+        // parents of modal dialogs can't be focused
+        let modal = match target {
+            Some(KeyboardFocusTarget::Element(mapped)) => state
+                .common
+                .shell
+                .read()
+                .modal_for(mapped)
+                .map(KeyboardFocusTarget::Element),
+            _ => None,
+        };
+        let target = modal.as_ref().or(target);
+
         let focus_target = match target {
             Some(KeyboardFocusTarget::Element(mapped)) => Some(FocusTarget::Window(mapped.clone())),
             Some(KeyboardFocusTarget::Fullscreen(surface)) => {
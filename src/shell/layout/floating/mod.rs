@@ -9,6 +9,7 @@ use std::{
 use cosmic_comp_config::AppearanceConfig;
 use cosmic_settings_config::shortcuts::action::ResizeDirection;
 use keyframe::{ease, functions::EaseInOutCubic};
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use smithay::{
     backend::{
@@ -26,7 +27,9 @@ use smithay::{
 };
 
 use crate::{
-    backend::render::{IndicatorShader, Key, Usage, element::AsGlowRenderer},
+    backend::render::{
+        BackdropShader, IndicatorShader, Key, MODAL_BACKDROP_ALPHA, Usage, element::AsGlowRenderer,
+    },
     shell::{
         CosmicSurface, Direction, ManagedLayer, MoveResult, ResizeMode,
         element::{
@@ -168,7 +171,7 @@ impl Animation {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TiledCorners {
     Top,
     TopRight,
@@ -739,6 +742,18 @@ impl FloatingLayout {
         }
     }
 
+    /// Moves `mapped` to `position` without animating it, e.g. to keep it over another window.
+    pub fn relocate(&mut self, mapped: &CosmicMapped, position: Point<i32, Local>) {
+        let Some(geometry) = self.element_geometry(mapped) else {
+            return;
+        };
+        let output = self.space.outputs().next().unwrap().clone();
+        mapped.set_geometry(Rectangle::new(position, geometry.size).to_global(&output));
+        mapped.configure();
+        self.space
+            .map_element(mapped.clone(), position.as_logical(), false);
+    }
+
     pub fn element_geometry(&self, elem: &CosmicMapped) -> Option<Rectangle<i32, Local>> {
         self.space.element_geometry(elem).map(RectExt::as_local)
     }
@@ -1587,6 +1602,21 @@ impl FloatingLayout {
                 None
             };
 
+            if elem.has_modal.load(Ordering::SeqCst) {
+                let radius = elem.corner_radius(geometry.size.as_logical(), 0);
+                push(
+                    BackdropShader::element(
+                        renderer,
+                        Key::Window(Usage::ModalBackdrop, elem.key()),
+                        geometry,
+                        radius.into_iter().max().unwrap_or(0) as f32,
+                        alpha * MODAL_BACKDROP_ALPHA,
+                        [0.0, 0.0, 0.0],
+                    )
+                    .into(),
+                );
+            }
+
             if focused == Some(elem) && !elem.is_maximized(false) {
                 let active_window_hint = crate::theme::active_window_hint(theme);
                 let radius = elem.corner_radius(geometry.size.as_logical(), indicator_thickness);
@@ -1,17 +1,21 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
+use cosmic_comp_config::window_rules::WindowRule;
 use cosmic_settings_config::{shortcuts::action::Orientation, window_rules::ApplicationException};
 use regex::{Regex, RegexSet};
 use smithay::{
     desktop::WindowSurface,
+    utils::{Logical, Point, Size},
     wayland::{compositor::with_states, shell::xdg::XdgToplevelSurfaceData},
     xwayland::xwm::WmWindowType,
 };
 use tracing::warn;
 
 use super::CosmicSurface;
+use crate::utils::prelude::Local;
 
 pub mod floating;
+pub mod restore;
 pub mod tiling;
 
 pub fn is_dialog(window: &CosmicSurface) -> bool {
@@ -101,3 +105,116 @@ pub fn has_floating_exception(exceptions: &TilingExceptions, window: &CosmicSurf
 
     false
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct WindowRules(Vec<CompiledWindowRule>);
+
+#[derive(Debug, Clone)]
+struct CompiledWindowRule {
+    app_id: Option<Regex>,
+    title: Option<Regex>,
+    x11_class: Option<Regex>,
+    join_stack: Option<Regex>,
+    rule: WindowRule,
+}
+
+/// Combined outcome of all rules matching a window
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedWindowRule {
+    pub output: Option<String>,
+    pub workspace: Option<usize>,
+    pub floating: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub maximized: Option<bool>,
+    pub sticky: Option<bool>,
+    pub minimized: Option<bool>,
+    pub size: Option<Size<i32, Logical>>,
+    pub position: Option<Point<i32, Local>>,
+    pub join_stack: Option<Regex>,
+    pub decorations: Option<bool>,
+    pub shadow: Option<bool>,
+}
+
+fn compile_regex(pattern: Option<&String>, field: &str) -> Result<Option<Regex>, ()> {
+    match pattern.map(|pattern| Regex::new(pattern)) {
+        Some(Ok(regex)) => Ok(Some(regex)),
+        Some(Err(e)) => {
+            warn!("Invalid regex for window rule {}: {}", field, e);
+            Err(())
+        }
+        None => Ok(None),
+    }
+}
+
+impl WindowRules {
+    pub fn new<'a, I>(rules_config: I) -> Self
+    where
+        I: Iterator<Item = &'a WindowRule>,
+    {
+        let rules = rules_config
+            .filter_map(|rule| {
+                Some(CompiledWindowRule {
+                    app_id: compile_regex(rule.matches.app_id.as_ref(), "app_id").ok()?,
+                    title: compile_regex(rule.matches.title.as_ref(), "title").ok()?,
+                    x11_class: compile_regex(rule.matches.x11_class.as_ref(), "x11_class").ok()?,
+                    join_stack: compile_regex(rule.join_stack.as_ref(), "join_stack").ok()?,
+                    rule: rule.clone(),
+                })
+            })
+            .collect();
+
+        Self(rules)
+    }
+
+    pub fn resolve(&self, window: &CosmicSurface) -> ResolvedWindowRule {
+        let mut resolved = ResolvedWindowRule::default();
+        if self.0.is_empty() {
+            return resolved;
+        }
+
+        let app_id = window.app_id();
+        let title = window.title();
+        let x11_class = window.x11_surface().map(|surface| surface.class());
+
+        for compiled in self.0.iter().filter(|compiled| {
+            compiled
+                .app_id
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&app_id))
+                && compiled
+                    .title
+                    .as_ref()
+                    .is_none_or(|regex| regex.is_match(&title))
+                && compiled.x11_class.as_ref().is_none_or(|regex| {
+                    x11_class
+                        .as_ref()
+                        .is_some_and(|class| regex.is_match(class))
+                })
+                && compiled
+                    .rule
+                    .matches
+                    .xwayland
+                    .is_none_or(|xwayland| xwayland == x11_class.is_some())
+        }) {
+            let rule = &compiled.rule;
+            if rule.output.is_some() {
+                resolved.output.clone_from(&rule.output);
+            }
+            if compiled.join_stack.is_some() {
+                resolved.join_stack.clone_from(&compiled.join_stack);
+            }
+            resolved.workspace = rule.workspace.or(resolved.workspace);
+            resolved.floating = rule.floating.or(resolved.floating);
+            resolved.fullscreen = rule.fullscreen.or(resolved.fullscreen);
+            resolved.maximized = rule.maximized.or(resolved.maximized);
+            resolved.sticky = rule.sticky.or(resolved.sticky);
+            resolved.minimized = rule.minimized.or(resolved.minimized);
+            resolved.size = rule.size.map(Size::from).or(resolved.size);
+            resolved.position = rule.position.map(Point::from).or(resolved.position);
+            resolved.decorations = rule.decorations.or(resolved.decorations);
+            resolved.shadow = rule.shadow.or(resolved.shadow);
+        }
+
+        resolved
+    }
+}
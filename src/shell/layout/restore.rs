@@ -0,0 +1,1001 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Persistence of window placement across compositor restarts.
+//!
+//! A [`LayoutSnapshot`] records the tiling trees, floating geometries and stacks
+//! of every workspace, identifying windows by app-id and title. After a restart
+//! the snapshot is loaded into a [`PendingRestore`], which puts windows back into
+//! their previous slots as they are mapped again.
+//!
+//! Toplevels of xdg-sessions are remembered individually by their session and
+//! name instead, see [`SessionSnapshots`].
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use calloop::{
+    LoopHandle,
+    timer::{TimeoutAction, Timer},
+};
+use cosmic_comp_config::{actions::TilingStrategy, workspace::OutputMatch};
+use cosmic_settings_config::shortcuts::action::Orientation;
+use id_tree::{NodeId, Tree};
+use serde::{Deserialize, Serialize};
+use smithay::{
+    output::Output,
+    utils::{IsAlive, Rectangle},
+};
+use tracing::error;
+
+use super::tiling::{Data, RestoreTilingState};
+use crate::{
+    shell::{
+        CosmicMapped, CosmicSurface, Workspace, Workspaces,
+        element::surface::WeakCosmicSurface,
+        workspace::{
+            FloatingRestoreData, FullscreenRestoreState, ManagedLayer, TilingRestoreData,
+            WorkspaceRestoreData, output_match_for_output, output_matches,
+        },
+    },
+    state::State,
+    utils::prelude::*,
+    wayland::{
+        handlers::session_management::{save_toplevels, toplevel_session},
+        protocols::workspace::WorkspaceHandle,
+    },
+};
+
+/// How often the layout is checked for changes and persisted, title changes alone don't count
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5);
+/// How long reappearing windows are matched against the previous layout
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Precision of split ratios, when turned back into sizes
+const RATIO_SCALE: f64 = 10000.0;
+/// How long toplevels of xdg-sessions are remembered without being seen
+const SESSION_MAX_AGE: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+/// How often unchanged toplevels of xdg-sessions are marked as seen
+const SESSION_REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How many xdg-sessions are remembered at most
+const MAX_SESSIONS: usize = 64;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutSnapshot {
+    pub workspaces: Vec<WorkspaceSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot {
+    pub output: OutputMatch,
+    pub idx: usize,
+    pub tiling: Option<NodeSnapshot>,
+    pub floating: Vec<FloatingSnapshot>,
+    /// Whether the tiled windows formed scrollable columns
+    #[serde(default)]
+    pub scrolling: bool,
+    #[serde(default)]
+    pub strategy: TilingStrategy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NodeSnapshot {
+    Group {
+        orientation: Orientation,
+        /// Share of every child of the length of the group
+        ratios: Vec<f64>,
+        children: Vec<NodeSnapshot>,
+    },
+    /// A single window, or a stack if it holds more than one
+    Windows(Vec<WindowKey>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FloatingSnapshot {
+    pub windows: Vec<WindowKey>,
+    /// Position relative to the output
+    pub position: (i32, i32),
+    pub size: (i32, i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WindowKey {
+    pub app_id: String,
+    pub title: String,
+}
+
+impl From<&CosmicSurface> for WindowKey {
+    fn from(surface: &CosmicSurface) -> Self {
+        WindowKey {
+            app_id: surface.app_id(),
+            title: surface.title(),
+        }
+    }
+}
+
+fn window_keys(mapped: &CosmicMapped) -> Vec<WindowKey> {
+    mapped.windows().map(|(w, _)| WindowKey::from(&w)).collect()
+}
+
+impl LayoutSnapshot {
+    pub fn capture(workspaces: &Workspaces) -> LayoutSnapshot {
+        let workspaces = workspaces
+            .sets
+            .iter()
+            .flat_map(|(output, set)| {
+                set.workspaces
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(idx, workspace)| {
+                        WorkspaceSnapshot::capture(output, idx, workspace)
+                    })
+            })
+            .collect();
+
+        LayoutSnapshot { workspaces }
+    }
+
+    /// Whether both snapshots place the same windows the same way.
+    ///
+    /// Titles change too often to rewrite the layout for, so they are ignored.
+    pub fn same_layout(&self, other: &LayoutSnapshot) -> bool {
+        self.without_titles() == other.without_titles()
+    }
+
+    fn without_titles(&self) -> LayoutSnapshot {
+        let mut snapshot = self.clone();
+        for workspace in &mut snapshot.workspaces {
+            workspace.tiling = workspace.tiling.as_ref().map(NodeSnapshot::without_titles);
+            for key in workspace
+                .floating
+                .iter_mut()
+                .flat_map(|floating| floating.windows.iter_mut())
+            {
+                key.title.clear();
+            }
+        }
+        snapshot
+    }
+}
+
+impl WorkspaceSnapshot {
+    fn capture(output: &Output, idx: usize, workspace: &Workspace) -> Option<WorkspaceSnapshot> {
+        let tree = workspace.tiling_layer.tree();
+        let tiling = tree
+            .root_node_id()
+            .and_then(|root| NodeSnapshot::capture(tree, root));
+        let floating = workspace
+            .floating_layer
+            .mapped()
+            .filter_map(|mapped| {
+                let geometry = workspace.floating_layer.element_geometry(mapped)?;
+                Some(FloatingSnapshot {
+                    windows: window_keys(mapped),
+                    position: (geometry.loc.x, geometry.loc.y),
+                    size: (geometry.size.w, geometry.size.h),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        (tiling.is_some() || !floating.is_empty()).then(|| WorkspaceSnapshot {
+            output: output_match_for_output(output),
+            idx,
+            tiling,
+            floating,
+            scrolling: workspace.tiling_layer.is_scrolling(),
+            strategy: workspace.tiling_layer.strategy(),
+        })
+    }
+}
+
+impl NodeSnapshot {
+    pub(super) fn capture(tree: &Tree<Data>, id: &NodeId) -> Option<NodeSnapshot> {
+        let node = tree.get(id).ok()?;
+        match node.data() {
+            Data::Group {
+                orientation, sizes, ..
+            } => {
+                let (ratios, children): (Vec<_>, Vec<_>) = node
+                    .children()
+                    .iter()
+                    .zip(sizes.iter())
+                    .filter_map(|(child, size)| {
+                        NodeSnapshot::capture(tree, child).map(|child| (*size as f64, child))
+                    })
+                    .unzip();
+                let total: f64 = ratios.iter().sum();
+                match children.len() {
+                    0 => None,
+                    1 => children.into_iter().next(),
+                    _ => Some(NodeSnapshot::Group {
+                        orientation: *orientation,
+                        ratios: ratios.into_iter().map(|size| size / total).collect(),
+                        children,
+                    }),
+                }
+            }
+            Data::Mapped { mapped, .. } => Some(NodeSnapshot::Windows(window_keys(mapped))),
+            Data::Placeholder { .. } => None,
+        }
+    }
+
+    fn without_titles(&self) -> NodeSnapshot {
+        let mut snapshot = self.clone();
+        for key in snapshot.keys_mut() {
+            key.title.clear();
+        }
+        snapshot
+    }
+
+    fn keys_mut(&mut self) -> Box<dyn Iterator<Item = &mut WindowKey> + '_> {
+        match self {
+            NodeSnapshot::Group { children, .. } => {
+                Box::new(children.iter_mut().flat_map(NodeSnapshot::keys_mut))
+            }
+            NodeSnapshot::Windows(windows) => Box::new(windows.iter_mut()),
+        }
+    }
+
+    fn get(&self, path: &[usize]) -> Option<&NodeSnapshot> {
+        match path.split_first() {
+            None => Some(self),
+            Some((idx, rest)) => match self {
+                NodeSnapshot::Group { children, .. } => children.get(*idx)?.get(rest),
+                NodeSnapshot::Windows(_) => None,
+            },
+        }
+    }
+
+    pub(super) fn leaves(
+        &self,
+        path: &mut Vec<usize>,
+        leaves: &mut Vec<(Vec<usize>, Vec<WindowKey>)>,
+    ) {
+        match self {
+            NodeSnapshot::Group { children, .. } => {
+                for (idx, child) in children.iter().enumerate() {
+                    path.push(idx);
+                    child.leaves(path, leaves);
+                    path.pop();
+                }
+            }
+            NodeSnapshot::Windows(windows) => leaves.push((path.clone(), windows.clone())),
+        }
+    }
+}
+
+/// Where a restored window should be mapped
+#[derive(Debug)]
+pub struct Placement {
+    pub workspace: WorkspaceHandle,
+    /// Layer to map into, or wherever the window would go otherwise
+    pub layer: Option<PlacementLayer>,
+    /// Stack of a previously restored window, the new window joins
+    pub stack: Option<CosmicMapped>,
+    /// Whether the window was part of a stack
+    pub stacked: bool,
+    /// Tiling mode of the saved workspace, to apply if the window is the first one mapped there
+    pub scrolling: Option<bool>,
+    pub strategy: Option<TilingStrategy>,
+}
+
+#[derive(Debug)]
+pub enum PlacementLayer {
+    Tiling(Option<RestoreTilingState>),
+    Floating(Rectangle<i32, Local>),
+}
+
+#[derive(Debug)]
+pub struct PendingRestore {
+    started: Instant,
+    snapshot: LayoutSnapshot,
+    slots: Vec<Slot>,
+}
+
+#[derive(Debug)]
+struct Slot {
+    key: WindowKey,
+    workspace: usize,
+    element: SlotElement,
+    stacked: bool,
+    restored: Option<WeakCosmicSurface>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SlotElement {
+    /// Path of child indices from the root of the tiling tree
+    Tiling(Vec<usize>),
+    /// Index into the floating windows of the workspace
+    Floating(usize),
+}
+
+impl Slot {
+    fn surface(&self) -> Option<CosmicSurface> {
+        self.restored
+            .as_ref()
+            .and_then(|weak| weak.upgrade())
+            .filter(|surface| surface.alive())
+    }
+}
+
+impl PendingRestore {
+    pub fn new(snapshot: LayoutSnapshot) -> Option<PendingRestore> {
+        let mut slots = Vec::new();
+        for (workspace, saved) in snapshot.workspaces.iter().enumerate() {
+            if let Some(tiling) = saved.tiling.as_ref() {
+                let mut leaves = Vec::new();
+                tiling.leaves(&mut Vec::new(), &mut leaves);
+                for (path, windows) in leaves {
+                    let stacked = windows.len() > 1;
+                    slots.extend(windows.into_iter().map(|key| Slot {
+                        key,
+                        workspace,
+                        element: SlotElement::Tiling(path.clone()),
+                        stacked,
+                        restored: None,
+                    }));
+                }
+            }
+            for (idx, floating) in saved.floating.iter().enumerate() {
+                let stacked = floating.windows.len() > 1;
+                slots.extend(floating.windows.iter().cloned().map(|key| Slot {
+                    key,
+                    workspace,
+                    element: SlotElement::Floating(idx),
+                    stacked,
+                    restored: None,
+                }));
+            }
+        }
+
+        (!slots.is_empty()).then(|| PendingRestore {
+            started: Instant::now(),
+            snapshot,
+            slots,
+        })
+    }
+
+    /// Whether windows are still matched against the previous layout
+    pub fn is_active(&self) -> bool {
+        self.started.elapsed() < RESTORE_TIMEOUT
+            && self.slots.iter().any(|slot| slot.surface().is_none())
+    }
+
+    /// Claims the slot `window` previously occupied and figures out how to map it there.
+    ///
+    /// Windows are matched by app-id and title first, by app-id only otherwise.
+    pub fn place(&mut self, window: &CosmicSurface, workspaces: &Workspaces) -> Option<Placement> {
+        if self.started.elapsed() >= RESTORE_TIMEOUT {
+            return None;
+        }
+
+        let key = WindowKey::from(window);
+        let slot_idx = self
+            .slots
+            .iter()
+            .position(|slot| slot.key == key && slot.surface().is_none())
+            .or_else(|| {
+                self.slots
+                    .iter()
+                    .position(|slot| slot.key.app_id == key.app_id && slot.surface().is_none())
+            })?;
+
+        let slot = &self.slots[slot_idx];
+        let saved = &self.snapshot.workspaces[slot.workspace];
+        let output = find_output(workspaces, &saved.output)?;
+        let len = workspaces.len(output);
+        let workspace = workspaces.get(saved.idx.min(len.saturating_sub(1)), output)?;
+
+        let stack = slot
+            .stacked
+            .then(|| {
+                self.slots
+                    .iter()
+                    .filter(|other| {
+                        other.workspace == slot.workspace && other.element == slot.element
+                    })
+                    .filter_map(Slot::surface)
+                    .find_map(|surface| {
+                        workspace
+                            .mapped()
+                            .find(|m| m.is_stack() && m.windows().any(|(w, _)| w == surface))
+                            .cloned()
+                    })
+            })
+            .flatten();
+
+        let layer = match &slot.element {
+            SlotElement::Tiling(path) => {
+                PlacementLayer::Tiling(self.tiling_state(slot.workspace, path, workspace))
+            }
+            SlotElement::Floating(idx) => {
+                let floating = &saved.floating[*idx];
+                PlacementLayer::Floating(Rectangle::new(
+                    floating.position.into(),
+                    floating.size.into(),
+                ))
+            }
+        };
+
+        let placement = Placement {
+            workspace: workspace.handle,
+            layer: Some(layer),
+            stack,
+            stacked: slot.stacked,
+            scrolling: Some(saved.scrolling),
+            strategy: Some(saved.strategy),
+        };
+        // only claim the slot, once the window actually goes there
+        self.slots[slot_idx].restored = Some(window.downgrade());
+        Some(placement)
+    }
+
+    fn tiling_state(
+        &self,
+        workspace: usize,
+        path: &[usize],
+        current: &Workspace,
+    ) -> Option<RestoreTilingState> {
+        let saved = self.snapshot.workspaces[workspace].tiling.as_ref()?;
+        let tree = current.tiling_layer.tree();
+        let restored = self
+            .slots
+            .iter()
+            .filter(|slot| slot.workspace == workspace)
+            .filter_map(|slot| {
+                let SlotElement::Tiling(path) = &slot.element else {
+                    return None;
+                };
+                let node = node_for_surface(tree, &slot.surface()?)?;
+                Some((path.as_slice(), node))
+            })
+            .collect::<Vec<_>>();
+
+        tiling_state(saved, path, &restored, tree)
+    }
+}
+
+fn find_output<'a>(workspaces: &'a Workspaces, output_match: &OutputMatch) -> Option<&'a Output> {
+    let outputs = || workspaces.sets.keys();
+    outputs()
+        .find(|output| output_matches(output_match, output, true))
+        .or_else(|| outputs().find(|output| output_matches(output_match, output, false)))
+}
+
+pub(super) fn node_for_surface(tree: &Tree<Data>, surface: &CosmicSurface) -> Option<NodeId> {
+    let root = tree.root_node_id()?;
+    tree.traverse_pre_order_ids(root).ok()?.find(|id| {
+        matches!(
+            tree.get(id).unwrap().data(),
+            Data::Mapped { mapped, .. } if mapped.windows().any(|(w, _)| &w == surface)
+        )
+    })
+}
+
+/// Finds the position in `tree`, that resembles `path` in the `saved` tree.
+///
+/// The saved tree is pruned to the `restored` windows, given by their saved path
+/// and current node. The window is inserted into the group mirroring its saved parent,
+/// if it still exists, or splits the closest restored sibling otherwise. Without any
+/// restored siblings the next ancestor is tried.
+pub(super) fn tiling_state(
+    saved: &NodeSnapshot,
+    path: &[usize],
+    restored: &[(&[usize], NodeId)],
+    tree: &Tree<Data>,
+) -> Option<RestoreTilingState> {
+    for depth in (1..=path.len()).rev() {
+        let parent_path = &path[..depth - 1];
+        let idx = path[depth - 1];
+        let Some(NodeSnapshot::Group {
+            orientation,
+            ratios,
+            ..
+        }) = saved.get(parent_path)
+        else {
+            return None;
+        };
+
+        let siblings = (0..ratios.len())
+            .filter(|child| *child != idx)
+            .filter_map(|child| {
+                let nodes = restored
+                    .iter()
+                    .filter(|(path, _)| {
+                        path.len() >= depth
+                            && path.starts_with(parent_path)
+                            && path[depth - 1] == child
+                    })
+                    .map(|(_, node)| node)
+                    .collect::<Vec<_>>();
+                common_ancestor(tree, &nodes).map(|node| (child, node))
+            })
+            .collect::<Vec<_>>();
+        if siblings.is_empty() {
+            continue;
+        }
+
+        let scale = |ratio: f64| (ratio * RATIO_SCALE).round().max(1.0) as i32;
+        let parent = tree.get(&siblings[0].1).ok()?.parent();
+        if let Some(parent) = parent
+            && let Data::Group {
+                orientation: current_orientation,
+                ..
+            } = tree.get(parent).unwrap().data()
+            && current_orientation == orientation
+            && tree
+                .children_ids(parent)
+                .unwrap()
+                .eq(siblings.iter().map(|(_, node)| node))
+        {
+            let new_idx = siblings.iter().filter(|(child, _)| *child < idx).count();
+            let mut sizes = siblings
+                .iter()
+                .map(|(child, _)| scale(ratios[*child]))
+                .collect::<Vec<_>>();
+            sizes.insert(new_idx, scale(ratios[idx]));
+            return Some(RestoreTilingState {
+                parent: Some(parent.clone()),
+                sibling: None,
+                orientation: *orientation,
+                idx: new_idx,
+                sizes,
+            });
+        }
+
+        let (child, sibling) = siblings
+            .into_iter()
+            .min_by_key(|(child, _)| child.abs_diff(idx))
+            .unwrap();
+        let (new_idx, sizes) = if child < idx {
+            (1, vec![scale(ratios[child]), scale(ratios[idx])])
+        } else {
+            (0, vec![scale(ratios[idx]), scale(ratios[child])])
+        };
+        return Some(RestoreTilingState {
+            parent: None,
+            sibling: Some(sibling),
+            orientation: *orientation,
+            idx: new_idx,
+            sizes,
+        });
+    }
+
+    None
+}
+
+fn common_ancestor(tree: &Tree<Data>, nodes: &[&NodeId]) -> Option<NodeId> {
+    let (first, rest) = nodes.split_first()?;
+    std::iter::once(*first)
+        .chain(tree.ancestor_ids(first).ok()?)
+        .find(|candidate| {
+            rest.iter().all(|node| {
+                node == candidate
+                    || tree
+                        .ancestor_ids(node)
+                        .is_ok_and(|mut ancestors| ancestors.any(|id| id == *candidate))
+            })
+        })
+        .cloned()
+}
+
+/// Restore data of the toplevels of xdg-sessions, by session id and toplevel name
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SessionSnapshots(HashMap<String, HashMap<String, ToplevelSnapshot>>);
+
+impl SessionSnapshots {
+    pub fn contains(&self, session: &str) -> bool {
+        self.0.contains_key(session)
+    }
+
+    pub fn get(&self, session: &str, name: &str) -> Option<&ToplevelSnapshot> {
+        self.0.get(session)?.get(name)
+    }
+
+    /// Whether `snapshot` differs from the stored one, or the stored one is due to be refreshed
+    pub fn needs_update(&self, session: &str, name: &str, snapshot: &ToplevelSnapshot) -> bool {
+        self.get(session, name).is_none_or(|stored| {
+            !stored.same_state(snapshot)
+                || snapshot.captured_at.saturating_sub(stored.captured_at)
+                    >= SESSION_REFRESH_INTERVAL.as_secs()
+        })
+    }
+
+    pub fn insert(&mut self, session: String, name: String, snapshot: ToplevelSnapshot) {
+        self.0.entry(session).or_default().insert(name, snapshot);
+        self.expire();
+    }
+
+    pub fn remove_session(&mut self, session: &str) {
+        self.0.remove(session);
+    }
+
+    pub fn remove_toplevel(&mut self, session: &str, name: &str) {
+        if let Some(toplevels) = self.0.get_mut(session) {
+            toplevels.remove(name);
+            if toplevels.is_empty() {
+                self.0.remove(session);
+            }
+        }
+    }
+
+    /// Forgets toplevels not seen for [`SESSION_MAX_AGE`] and the least recently seen
+    /// sessions beyond [`MAX_SESSIONS`].
+    pub fn expire(&mut self) {
+        let now = unix_time();
+        for toplevels in self.0.values_mut() {
+            toplevels.retain(|_, snapshot| {
+                now.saturating_sub(snapshot.captured_at) < SESSION_MAX_AGE.as_secs()
+            });
+        }
+        self.0.retain(|_, toplevels| !toplevels.is_empty());
+
+        while self.0.len() > MAX_SESSIONS {
+            let last_seen = |toplevels: &HashMap<String, ToplevelSnapshot>| {
+                toplevels
+                    .values()
+                    .map(|snapshot| snapshot.captured_at)
+                    .max()
+                    .unwrap_or_default()
+            };
+            let Some(oldest) = self
+                .0
+                .iter()
+                .min_by_key(|(_, toplevels)| last_seen(toplevels))
+                .map(|(session, _)| session.clone())
+            else {
+                break;
+            };
+            self.0.remove(&oldest);
+        }
+    }
+}
+
+/// State of a single toplevel of an xdg-session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToplevelSnapshot {
+    pub output: OutputMatch,
+    /// Index of the workspace on the output, as handles don't outlive the compositor
+    pub workspace: usize,
+    /// Tiling or floating state of the toplevel, or the one it returns to from fullscreen
+    pub previous: Option<WorkspaceRestoreData>,
+    /// Tiling tree of the workspace and the path of child indices from its root to the toplevel
+    pub tiling: Option<(NodeSnapshot, Vec<usize>)>,
+    pub fullscreen: bool,
+    /// Seconds since the unix epoch, the state was captured at
+    pub captured_at: u64,
+}
+
+impl ToplevelSnapshot {
+    /// Records the state of `surface`, if it is mapped on a workspace.
+    pub fn capture<S>(workspaces: &Workspaces, surface: &S) -> Option<ToplevelSnapshot>
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        workspaces.sets.iter().find_map(|(output, set)| {
+            set.workspaces
+                .iter()
+                .enumerate()
+                .find_map(|(idx, workspace)| {
+                    let (previous, tiling, fullscreen) = if let Some(fullscreen) = workspace
+                        .get_fullscreen_surfaces()
+                        .find(|f| &f.surface == surface)
+                    {
+                        let previous = match fullscreen.previous_state.as_ref() {
+                            Some(
+                                FullscreenRestoreState::Floating { state, .. }
+                                | FullscreenRestoreState::Sticky { state, .. },
+                            ) => Some(WorkspaceRestoreData::Floating(state.clone())),
+                            Some(FullscreenRestoreState::Tiling { state, .. }) => {
+                                Some(WorkspaceRestoreData::Tiling(TilingRestoreData {
+                                    state: None,
+                                    was_maximized: state.was_maximized,
+                                }))
+                            }
+                            _ => None,
+                        };
+                        (previous, None, true)
+                    } else {
+                        let mapped = workspace.element_for_surface(surface)?;
+                        let (previous, tiling) = Self::capture_layer(workspace, mapped, surface);
+                        (previous, tiling, false)
+                    };
+
+                    Some(ToplevelSnapshot {
+                        output: output_match_for_output(output),
+                        workspace: idx,
+                        previous,
+                        tiling,
+                        fullscreen,
+                        captured_at: unix_time(),
+                    })
+                })
+        })
+    }
+
+    /// Records the state of every mapped toplevel of an xdg-session, by session id and name.
+    pub fn capture_sessions(workspaces: &Workspaces) -> Vec<((String, String), ToplevelSnapshot)> {
+        workspaces
+            .sets
+            .values()
+            .flat_map(|set| set.workspaces.iter())
+            .flat_map(|workspace| {
+                workspace
+                    .mapped()
+                    .flat_map(|mapped| mapped.windows().map(|(w, _)| w))
+                    .chain(
+                        workspace
+                            .get_fullscreen_surfaces()
+                            .map(|f| f.surface.clone()),
+                    )
+                    .collect::<Vec<_>>()
+            })
+            .filter_map(|window| {
+                let session = toplevel_session(&*window.wl_surface()?)?;
+                Some((session, ToplevelSnapshot::capture(workspaces, &window)?))
+            })
+            .collect()
+    }
+
+    fn capture_layer<S>(
+        workspace: &Workspace,
+        mapped: &CosmicMapped,
+        surface: &S,
+    ) -> (
+        Option<WorkspaceRestoreData>,
+        Option<(NodeSnapshot, Vec<usize>)>,
+    )
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        let maximized_state = *mapped.maximized_state.lock().unwrap();
+        let was_maximized = maximized_state.is_some();
+        let tiled = || {
+            WorkspaceRestoreData::Tiling(TilingRestoreData {
+                state: None,
+                was_maximized,
+            })
+        };
+
+        let tree = workspace.tiling_layer.tree();
+        if workspace.tiling_layer.mapped().any(|(m, _)| m == mapped)
+            && let Some(saved) = tree
+                .root_node_id()
+                .and_then(|root| NodeSnapshot::capture(tree, root))
+            && let Some(window) = mapped.windows().map(|(w, _)| w).find(|w| w == surface)
+        {
+            let key = WindowKey::from(&window);
+            let mut leaves = Vec::new();
+            saved.leaves(&mut Vec::new(), &mut leaves);
+            let path = leaves
+                .into_iter()
+                .find(|(_, keys)| keys.contains(&key))
+                .map(|(path, _)| path);
+            return (Some(tiled()), path.map(|path| (saved, path)));
+        }
+
+        let (geometry, was_snapped) = match maximized_state {
+            Some(state) if state.original_layer == ManagedLayer::Tiling => {
+                return (Some(tiled()), None);
+            }
+            Some(state) => (Some(state.original_geometry), state.original_snapped),
+            None => (
+                workspace.floating_layer.element_geometry(mapped),
+                *mapped.floating_tiled.lock().unwrap(),
+            ),
+        };
+        let previous = geometry.map(|geometry| {
+            WorkspaceRestoreData::Floating(FloatingRestoreData {
+                geometry,
+                output_size: workspace.output.geometry().size.as_logical(),
+                was_maximized,
+                was_snapped,
+            })
+        });
+        (previous, None)
+    }
+
+    /// Whether both snapshots restore the toplevel the same way.
+    ///
+    /// Like for [`LayoutSnapshot::same_layout`] titles don't count, neither does the capture time.
+    pub fn same_state(&self, other: &ToplevelSnapshot) -> bool {
+        let same_previous = match (&self.previous, &other.previous) {
+            (Some(WorkspaceRestoreData::Tiling(a)), Some(WorkspaceRestoreData::Tiling(b))) => {
+                a == b
+            }
+            (Some(WorkspaceRestoreData::Floating(a)), Some(WorkspaceRestoreData::Floating(b))) => {
+                a == b
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        let without_titles = |snapshot: &ToplevelSnapshot| {
+            snapshot
+                .tiling
+                .as_ref()
+                .map(|(tree, path)| (tree.without_titles(), path.clone()))
+        };
+        same_previous
+            && self.output == other.output
+            && self.workspace == other.workspace
+            && self.fullscreen == other.fullscreen
+            && without_titles(self) == without_titles(other)
+    }
+
+    pub fn maximized(&self) -> bool {
+        match &self.previous {
+            Some(WorkspaceRestoreData::Tiling(data)) => data.was_maximized,
+            Some(WorkspaceRestoreData::Floating(data)) => data.was_maximized,
+            _ => false,
+        }
+    }
+
+    /// The output the toplevel was on, or one resembling it
+    pub fn output<'a>(&self, workspaces: &'a Workspaces) -> Option<&'a Output> {
+        find_output(workspaces, &self.output)
+    }
+
+    /// Figures out how to map `window` back into its previous slot.
+    pub fn place(&self, workspaces: &Workspaces) -> Option<Placement> {
+        let output = self.output(workspaces)?;
+        let len = workspaces.len(output);
+        let workspace = workspaces.get(self.workspace.min(len.saturating_sub(1)), output)?;
+
+        let layer = match self.previous.as_ref() {
+            Some(WorkspaceRestoreData::Tiling(_)) => {
+                let state = self.tiling.as_ref().and_then(|(saved, path)| {
+                    let tree = workspace.tiling_layer.tree();
+                    let mut leaves = Vec::new();
+                    saved.leaves(&mut Vec::new(), &mut leaves);
+                    let restored = leaves
+                        .into_iter()
+                        .filter(|(leaf, _)| leaf != path)
+                        .filter_map(|(leaf, keys)| Some((leaf, node_for_keys(tree, &keys)?)))
+                        .collect::<Vec<_>>();
+                    let restored = restored
+                        .iter()
+                        .map(|(leaf, node)| (leaf.as_slice(), node.clone()))
+                        .collect::<Vec<_>>();
+                    tiling_state(saved, path, &restored, tree)
+                });
+                Some(PlacementLayer::Tiling(state))
+            }
+            Some(WorkspaceRestoreData::Floating(data)) => {
+                let position =
+                    data.position_relative(workspace.output.geometry().size.as_logical());
+                Some(PlacementLayer::Floating(Rectangle::new(
+                    position,
+                    data.geometry.size,
+                )))
+            }
+            _ => None,
+        };
+
+        Some(Placement {
+            workspace: workspace.handle,
+            layer,
+            stack: None,
+            stacked: false,
+            scrolling: None,
+            strategy: None,
+        })
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Finds the node of a window, that carries any of the given `keys`.
+fn node_for_keys(tree: &Tree<Data>, keys: &[WindowKey]) -> Option<NodeId> {
+    let root = tree.root_node_id()?;
+    tree.traverse_pre_order_ids(root).ok()?.find(|id| {
+        matches!(
+            tree.get(id).unwrap().data(),
+            Data::Mapped { mapped, .. }
+                if mapped.windows().any(|(w, _)| keys.contains(&WindowKey::from(&w)))
+        )
+    })
+}
+
+/// Periodically persists the layout, once windows had the chance to be restored,
+/// and the state of toplevels of xdg-sessions.
+pub fn persist_layout(handle: &LoopHandle<'static, State>) {
+    if let Err(err) = handle.insert_source(Timer::from_duration(PERSIST_INTERVAL), |_, _, state| {
+        let toplevels = ToplevelSnapshot::capture_sessions(&state.common.shell.read().workspaces);
+        save_toplevels(&mut state.common.config.dynamic_conf, toplevels);
+
+        let mut shell = state.common.shell.write();
+        if shell
+            .pending_restore
+            .as_ref()
+            .is_some_and(|restore| restore.is_active())
+        {
+            return TimeoutAction::ToDuration(PERSIST_INTERVAL);
+        }
+        shell.pending_restore = None;
+        let snapshot = LayoutSnapshot::capture(&shell.workspaces);
+        std::mem::drop(shell);
+
+        if !state
+            .common
+            .config
+            .dynamic_conf
+            .layout()
+            .same_layout(&snapshot)
+        {
+            *state.common.config.dynamic_conf.layout_mut() = snapshot;
+        }
+        TimeoutAction::ToDuration(PERSIST_INTERVAL)
+    }) {
+        error!(?err, "Failed to schedule layout persistence");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn toplevel(captured_at: u64) -> ToplevelSnapshot {
+        ToplevelSnapshot {
+            output: OutputMatch {
+                name: "DP-1".into(),
+                edid: None,
+            },
+            workspace: 0,
+            previous: Some(WorkspaceRestoreData::Floating(FloatingRestoreData {
+                geometry: Rectangle::new((10, 20).into(), (300, 200).into()),
+                output_size: (1920, 1080).into(),
+                was_maximized: true,
+                was_snapped: None,
+            })),
+            tiling: None,
+            fullscreen: false,
+            captured_at,
+        }
+    }
+
+    #[test]
+    fn session_snapshots_roundtrip() {
+        let mut sessions = SessionSnapshots::default();
+        sessions.insert("session".into(), "main".into(), toplevel(unix_time()));
+
+        let serialized = ron::to_string(&sessions).unwrap();
+        let deserialized = ron::from_str::<SessionSnapshots>(&serialized).unwrap();
+        let snapshot = deserialized.get("session", "main").unwrap();
+        assert!(snapshot.same_state(sessions.get("session", "main").unwrap()));
+        assert!(snapshot.maximized());
+    }
+
+    #[test]
+    fn forgets_old_and_excess_sessions() {
+        let now = unix_time();
+        let mut sessions = SessionSnapshots::default();
+        sessions.insert("stale".into(), "main".into(), toplevel(0));
+        assert!(!sessions.contains("stale"));
+
+        for idx in 0..=MAX_SESSIONS {
+            sessions.insert(format!("{idx}"), "main".into(), toplevel(now - idx as u64));
+        }
+        assert_eq!(sessions.0.len(), MAX_SESSIONS);
+        assert!(sessions.contains("0"));
+        assert!(!sessions.contains(&format!("{MAX_SESSIONS}")));
+    }
+
+    #[test]
+    fn refreshes_unchanged_toplevels_daily() {
+        let now = unix_time();
+        let mut sessions = SessionSnapshots::default();
+        sessions.insert("session".into(), "main".into(), toplevel(now));
+
+        assert!(!sessions.needs_update("session", "main", &toplevel(now + 60)));
+        let tomorrow = now + SESSION_REFRESH_INTERVAL.as_secs();
+        assert!(sessions.needs_update("session", "main", &toplevel(tomorrow)));
+        let mut moved = toplevel(now);
+        moved.workspace = 1;
+        assert!(sessions.needs_update("session", "main", &moved));
+    }
+}
@@ -269,6 +269,27 @@ impl ResizeForkGrab {
                 .round();
                 self.accumulated_delta += delta;
 
+                if tiling_layer.is_scrolling() && tree.root_node_id() == Some(&self.node) {
+                    // columns are resized on their own, without taking space from their neighbour
+                    let Some(column) = tree
+                        .children_ids(&self.node)
+                        .unwrap()
+                        .nth(self.left_up_idx)
+                        .cloned()
+                    else {
+                        return true;
+                    };
+                    tiling_layer.resize_column(&column, delta as i32);
+                    let blocker = TilingLayout::update_positions(
+                        &output,
+                        &mut tree,
+                        gaps,
+                        tiling_layer.scroll_state(),
+                    );
+                    tiling_layer.queue.push_tree(tree, None, blocker);
+                    return false;
+                }
+
                 // check that we are still alive
                 let mut iter = tree
                     .children_ids(&self.node)
@@ -328,7 +349,12 @@ impl ResizeForkGrab {
                             _ => true,
                         });
                 if should_configure {
-                    let blocker = TilingLayout::update_positions(&output, &mut tree, gaps);
+                    let blocker = TilingLayout::update_positions(
+                        &output,
+                        &mut tree,
+                        gaps,
+                        tiling_layer.scroll_state(),
+                    );
                     tiling_layer.queue.push_tree(tree, None, blocker);
                 }
             } else {
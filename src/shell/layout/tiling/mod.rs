@@ -2,8 +2,8 @@
 
 use crate::{
     backend::render::{
-        ACTIVE_GROUP_COLOR, BackdropShader, GROUP_COLOR, IndicatorShader, Key, Usage,
-        element::AsGlowRenderer,
+        ACTIVE_GROUP_COLOR, BackdropShader, GROUP_COLOR, IndicatorShader, Key,
+        MODAL_BACKDROP_ALPHA, Usage, element::AsGlowRenderer,
     },
     shell::{
         CosmicSurface, Direction, FocusResult, MoveResult, OverviewMode, ResizeMode, Trigger,
@@ -37,7 +37,7 @@ use crate::{
     },
 };
 
-use cosmic_comp_config::AppearanceConfig;
+use cosmic_comp_config::{AppearanceConfig, actions::TilingStrategy};
 use cosmic_settings_config::shortcuts::action::{FocusDirection, ResizeDirection};
 use id_tree::{InsertBehavior, MoveBehavior, Node, NodeId, NodeIdError, RemoveBehavior, Tree};
 use keyframe::{
@@ -68,7 +68,7 @@ use smithay::{
 };
 use std::{
     collections::{HashMap, VecDeque},
-    sync::{Arc, Weak},
+    sync::{Arc, Weak, atomic::Ordering},
     time::{Duration, Instant},
 };
 use tracing::trace;
@@ -76,8 +76,16 @@ use wayland_backend::server::ClientId;
 
 mod blocker;
 mod grabs;
+mod presets;
+mod scrolling;
+mod strategy;
 pub use self::blocker::*;
 pub use self::grabs::*;
+use self::presets::ActivePreset;
+pub use self::presets::DragInProgress;
+use self::scrolling::ScrollState;
+pub use self::strategy::MASTER_RATIO_STEP;
+use self::strategy::MasterState;
 
 pub const ANIMATION_DURATION: Duration = Duration::from_millis(200);
 pub const MINIMIZE_ANIMATION_DURATION: Duration = Duration::from_millis(320);
@@ -136,6 +144,10 @@ pub struct TilingLayout {
     backdrop_id: Id,
     swapping_stack_surface_id: Id,
     last_overview_hover: Option<(Option<Instant>, TargetZone)>,
+    scrolling: Option<ScrollState>,
+    strategy: TilingStrategy,
+    master: MasterState,
+    preset: Option<ActivePreset>,
     pub theme: cosmic::Theme,
     pub appearance: AppearanceConfig,
 }
@@ -339,7 +351,7 @@ enum FocusedNodeData {
     Window(CosmicMapped),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RestoreTilingState {
     pub parent: Option<id_tree::NodeId>,
     pub sibling: Option<id_tree::NodeId>,
@@ -367,6 +379,10 @@ impl TilingLayout {
             backdrop_id: Id::new(),
             swapping_stack_surface_id: Id::new(),
             last_overview_hover: None,
+            scrolling: None,
+            strategy: TilingStrategy::default(),
+            master: MasterState::default(),
+            preset: None,
             theme,
             appearance,
         }
@@ -388,7 +404,7 @@ impl TilingLayout {
             }
         }
 
-        let blocker = TilingLayout::update_positions(output, &mut tree, gaps);
+        let blocker = TilingLayout::update_positions(output, &mut tree, gaps, self.scroll_state());
         self.queue.push_tree(tree, None, blocker);
         self.output = output.clone();
     }
@@ -423,15 +439,21 @@ impl TilingLayout {
             ANIMATION_DURATION
         };
 
-        TilingLayout::map_to_tree(
-            &mut tree,
-            window,
-            &self.output,
-            last_active,
-            direction,
-            minimize_rect,
-        );
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        if self.scrolling.is_some() && direction.is_none() {
+            TilingLayout::map_to_column(&mut tree, window, last_active, minimize_rect);
+        } else {
+            TilingLayout::map_to_tree(
+                &mut tree,
+                window,
+                &self.output,
+                last_active,
+                direction,
+                minimize_rect,
+            );
+            self.arrange(&mut tree);
+        }
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
         self.queue.push_tree(tree, duration, blocker);
     }
 
@@ -495,8 +517,14 @@ impl TilingLayout {
                     .unwrap();
                 tree.make_nth_sibling(&new_id, idx).unwrap();
                 *window.tiling_node_id.lock().unwrap() = Some(new_id);
+                self.arrange(&mut tree);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
                 self.queue
                     .push_tree(tree, MINIMIZE_ANIMATION_DURATION, blocker);
                 return;
@@ -533,8 +561,14 @@ impl TilingLayout {
                 }
 
                 *window.tiling_node_id.lock().unwrap() = Some(new_id);
+                self.arrange(&mut tree);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
                 self.queue
                     .push_tree(tree, MINIMIZE_ANIMATION_DURATION, blocker);
                 return;
@@ -637,7 +671,8 @@ impl TilingLayout {
             old.output_leave(&self.output);
             new.output_enter(&self.output, new.bbox());
 
-            let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+            let blocker =
+                TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
             self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
         }
     }
@@ -814,12 +849,20 @@ impl TilingLayout {
                 let other_gaps = other.gaps();
 
                 TilingLayout::unmap_internal(&mut this_tree, &desc.node);
-                let blocker =
-                    TilingLayout::update_positions(&this.output, &mut this_tree, this_gaps);
+                let blocker = TilingLayout::update_positions(
+                    &this.output,
+                    &mut this_tree,
+                    this_gaps,
+                    this.scroll_state(),
+                );
                 this.queue.push_tree(this_tree, ANIMATION_DURATION, blocker);
 
-                let blocker =
-                    TilingLayout::update_positions(&other.output, &mut other_tree, other_gaps);
+                let blocker = TilingLayout::update_positions(
+                    &other.output,
+                    &mut other_tree,
+                    other_gaps,
+                    other.scroll_state(),
+                );
                 other
                     .queue
                     .push_tree(other_tree, ANIMATION_DURATION, blocker);
@@ -1255,18 +1298,29 @@ impl TilingLayout {
         }
 
         let this_gaps = this.gaps();
-        let blocker = TilingLayout::update_positions(&this.output, &mut this_tree, this_gaps);
+        let this_scroll = this.scroll_state();
+        this.arrange(&mut this_tree);
+        let blocker = TilingLayout::update_positions(
+            &this.output,
+            &mut this_tree,
+            this_gaps,
+            this_scroll.clone(),
+        );
         this.queue.push_tree(this_tree, ANIMATION_DURATION, blocker);
 
         let has_other_tree = other_tree.is_some();
         if let Some(mut other_tree) = other_tree {
-            let (other_queue, gaps) = if let Some(other) = other.as_mut() {
+            let (other_queue, gaps, scroll) = if let Some(other) = other.as_mut() {
+                other.arrange(&mut other_tree);
                 let other_gaps = other.gaps();
-                (&mut other.queue, other_gaps)
+                let other_scroll = other.scroll_state();
+                (&mut other.queue, other_gaps, other_scroll)
             } else {
-                (&mut this.queue, this_gaps)
+                this.arrange(&mut other_tree);
+                (&mut this.queue, this_gaps, this_scroll)
             };
-            let blocker = TilingLayout::update_positions(&other_output, &mut other_tree, gaps);
+            let blocker =
+                TilingLayout::update_positions(&other_output, &mut other_tree, gaps, scroll);
             other_queue.push_tree(other_tree, ANIMATION_DURATION, blocker);
         }
 
@@ -1429,13 +1483,15 @@ impl TilingLayout {
             let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
 
             TilingLayout::unmap_internal(&mut tree, &node_id);
+            self.arrange(&mut tree);
 
             let duration = if minimizing {
                 MINIMIZE_ANIMATION_DURATION
             } else {
                 ANIMATION_DURATION
             };
-            let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+            let blocker =
+                TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
             self.queue.push_tree(tree, duration, blocker);
 
             return true;
@@ -1551,7 +1607,13 @@ impl TilingLayout {
                     .unwrap();
                     *mapped.tiling_node_id.lock().unwrap() = Some(new_id);
 
-                    let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                    self.arrange(&mut tree);
+                    let blocker = TilingLayout::update_positions(
+                        &self.output,
+                        &mut tree,
+                        gaps,
+                        self.scroll_state(),
+                    );
                     self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                     return MoveResult::ShiftFocus(mapped.into());
                 }
@@ -1627,7 +1689,13 @@ impl TilingLayout {
                     .data_mut()
                     .remove_window(og_idx);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                self.arrange(&mut tree);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                 return MoveResult::Done;
             }
@@ -1653,7 +1721,13 @@ impl TilingLayout {
                     .data_mut()
                     .remove_window(og_idx);
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                self.arrange(&mut tree);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                 return MoveResult::Done;
             }
@@ -1809,7 +1883,13 @@ impl TilingLayout {
                     MoveResult::Done
                 };
 
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                self.arrange(&mut tree);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
                 return result;
             }
@@ -2124,7 +2204,8 @@ impl TilingLayout {
 
             *orientation = new_orientation;
 
-            let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+            let blocker =
+                TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
             self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
         }
     }
@@ -2244,7 +2325,8 @@ impl TilingLayout {
             }
         };
 
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
         self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
 
         Some(result)
@@ -2321,7 +2403,12 @@ impl TilingLayout {
                         minimize_rect: None,
                     };
 
-                    let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                    let blocker = TilingLayout::update_positions(
+                        &self.output,
+                        &mut tree,
+                        gaps,
+                        self.scroll_state(),
+                    );
                     self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
 
                     return Some(KeyboardFocusTarget::Element(mapped));
@@ -2336,7 +2423,8 @@ impl TilingLayout {
         let gaps = self.gaps();
 
         let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
         self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
     }
 
@@ -2550,6 +2638,24 @@ impl TilingLayout {
                 continue;
             }
 
+            if self.is_scrolling() && tree.root_node_id() == Some(&group_id) {
+                // columns don't share the output, so only the column itself changes
+                let amount = if direction == ResizeDirection::Inwards {
+                    -amount
+                } else {
+                    amount
+                };
+                self.resize_column(&node_id, amount);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
+                self.queue.push_tree(tree, None, blocker);
+                return true;
+            }
+
             let node_idx = tree
                 .children_ids(&group_id)
                 .unwrap()
@@ -2609,7 +2715,12 @@ impl TilingLayout {
                         _ => true,
                     });
             if should_configure {
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
                 self.queue.push_tree(tree, None, blocker);
             }
 
@@ -2656,8 +2767,12 @@ impl TilingLayout {
 
             // If anything was changed, push updated tree
             if let Some(mut new_tree) = new_tree {
-                let blocker =
-                    TilingLayout::update_positions(&self.output, &mut new_tree, self.gaps());
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut new_tree,
+                    self.gaps(),
+                    self.scroll_state(),
+                );
                 self.queue.push_tree(new_tree, ANIMATION_DURATION, blocker);
             }
         }
@@ -2816,7 +2931,9 @@ impl TilingLayout {
             }
         }
 
-        let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+        self.arrange(&mut tree);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
         self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
 
         let location = self.element_geometry(&mapped).unwrap().loc;
@@ -2999,8 +3116,9 @@ impl TilingLayout {
         output: &Output,
         tree: &mut Tree<Data>,
         gaps: (i32, i32),
+        scroll: Option<ScrollState>,
     ) -> Option<TilingBlocker> {
-        if let Some(root_id) = tree.root_node_id() {
+        if let Some(root_id) = tree.root_node_id().cloned() {
             let mut configures = Vec::new();
 
             let (outer, inner) = gaps;
@@ -3009,10 +3127,13 @@ impl TilingLayout {
             geo.loc.y += outer;
             geo.size.w -= outer * 2;
             geo.size.h -= outer * 2;
+            if let Some(scroll) = scroll.as_ref() {
+                geo = TilingLayout::layout_columns(tree, geo, scroll);
+            }
             let mut stack = vec![geo];
 
             for node_id in tree
-                .traverse_pre_order_ids(root_id)
+                .traverse_pre_order_ids(&root_id)
                 .unwrap()
                 .collect::<Vec<_>>()
                 .into_iter()
@@ -3394,7 +3515,12 @@ impl TilingLayout {
                     InsertBehavior::AsRoot,
                 )
                 .unwrap();
-                let blocker = TilingLayout::update_positions(&self.output, &mut tree, gaps);
+                let blocker = TilingLayout::update_positions(
+                    &self.output,
+                    &mut tree,
+                    gaps,
+                    self.scroll_state(),
+                );
                 self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
             }
             return;
@@ -3866,6 +3992,7 @@ impl TilingLayout {
                                         &self.output,
                                         &mut tree,
                                         gaps,
+                                        self.scroll_state(),
                                     );
                                     self.queue.push_tree(tree, duration, blocker);
                                 }
@@ -3965,7 +4092,8 @@ impl TilingLayout {
         };
         TilingLayout::merge_trees(src, &mut dst, orientation);
 
-        let blocker = TilingLayout::update_positions(&self.output, &mut dst, gaps);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut dst, gaps, self.scroll_state());
         self.queue.push_tree(dst, ANIMATION_DURATION, blocker);
     }
 
@@ -5681,6 +5809,22 @@ fn render_new_tree_windows<R>(
                     },
                 );
 
+                // dim the parent of a dialog, which floats above
+                if mapped.has_modal.load(Ordering::SeqCst) {
+                    let radius = mapped.corner_radius(geo.size.as_logical(), 0);
+                    upper_elements.insert(
+                        0,
+                        CosmicMappedRenderElement::Overlay(BackdropShader::element(
+                            renderer,
+                            Key::Window(Usage::ModalBackdrop, mapped.key()),
+                            geo,
+                            radius.into_iter().max().unwrap_or(0) as f32,
+                            alpha * MODAL_BACKDROP_ALPHA,
+                            [0.0, 0.0, 0.0],
+                        )),
+                    );
+                }
+
                 if swap_desc
                     .as_ref()
                     .filter(|swap_desc| swap_desc.node == node_id)
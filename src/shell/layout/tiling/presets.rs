@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Named layout presets of the tiling layout.
+//!
+//! A preset is the shape of a tiling tree with app-id hints for its slots.
+//! Applying one moves the current windows into the slots matching their
+//! app-id first and into the slots without hints afterwards. Slots left
+//! empty take no space, but are filled by windows mapped later on, using the
+//! same machinery that restores windows after a restart. Stacks are moved as
+//! a whole and keep their slot, as long as any of their windows is left.
+
+use cosmic_comp_config::presets::{PresetNode, SplitOrientation};
+use id_tree::NodeId;
+use smithay::utils::IsAlive;
+
+use super::{ANIMATION_DURATION, Data, RestoreTilingState, TilingLayout, strategy::Shape};
+use crate::shell::{
+    CosmicMapped, CosmicSurface,
+    element::surface::WeakCosmicSurface,
+    layout::{
+        Orientation,
+        restore::{NodeSnapshot, WindowKey, node_for_surface, tiling_state},
+    },
+};
+
+/// A window is dragged over the tiling layout, whose tree can't be rearranged meanwhile
+#[derive(Debug)]
+pub struct DragInProgress;
+
+/// A preset applied to the layout, whose slots may still be empty
+#[derive(Debug)]
+pub struct ActivePreset {
+    snapshot: NodeSnapshot,
+    slots: Vec<PresetSlot>,
+}
+
+#[derive(Debug)]
+struct PresetSlot {
+    /// Path of child indices from the root of the preset
+    path: Vec<usize>,
+    app_id: Option<String>,
+    /// Window or windows of the stack, that fill the slot
+    windows: Vec<WeakCosmicSurface>,
+}
+
+impl PresetSlot {
+    fn surface(&self) -> Option<CosmicSurface> {
+        self.windows
+            .iter()
+            .filter_map(|weak| weak.upgrade())
+            .find(|surface| surface.alive())
+    }
+
+    fn matches(&self, mapped: &CosmicMapped) -> bool {
+        self.app_id
+            .as_ref()
+            .is_some_and(|app_id| mapped.windows().any(|(w, _)| &w.app_id() == app_id))
+    }
+}
+
+fn preset_node(snapshot: &NodeSnapshot) -> PresetNode {
+    match snapshot {
+        NodeSnapshot::Group {
+            orientation,
+            ratios,
+            children,
+        } => PresetNode::Split {
+            orientation: match orientation {
+                Orientation::Horizontal => SplitOrientation::Horizontal,
+                Orientation::Vertical => SplitOrientation::Vertical,
+            },
+            ratios: ratios.clone(),
+            children: children.iter().map(preset_node).collect(),
+        },
+        NodeSnapshot::Windows(windows) => PresetNode::Slot {
+            app_id: windows.first().map(|key| key.app_id.clone()),
+        },
+    }
+}
+
+/// Turns a preset into a snapshot, fixing up ratios not matching the children.
+fn snapshot_node(preset: &PresetNode) -> NodeSnapshot {
+    match preset {
+        PresetNode::Split {
+            orientation,
+            ratios,
+            children,
+        } if !children.is_empty() => {
+            let total: f64 = ratios.iter().sum();
+            let ratios = if ratios.len() == children.len() && ratios.iter().all(|ratio| *ratio > 0.)
+            {
+                ratios.iter().map(|ratio| ratio / total).collect()
+            } else {
+                vec![1. / children.len() as f64; children.len()]
+            };
+            NodeSnapshot::Group {
+                orientation: match orientation {
+                    SplitOrientation::Horizontal => Orientation::Horizontal,
+                    SplitOrientation::Vertical => Orientation::Vertical,
+                },
+                ratios,
+                children: children.iter().map(snapshot_node).collect(),
+            }
+        }
+        PresetNode::Split { .. } => NodeSnapshot::Windows(Vec::new()),
+        PresetNode::Slot { app_id } => NodeSnapshot::Windows(
+            app_id
+                .iter()
+                .map(|app_id| WindowKey {
+                    app_id: app_id.clone(),
+                    title: String::new(),
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Prunes `snapshot` to the slots holding a window, collecting their nodes in pre-order.
+fn filled_shape(
+    snapshot: &NodeSnapshot,
+    path: &mut Vec<usize>,
+    filled: &[(Vec<usize>, NodeId)],
+    windows: &mut Vec<NodeId>,
+) -> Option<Shape> {
+    match snapshot {
+        NodeSnapshot::Group {
+            orientation,
+            ratios,
+            children,
+        } => {
+            let (ratios, mut children): (Vec<_>, Vec<_>) = children
+                .iter()
+                .zip(ratios.iter())
+                .enumerate()
+                .filter_map(|(idx, (child, ratio))| {
+                    path.push(idx);
+                    let child = filled_shape(child, path, filled, windows);
+                    path.pop();
+                    child.map(|child| (*ratio, child))
+                })
+                .unzip();
+            let total: f64 = ratios.iter().sum();
+            match children.len() {
+                0 => None,
+                1 => children.pop(),
+                _ => Some(Shape::Group {
+                    orientation: *orientation,
+                    ratios: ratios.into_iter().map(|ratio| ratio / total).collect(),
+                    children,
+                }),
+            }
+        }
+        NodeSnapshot::Windows(_) => {
+            let (_, node) = filled.iter().find(|(slot, _)| slot == path)?;
+            windows.push(node.clone());
+            Some(Shape::Window)
+        }
+    }
+}
+
+impl TilingLayout {
+    /// Captures the tiling tree as a preset, hinting every slot with the app-id of its window.
+    pub fn capture_preset(&self) -> Option<PresetNode> {
+        let tree = self.tree();
+        let root = tree.root_node_id()?;
+        NodeSnapshot::capture(tree, root).map(|snapshot| preset_node(&snapshot))
+    }
+
+    /// Whether a window is dragged over the layout, leaving placeholders in the tree
+    pub fn has_placeholders(&self) -> bool {
+        let tree = self.tree();
+        tree.root_node_id().is_some_and(|root| {
+            tree.traverse_pre_order(root)
+                .unwrap()
+                .any(|node| node.data().is_placeholder())
+        })
+    }
+
+    /// Rearranges the tiled windows into the shape of `preset`.
+    ///
+    /// Windows not fitting into any slot are placed in a column next to the preset.
+    pub fn apply_preset(&mut self, preset: &PresetNode) -> Result<(), DragInProgress> {
+        if self.has_placeholders() {
+            return Err(DragInProgress);
+        }
+
+        let snapshot = snapshot_node(preset);
+        let mut leaves = Vec::new();
+        snapshot.leaves(&mut Vec::new(), &mut leaves);
+        let mut slots = leaves
+            .into_iter()
+            .map(|(path, keys)| PresetSlot {
+                path,
+                app_id: keys.into_iter().next().map(|key| key.app_id),
+                windows: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let gaps = self.gaps();
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        let mut windows = Vec::new();
+        if let Some(root) = tree.root_node_id() {
+            for node_id in tree.traverse_pre_order_ids(root).unwrap() {
+                if let Data::Mapped { mapped, .. } = tree.get(&node_id).unwrap().data() {
+                    windows.push(Some((node_id, mapped.clone())));
+                }
+            }
+        }
+
+        // windows go to the slots hinting their app-id first, then to the ones without hints
+        let mut filled = Vec::new();
+        for hinted in [true, false] {
+            for slot in slots
+                .iter_mut()
+                .filter(|slot| slot.app_id.is_some() == hinted)
+            {
+                let Some((node_id, mapped)) = windows
+                    .iter_mut()
+                    .find(|window| {
+                        window
+                            .as_ref()
+                            .is_some_and(|(_, mapped)| !hinted || slot.matches(mapped))
+                    })
+                    .and_then(Option::take)
+                else {
+                    continue;
+                };
+                slot.windows = mapped.windows().map(|(w, _)| w.downgrade()).collect();
+                filled.push((slot.path.clone(), node_id));
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let shape = filled_shape(&snapshot, &mut Vec::new(), &filled, &mut nodes);
+        let leftovers = windows.into_iter().flatten().collect::<Vec<_>>();
+        let leftover_shape =
+            (!leftovers.is_empty()).then(|| Shape::even(Orientation::Horizontal, leftovers.len()));
+        let shape = match (shape, leftover_shape) {
+            (Some(shape), Some(leftover_shape)) => Some(Shape::Group {
+                orientation: Orientation::Vertical,
+                ratios: vec![
+                    nodes.len() as f64 / (nodes.len() + leftovers.len()) as f64,
+                    leftovers.len() as f64 / (nodes.len() + leftovers.len()) as f64,
+                ],
+                children: vec![shape, leftover_shape],
+            }),
+            (shape, leftover_shape) => shape.or(leftover_shape),
+        };
+        nodes.extend(leftovers.into_iter().map(|(node_id, _)| node_id));
+
+        if let Some(shape) = shape {
+            shape.reshape(&mut tree, nodes);
+        }
+        self.preset = Some(ActivePreset { snapshot, slots });
+
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+        Ok(())
+    }
+
+    /// Claims an empty slot of the active preset for `window`, if any is left.
+    ///
+    /// Slots hinting the app-id of the window are preferred over slots without hints.
+    pub fn preset_slot(&mut self, window: &CosmicSurface) -> Option<RestoreTilingState> {
+        let preset = self.preset.as_mut()?;
+        let app_id = window.app_id();
+        let idx = preset
+            .slots
+            .iter()
+            .position(|slot| slot.app_id.as_ref() == Some(&app_id) && slot.surface().is_none())
+            .or_else(|| {
+                preset
+                    .slots
+                    .iter()
+                    .position(|slot| slot.app_id.is_none() && slot.surface().is_none())
+            })?;
+        let slot = &mut preset.slots[idx];
+        slot.windows = vec![window.downgrade()];
+        let path = slot.path.clone();
+
+        let preset = self.preset.as_ref()?;
+        let tree = self.tree();
+        let restored = preset
+            .slots
+            .iter()
+            .filter_map(|slot| {
+                let node = node_for_surface(tree, &slot.surface()?)?;
+                Some((slot.path.as_slice(), node))
+            })
+            .collect::<Vec<_>>();
+        tiling_state(&preset.snapshot, &path, &restored, tree)
+    }
+}
@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Scrollable column mode of the tiling layout.
+//!
+//! While scrolling, the root of the tree is a vertical group, whose children
+//! form the columns of an unbounded horizontal strip. Every column spans the
+//! full height and by default half the width of the output, and the viewport
+//! scrolls to keep the focused column visible. Columns are split or stacked
+//! with the regular tiling actions and resized on their own, without taking
+//! space from their neighbours.
+
+use std::{collections::HashMap, time::Duration};
+
+use id_tree::{InsertBehavior, Node, NodeId, Tree};
+use smithay::{desktop::layer_map_for_output, utils::Rectangle};
+
+use super::{ANIMATION_DURATION, Data, TilingLayout};
+use crate::{
+    shell::{element::CosmicMapped, layout::Orientation},
+    utils::prelude::*,
+};
+
+/// Share of the output width occupied by a column, that wasn't resized
+const COLUMN_WIDTH: f64 = 0.5;
+/// Minimum width of a resized column, matching the minimum of tiled windows
+const MIN_COLUMN_WIDTH: i32 = 360;
+
+#[derive(Debug, Clone, Default)]
+pub struct ScrollState {
+    /// Horizontal offset of the viewport into the strip of columns
+    offset: i32,
+    /// Node the viewport was last scrolled to
+    focused: Option<NodeId>,
+    /// Widths of resized columns, keyed by the child of the root forming the column
+    widths: HashMap<NodeId, i32>,
+}
+
+fn column_width(viewport: &Rectangle<i32, Local>) -> i32 {
+    (viewport.size.w as f64 * COLUMN_WIDTH).round() as i32
+}
+
+/// Widths of all columns from left to right
+fn column_widths(
+    tree: &Tree<Data>,
+    state: &ScrollState,
+    viewport: &Rectangle<i32, Local>,
+) -> Vec<i32> {
+    let width_of = |id: &NodeId| {
+        state
+            .widths
+            .get(id)
+            .copied()
+            .unwrap_or_else(|| column_width(viewport))
+    };
+    let Some(root_id) = tree.root_node_id() else {
+        return Vec::new();
+    };
+    match tree.get(root_id).unwrap().data() {
+        Data::Group {
+            orientation: Orientation::Vertical,
+            ..
+        } => tree.children_ids(root_id).unwrap().map(width_of).collect(),
+        _ => vec![width_of(root_id)],
+    }
+}
+
+fn column_count(tree: &Tree<Data>) -> usize {
+    match tree
+        .root_node_id()
+        .map(|root| tree.get(root).unwrap().data())
+    {
+        Some(Data::Group {
+            orientation: Orientation::Vertical,
+            sizes,
+            ..
+        }) => sizes.len(),
+        Some(_) => 1,
+        None => 0,
+    }
+}
+
+/// Index of the column containing `node_id`
+fn column_of(tree: &Tree<Data>, node_id: &NodeId) -> Option<usize> {
+    let root_id = tree.root_node_id()?;
+    if !matches!(
+        tree.get(root_id).ok()?.data(),
+        Data::Group {
+            orientation: Orientation::Vertical,
+            ..
+        }
+    ) {
+        return Some(0);
+    }
+
+    let column_id = std::iter::once(node_id)
+        .chain(tree.ancestor_ids(node_id).ok()?)
+        .find(|id| tree.get(id).unwrap().parent() == Some(root_id))?;
+    tree.children_ids(root_id)
+        .unwrap()
+        .position(|id| id == column_id)
+}
+
+impl TilingLayout {
+    pub fn is_scrolling(&self) -> bool {
+        self.scrolling.is_some()
+    }
+
+    /// Switches between the split tree and the scrollable column mode.
+    ///
+    /// Existing windows keep their place in the tree, the children of a
+    /// vertical root group simply become columns.
+    pub fn set_scrolling(&mut self, scrolling: bool) {
+        if self.is_scrolling() == scrolling {
+            return;
+        }
+
+        self.scrolling = scrolling.then(ScrollState::default);
+        if scrolling {
+            self.preset = None;
+        }
+        self.recalculate();
+    }
+
+    pub(super) fn scroll_state(&self) -> Option<ScrollState> {
+        self.scrolling.clone()
+    }
+
+    /// Lays out the root group as columns and returns the geometry of the strip.
+    pub(super) fn layout_columns(
+        tree: &mut Tree<Data>,
+        viewport: Rectangle<i32, Local>,
+        state: &ScrollState,
+    ) -> Rectangle<i32, Local> {
+        let widths = column_widths(tree, state, &viewport);
+        let strip_width = widths.iter().sum::<i32>();
+        if let Some(root_id) = tree.root_node_id().cloned()
+            && let Data::Group {
+                orientation: Orientation::Vertical,
+                sizes,
+                last_geometry,
+                ..
+            } = tree.get_mut(&root_id).unwrap().data_mut()
+        {
+            // the strip never rescales, so don't let `update_geometry` do that either
+            *sizes = widths;
+            last_geometry.size.w = strip_width;
+        }
+
+        Rectangle::new(
+            (viewport.loc.x - state.offset, viewport.loc.y).into(),
+            (strip_width, viewport.size.h).into(),
+        )
+    }
+
+    /// Grows or shrinks the column formed by `column`, a child of the root.
+    ///
+    /// The width of the other columns is unaffected, the strip just gets wider or narrower.
+    pub(super) fn resize_column(&mut self, column: &NodeId, amount: i32) {
+        let viewport = self.viewport();
+        let tree = &self.queue.trees.back().unwrap().0;
+        let Some(state) = self.scrolling.as_mut() else {
+            return;
+        };
+
+        // forget about columns, that were closed or merged in the meantime
+        state.widths.retain(|id, _| {
+            tree.get(id)
+                .is_ok_and(|node| node.parent().is_none() || node.parent() == tree.root_node_id())
+        });
+        let width = state
+            .widths
+            .get(column)
+            .copied()
+            .unwrap_or_else(|| column_width(&viewport));
+        state.widths.insert(
+            column.clone(),
+            (width + amount).clamp(MIN_COLUMN_WIDTH, viewport.size.w.max(MIN_COLUMN_WIDTH)),
+        );
+    }
+
+    /// Adds a window as a new column right of the column containing `node`.
+    pub(super) fn map_to_column(
+        tree: &mut Tree<Data>,
+        window: impl Into<CosmicMapped>,
+        node: Option<NodeId>,
+        minimize_rect: Option<Rectangle<i32, Local>>,
+    ) {
+        let window = window.into();
+        let new_window = Node::new(Data::Mapped {
+            mapped: window.clone(),
+            last_geometry: Rectangle::from_size((100, 100).into()),
+            minimize_rect,
+        });
+
+        let window_id = match tree.root_node_id().cloned() {
+            Some(root_id) if column_count(tree) > 1 => {
+                let idx = node
+                    .as_ref()
+                    .and_then(|node| column_of(tree, node))
+                    .map(|column| column + 1)
+                    .unwrap_or_else(|| column_count(tree));
+                let new_id = tree
+                    .insert(new_window, InsertBehavior::UnderNode(&root_id))
+                    .unwrap();
+                tree.make_nth_sibling(&new_id, idx).unwrap();
+                tree.get_mut(&root_id).unwrap().data_mut().add_window(idx);
+                new_id
+            }
+            Some(root_id) => {
+                let new_id = tree.insert(new_window, InsertBehavior::AsRoot).unwrap();
+                TilingLayout::new_group(tree, &root_id, &new_id, Orientation::Vertical).unwrap();
+                new_id
+            }
+            None => tree.insert(new_window, InsertBehavior::AsRoot).unwrap(),
+        };
+
+        *window.tiling_node_id.lock().unwrap() = Some(window_id);
+    }
+
+    /// Scrolls the viewport to the column of `focused`, if focus changed since the last call.
+    ///
+    /// Only reacting to focus changes leaves the viewport, where a swipe gesture put it.
+    pub fn scroll_to_focused(&mut self, focused: Option<&CosmicMapped>) {
+        let node_id = focused
+            .and_then(|mapped| mapped.tiling_node_id.lock().unwrap().clone())
+            .filter(|node_id| self.tree().get(node_id).is_ok());
+        let Some(state) = self.scrolling.as_mut() else {
+            return;
+        };
+        if state.focused == node_id {
+            return;
+        }
+        state.focused = node_id.clone();
+        let offset = state.offset;
+
+        let Some(column) = node_id.and_then(|node_id| column_of(self.tree(), &node_id)) else {
+            return;
+        };
+
+        let viewport = self.viewport();
+        let widths = column_widths(self.tree(), self.scrolling.as_ref().unwrap(), &viewport);
+        let start = widths[..column].iter().sum::<i32>();
+        let end = start + widths[column];
+        let offset = if start < offset {
+            start
+        } else if end > offset + viewport.size.w {
+            end - viewport.size.w
+        } else {
+            offset
+        };
+        self.scroll_to(offset, ANIMATION_DURATION);
+    }
+
+    /// Moves the viewport by `delta` pixels, e.g. following a swipe gesture.
+    pub fn scroll_by(&mut self, delta: f64) {
+        if let Some(state) = self.scrolling.as_ref() {
+            self.scroll_to(state.offset + delta.round() as i32, None);
+        }
+    }
+
+    /// Snaps the viewport to the closest column edge.
+    pub fn end_scroll(&mut self) {
+        let Some(state) = self.scrolling.as_ref() else {
+            return;
+        };
+        let edges = column_widths(self.tree(), state, &self.viewport())
+            .into_iter()
+            .scan(0, |edge, width| {
+                *edge += width;
+                Some(*edge)
+            });
+        let offset = std::iter::once(0)
+            .chain(edges)
+            .min_by_key(|edge| (edge - state.offset).abs())
+            .unwrap();
+        self.scroll_to(offset, ANIMATION_DURATION);
+    }
+
+    fn scroll_to(&mut self, offset: i32, duration: impl Into<Option<Duration>>) {
+        let viewport = self.viewport();
+        let Some(state) = self.scrolling.as_ref() else {
+            return;
+        };
+        let strip_width = column_widths(self.tree(), state, &viewport)
+            .iter()
+            .sum::<i32>();
+        let offset = offset.clamp(0, (strip_width - viewport.size.w).max(0));
+        let state = self.scrolling.as_mut().unwrap();
+        if state.offset == offset {
+            return;
+        }
+        state.offset = offset;
+
+        let gaps = self.gaps();
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
+        self.queue.push_tree(tree, duration, blocker);
+    }
+
+    fn viewport(&self) -> Rectangle<i32, Local> {
+        let (outer, _) = self.gaps();
+        let mut geo = layer_map_for_output(&self.output)
+            .non_exclusive_zone()
+            .as_local();
+        geo.loc.x += outer;
+        geo.loc.y += outer;
+        geo.size.w -= outer * 2;
+        geo.size.h -= outer * 2;
+        geo
+    }
+}
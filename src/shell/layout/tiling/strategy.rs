@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Placement strategies of the tiling layout.
+//!
+//! Unless the manual `Split` strategy is active, the tree is reshaped after
+//! the strategy whenever windows are added or removed. Windows keep the order
+//! of a pre-order traversal, and their nodes are moved rather than recreated,
+//! so animations and focus carry over.
+
+use std::sync::Arc;
+
+use cosmic_comp_config::actions::TilingStrategy;
+use id_tree::{InsertBehavior, MoveBehavior, Node, NodeId, RemoveBehavior, Tree};
+use smithay::utils::Rectangle;
+
+use super::{ANIMATION_DURATION, Data, TilingLayout};
+use crate::shell::layout::Orientation;
+
+/// Step of the grow and shrink master actions, as a share of the output width
+pub const MASTER_RATIO_STEP: f64 = 0.05;
+/// Length groups created by a strategy are sized against, before `update_geometry` rescales them
+const SCALE: i32 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct MasterState {
+    /// Number of windows in the master area
+    count: usize,
+    /// Share of the output width taken by the master area
+    ratio: f64,
+}
+
+impl Default for MasterState {
+    fn default() -> Self {
+        MasterState {
+            count: 1,
+            ratio: 0.55,
+        }
+    }
+}
+
+/// Layout of a tree, with windows filled in in pre-order.
+pub(super) enum Shape {
+    Window,
+    Group {
+        orientation: Orientation,
+        ratios: Vec<f64>,
+        children: Vec<Shape>,
+    },
+}
+
+impl Shape {
+    /// Places `len` windows next to each other with equal sizes.
+    pub(super) fn even(orientation: Orientation, len: usize) -> Shape {
+        Shape::even_children(orientation, (0..len).map(|_| Shape::Window).collect())
+    }
+
+    pub(super) fn even_children(orientation: Orientation, mut children: Vec<Shape>) -> Shape {
+        if children.len() == 1 {
+            return children.pop().unwrap();
+        }
+        Shape::Group {
+            orientation,
+            ratios: vec![1. / children.len() as f64; children.len()],
+            children,
+        }
+    }
+
+    fn master_stack(len: usize, master: &MasterState) -> Shape {
+        let count = master.count.min(len);
+        if count == 0 || count == len {
+            return Shape::even(Orientation::Horizontal, len);
+        }
+        Shape::Group {
+            orientation: Orientation::Vertical,
+            ratios: vec![master.ratio, 1. - master.ratio],
+            children: vec![
+                Shape::even(Orientation::Horizontal, count),
+                Shape::even(Orientation::Horizontal, len - count),
+            ],
+        }
+    }
+
+    /// Every window takes half of the remaining space, alternating between
+    /// splitting it horizontally and vertically.
+    ///
+    /// `spiral` places the remaining space first on every other pair of
+    /// levels, so the windows wind inwards.
+    fn dwindle(len: usize, level: usize, spiral: bool) -> Shape {
+        if len <= 1 {
+            return Shape::Window;
+        }
+        let rest = Shape::dwindle(len - 1, level + 1, spiral);
+        Shape::Group {
+            orientation: if level % 2 == 0 {
+                Orientation::Vertical
+            } else {
+                Orientation::Horizontal
+            },
+            ratios: vec![0.5, 0.5],
+            children: if spiral && level % 4 >= 2 {
+                vec![rest, Shape::Window]
+            } else {
+                vec![Shape::Window, rest]
+            },
+        }
+    }
+
+    /// Rows of equally sized windows, only the last row may hold fewer windows.
+    fn grid(len: usize) -> Shape {
+        let columns = (len as f64).sqrt().ceil() as usize;
+        let rows = (0..len)
+            .step_by(columns.max(1))
+            .map(|start| Shape::even(Orientation::Vertical, columns.min(len - start)))
+            .collect();
+        Shape::even_children(Orientation::Horizontal, rows)
+    }
+
+    fn group_data(orientation: Orientation, ratios: &[f64]) -> Data {
+        let mut sizes = ratios
+            .iter()
+            .map(|ratio| (ratio * SCALE as f64).round() as i32)
+            .collect::<Vec<_>>();
+        let sum: i32 = sizes.iter().sum();
+        *sizes.last_mut().unwrap() += SCALE - sum;
+
+        Data::Group {
+            orientation,
+            sizes,
+            last_geometry: Rectangle::from_size((SCALE, SCALE).into()),
+            alive: Arc::new(()),
+            pill_indicator: None,
+        }
+    }
+
+    /// Creates the nodes of this shape below `parent`, moving the window nodes into place.
+    fn build(
+        self,
+        tree: &mut Tree<Data>,
+        parent: &NodeId,
+        windows: &mut impl Iterator<Item = NodeId>,
+    ) {
+        match self {
+            Shape::Window => {
+                let window_id = windows.next().unwrap();
+                tree.move_node(&window_id, MoveBehavior::ToParent(parent))
+                    .unwrap();
+            }
+            Shape::Group {
+                orientation,
+                ratios,
+                children,
+            } => {
+                let group_id = tree
+                    .insert(
+                        Node::new(Shape::group_data(orientation, &ratios)),
+                        InsertBehavior::UnderNode(parent),
+                    )
+                    .unwrap();
+                for child in children {
+                    child.build(tree, &group_id, windows);
+                }
+            }
+        }
+    }
+
+    /// Replaces the tree with this shape, moving the nodes of `windows` into place.
+    ///
+    /// `windows` has to hold exactly the mapped nodes of the tree.
+    pub(super) fn reshape(self, tree: &mut Tree<Data>, windows: Vec<NodeId>) {
+        let Some(old_root) = tree.root_node_id().cloned() else {
+            return;
+        };
+
+        let mut windows = windows.into_iter();
+        let root_id = match self {
+            Shape::Window => {
+                let window_id = windows.next().unwrap();
+                if window_id == old_root {
+                    return;
+                }
+                // the old root becomes a child of the window, until it is removed
+                tree.move_node(&window_id, MoveBehavior::ToRoot).unwrap();
+                window_id
+            }
+            Shape::Group {
+                orientation,
+                ratios,
+                children,
+            } => {
+                // the old root becomes the first child of the new one, until everything was moved out of it
+                let root_id = tree
+                    .insert(
+                        Node::new(Shape::group_data(orientation, &ratios)),
+                        InsertBehavior::AsRoot,
+                    )
+                    .unwrap();
+                for child in children {
+                    child.build(tree, &root_id, &mut windows);
+                }
+                root_id
+            }
+        };
+        if root_id != old_root {
+            tree.remove_node(old_root, RemoveBehavior::DropChildren)
+                .unwrap();
+        }
+    }
+}
+
+impl TilingLayout {
+    pub fn strategy(&self) -> TilingStrategy {
+        self.strategy
+    }
+
+    pub fn set_strategy(&mut self, strategy: TilingStrategy) {
+        if self.strategy == strategy {
+            return;
+        }
+
+        self.strategy = strategy;
+        if strategy != TilingStrategy::Split {
+            self.preset = None;
+        }
+        self.rearrange();
+    }
+
+    /// Changes the share of the output width taken by the master area by `delta`.
+    pub fn resize_master(&mut self, delta: f64) {
+        self.master.ratio = (self.master.ratio + delta).clamp(0.1, 0.9);
+        if self.strategy == TilingStrategy::MasterStack {
+            self.rearrange();
+        }
+    }
+
+    /// Changes the number of windows in the master area by `delta`.
+    pub fn change_master_count(&mut self, delta: isize) {
+        let windows = self.mapped().count().max(1);
+        self.master.count = self.master.count.saturating_add_signed(delta).min(windows);
+        if self.strategy == TilingStrategy::MasterStack {
+            self.rearrange();
+        }
+    }
+
+    fn rearrange(&mut self) {
+        let gaps = self.gaps();
+        let mut tree = self.queue.trees.back().unwrap().0.copy_clone();
+        self.arrange(&mut tree);
+        let blocker =
+            TilingLayout::update_positions(&self.output, &mut tree, gaps, self.scroll_state());
+        self.queue.push_tree(tree, ANIMATION_DURATION, blocker);
+    }
+
+    /// Reshapes `tree` after the active strategy.
+    ///
+    /// Trees are left untouched while windows are dragged around,
+    /// as the placeholders belong to the shape the user is editing.
+    pub(super) fn arrange(&self, tree: &mut Tree<Data>) {
+        if self.strategy == TilingStrategy::Split || self.is_scrolling() {
+            return;
+        }
+        let Some(old_root) = tree.root_node_id().cloned() else {
+            return;
+        };
+
+        let mut windows = Vec::new();
+        for node_id in tree.traverse_pre_order_ids(&old_root).unwrap() {
+            match tree.get(&node_id).unwrap().data() {
+                Data::Mapped { .. } => windows.push(node_id),
+                Data::Placeholder { .. } => return,
+                Data::Group { .. } => {}
+            }
+        }
+        if windows.len() < 2 {
+            return;
+        }
+
+        let shape = match self.strategy {
+            TilingStrategy::Split => unreachable!(),
+            TilingStrategy::MasterStack => Shape::master_stack(windows.len(), &self.master),
+            TilingStrategy::Dwindle => Shape::dwindle(windows.len(), 0, false),
+            TilingStrategy::Spiral => Shape::dwindle(windows.len(), 0, true),
+            TilingStrategy::Grid => Shape::grid(windows.len()),
+        };
+        shape.reshape(tree, windows);
+    }
+}
@@ -2,7 +2,7 @@ use calloop::LoopHandle;
 use focus::target::WindowGroup;
 use grabs::{MenuAlignment, SeatMoveGrabState};
 use indexmap::IndexMap;
-use layout::TilingExceptions;
+use layout::{TilingExceptions, WindowRules};
 use std::{
     collections::HashMap,
     sync::{Mutex, atomic::Ordering},
@@ -24,6 +24,7 @@ use crate::{
 };
 use cosmic_comp_config::{
     AppearanceConfig, TileBehavior, ZoomConfig, ZoomMovement,
+    window_rules::WindowRule,
     workspace::{PinnedWorkspace, WorkspaceLayout, WorkspaceMode},
 };
 use cosmic_config::ConfigSet;
@@ -50,7 +51,7 @@ use smithay::{
     output::{Output, WeakOutput},
     reexports::{
         wayland_protocols::ext::session_lock::v1::server::ext_session_lock_v1::ExtSessionLockV1,
-        wayland_server::{Client, protocol::wl_surface::WlSurface},
+        wayland_server::{Client, DisplayHandle, protocol::wl_surface::WlSurface},
     },
     utils::{IsAlive, Logical, Point, Rectangle, Serial, Size},
     wayland::{
@@ -66,7 +67,7 @@ use smithay::{
     },
     xwayland::X11Surface,
 };
-use tracing::error;
+use tracing::{error, warn};
 
 use crate::{
     backend::render::animations::spring::{Spring, SpringParams},
@@ -74,8 +75,8 @@ use crate::{
     utils::{prelude::*, quirks::WORKSPACE_OVERVIEW_NAMESPACE},
     wayland::{
         handlers::{
-            toplevel_management::minimize_rectangle, xdg_activation::ActivationContext,
-            xdg_shell::popup::get_popup_toplevel,
+            session_management, toplevel_management::minimize_rectangle,
+            xdg_activation::ActivationContext, xdg_shell::popup::get_popup_toplevel,
         },
         protocols::{
             toplevel_info::{
@@ -89,15 +90,25 @@ use crate::{
     },
 };
 
+mod bell;
 pub mod element;
 pub mod focus;
 pub mod grabs;
 pub mod layout;
+pub mod modal;
+mod scratchpad;
 mod seats;
+mod swallow;
 mod workspace;
 pub mod zoom;
+use self::bell::Flashes;
 pub use self::element::{CosmicMapped, CosmicMappedRenderElement, CosmicSurface};
+use self::modal::Modals;
+use self::scratchpad::Scratchpad;
 pub use self::seats::*;
+use self::swallow::Swallowing;
+#[cfg(test)]
+pub(crate) use self::swallow::TEST_PIDS;
 pub use self::workspace::*;
 use self::zoom::{OutputZoomState, ZoomState};
 
@@ -113,6 +124,7 @@ use self::{
     },
     layout::{
         floating::{FloatingLayout, ResizeState},
+        restore::{PendingRestore, PlacementLayer},
         tiling::{NodeDesc, ResizeForkGrab, TilingLayout},
     },
 };
@@ -278,6 +290,11 @@ pub struct Shell {
     pub seats: Seats,
     pub previous_workspace_idx: Option<(Serial, WeakOutput, usize)>,
     pub xwayland_keyboard_grab: Option<XWaylandKeyboardGrab<State>>,
+    pub pending_restore: Option<PendingRestore>,
+    pub scratchpad: Scratchpad,
+    pub swallowing: Swallowing,
+    modals: Modals,
+    flashes: Flashes,
 
     theme: cosmic::Theme,
     pub active_hint: bool,
@@ -296,6 +313,7 @@ pub struct Shell {
     zoom_state: Option<ZoomState>,
     appearance_conf: AppearanceConfig,
     tiling_exceptions: TilingExceptions,
+    window_rules: WindowRules,
 
     #[cfg(feature = "debug")]
     pub debug_active: bool,
@@ -903,7 +921,40 @@ impl Workspaces {
             workspace_state.remove_workspace_state(&workspace.handle, WState::Active);
             workspace_state.move_workspace_to_group(set.group, workspace.handle);
         }
-        set.workspaces.extend(moved_workspaces);
+        // Workspaces returning after the output was disconnected go back to their old position
+        moved_workspaces.sort_by_key(|workspace| {
+            workspace
+                .displaced_from(output)
+                .map_or(usize::MAX, |(idx, _)| idx)
+        });
+        // inserting workspaces shifts the active one, so keep track of it by its handle
+        let previous_active = set
+            .workspaces
+            .get(set.active)
+            .map(|workspace| workspace.handle);
+        if let Some(handle) = previous_active.as_ref() {
+            workspace_state.remove_workspace_state(handle, WState::Active);
+        }
+        let mut active = None;
+        for workspace in moved_workspaces {
+            match workspace.displaced_from(output) {
+                Some((idx, was_active)) => {
+                    if was_active {
+                        active = Some(workspace.handle);
+                    }
+                    set.workspaces
+                        .insert(idx.min(set.workspaces.len()), workspace);
+                }
+                None => set.workspaces.push(workspace),
+            }
+        }
+        if let Some(idx) = active.or(previous_active).and_then(|handle| {
+            set.workspaces
+                .iter()
+                .position(|workspace| workspace.handle == handle)
+        }) {
+            set.active = idx;
+        }
         if set.workspaces.is_empty() {
             set.add_empty_workspace(workspace_state);
         }
@@ -962,7 +1013,7 @@ impl Workspaces {
                         workspace_state.move_workspace_to_group(workspace_group, workspace.handle);
 
                         // update mapping
-                        workspace.set_output(&new_output, false);
+                        workspace.displace(&new_output, i, i == set.active);
                         workspace.refresh();
                         new_set.workspaces.push(workspace);
 
@@ -1564,6 +1615,7 @@ impl Common {
             &mut self.workspace_state.update(),
             &self.xdg_activation_state,
         );
+        self.gamma_control_state.output_removed(output);
 
         std::mem::drop(shell);
         self.refresh(); // cleans up excess of workspaces and empty workspaces
@@ -1608,7 +1660,17 @@ impl Common {
             &mut self.workspace_state.update(),
         );
         self.popups.cleanup();
+        // outputs disabled through the output configuration don't pass through `remove_output`
+        let disabled_outputs = self
+            .gamma_control_state
+            .outputs()
+            .filter(|output| !output.is_enabled())
+            .collect::<Vec<_>>();
+        for output in disabled_outputs {
+            self.gamma_control_state.output_removed(&output);
+        }
         self.toplevel_info_state.refresh(&self.workspace_state);
+        self.color_management_state.refresh();
         self.refresh_idle_inhibit();
         if let Some(mut a11y_keyboard_monitor) = self.dbus_state.a11y_keyboard_monitor() {
             a11y_keyboard_monitor.refresh();
@@ -1690,6 +1752,7 @@ impl Shell {
         let theme = cosmic::theme::system_preference();
 
         let tiling_exceptions = layout::TilingExceptions::new(config.tiling_exceptions.iter());
+        let window_rules = layout::WindowRules::new(config.cosmic_conf.window_rules.iter());
 
         Shell {
             workspaces: Workspaces::new(config, theme.clone()),
@@ -1702,6 +1765,11 @@ impl Shell {
             session_lock: None,
             previous_workspace_idx: None,
             xwayland_keyboard_grab: None,
+            pending_restore: PendingRestore::new(config.dynamic_conf.layout().clone()),
+            scratchpad: Scratchpad::default(),
+            swallowing: Swallowing::new(config.cosmic_conf.swallow_terminals.clone()),
+            modals: Modals::default(),
+            flashes: Flashes::default(),
 
             theme,
             active_hint: config.cosmic_conf.active_hint,
@@ -1713,6 +1781,7 @@ impl Shell {
             appearance_conf: config.cosmic_conf.appearance_settings,
             zoom_state: None,
             tiling_exceptions,
+            window_rules,
 
             #[cfg(feature = "debug")]
             debug_active: false,
@@ -1771,6 +1840,25 @@ impl Shell {
         }
     }
 
+    /// Whether the active workspace of `output` tiles in scrollable columns
+    pub fn is_scrolling(&self, output: &Output) -> bool {
+        self.active_space(output).is_some_and(|workspace| {
+            workspace.tiling_enabled && workspace.tiling_layer.is_scrolling()
+        })
+    }
+
+    pub fn scroll_columns(&mut self, output: &Output, delta: f64) {
+        if let Some(workspace) = self.workspaces.active_mut(output) {
+            workspace.tiling_layer.scroll_by(delta);
+        }
+    }
+
+    pub fn end_scroll_columns(&mut self, output: &Output) {
+        if let Some(workspace) = self.workspaces.active_mut(output) {
+            workspace.tiling_layer.end_scroll();
+        }
+    }
+
     pub fn end_workspace_swipe(
         &mut self,
         output: &Output,
@@ -2251,6 +2339,14 @@ impl Shell {
         self.outputs().find(|output| output.is_internal())
     }
 
+    /// Removes `seat` along with its focus history.
+    pub fn remove_seat(&mut self, seat: &Seat<State>) {
+        self.seats.remove_seat(seat);
+        for workspace in self.workspaces.spaces_mut() {
+            workspace.focus_stack.remove(seat);
+        }
+    }
+
     pub fn global_space(&self) -> Rectangle<i32, Global> {
         self.outputs()
             .fold(
@@ -2279,6 +2375,7 @@ impl Shell {
             .workspaces
             .spaces()
             .any(|workspace| workspace.animations_going())
+            || self.flashes.is_animating()
             || self.zoom_state.as_ref().is_some_and(|_| {
                 self.outputs().any(|o| {
                     o.user_data()
@@ -2580,11 +2677,23 @@ impl Shell {
         self.workspaces
             .refresh(workspace_state, xdg_activation_state);
 
+        if self.seats.iter().next().is_some() {
+            let seat = self.seats.last_active().clone();
+            for workspace in self.workspaces.spaces_mut() {
+                workspace.scroll_to_focused(&seat);
+            }
+        }
+
         for output in self.outputs() {
             let mut map = layer_map_for_output(output);
             map.cleanup();
         }
 
+        self.scratchpad.refresh();
+        self.swallowing.refresh();
+        self.refresh_modals();
+        self.flashes.refresh();
+
         self.override_redirect_windows.retain(|or| or.alive());
         self.override_redirect_windows
             .iter()
@@ -2818,6 +2927,7 @@ impl Shell {
         toplevel_info: &mut ToplevelInfoState<State, CosmicSurface>,
         workspace_state: &mut WorkspaceState<State>,
         loop_handle: &LoopHandle<'static, State>,
+        dh: &DisplayHandle,
     ) -> Option<KeyboardFocusTarget> {
         let pos = self
             .pending_windows
@@ -2832,6 +2942,15 @@ impl Shell {
             sticky: mut should_be_sticky,
         } = self.pending_windows.remove(pos);
 
+        let rule = self.window_rules.resolve(&window);
+        let should_be_maximized = rule.maximized.unwrap_or(should_be_maximized);
+        if rule.decorations == Some(false) {
+            window.set_decorations_disabled(true);
+        }
+        if rule.shadow == Some(false) {
+            window.set_shadow_disabled(true);
+        }
+
         if !should_be_sticky
             && let Some(toplevel) = window.0.toplevel()
             && let Some(parent) = toplevel.parent()
@@ -2844,14 +2963,71 @@ impl Shell {
                 .any(|set| set.sticky_layer.mapped().any(|m| m == elem));
         };
 
+        if let Some(sticky) = rule.sticky {
+            should_be_sticky = sticky;
+        }
+
+        // modal dialogs are placed over their parent, regardless of any rules
+        let modal_parent = self
+            .modal_parent(&window)
+            .and_then(|parent| self.element_for_surface(&parent).cloned())
+            .filter(|parent| !should_be_sticky && self.space_for(parent).is_some());
+
         let pending_activation = self.pending_activations.remove(&(&window).into());
         let workspace_handle = match pending_activation {
             Some(ActivationContext::Workspace(handle)) => Some(handle),
             _ => None,
         };
 
-        let should_be_fullscreen = output.is_some();
-        let mut output = output.unwrap_or_else(|| seat.active_output());
+        let should_be_fullscreen = rule.fullscreen.unwrap_or(output.is_some());
+        let rule_output = rule
+            .output
+            .as_ref()
+            .and_then(|name| self.outputs().find(|o| &o.name() == name).cloned());
+        let mut output = rule_output
+            .or(output)
+            .unwrap_or_else(|| seat.active_output());
+        let workspace_handle = rule
+            .workspace
+            .and_then(|idx| {
+                let workspace = self.workspaces.get(idx, &output);
+                if workspace.is_none() {
+                    warn!(
+                        app_id = window.app_id(),
+                        "Workspace {} of window rule doesn't exist on {}, ignoring",
+                        idx,
+                        output.name()
+                    );
+                }
+                workspace.map(|workspace| workspace.handle)
+            })
+            .or(workspace_handle);
+
+        let session_restore = window
+            .wl_surface()
+            .and_then(|surface| session_management::take_restore_data(&surface));
+        // windows placed by rules are not restored to their previous slot
+        let placement = if modal_parent.is_some()
+            || rule.output.is_some()
+            || rule.workspace.is_some()
+            || rule.floating.is_some()
+        {
+            None
+        } else if let Some(snapshot) = session_restore {
+            snapshot.place(&self.workspaces)
+        } else if should_be_fullscreen {
+            None
+        } else {
+            self.pending_restore
+                .as_mut()
+                .and_then(|restore| restore.place(&window, &self.workspaces))
+        };
+        let workspace_handle = modal_parent
+            .as_ref()
+            .and_then(|parent| self.space_for(parent))
+            .map(|workspace| workspace.handle)
+            .or(placement.as_ref().map(|placement| placement.workspace))
+            .or(workspace_handle);
 
         // this is beyond stupid, just to make the borrow checker happy
         let workspace = if let Some(handle) = workspace_handle.filter(|handle| {
@@ -2895,7 +3071,9 @@ impl Shell {
             && (workspace_output != seat.active_output() || active_handle != workspace.handle);
         let workspace_handle = workspace.handle;
         let is_dialog = layout::is_dialog(&window);
-        let floating_exception = layout::has_floating_exception(&self.tiling_exceptions, &window);
+        let wants_floating = rule.floating.unwrap_or_else(|| {
+            is_dialog || layout::has_floating_exception(&self.tiling_exceptions, &window)
+        });
 
         if should_be_fullscreen {
             workspace.map_fullscreen(&window, &seat, None, None);
@@ -2907,12 +3085,37 @@ impl Shell {
                 .then_some(KeyboardFocusTarget::Fullscreen(window));
         }
 
+        let join_stack = placement
+            .as_ref()
+            .and_then(|placement| placement.stack.clone())
+            .or_else(|| {
+                let pattern = rule
+                    .join_stack
+                    .as_ref()
+                    .filter(|_| !should_be_maximized && modal_parent.is_none())?;
+                workspace
+                    .mapped()
+                    .find(|m| {
+                        m.is_stack() && m.windows().any(|(w, _)| pattern.is_match(&w.app_id()))
+                    })
+                    .cloned()
+            });
+        if let Some(stack) = join_stack {
+            stack.stack_ref().unwrap().add_window(window, None, None);
+            if was_activated {
+                workspace_state.add_workspace_state(&workspace_handle, WState::Urgent);
+            }
+            return (workspace_output == seat.active_output() && active_handle == workspace_handle)
+                .then_some(KeyboardFocusTarget::Element(stack));
+        }
+
         let maybe_focused = workspace.focus_stack.get(&seat).iter().next().cloned();
         if let Some(FocusTarget::Window(focused)) = maybe_focused
             && let Some(stack) = focused.stack_ref()
             && !is_dialog
             && !should_be_maximized
-            && !(workspace.is_tiled(&focused.active_window()) && floating_exception)
+            && placement.is_none()
+            && !(workspace.is_tiled(&focused.active_window()) && wants_floating)
         {
             stack.add_window(window, None, None);
             if was_activated {
@@ -2922,20 +3125,58 @@ impl Shell {
                 .then_some(KeyboardFocusTarget::Element(focused));
         }
 
-        let mapped = CosmicMapped::from(CosmicWindow::new(
-            window.clone(),
-            loop_handle.clone(),
-            self.theme.clone(),
-            self.appearance_conf,
-        ));
+        let mapped = if placement
+            .as_ref()
+            .is_some_and(|placement| placement.stacked)
+        {
+            CosmicMapped::from(CosmicStack::new(
+                std::iter::once(window.clone()),
+                loop_handle.clone(),
+                self.theme.clone(),
+                self.appearance_conf,
+            ))
+        } else {
+            CosmicMapped::from(CosmicWindow::new(
+                window.clone(),
+                loop_handle.clone(),
+                self.theme.clone(),
+                self.appearance_conf,
+            ))
+        };
         #[cfg(feature = "debug")]
         {
             mapped.set_debug(self.debug_active);
         }
 
         let workspace_empty = workspace.mapped().next().is_none();
-        if is_dialog || floating_exception || !workspace.tiling_enabled {
-            workspace.floating_layer.map(mapped.clone(), None);
+        if workspace_empty && let Some(placement) = placement.as_ref() {
+            if let Some(strategy) = placement.strategy {
+                workspace.tiling_layer.set_strategy(strategy);
+            }
+            if let Some(scrolling) = placement.scrolling {
+                workspace.tiling_layer.set_scrolling(scrolling);
+            }
+        }
+        let modal_position = modal_parent
+            .as_ref()
+            .and_then(|parent| workspace.element_geometry(parent))
+            .map(|geometry| modal::centered(geometry, mapped.geometry().size.as_local()));
+        let (should_float, position, size, tiling_state) =
+            match placement.and_then(|placement| placement.layer) {
+                Some(PlacementLayer::Floating(geometry)) => (
+                    true,
+                    Some(geometry.loc),
+                    Some(geometry.size.as_logical()),
+                    None,
+                ),
+                Some(PlacementLayer::Tiling(tiling_state)) => (false, None, None, tiling_state),
+                None if modal_position.is_some() => (true, modal_position, None, None),
+                None => (wants_floating, rule.position, rule.size, None),
+            };
+        if should_float || !workspace.tiling_enabled {
+            workspace
+                .floating_layer
+                .map_internal(mapped.clone(), position, size, None);
         } else {
             for mapped in workspace
                 .mapped()
@@ -2946,10 +3187,32 @@ impl Shell {
             {
                 workspace.unmaximize_request(&mapped);
             }
+            let tiling_state = tiling_state.or_else(|| {
+                (!is_dialog && !should_be_maximized)
+                    .then(|| workspace.tiling_layer.preset_slot(&window))
+                    .flatten()
+            });
+            let swallowed = tiling_state.is_none()
+                && !is_dialog
+                && !should_be_maximized
+                && self.swallowing.swallow(workspace, &mapped, dh);
             let focus_stack = workspace.focus_stack.get(&seat);
-            workspace
-                .tiling_layer
-                .map(mapped.clone(), Some(focus_stack.iter()), None);
+            if tiling_state.is_some() {
+                workspace.tiling_layer.remap(
+                    mapped.clone(),
+                    None,
+                    tiling_state,
+                    Some(focus_stack.iter()),
+                );
+            } else if !swallowed {
+                workspace
+                    .tiling_layer
+                    .map(mapped.clone(), Some(focus_stack.iter()), None);
+            }
+        }
+
+        if modal_parent.is_some() {
+            self.update_modal(&window);
         }
 
         if should_be_sticky {
@@ -2960,8 +3223,14 @@ impl Shell {
             self.maximize_request(&mapped, &seat, false, loop_handle);
         }
 
-        let new_target = if (workspace_output == seat.active_output()
-            && active_handle == workspace_handle)
+        let minimized = rule.minimized == Some(true);
+        if minimized {
+            self.minimize_request(&window);
+        }
+
+        let new_target = if minimized {
+            None
+        } else if (workspace_output == seat.active_output() && active_handle == workspace_handle)
             || should_be_sticky
         {
             // TODO: enforce focus stealing prevention by also checking the same rules as for the else case.
@@ -3033,6 +3302,25 @@ impl Shell {
     where
         CosmicSurface: PartialEq<S>,
     {
+        if let Some(surface) = self
+            .scratchpad
+            .remove_surface(surface)
+            .or_else(|| self.swallowing.remove_terminal(surface))
+            .or_else(|| {
+                let swallowed = self.swallowing.take(surface)?;
+                self.unswallow(swallowed, seat)
+            })
+        {
+            toplevel_info.remove_toplevel(&surface);
+            return Some(PendingWindow {
+                surface,
+                seat: seat.clone(),
+                fullscreen: None,
+                maximized: false,
+                sticky: false,
+            });
+        }
+
         for set in self.workspaces.sets.values_mut() {
             let sticky_res = set.sticky_layer.mapped().find_map(|m| {
                 m.windows()
@@ -4348,6 +4636,19 @@ impl Shell {
     ) where
         CosmicSurface: PartialEq<S>,
     {
+        if let Some(mapped) = self
+            .scratchpad
+            .hidden()
+            .find(|m| m.windows().any(|(s, _)| &s == surface))
+            .cloned()
+        {
+            if mapped.is_stack() {
+                mapped.set_active(surface);
+            }
+            self.show_scratchpad_window(&mapped, seat);
+            return;
+        }
+
         if let Some((set, window)) = self.workspaces.sets.values_mut().find_map(|set| {
             set.minimized_windows
                 .iter()
@@ -5016,6 +5317,17 @@ impl Shell {
         self.tiling_exceptions = layout::TilingExceptions::new(exceptions);
     }
 
+    pub fn update_window_rules<'a, I>(&mut self, rules: I)
+    where
+        I: Iterator<Item = &'a WindowRule>,
+    {
+        self.window_rules = layout::WindowRules::new(rules);
+    }
+
+    pub fn update_swallow_terminals(&mut self, terminals: Vec<String>) {
+        self.swallowing.set_terminals(terminals);
+    }
+
     pub fn take_presentation_feedback(
         &self,
         output: &Output,
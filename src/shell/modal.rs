@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Modal dialogs.
+//!
+//! A modal dialog floats centered over its parent, follows the parent to other
+//! workspaces and outputs, and takes the keyboard focus whenever the parent would
+//! get it. The parent is dimmed for as long as the dialog is open.
+
+use std::sync::atomic::Ordering;
+
+use smithay::{
+    desktop::WindowSurface,
+    utils::{IsAlive, Point, Rectangle, Size},
+};
+
+use super::{CosmicMapped, CosmicSurface, Shell};
+use crate::{
+    utils::prelude::*,
+    wayland::protocols::toplevel_info::{
+        toplevel_enter_output, toplevel_enter_workspace, toplevel_leave_output,
+        toplevel_leave_workspace,
+    },
+};
+
+/// Upper bound of nested dialogs to follow, in case of a loop
+const MAX_NESTED: usize = 8;
+
+#[derive(Debug, Default)]
+pub struct Modals {
+    modals: Vec<Modal>,
+}
+
+#[derive(Debug, Clone)]
+struct Modal {
+    dialog: CosmicSurface,
+    parent: CosmicSurface,
+}
+
+/// Location of an element of `size`, which is centered over `parent`.
+pub fn centered(parent: Rectangle<i32, Local>, size: Size<i32, Local>) -> Point<i32, Local> {
+    parent.loc + Point::from(((parent.size.w - size.w) / 2, (parent.size.h - size.h) / 2))
+}
+
+impl Shell {
+    /// The mapped window `window` is a modal dialog of, if any.
+    pub(super) fn modal_parent(&self, window: &CosmicSurface) -> Option<CosmicSurface> {
+        if !window.is_modal() {
+            return None;
+        }
+
+        match window.0.underlying_surface() {
+            WindowSurface::Wayland(toplevel) => {
+                let parent = toplevel.parent()?;
+                self.element_for_surface(&parent)?
+                    .windows()
+                    .map(|(w, _)| w)
+                    .find(|w| w == &parent)
+            }
+            WindowSurface::X11(surface) => {
+                let parent = surface.is_transient_for()?;
+                self.mapped()
+                    .flat_map(|m| m.windows().map(|(w, _)| w))
+                    .find(|w| w.x11_surface().is_some_and(|s| s.window_id() == parent))
+            }
+        }
+    }
+
+    /// Starts or stops treating `window` as a modal dialog, after its hint changed.
+    pub fn update_modal(&mut self, window: &CosmicSurface) {
+        self.modals.modals.retain(|modal| &modal.dialog != window);
+        if let Some(parent) = self.modal_parent(window) {
+            self.modals.modals.push(Modal {
+                dialog: window.clone(),
+                parent,
+            });
+        }
+    }
+
+    /// The dialog, which gets the focus instead of `mapped`, if any.
+    pub fn modal_for(&self, mapped: &CosmicMapped) -> Option<CosmicMapped> {
+        let mut dialog: Option<CosmicMapped> = None;
+        for _ in 0..MAX_NESTED {
+            let active = dialog.as_ref().unwrap_or(mapped).active_window();
+            let Some(next) = self
+                .modals
+                .modals
+                .iter()
+                .filter(|modal| modal.parent == active)
+                .find_map(|modal| self.element_for_surface(&modal.dialog))
+                .filter(|next| *next != mapped && dialog.as_ref() != Some(*next))
+                .cloned()
+            else {
+                break;
+            };
+            dialog = Some(next);
+        }
+        dialog
+    }
+
+    /// Keeps dialogs on the workspace of their parent and centered over it.
+    pub(super) fn refresh_modals(&mut self) {
+        self.modals.modals.retain(|modal| {
+            modal.dialog.alive() && modal.parent.alive() && modal.dialog.is_modal()
+        });
+        for mapped in self.mapped() {
+            mapped.has_modal.store(false, Ordering::SeqCst);
+        }
+
+        for Modal { dialog, parent } in self.modals.modals.clone() {
+            let (Some(dialog), Some(parent)) = (
+                self.element_for_surface(&dialog).cloned(),
+                self.element_for_surface(&parent).cloned(),
+            ) else {
+                continue;
+            };
+            // e.g. stacked onto each other by the user
+            if dialog == parent {
+                continue;
+            }
+            parent.has_modal.store(true, Ordering::SeqCst);
+
+            if dialog.is_minimized() || parent.is_minimized() {
+                continue;
+            }
+            // sticky or currently grabbed windows are left alone
+            let (Some(from), Some(to)) = (
+                self.space_for(&dialog).map(|workspace| workspace.handle),
+                self.space_for(&parent).map(|workspace| workspace.handle),
+            ) else {
+                continue;
+            };
+
+            if from != to {
+                let workspace = self.workspaces.space_for_handle_mut(&from).unwrap();
+                if workspace.unmap_element(&dialog).is_none() {
+                    continue;
+                }
+                for (window, _) in dialog.windows() {
+                    toplevel_leave_output(&window, &workspace.output);
+                    toplevel_leave_workspace(&window, &workspace.handle);
+                }
+
+                let workspace = self.workspaces.space_for_handle_mut(&to).unwrap();
+                workspace.floating_layer.map(dialog.clone(), None);
+                for (window, _) in dialog.windows() {
+                    toplevel_enter_output(&window, &workspace.output);
+                    toplevel_enter_workspace(&window, &workspace.handle);
+                }
+            }
+
+            let workspace = self.workspaces.space_for_handle_mut(&to).unwrap();
+            let (Some(parent_geometry), Some(geometry)) = (
+                workspace.element_geometry(&parent),
+                workspace.floating_layer.element_geometry(&dialog),
+            ) else {
+                continue;
+            };
+            let position = centered(parent_geometry, geometry.size);
+            if position != geometry.loc {
+                workspace.floating_layer.relocate(&dialog, position);
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Scratchpad of windows hidden from all workspaces.
+//!
+//! Windows sent to the scratchpad stay members of it, until they are closed.
+//! Summoning a hidden member floats it centered on the active workspace,
+//! toggling it again hides it once more.
+
+use smithay::{
+    desktop::layer_map_for_output,
+    input::Seat,
+    utils::{IsAlive, Point, Size},
+};
+
+use super::{CosmicMapped, CosmicSurface, Shell, focus::target::KeyboardFocusTarget};
+use crate::{
+    state::State,
+    utils::prelude::*,
+    wayland::protocols::toplevel_info::{
+        toplevel_enter_output, toplevel_enter_workspace, toplevel_leave_output,
+        toplevel_leave_workspace,
+    },
+};
+
+/// Share of the usable output area a summoned window may take at most
+const MAX_SIZE: f64 = 0.8;
+/// Share of the usable output area given to windows without a usable size
+const DEFAULT_SIZE: f64 = 0.6;
+
+#[derive(Debug, Default)]
+pub struct Scratchpad {
+    windows: Vec<ScratchpadWindow>,
+}
+
+#[derive(Debug)]
+struct ScratchpadWindow {
+    mapped: CosmicMapped,
+    hidden: bool,
+}
+
+impl Scratchpad {
+    fn position(&self, mapped: &CosmicMapped) -> Option<usize> {
+        self.windows.iter().position(|w| &w.mapped == mapped)
+    }
+
+    /// Hidden windows, which are not mapped anywhere else
+    pub fn hidden(&self) -> impl Iterator<Item = &CosmicMapped> {
+        self.windows.iter().filter(|w| w.hidden).map(|w| &w.mapped)
+    }
+
+    /// Removes a hidden surface, e.g. because it got unmapped by its client.
+    pub fn remove_surface<S>(&mut self, surface: &S) -> Option<CosmicSurface>
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        let idx = self
+            .windows
+            .iter()
+            .position(|w| w.hidden && w.mapped.windows().any(|(s, _)| &s == surface))?;
+        let mapped = &self.windows[idx].mapped;
+        if let Some(stack) = mapped.stack_ref()
+            && stack.len() > 1
+        {
+            let idx = stack.surfaces().position(|s| &s == surface)?;
+            return stack.remove_idx(idx);
+        }
+        Some(self.windows.remove(idx).mapped.active_window())
+    }
+
+    pub fn refresh(&mut self) {
+        self.windows.retain(|w| w.mapped.alive());
+    }
+}
+
+impl Shell {
+    /// Hides `mapped` from its workspace and adds it to the scratchpad.
+    pub fn send_to_scratchpad(&mut self, mapped: &CosmicMapped, seat: &Seat<State>) {
+        if self
+            .workspaces
+            .sets
+            .values()
+            .any(|set| set.sticky_layer.mapped().any(|m| m == mapped))
+        {
+            self.toggle_sticky(seat, mapped);
+        }
+
+        let Some(workspace) = self.space_for_mut(mapped) else {
+            return;
+        };
+        workspace.unmap_element(mapped);
+        for (window, _) in mapped.windows() {
+            toplevel_leave_output(&window, &workspace.output);
+            toplevel_leave_workspace(&window, &workspace.handle);
+        }
+        mapped.set_minimized(true);
+
+        match self.scratchpad.position(mapped) {
+            Some(idx) => self.scratchpad.windows[idx].hidden = true,
+            None => self.scratchpad.windows.push(ScratchpadWindow {
+                mapped: mapped.clone(),
+                hidden: true,
+            }),
+        }
+    }
+
+    /// Floats a hidden scratchpad window centered on the active workspace of `seat`.
+    pub fn show_scratchpad_window(
+        &mut self,
+        mapped: &CosmicMapped,
+        seat: &Seat<State>,
+    ) -> Option<KeyboardFocusTarget> {
+        let idx = self
+            .scratchpad
+            .position(mapped)
+            .filter(|idx| self.scratchpad.windows[*idx].hidden)?;
+        self.scratchpad.windows[idx].hidden = false;
+
+        let output = seat.active_output();
+        let area = layer_map_for_output(&output)
+            .non_exclusive_zone()
+            .as_local();
+        let fits = |size: i32, max: i32| size > 0 && size as f64 <= max as f64 * MAX_SIZE;
+        let current = mapped.geometry().size;
+        let size = if fits(current.w, area.size.w) && fits(current.h, area.size.h) {
+            current.as_local()
+        } else {
+            Size::from((
+                (area.size.w as f64 * DEFAULT_SIZE).round() as i32,
+                (area.size.h as f64 * DEFAULT_SIZE).round() as i32,
+            ))
+        };
+        let position =
+            area.loc + Point::from(((area.size.w - size.w) / 2, (area.size.h - size.h) / 2));
+
+        let workspace = self.workspaces.active_mut(&output)?;
+        mapped.set_minimized(false);
+        workspace.floating_layer.map_internal(
+            mapped.clone(),
+            Some(position),
+            Some(size.as_logical()),
+            None,
+        );
+        for (window, _) in mapped.windows() {
+            toplevel_enter_output(&window, &output);
+            toplevel_enter_workspace(&window, &workspace.handle);
+        }
+
+        Some(KeyboardFocusTarget::Element(mapped.clone()))
+    }
+
+    /// Shows the scratchpad window with `app_id`, or hides it if it is already shown.
+    ///
+    /// Windows shown on another workspace are brought over to the active one.
+    pub fn toggle_scratchpad(
+        &mut self,
+        app_id: &str,
+        seat: &Seat<State>,
+    ) -> Option<KeyboardFocusTarget> {
+        let window = self
+            .scratchpad
+            .windows
+            .iter()
+            .find(|w| w.mapped.windows().any(|(s, _)| s.app_id() == app_id))?;
+        let mapped = window.mapped.clone();
+        if !window.hidden {
+            let on_active = self
+                .active_space(&seat.active_output())
+                .is_some_and(|workspace| workspace.mapped().any(|m| m == &mapped));
+            self.send_to_scratchpad(&mapped, seat);
+            if on_active {
+                return None;
+            }
+        }
+
+        self.show_scratchpad_window(&mapped, seat)
+    }
+
+    /// Hides the scratchpad window shown on the active workspace and shows the next one.
+    ///
+    /// Without a shown window, the first hidden one is shown.
+    pub fn cycle_scratchpad(&mut self, seat: &Seat<State>) -> Option<KeyboardFocusTarget> {
+        let len = self.scratchpad.windows.len();
+        let workspace = self.active_space(&seat.active_output())?;
+        let current = self
+            .scratchpad
+            .windows
+            .iter()
+            .position(|w| !w.hidden && workspace.mapped().any(|m| m == &w.mapped));
+
+        let next = match current {
+            Some(idx) => {
+                let mapped = self.scratchpad.windows[idx].mapped.clone();
+                self.send_to_scratchpad(&mapped, seat);
+                (1..len)
+                    .map(|offset| (idx + offset) % len)
+                    .find(|idx| self.scratchpad.windows[*idx].hidden)
+            }
+            None => self.scratchpad.windows.iter().position(|w| w.hidden),
+        }?;
+
+        let mapped = self.scratchpad.windows[next].mapped.clone();
+        self.show_scratchpad_window(&mapped, seat)
+    }
+}
@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Swallowing of terminals by the apps launched from them.
+//!
+//! A window mapped by a process descending from the process of a tiled
+//! terminal takes over the tile of the terminal. The terminal is minimized until
+//! the window is unmapped, when it is put back into the same tile.
+
+use std::fs;
+#[cfg(test)]
+use std::{cell::RefCell, collections::HashMap};
+
+use smithay::{input::Seat, reexports::wayland_server::DisplayHandle, utils::IsAlive};
+
+use super::{CosmicMapped, CosmicSurface, Shell, Workspace};
+use crate::{
+    state::State,
+    utils::prelude::*,
+    wayland::protocols::toplevel_info::{
+        toplevel_enter_output, toplevel_enter_workspace, toplevel_leave_output,
+        toplevel_leave_workspace,
+    },
+};
+
+/// Upper bound of parent processes to look at, in case of a loop
+const MAX_ANCESTORS: usize = 64;
+
+#[cfg(test)]
+thread_local! {
+    /// Process ids by app-id, replacing the ids of the clients of windows mapped on this thread,
+    /// as all windows of a test client belong to the same process.
+    pub static TEST_PIDS: RefCell<HashMap<String, u32>> = RefCell::default();
+}
+
+#[derive(Debug, Default)]
+pub struct Swallowing {
+    /// App-ids of the terminals, which may be swallowed
+    terminals: Vec<String>,
+    swallowed: Vec<Swallowed>,
+}
+
+#[derive(Debug)]
+pub(super) struct Swallowed {
+    /// Window holding the tile of the terminal
+    window: CosmicMapped,
+    terminal: CosmicMapped,
+}
+
+/// Process id of the client of `window`.
+fn window_pid(window: &CosmicSurface, dh: &DisplayHandle) -> Option<u32> {
+    #[cfg(test)]
+    if let Some(pid) = TEST_PIDS.with_borrow(|pids| pids.get(&window.app_id()).copied()) {
+        return Some(pid);
+    }
+    window.pid(dh)
+}
+
+/// Reads the parent of `pid` from procfs.
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    parse_parent_pid(&stat)
+}
+
+fn parse_parent_pid(stat: &str) -> Option<u32> {
+    // the command name may contain spaces and parentheses, the fields after it don't
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Whether `pid` is a child, grandchild, etc. of `ancestor`.
+fn descends_from(pid: u32, ancestor: u32) -> bool {
+    let mut pid = pid;
+    for _ in 0..MAX_ANCESTORS {
+        match parent_pid(pid) {
+            Some(parent) if parent == ancestor => return true,
+            Some(parent) if parent > 1 => pid = parent,
+            _ => return false,
+        }
+    }
+    false
+}
+
+impl Swallowing {
+    pub fn new(terminals: Vec<String>) -> Swallowing {
+        Swallowing {
+            terminals,
+            swallowed: Vec::new(),
+        }
+    }
+
+    pub fn set_terminals(&mut self, terminals: Vec<String>) {
+        self.terminals = terminals;
+    }
+
+    /// Puts `mapped` into the tile of the terminal it was launched from, if any.
+    pub fn swallow(
+        &mut self,
+        workspace: &mut Workspace,
+        mapped: &CosmicMapped,
+        dh: &DisplayHandle,
+    ) -> bool {
+        if self.terminals.is_empty() {
+            return false;
+        }
+        let Some(pid) = window_pid(&mapped.active_window(), dh) else {
+            return false;
+        };
+
+        let Some(terminal) = workspace
+            .tiling_layer
+            .mapped()
+            .map(|(m, _)| m)
+            .find(|m| {
+                let window = m.active_window();
+                !m.is_stack()
+                    && m.maximized_state.lock().unwrap().is_none()
+                    && self.terminals.contains(&window.app_id())
+                    && window_pid(&window, dh)
+                        .is_some_and(|terminal_pid| descends_from(pid, terminal_pid))
+            })
+            .cloned()
+        else {
+            return false;
+        };
+
+        self.take_tile(workspace, &terminal, mapped);
+        true
+    }
+
+    /// Puts `mapped` into the tile of `terminal` and minimizes the terminal.
+    pub fn take_tile(
+        &mut self,
+        workspace: &mut Workspace,
+        terminal: &CosmicMapped,
+        mapped: &CosmicMapped,
+    ) {
+        mapped.set_bounds(workspace.output.geometry().size.as_logical());
+        workspace.replace_tiled(terminal, mapped);
+        terminal.set_minimized(true);
+        terminal.configure();
+        for (window, _) in terminal.windows() {
+            toplevel_leave_output(&window, &workspace.output);
+            toplevel_leave_workspace(&window, &workspace.handle);
+        }
+        self.swallowed.push(Swallowed {
+            window: mapped.clone(),
+            terminal: terminal.clone(),
+        });
+    }
+
+    /// Takes the entry of the window of `surface`, if it holds the tile of a terminal.
+    pub(super) fn take<S>(&mut self, surface: &S) -> Option<Swallowed>
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        // windows stacked onto the swallowing window keep its tile
+        let idx = self.swallowed.iter().position(|s| {
+            s.window.windows().count() == 1 && s.window.windows().any(|(w, _)| &w == surface)
+        })?;
+        Some(self.swallowed.remove(idx))
+    }
+
+    /// Forgets a hidden terminal, e.g. because it got unmapped by its client.
+    pub fn remove_terminal<S>(&mut self, surface: &S) -> Option<CosmicSurface>
+    where
+        CosmicSurface: PartialEq<S>,
+    {
+        let idx = self
+            .swallowed
+            .iter()
+            .position(|s| s.terminal.windows().any(|(w, _)| &w == surface))?;
+        Some(self.swallowed.remove(idx).terminal.active_window())
+    }
+
+    pub fn refresh(&mut self) {
+        self.swallowed
+            .retain(|s| s.window.alive() && s.terminal.alive());
+    }
+}
+
+impl Shell {
+    /// Puts a swallowed terminal back into the tile of the window, that is getting unmapped.
+    ///
+    /// Returns the surface, if the window was taken out of its workspace in the process.
+    pub(super) fn unswallow(
+        &mut self,
+        swallowed: Swallowed,
+        seat: &Seat<State>,
+    ) -> Option<CosmicSurface> {
+        let Swallowed { window, terminal } = swallowed;
+        if !terminal.alive() {
+            return None;
+        }
+        terminal.set_minimized(false);
+        terminal.configure();
+
+        let tiled = self
+            .space_for(&window)
+            .is_some_and(|workspace| workspace.tiling_layer.mapped().any(|(m, _)| m == &window));
+        if tiled {
+            let workspace = self.space_for_mut(&window).unwrap();
+            if window.maximized_state.lock().unwrap().is_some() {
+                workspace.unmaximize_request(&window);
+            }
+            workspace.replace_tiled(&window, &terminal);
+            for (surface, _) in terminal.windows() {
+                toplevel_enter_output(&surface, &workspace.output);
+                toplevel_enter_workspace(&surface, &workspace.handle);
+            }
+            return Some(window.active_window());
+        }
+
+        // the window left its tile, so the terminal gets a new one where the window is now
+        let output = seat.active_output();
+        let workspace = match self.space_for(&window).map(|workspace| workspace.handle) {
+            Some(handle) => self.workspaces.space_for_handle_mut(&handle),
+            None => self.workspaces.active_mut(&output),
+        }?;
+        if workspace.tiling_enabled {
+            let focus_stack = workspace.focus_stack.get(seat);
+            workspace
+                .tiling_layer
+                .map(terminal.clone(), Some(focus_stack.iter()), None);
+        } else {
+            workspace.floating_layer.map(terminal.clone(), None);
+        }
+        for (surface, _) in terminal.windows() {
+            toplevel_enter_output(&surface, &workspace.output);
+            toplevel_enter_workspace(&surface, &workspace.handle);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::{self, Command};
+
+    use super::*;
+
+    #[test]
+    fn parses_parent_of_odd_command_names() {
+        assert_eq!(parse_parent_pid("42 (bash) S 7 42 42 0"), Some(7));
+        assert_eq!(parse_parent_pid("42 (a) b (c)) S 7 42 42 0"), Some(7));
+        assert_eq!(parse_parent_pid("42 (bash"), None);
+    }
+
+    #[test]
+    fn finds_parent_of_child() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let pid = child.id();
+        let parent = parent_pid(pid);
+        let descends = descends_from(pid, process::id());
+        let ascends = descends_from(process::id(), pid);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(parent, Some(process::id()));
+        assert!(descends);
+        assert!(!ascends);
+        assert!(!descends_from(pid, pid));
+    }
+
+    #[test]
+    fn missing_process_has_no_parent() {
+        assert_eq!(parent_pid(u32::MAX), None);
+        assert!(!descends_from(u32::MAX, process::id()));
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
         ANIMATION_DURATION, OverviewMode, SeatMoveGrabState,
         layout::{
             floating::{FloatingLayout, TiledCorners},
-            tiling::TilingLayout,
+            tiling::{DragInProgress, TilingLayout},
         },
     },
     state::State,
@@ -22,6 +22,8 @@ use crate::{
     },
 };
 use cosmic_comp_config::AppearanceConfig;
+use cosmic_comp_config::actions::TilingStrategy;
+use cosmic_comp_config::presets::PresetNode;
 use cosmic_comp_config::workspace::{OutputMatch, PinnedWorkspace};
 
 use cosmic::theme::CosmicTheme;
@@ -29,6 +31,7 @@ use cosmic_protocols::workspace::v2::server::zcosmic_workspace_handle_v2::Tiling
 use id_tree::Tree;
 use indexmap::IndexSet;
 use keyframe::{ease, functions::EaseInOutCubic};
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use smithay::backend::drm::DrmNode;
 use smithay::backend::renderer::element::{Kind, NamespacedElement};
@@ -61,7 +64,7 @@ use super::{
     CosmicMappedRenderElement, CosmicSurface, ResizeDirection, ResizeMode,
     element::{
         CosmicMapped, CosmicMappedKey, MaximizedState, resize_indicator::ResizeIndicator,
-        stack::CosmicStackRenderElement, swap_indicator::SwapIndicator,
+        stack::CosmicStackRenderElement, surface::WeakCosmicSurface, swap_indicator::SwapIndicator,
         window::CosmicWindowRenderElement,
     },
     focus::{
@@ -81,7 +84,7 @@ pub fn random_workspace_id() -> String {
     format!("{:x}", id)
 }
 
-fn output_match_for_output(output: &Output) -> OutputMatch {
+pub(in crate::shell) fn output_match_for_output(output: &Output) -> OutputMatch {
     OutputMatch {
         name: output.name(),
         edid: output.edid().cloned(),
@@ -90,7 +93,11 @@ fn output_match_for_output(output: &Output) -> OutputMatch {
 
 // If `disambguate` is true, check that edid *and* connector name match.
 // Otherwise, match only edid (if it exists)
-fn output_matches(output_match: &OutputMatch, output: &Output, disambiguate: bool) -> bool {
+pub(in crate::shell) fn output_matches(
+    output_match: &OutputMatch,
+    output: &Output,
+    disambiguate: bool,
+) -> bool {
     if output_match.edid.as_ref() != output.edid() {
         false
     } else if disambiguate || output_match.edid.is_none() {
@@ -116,10 +123,30 @@ pub struct Workspace {
     pub focus_stack: FocusStacks,
     pub image_copy: ImageCopySessions,
     output_stack: VecDeque<OutputMatch>,
+    displaced: Option<DisplacedState>,
     pub(super) backdrop_id: Id,
     pub dirty: AtomicBool,
 }
 
+/// State of a workspace on an output, that got disconnected
+#[derive(Debug)]
+struct DisplacedState {
+    output: OutputMatch,
+    /// Index in the workspace set of the output
+    idx: usize,
+    active: bool,
+    floating: Vec<DisplacedWindow>,
+}
+
+#[derive(Debug)]
+struct DisplacedWindow {
+    surface: WeakCosmicSurface,
+    /// Geometry on the disconnected output
+    original: Rectangle<i32, Local>,
+    /// Geometry on the current output, to tell if the window was moved in the meantime
+    displaced: Rectangle<i32, Local>,
+}
+
 #[derive(Debug)]
 pub enum MinimizedWindow {
     Fullscreen {
@@ -291,17 +318,22 @@ impl FullscreenRestoreState {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Only tiling and floating state can be persisted, see `ToplevelSnapshot`
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WorkspaceRestoreData {
+    #[serde(skip)]
     Fullscreen(Option<FullscreenRestoreData>),
     Tiling(TilingRestoreData),
     Floating(FloatingRestoreData),
+    #[serde(skip)]
     Stack(StackRestoreData),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FloatingRestoreData {
+    #[serde(with = "crate::utils::geometry::serde_rectangle")]
     pub geometry: Rectangle<i32, Local>,
+    #[serde(with = "crate::utils::geometry::serde_size")]
     pub output_size: Size<i32, Logical>,
     pub was_maximized: bool,
     pub was_snapped: Option<TiledCorners>,
@@ -322,8 +354,10 @@ impl FloatingRestoreData {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TilingRestoreData {
+    /// Position in the tiling tree, which doesn't outlive the compositor
+    #[serde(skip)]
     pub state: Option<RestoreTilingState>,
     pub was_maximized: bool,
 }
@@ -410,6 +444,7 @@ impl Workspace {
                 queue.push_back(output_match);
                 queue
             },
+            displaced: None,
             backdrop_id: Id::new(),
             dirty: AtomicBool::new(false),
         }
@@ -447,6 +482,7 @@ impl Workspace {
                 }
                 queue
             },
+            displaced: None,
             backdrop_id: Id::new(),
             dirty: AtomicBool::new(false),
         }
@@ -480,6 +516,17 @@ impl Workspace {
         self.tiling_layer.refresh();
     }
 
+    /// Scrolls the columns of the tiling layer to the window focused by `seat`.
+    pub fn scroll_to_focused(&mut self, seat: &Seat<State>) {
+        if self.tiling_layer.is_scrolling() {
+            let focused = self.focus_stack.get(seat).last().cloned();
+            self.tiling_layer.scroll_to_focused(match focused.as_ref() {
+                Some(FocusTarget::Window(mapped)) => Some(mapped),
+                _ => None,
+            });
+        }
+    }
+
     fn has_activation_token(&self, xdg_activation_state: &XdgActivationState) -> bool {
         xdg_activation_state.tokens().any(|(_, data)| {
             if let ActivationContext::Workspace(handle) =
@@ -581,6 +628,19 @@ impl Workspace {
     // If `explicit` is `true`, the user has explicitly moved the workspace
     // to this output, so previous outputs it was on can be forgotten.
     pub fn set_output(&mut self, output: &Output, explicit: bool) {
+        let disambiguate = self.needs_disambiguation(output);
+        let returning = self
+            .displaced
+            .take_if(|displaced| {
+                explicit || output_matches(&displaced.output, output, disambiguate)
+            })
+            .filter(|_| !explicit);
+        let floating = if returning.is_some() {
+            self.floating_geometries()
+        } else {
+            Vec::new()
+        };
+
         self.tiling_layer.set_output(output);
         self.floating_layer.set_output(output);
         for mapped in self.mapped() {
@@ -602,6 +662,13 @@ impl Workspace {
         {
             toplevel_leave_output(&f.surface, &self.output);
             toplevel_enter_output(&f.surface, output);
+            f.surface.output_leave(&self.output);
+            f.surface.output_enter(
+                output,
+                Rectangle::from_size(output.geometry().size.as_logical()),
+            );
+            f.surface.set_geometry(output.geometry(), 0);
+            f.surface.send_configure();
         }
         if explicit {
             self.output_stack.clear();
@@ -626,18 +693,136 @@ impl Workspace {
             self.output_stack.push_back(output_match_for_output(output));
         }
         self.output = output.clone();
+
+        if let Some(displaced) = returning {
+            self.restore_floating(displaced, floating);
+        }
+    }
+
+    /// Moves the workspace away from its disconnected output, remembering its state there.
+    ///
+    /// `idx` is the position of the workspace in the set of the disconnected output.
+    pub fn displace(&mut self, new_output: &Output, idx: usize, active: bool) {
+        let floating = self.floating_geometries();
+        if self.displaced.is_none() {
+            self.displaced = Some(DisplacedState {
+                output: output_match_for_output(&self.output),
+                idx,
+                active,
+                floating: floating
+                    .iter()
+                    .map(|(surface, geometry)| DisplacedWindow {
+                        surface: surface.downgrade(),
+                        original: *geometry,
+                        displaced: *geometry,
+                    })
+                    .collect(),
+            });
+        }
+
+        self.set_output(new_output, false);
+
+        // forget windows moved in the meantime, if this isn't the first output to disconnect
+        let moved = self.floating_geometries();
+        if let Some(displaced) = self.displaced.as_mut() {
+            displaced.floating.retain_mut(|window| {
+                let Some(surface) = window.surface.upgrade() else {
+                    return false;
+                };
+                let geometry_of = |geometries: &[(CosmicSurface, Rectangle<i32, Local>)]| {
+                    geometries
+                        .iter()
+                        .find(|(s, _)| *s == surface)
+                        .map(|(_, geometry)| *geometry)
+                };
+                match (geometry_of(&floating), geometry_of(&moved)) {
+                    (Some(before), Some(after)) if before == window.displaced => {
+                        window.displaced = after;
+                        true
+                    }
+                    _ => false,
+                }
+            });
+        }
+    }
+
+    /// Position in the workspace set of `output` and whether the workspace was active there,
+    /// if it is returning to `output` after it got disconnected.
+    pub fn displaced_from(&self, output: &Output) -> Option<(usize, bool)> {
+        let disambiguate = self.needs_disambiguation(output);
+        self.displaced
+            .as_ref()
+            .filter(|displaced| output_matches(&displaced.output, output, disambiguate))
+            .map(|displaced| (displaced.idx, displaced.active))
+    }
+
+    /// Floating windows, that are neither maximized nor snapped, and their geometry
+    fn floating_geometries(&self) -> Vec<(CosmicSurface, Rectangle<i32, Local>)> {
+        self.floating_layer
+            .mapped()
+            .filter(|mapped| {
+                mapped.maximized_state.lock().unwrap().is_none()
+                    && mapped.floating_tiled.lock().unwrap().is_none()
+            })
+            .filter_map(|mapped| {
+                Some((
+                    mapped.active_window(),
+                    self.floating_layer.element_geometry(mapped)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Puts floating windows back to their geometry on the reconnected output.
+    ///
+    /// `floating` are the geometries before returning, windows moved while the output was
+    /// gone keep their position relative to the output size instead.
+    fn restore_floating(
+        &mut self,
+        displaced: DisplacedState,
+        floating: Vec<(CosmicSurface, Rectangle<i32, Local>)>,
+    ) {
+        for window in displaced.floating {
+            let Some(surface) = window.surface.upgrade() else {
+                continue;
+            };
+            if !floating
+                .iter()
+                .any(|(s, geometry)| *s == surface && *geometry == window.displaced)
+            {
+                continue;
+            }
+            let Some(mapped) = self
+                .floating_layer
+                .mapped()
+                .find(|mapped| mapped.windows().any(|(s, _)| s == surface))
+                .cloned()
+            else {
+                continue;
+            };
+            self.floating_layer.map_internal(
+                mapped,
+                Some(window.original.loc),
+                Some(window.original.size.as_logical()),
+                None,
+            );
+        }
     }
 
     pub fn prefers_output(&self, output: &Output) -> bool {
-        // Disambiguate match by connector name if existing output has same edid
-        let disambiguate = output
-            .edid()
-            .is_some_and(|edid| self.output().edid() == Some(edid));
+        let disambiguate = self.needs_disambiguation(output);
         self.output_stack
             .iter()
             .any(|i| output_matches(i, output, disambiguate))
     }
 
+    // Disambiguate matches by connector name if the existing output has the same edid
+    fn needs_disambiguation(&self, output: &Output) -> bool {
+        output
+            .edid()
+            .is_some_and(|edid| self.output().edid() == Some(edid))
+    }
+
     pub fn unmap_element(&mut self, mapped: &CosmicMapped) -> Option<WorkspaceRestoreData> {
         let was_maximized = if mapped.maximized_state.lock().unwrap().is_some() {
             // If surface is maximized then unmaximize it, so it is assigned to only one layer
@@ -684,6 +869,14 @@ impl Workspace {
         None
     }
 
+    /// Puts `new` into the tile of `old`, which leaves the workspace.
+    pub fn replace_tiled(&mut self, old: &CosmicMapped, new: &CosmicMapped) {
+        self.focus_stack.0.values_mut().for_each(|set| {
+            set.shift_remove(old);
+        });
+        self.tiling_layer.replace_window(old, new);
+    }
+
     pub fn unmap_surface<S>(&mut self, surface: &S) -> Option<(CosmicSurface, WorkspaceRestoreData)>
     where
         CosmicSurface: PartialEq<S>,
@@ -1482,6 +1675,53 @@ impl Workspace {
         }
     }
 
+    /// Switches the tiling layer between the split tree and scrollable columns.
+    ///
+    /// Enabling scrolling also enables tiling, disabling it keeps tiling enabled.
+    pub fn set_scrolling(
+        &mut self,
+        scrolling: bool,
+        seat: &Seat<State>,
+        workspace_state: &mut WorkspaceUpdateGuard<'_, State>,
+    ) {
+        self.tiling_layer.set_scrolling(scrolling);
+        if scrolling && !self.tiling_enabled {
+            self.set_tiling(true, seat, workspace_state);
+        }
+    }
+
+    /// Arranges the tiled windows by `strategy`, leaving the scrollable column mode.
+    pub fn set_tiling_strategy(
+        &mut self,
+        strategy: TilingStrategy,
+        seat: &Seat<State>,
+        workspace_state: &mut WorkspaceUpdateGuard<'_, State>,
+    ) {
+        self.tiling_layer.set_scrolling(false);
+        self.tiling_layer.set_strategy(strategy);
+        if !self.tiling_enabled {
+            self.set_tiling(true, seat, workspace_state);
+        }
+    }
+
+    /// Rearranges the tiled windows into `preset`, enabling tiling if necessary.
+    pub fn apply_layout_preset(
+        &mut self,
+        preset: &PresetNode,
+        seat: &Seat<State>,
+        workspace_state: &mut WorkspaceUpdateGuard<'_, State>,
+    ) -> Result<(), DragInProgress> {
+        if self.tiling_layer.has_placeholders() {
+            return Err(DragInProgress);
+        }
+        self.tiling_layer.set_scrolling(false);
+        self.tiling_layer.set_strategy(TilingStrategy::Split);
+        if !self.tiling_enabled {
+            self.set_tiling(true, seat, workspace_state);
+        }
+        self.tiling_layer.apply_preset(preset)
+    }
+
     pub fn toggle_floating_window(&mut self, seat: &Seat<State>, window: &CosmicMapped) {
         if self.tiling_enabled {
             if window.is_maximized(false) {
@@ -1966,6 +2206,10 @@ impl FocusStacks {
     pub fn get_mut<'a>(&'a mut self, seat: &Seat<State>) -> FocusStackMut<'a> {
         FocusStackMut(self.0.entry(seat.clone()).or_default())
     }
+
+    pub fn remove(&mut self, seat: &Seat<State>) {
+        self.0.remove(seat);
+    }
 }
 
 pub struct OutputNotMapped;
@@ -2,7 +2,8 @@
 
 use crate::{
     backend::{
-        kms::{KmsGuard, KmsState},
+        headless::{HeadlessState, OutputSpec},
+        kms::{KmsGuard, KmsState, SurfaceStats},
         render::{GlMultiError, RendererRef},
         winit::WinitState,
         x11::X11State,
@@ -10,21 +11,27 @@ use crate::{
     config::{CompOutputConfig, Config, ScreenFilter},
     dbus::DBusState,
     input::{PointerFocusState, gestures::GestureState},
+    ipc::IpcState,
     shell::{CosmicSurface, SeatExt, Shell, grabs::SeatMoveGrabState},
     utils::prelude::OutputExt,
     wayland::{
         handlers::{data_device::get_dnd_icon, image_copy_capture::SessionHolder},
         protocols::{
             a11y::A11yState,
+            color_management::ColorManagementState,
             corner_radius::CornerRadiusState,
             drm::WlDrmState,
+            gamma_control::GammaControlState,
             image_capture_source::CosmicImageCaptureSourceState,
             keyboard_layout::KeyboardLayoutState,
             output_configuration::OutputConfigurationState,
             output_power::OutputPowerState,
             overlap_notify::OverlapNotifyState,
+            session_management::SessionManagementState,
             toplevel_info::ToplevelInfoState,
             toplevel_management::{ManagementCapabilities, ToplevelManagementState},
+            transient_seat::TransientSeatState,
+            virtual_pointer::VirtualPointerManagerState,
             workspace::{WorkspaceState, WorkspaceUpdateGuard},
         },
     },
@@ -75,6 +82,7 @@ use smithay::{
         alpha_modifier::AlphaModifierState,
         background_effect::BackgroundEffectState,
         compositor::{CompositorClientState, CompositorState, SurfaceData},
+        content_type::ContentTypeState,
         cursor_shape::CursorShapeManagerState,
         dmabuf::{DmabufFeedback, DmabufGlobal, DmabufState},
         fixes::FixesState,
@@ -102,16 +110,19 @@ use smithay::{
         shell::{
             kde::decoration::KdeDecorationState,
             wlr_layer::WlrLayerShellState,
-            xdg::{XdgShellState, decoration::XdgDecorationState},
+            xdg::{XdgShellState, decoration::XdgDecorationState, dialog::XdgDialogState},
         },
         shm::ShmState,
         single_pixel_buffer::SinglePixelBufferState,
         tablet_manager::TabletManagerState,
+        tearing_control::TearingControlState,
         text_input::TextInputManagerState,
         viewporter::ViewporterState,
         virtual_keyboard::VirtualKeyboardManagerState,
         xdg_activation::XdgActivationState,
         xdg_foreign::XdgForeignState,
+        xdg_system_bell::XdgSystemBellState,
+        xdg_toplevel_icon::XdgToplevelIconManager,
         xwayland_keyboard_grab::XWaylandKeyboardGrabState,
         xwayland_shell::XWaylandShellState,
     },
@@ -251,11 +262,13 @@ pub struct Common {
     pub theme: cosmic::Theme,
 
     // wayland state
+    pub color_management_state: ColorManagementState,
     pub compositor_state: CompositorState,
     pub corner_radius_state: CornerRadiusState,
     pub data_device_state: DataDeviceState,
     pub dmabuf_state: DmabufState,
     pub fractional_scale_state: FractionalScaleManagerState,
+    pub gamma_control_state: GammaControlState,
     pub keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
     pub output_state: OutputManagerState,
     pub output_configuration_state: OutputConfigurationState<State>,
@@ -270,6 +283,7 @@ pub struct Common {
     pub image_copy_capture_state: ImageCopyCaptureState,
     pub seat_state: SeatState<State>,
     pub session_lock_manager_state: SessionLockManagerState,
+    pub session_management_state: SessionManagementState,
     pub idle_notifier_state: IdleNotifierState<State>,
     pub idle_inhibit_manager_state: IdleInhibitManagerState,
     pub idle_inhibiting_surfaces: HashSet<WlSurface>,
@@ -284,6 +298,7 @@ pub struct Common {
     pub dbus_state: DBusState,
     pub keyboard_layout_state: KeyboardLayoutState,
     pub background_effect_state: BackgroundEffectState,
+    pub ipc_state: IpcState,
 
     // shell-related wayland state
     pub xdg_shell_state: XdgShellState,
@@ -310,6 +325,7 @@ pub enum BackendData {
     X11(X11State),
     Winit(WinitState),
     Kms(KmsState),
+    Headless(HeadlessState),
     // TODO
     // Wayland(WaylandState),
     Unset,
@@ -319,6 +335,7 @@ pub enum LockedBackend<'a> {
     X11(&'a mut X11State),
     Winit(&'a mut WinitState),
     Kms(KmsGuard<'a>),
+    Headless(&'a mut HeadlessState),
 }
 
 #[derive(Debug, Clone)]
@@ -362,6 +379,13 @@ impl BackendData {
         }
     }
 
+    pub fn headless(&mut self) -> &mut HeadlessState {
+        match self {
+            BackendData::Headless(headless_state) => headless_state,
+            _ => unreachable!("Called headless in non headless backend"),
+        }
+    }
+
     pub fn schedule_render(&mut self, output: &Output) {
         match self {
             BackendData::Winit(_) => {} // We cannot do this on the winit backend.
@@ -369,6 +393,7 @@ impl BackendData {
             // Swapping with damage (which should be empty on these frames) is likely good enough anyway.
             BackendData::X11(state) => state.schedule_render(output),
             BackendData::Kms(state) => state.schedule_render(output),
+            BackendData::Headless(state) => state.schedule_render(output),
             _ => unreachable!("No backend was initialized"),
         }
     }
@@ -389,6 +414,9 @@ impl BackendData {
             BackendData::X11(state) => {
                 state.renderer.import_dmabuf(&dmabuf, None)?;
             }
+            BackendData::Headless(_) => {
+                anyhow::bail!("Dmabufs are not supported by the headless backend");
+            }
             _ => unreachable!("No backend set when importing dmabuf"),
         };
         Ok(None)
@@ -398,7 +426,7 @@ impl BackendData {
     ///
     /// `kms_node_cb` callback use used to determine nodes to render with when using kms backend.
     /// If this returns `None`, it will attempt to use llvmpipe, then panic if no renderer is
-    /// found.
+    /// found. The headless backend returns an error instead, if llvmpipe is unavailable.
     pub fn offscreen_renderer<N: Into<KmsNodes>, F: FnOnce(&mut KmsState) -> Option<N>>(
         &mut self,
         kms_node_cb: F,
@@ -422,6 +450,11 @@ impl BackendData {
             }
             BackendData::Winit(winit) => Ok(RendererRef::Glow(winit.backend.renderer())),
             BackendData::X11(x11) => Ok(RendererRef::Glow(&mut x11.renderer)),
+            BackendData::Headless(headless) => headless
+                .software_renderer
+                .as_mut()
+                .map(RendererRef::Glow)
+                .ok_or(GlMultiError::DeviceMissing),
             _ => unreachable!("No backend set when getting offscreen renderer"),
         }
     }
@@ -431,15 +464,63 @@ impl BackendData {
             BackendData::Kms(state) => state.update_screen_filter(screen_filter),
             BackendData::Winit(state) => state.update_screen_filter(screen_filter),
             BackendData::X11(state) => state.update_screen_filter(screen_filter),
+            BackendData::Headless(state) => state.update_screen_filter(screen_filter),
             _ => unreachable!("No backend set when setting screen filters"),
         }
     }
 
+    /// Creates an output without a connector, backed by offscreen rendering.
+    pub fn create_virtual_output(
+        &mut self,
+        spec: OutputSpec,
+        screen_filter: &ScreenFilter,
+        loop_handle: &LoopHandle<'static, State>,
+    ) -> anyhow::Result<Output> {
+        match self {
+            BackendData::Kms(state) => {
+                state.create_virtual_output(spec, screen_filter, loop_handle)
+            }
+            BackendData::Headless(state) => state.add_output(spec, loop_handle),
+            _ => Err(anyhow::anyhow!(
+                "Virtual outputs are not supported by this backend"
+            )),
+        }
+    }
+
+    pub fn remove_virtual_output(
+        &mut self,
+        output: &Output,
+        loop_handle: &LoopHandle<'static, State>,
+    ) -> anyhow::Result<()> {
+        match self {
+            BackendData::Kms(state) => state.remove_virtual_output(output),
+            BackendData::Headless(state) => state.remove_output(output, loop_handle),
+            _ => Err(anyhow::anyhow!(
+                "Virtual outputs are not supported by this backend"
+            )),
+        }
+    }
+
+    /// Applies the gamma ramp of `output`, the other backends don't support gamma ramps.
+    pub fn update_gamma(&mut self, output: &Output) {
+        if let BackendData::Kms(state) = self {
+            state.update_gamma(output);
+        }
+    }
+
+    pub fn surface_stats(&self, output: &Output) -> Option<SurfaceStats> {
+        match self {
+            BackendData::Kms(state) => state.surface_stats(output),
+            _ => None,
+        }
+    }
+
     pub fn lock(&mut self) -> LockedBackend<'_> {
         match self {
             BackendData::Kms(state) => LockedBackend::Kms(state.lock_devices()),
             BackendData::X11(state) => LockedBackend::X11(state),
             BackendData::Winit(state) => LockedBackend::Winit(state),
+            BackendData::Headless(state) => LockedBackend::Headless(state),
             _ => unreachable!("Tried to lock unset backend"),
         }
     }
@@ -451,6 +532,7 @@ impl LockedBackend<'_> {
             LockedBackend::Kms(state) => state.all_outputs(),
             LockedBackend::X11(state) => state.all_outputs(),
             LockedBackend::Winit(state) => state.all_outputs(),
+            LockedBackend::Headless(state) => state.all_outputs(),
         }
     }
 
@@ -538,6 +620,7 @@ impl LockedBackend<'_> {
             ),
             LockedBackend::Winit(state) => state.apply_config_for_outputs(test_only),
             LockedBackend::X11(state) => state.apply_config_for_outputs(test_only),
+            LockedBackend::Headless(state) => state.apply_config_for_outputs(test_only),
         }?;
 
         let mut shell_ref = shell.write();
@@ -585,6 +668,7 @@ impl LockedBackend<'_> {
                 // Swapping with damage (which should be empty on these frames) is likely good enough anyway.
                 LockedBackend::X11(state) => state.schedule_render(&output),
                 LockedBackend::Kms(state) => state.schedule_render(&output),
+                LockedBackend::Headless(state) => state.schedule_render(&output),
             }
         }
 
@@ -643,11 +727,13 @@ impl State {
 
         let clock = Clock::new();
         let config = Config::load(&handle);
+        let color_management_state = ColorManagementState::new::<Self>(dh);
         let compositor_state = CompositorState::new::<Self>(dh);
         let corner_radius_state = CornerRadiusState::new::<Self>(dh);
         let data_device_state = DataDeviceState::new::<Self>(dh);
         let dmabuf_state = DmabufState::new();
         let fractional_scale_state = FractionalScaleManagerState::new::<State>(dh);
+        let gamma_control_state = GammaControlState::new::<Self, _>(dh, client_not_sandboxed);
         let keyboard_shortcuts_inhibit_state = KeyboardShortcutsInhibitState::new::<Self>(dh);
         let output_state = OutputManagerState::new_with_xdg_output::<Self>(dh);
         let output_configuration_state =
@@ -675,6 +761,7 @@ impl State {
         let xdg_decoration_state = XdgDecorationState::new::<Self>(dh);
         let session_lock_manager_state =
             SessionLockManagerState::new::<Self, _>(dh, client_not_sandboxed);
+        let session_management_state = SessionManagementState::new::<Self>(dh);
         XWaylandKeyboardGrabState::new::<Self>(dh);
         let xwayland_shell_state = XWaylandShellState::new::<Self>(dh);
         PointerConstraintsState::new::<Self>(dh);
@@ -685,8 +772,12 @@ impl State {
         InputMethodManagerState::new::<Self, _>(dh, client_not_sandboxed);
         TextInputManagerState::new::<Self>(dh);
         VirtualKeyboardManagerState::new::<State, _>(dh, client_not_sandboxed);
+        VirtualPointerManagerState::new::<State, _>(dh, client_not_sandboxed);
+        TransientSeatState::new::<State, _>(dh, client_not_sandboxed);
         AlphaModifierState::new::<Self>(dh);
         SinglePixelBufferState::new::<Self>(dh);
+        TearingControlState::new::<Self>(dh);
+        ContentTypeState::new::<Self>(dh);
         FixesState::new::<Self>(dh);
         let keyboard_layout_state = KeyboardLayoutState::new::<State, _>(dh, client_not_sandboxed);
 
@@ -722,6 +813,9 @@ impl State {
         );
         let xdg_activation_state = XdgActivationState::new::<State>(dh);
         let xdg_foreign_state = XdgForeignState::new::<State>(dh);
+        XdgToplevelIconManager::new::<State>(dh);
+        XdgDialogState::new::<State>(dh);
+        XdgSystemBellState::new::<State>(dh);
         let toplevel_info_state = ToplevelInfoState::new(dh, client_not_sandboxed);
         let toplevel_management_state = ToplevelManagementState::new::<State, _>(
             dh,
@@ -759,11 +853,13 @@ impl State {
                 kiosk_child: None,
                 theme: cosmic::theme::system_preference(),
 
+                color_management_state,
                 compositor_state,
                 corner_radius_state,
                 data_device_state,
                 dmabuf_state,
                 fractional_scale_state,
+                gamma_control_state,
                 idle_notifier_state,
                 idle_inhibit_manager_state,
                 idle_inhibiting_surfaces,
@@ -775,6 +871,7 @@ impl State {
                 cursor_shape_manager_state,
                 seat_state,
                 session_lock_manager_state,
+                session_management_state,
                 keyboard_shortcuts_inhibit_state,
                 output_state,
                 output_configuration_state,
@@ -803,6 +900,7 @@ impl State {
                 pointer_focus_state: None,
                 dbus_state,
                 keyboard_layout_state,
+                ipc_state: IpcState::default(),
 
                 #[cfg(feature = "logind")]
                 inhibit_lid_fd: None,
@@ -829,6 +927,36 @@ impl State {
         }
     }
 
+    /// Creates a virtual output and adds it to the layout, like a newly connected monitor.
+    pub fn create_virtual_output(&mut self, spec: OutputSpec) -> anyhow::Result<Output> {
+        let output = self.backend.create_virtual_output(
+            spec,
+            self.common.config.dynamic_conf.screen_filter(),
+            &self.common.event_loop_handle,
+        )?;
+        self.common
+            .output_configuration_state
+            .add_heads(std::iter::once(&output));
+        self.common.add_output(&output);
+
+        if let Err(err) = self.refresh_output_config() {
+            let _ = self.remove_virtual_output(&output);
+            return Err(err);
+        }
+        Ok(output)
+    }
+
+    /// Removes a virtual output, moving its windows to the remaining outputs.
+    pub fn remove_virtual_output(&mut self, output: &Output) -> anyhow::Result<()> {
+        self.backend
+            .remove_virtual_output(output, &self.common.event_loop_handle)?;
+        self.common
+            .output_configuration_state
+            .remove_heads(std::iter::once(output));
+        self.common.remove_output(output);
+        self.refresh_output_config()
+    }
+
     fn update_inhibitor_locks(&mut self) {
         #[cfg(feature = "logind")]
         {
@@ -0,0 +1,489 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::os::{fd::AsFd, unix::net::UnixStream};
+
+use wayland_client::{
+    Connection, Dispatch, EventQueue, QueueHandle, WEnum, delegate_noop,
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_compositor::WlCompositor,
+        wl_output::{self, WlOutput},
+        wl_registry::{self, WlRegistry},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_surface::WlSurface,
+    },
+};
+use wayland_protocols::{
+    ext::{
+        image_capture_source::v1::client::{
+            ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+            ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1,
+        },
+        image_copy_capture::v1::client::{
+            ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1},
+            ext_image_copy_capture_manager_v1::{self, ExtImageCopyCaptureManagerV1},
+            ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+        },
+    },
+    xdg::{
+        dialog::v1::client::{xdg_dialog_v1::XdgDialogV1, xdg_wm_dialog_v1::XdgWmDialogV1},
+        shell::client::{
+            xdg_surface::{self, XdgSurface},
+            xdg_toplevel::{self, XdgToplevel},
+            xdg_wm_base::{self, XdgWmBase},
+        },
+    },
+};
+
+/// Size used for toplevels, if the compositor leaves the choice to the client
+const DEFAULT_SIZE: (i32, i32) = (640, 480);
+
+/// Minimal in-process xdg-shell client.
+///
+/// Every configure is acked right away and answered with a freshly allocated
+/// shm buffer of the requested size, so the shell never waits on the client.
+pub struct TestClient {
+    connection: Connection,
+    queue: EventQueue<ClientState>,
+    state: ClientState,
+}
+
+#[derive(Default)]
+struct ClientState {
+    compositor: Option<WlCompositor>,
+    wm_base: Option<XdgWmBase>,
+    shm: Option<WlShm>,
+    wm_dialog: Option<XdgWmDialogV1>,
+    output_sources: Option<ExtOutputImageCaptureSourceManagerV1>,
+    image_copy: Option<ExtImageCopyCaptureManagerV1>,
+    windows: Vec<TestWindow>,
+    outputs: Vec<TestOutput>,
+    captures: Vec<TestCapture>,
+}
+
+struct TestWindow {
+    app_id: String,
+    surface: WlSurface,
+    xdg_surface: XdgSurface,
+    toplevel: XdgToplevel,
+    pending_configure: Option<u32>,
+    size: (i32, i32),
+    destroyed: bool,
+}
+
+struct TestOutput {
+    output: WlOutput,
+    name: Option<String>,
+}
+
+/// A single frame captured through ext-image-copy-capture
+struct TestCapture {
+    session: ExtImageCopyCaptureSessionV1,
+    size: (i32, i32),
+    format: Option<wl_shm::Format>,
+    frame: Option<ExtImageCopyCaptureFrameV1>,
+    result: Option<CaptureResult>,
+}
+
+/// How a capture of [`TestClient::capture_output`] ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureResult {
+    Ready,
+    Failed,
+}
+
+impl TestClient {
+    pub fn new(stream: UnixStream) -> TestClient {
+        stream
+            .set_nonblocking(true)
+            .expect("Failed to set client socket non-blocking");
+        let connection = Connection::from_socket(stream).expect("Failed to connect test client");
+        let queue = connection.new_event_queue();
+        connection.display().get_registry(&queue.handle(), ());
+
+        TestClient {
+            connection,
+            queue,
+            state: ClientState::default(),
+        }
+    }
+
+    /// Whether all globals the client needs have been bound
+    pub fn is_ready(&self) -> bool {
+        self.state.compositor.is_some() && self.state.wm_base.is_some() && self.state.shm.is_some()
+    }
+
+    /// Whether a `wl_output` global named `name` has been bound
+    pub fn has_output(&self, name: &str) -> bool {
+        self.state
+            .outputs
+            .iter()
+            .any(|o| o.name.as_deref() == Some(name))
+    }
+
+    pub fn flush(&self) {
+        // the socket is non-blocking, a partial flush is picked up by the next round
+        let _ = self.connection.flush();
+    }
+
+    /// Reads and handles all pending events and answers outstanding configures.
+    pub fn dispatch(&mut self) {
+        if let Some(guard) = self.queue.prepare_read() {
+            // `WouldBlock` just means there is nothing to read yet
+            let _ = guard.read();
+        }
+        self.queue
+            .dispatch_pending(&mut self.state)
+            .expect("Test client protocol error");
+
+        let qh = self.queue.handle();
+        let Some(shm) = self.state.shm.clone() else {
+            return;
+        };
+        for window in self.state.windows.iter_mut().filter(|w| !w.destroyed) {
+            let Some(serial) = window.pending_configure.take() else {
+                continue;
+            };
+            window.xdg_surface.ack_configure(serial);
+            let (width, height) = window.size;
+            let buffer = create_buffer(&shm, width, height, wl_shm::Format::Argb8888, &qh);
+            window.surface.attach(Some(&buffer), 0, 0);
+            window.surface.damage_buffer(0, 0, width, height);
+            window.surface.commit();
+        }
+        self.flush();
+    }
+
+    /// Creates a new toplevel and sends the initial (buffer-less) commit.
+    pub fn create_window(&mut self, app_id: &str) {
+        self.create_toplevel(app_id, None);
+    }
+
+    /// Creates a new toplevel, which is a modal dialog of the toplevel with app-id `parent`.
+    pub fn create_dialog(&mut self, app_id: &str, parent: &str) {
+        let parent = self
+            .state
+            .windows
+            .iter()
+            .find(|w| !w.destroyed && w.app_id == parent)
+            .map(|w| w.toplevel.clone())
+            .unwrap_or_else(|| panic!("No window with app-id {parent:?}"));
+        self.create_toplevel(app_id, Some(&parent));
+    }
+
+    fn create_toplevel(&mut self, app_id: &str, parent: Option<&XdgToplevel>) {
+        let qh = self.queue.handle();
+        let compositor = self.state.compositor.as_ref().expect("Client is not ready");
+        let wm_base = self.state.wm_base.as_ref().expect("Client is not ready");
+
+        let idx = self.state.windows.len();
+        let surface = compositor.create_surface(&qh, ());
+        let xdg_surface = wm_base.get_xdg_surface(&surface, &qh, idx);
+        let toplevel = xdg_surface.get_toplevel(&qh, idx);
+        toplevel.set_app_id(app_id.to_string());
+        toplevel.set_title(app_id.to_string());
+        if let Some(parent) = parent {
+            let wm_dialog = self
+                .state
+                .wm_dialog
+                .as_ref()
+                .expect("Compositor has no xdg_wm_dialog_v1");
+            toplevel.set_parent(Some(parent));
+            wm_dialog.get_xdg_dialog(&toplevel, &qh, ()).set_modal();
+        }
+        surface.commit();
+
+        self.state.windows.push(TestWindow {
+            app_id: app_id.to_string(),
+            surface,
+            xdg_surface,
+            toplevel,
+            pending_configure: None,
+            size: DEFAULT_SIZE,
+            destroyed: false,
+        });
+        self.flush();
+    }
+
+    /// Destroys the toplevel with the given app-id.
+    pub fn destroy_window(&mut self, app_id: &str) {
+        let window = self
+            .state
+            .windows
+            .iter_mut()
+            .find(|w| !w.destroyed && w.app_id == app_id)
+            .unwrap_or_else(|| panic!("No window with app-id {app_id:?}"));
+        window.toplevel.destroy();
+        window.xdg_surface.destroy();
+        window.surface.destroy();
+        window.destroyed = true;
+        self.flush();
+    }
+
+    /// Starts capturing a single frame of the output named `name` into a shm buffer.
+    ///
+    /// Returns the index to look up the outcome with [`TestClient::capture_result`].
+    pub fn capture_output(&mut self, name: &str) -> usize {
+        let qh = self.queue.handle();
+        let output = self
+            .state
+            .outputs
+            .iter()
+            .find(|o| o.name.as_deref() == Some(name))
+            .map(|o| &o.output)
+            .unwrap_or_else(|| panic!("No output named {name:?}"));
+        let output_sources = self
+            .state
+            .output_sources
+            .as_ref()
+            .expect("Compositor has no ext_output_image_capture_source_manager_v1");
+        let image_copy = self
+            .state
+            .image_copy
+            .as_ref()
+            .expect("Compositor has no ext_image_copy_capture_manager_v1");
+
+        let idx = self.state.captures.len();
+        let source = output_sources.create_source(output, &qh, ());
+        let session = image_copy.create_session(
+            &source,
+            ext_image_copy_capture_manager_v1::Options::empty(),
+            &qh,
+            idx,
+        );
+        source.destroy();
+
+        self.state.captures.push(TestCapture {
+            session,
+            size: (0, 0),
+            format: None,
+            frame: None,
+            result: None,
+        });
+        self.flush();
+        idx
+    }
+
+    /// Outcome of the capture with index `idx`, once the compositor finished it
+    pub fn capture_result(&self, idx: usize) -> Option<CaptureResult> {
+        self.state.captures[idx].result
+    }
+}
+
+fn create_buffer(
+    shm: &WlShm,
+    width: i32,
+    height: i32,
+    format: wl_shm::Format,
+    qh: &QueueHandle<ClientState>,
+) -> WlBuffer {
+    let stride = width * 4;
+    let size = stride * height;
+    let file = tempfile::tempfile().expect("Failed to create shm file");
+    file.set_len(size as u64)
+        .expect("Failed to allocate shm file");
+
+    let pool = shm.create_pool(file.as_fd(), size, qh, ());
+    let buffer = pool.create_buffer(0, width, height, stride, format, qh, ());
+    pool.destroy();
+    buffer
+}
+
+impl Dispatch<WlRegistry, ()> for ClientState {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            match interface.as_str() {
+                "wl_compositor" => {
+                    state.compositor = Some(registry.bind(name, version.min(6), qh, ()));
+                }
+                "xdg_wm_base" => {
+                    state.wm_base = Some(registry.bind(name, version.min(5), qh, ()));
+                }
+                "wl_shm" => {
+                    state.shm = Some(registry.bind(name, 1, qh, ()));
+                }
+                "xdg_wm_dialog_v1" => {
+                    state.wm_dialog = Some(registry.bind(name, 1, qh, ()));
+                }
+                "wl_output" if version >= 4 => {
+                    let idx = state.outputs.len();
+                    state.outputs.push(TestOutput {
+                        output: registry.bind(name, 4, qh, idx),
+                        name: None,
+                    });
+                }
+                "ext_output_image_capture_source_manager_v1" => {
+                    state.output_sources = Some(registry.bind(name, 1, qh, ()));
+                }
+                "ext_image_copy_capture_manager_v1" => {
+                    state.image_copy = Some(registry.bind(name, 1, qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for ClientState {
+    fn event(
+        _state: &mut Self,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, usize> for ClientState {
+    fn event(
+        state: &mut Self,
+        _xdg_surface: &XdgSurface,
+        event: xdg_surface::Event,
+        idx: &usize,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            state.windows[*idx].pending_configure = Some(serial);
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, usize> for ClientState {
+    fn event(
+        state: &mut Self,
+        _toplevel: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        idx: &usize,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_toplevel::Event::Configure { width, height, .. } = event {
+            let window = &mut state.windows[*idx];
+            window.size = (
+                if width > 0 { width } else { DEFAULT_SIZE.0 },
+                if height > 0 { height } else { DEFAULT_SIZE.1 },
+            );
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for ClientState {
+    fn event(
+        _state: &mut Self,
+        buffer: &WlBuffer,
+        event: wl_buffer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
+        }
+    }
+}
+
+impl Dispatch<WlOutput, usize> for ClientState {
+    fn event(
+        state: &mut Self,
+        _output: &WlOutput,
+        event: wl_output::Event,
+        idx: &usize,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state.outputs[*idx].name = Some(name);
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, usize> for ClientState {
+    fn event(
+        state: &mut Self,
+        session: &ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        idx: &usize,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let capture = &mut state.captures[*idx];
+        match event {
+            ext_image_copy_capture_session_v1::Event::BufferSize { width, height } => {
+                capture.size = (width as i32, height as i32);
+            }
+            ext_image_copy_capture_session_v1::Event::ShmFormat {
+                format: WEnum::Value(format),
+            } => {
+                capture.format.get_or_insert(format);
+            }
+            // constraints are complete, capture a single frame with the first shm format
+            ext_image_copy_capture_session_v1::Event::Done if capture.frame.is_none() => {
+                let shm = state.shm.as_ref().expect("Client is not ready");
+                let format = capture
+                    .format
+                    .expect("Capture session offers no shm format");
+                let (width, height) = capture.size;
+                let buffer = create_buffer(shm, width, height, format, qh);
+                let frame = session.create_frame(qh, *idx);
+                frame.attach_buffer(&buffer);
+                frame.damage_buffer(0, 0, width, height);
+                frame.capture();
+                capture.frame = Some(frame);
+            }
+            ext_image_copy_capture_session_v1::Event::Stopped => {
+                capture.result.get_or_insert(CaptureResult::Failed);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, usize> for ClientState {
+    fn event(
+        state: &mut Self,
+        _frame: &ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        idx: &usize,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_image_copy_capture_frame_v1::Event::Ready => {
+                state.captures[*idx].result = Some(CaptureResult::Ready);
+            }
+            ext_image_copy_capture_frame_v1::Event::Failed { .. } => {
+                state.captures[*idx].result = Some(CaptureResult::Failed);
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(ClientState: ignore WlCompositor);
+delegate_noop!(ClientState: ignore WlSurface);
+delegate_noop!(ClientState: ignore WlShm);
+delegate_noop!(ClientState: ignore WlShmPool);
+delegate_noop!(ClientState: ignore XdgWmDialogV1);
+delegate_noop!(ClientState: ignore XdgDialogV1);
+delegate_noop!(ClientState: ignore ExtOutputImageCaptureSourceManagerV1);
+delegate_noop!(ClientState: ignore ExtImageCaptureSourceV1);
+delegate_noop!(ClientState: ignore ExtImageCopyCaptureManagerV1);
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_settings_config::shortcuts::{Action, action::FocusDirection};
+
+use super::TestCompositor;
+
+#[test]
+fn focus_follows_tiling_tree() {
+    let mut compositor = TestCompositor::new();
+    compositor.set_tiling(true);
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,c]]");
+
+    compositor.action(Action::Focus(FocusDirection::Up));
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+    compositor.action(Action::Focus(FocusDirection::Left));
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("a"));
+    // entering a group restores the last focused window inside of it
+    compositor.action(Action::Focus(FocusDirection::Right));
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+}
+
+#[test]
+fn focus_moves_to_remaining_window_on_unmap() {
+    let mut compositor = TestCompositor::new();
+    compositor.set_tiling(true);
+    compositor.map_window("a");
+    compositor.map_window("b");
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+
+    compositor.unmap_window("b");
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("a"));
+}
+
+#[test]
+fn floating_windows_take_focus() {
+    let mut compositor = TestCompositor::new();
+    compositor.map_window("a");
+    compositor.map_window("b");
+
+    assert_eq!(compositor.tree_shape(), "");
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+}
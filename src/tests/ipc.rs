@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::UnixStream,
+};
+
+use super::{MAX_ROUNDS, TestCompositor};
+use crate::ipc::{self, Event, Reply, Response};
+
+fn connect(compositor: &mut TestCompositor) -> BufReader<UnixStream> {
+    let (server, client) = UnixStream::pair().expect("Failed to create socket pair");
+    ipc::add_client(&mut compositor.state, server).expect("Failed to add IPC client");
+    client
+        .set_nonblocking(true)
+        .expect("Failed to set IPC client non-blocking");
+    BufReader::new(client)
+}
+
+/// Reads the next line the compositor sent, if any.
+fn read_line(client: &mut BufReader<UnixStream>) -> Option<String> {
+    let mut line = String::new();
+    match client.read_line(&mut line) {
+        Ok(0) => None,
+        Ok(_) => Some(line),
+        Err(err) if err.kind() == ErrorKind::WouldBlock => None,
+        Err(err) => panic!("Failed to read from IPC socket: {err}"),
+    }
+}
+
+#[test]
+fn requests_are_answered_in_order() {
+    let mut compositor = TestCompositor::new();
+    compositor.map_window("a");
+    let mut client = connect(&mut compositor);
+
+    client
+        .get_mut()
+        .write_all(b"{\"request\":\"version\"}\nnonsense\n{\"request\":\"windows\"}\n")
+        .unwrap();
+    compositor.settle();
+
+    let version = serde_json::from_str::<Reply>(&read_line(&mut client).unwrap()).unwrap();
+    assert!(matches!(version, Reply::Ok(Response::Version { .. })));
+    let invalid = serde_json::from_str::<Reply>(&read_line(&mut client).unwrap()).unwrap();
+    assert!(matches!(invalid, Reply::Err(_)));
+    let Reply::Ok(Response::Windows(windows)) =
+        serde_json::from_str::<Reply>(&read_line(&mut client).unwrap()).unwrap()
+    else {
+        panic!("Expected a list of windows");
+    };
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].app_id, "a");
+    assert!(read_line(&mut client).is_none());
+}
+
+#[test]
+fn subscribers_only_receive_events() {
+    let mut compositor = TestCompositor::new();
+    let mut client = connect(&mut compositor);
+
+    client
+        .get_mut()
+        .write_all(
+            b"{\"request\":\"subscribe\",\"events\":[\"windows\"]}\n{\"request\":\"version\"}\n",
+        )
+        .unwrap();
+    compositor.settle();
+    let reply = serde_json::from_str::<Reply>(&read_line(&mut client).unwrap()).unwrap();
+    assert!(matches!(reply, Reply::Ok(Response::Subscribed)));
+    assert!(read_line(&mut client).is_none());
+
+    compositor.map_window("a");
+    let Event::WindowMapped { window } =
+        serde_json::from_str::<Event>(&read_line(&mut client).unwrap()).unwrap()
+    else {
+        panic!("Expected a window to be mapped");
+    };
+    assert_eq!(window.app_id, "a");
+
+    client
+        .get_mut()
+        .write_all(b"{\"request\":\"version\"}\n")
+        .unwrap();
+    compositor.settle();
+    assert!(read_line(&mut client).is_none());
+}
+
+#[test]
+fn stalled_client_is_dropped() {
+    let mut compositor = TestCompositor::new();
+    let mut client = connect(&mut compositor).into_inner();
+
+    // never read any of the replies
+    let request = b"{\"request\":\"outputs\"}\n";
+    for _ in 0..MAX_ROUNDS * 10 {
+        loop {
+            match client.write(request) {
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::BrokenPipe => return,
+                Err(err) => panic!("Failed to write to IPC socket: {err}"),
+            }
+        }
+        compositor.dispatch();
+    }
+    panic!("Stalled client was never dropped");
+}
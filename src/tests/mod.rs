@@ -0,0 +1,383 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Integration tests driving the shell through the headless backend.
+//!
+//! A [`TestCompositor`] runs a full `State` with virtual outputs and a single
+//! seat, plus an in-process xdg-shell client to map toplevels by app-id.
+//! Tests feed shortcut and compositor actions through `State::handle_action`
+//! and assert on the resulting tiling trees, geometries and keyboard focus.
+
+use std::{
+    os::unix::net::UnixStream,
+    sync::{Arc, Once, atomic::Ordering},
+    time::Duration,
+};
+
+use cosmic_settings_config::shortcuts::{self, action::Orientation};
+use id_tree::{NodeId, Tree};
+use smithay::{
+    input::Seat,
+    output::Output,
+    reexports::{
+        calloop::EventLoop,
+        wayland_server::{Display, DisplayHandle},
+    },
+    utils::{Rectangle, SERIAL_COUNTER},
+};
+use tempfile::TempDir;
+
+use crate::{
+    backend::headless::{self, OutputSpec},
+    config::{Action, TEST_HOME},
+    hooks::{HOOKS, Hooks},
+    ipc::IpcState,
+    shell::{
+        CosmicMapped, CosmicSurface, create_seat, focus::target::KeyboardFocusTarget,
+        layout::tiling::Data,
+    },
+    state::{Common, State},
+    utils::prelude::*,
+    wayland::handlers::compositor::client_compositor_state,
+};
+
+mod client;
+mod focus;
+mod ipc;
+mod modal;
+mod outputs;
+mod presets;
+mod restore;
+mod scratchpad;
+mod scrolling;
+mod strategy;
+mod swallow;
+mod tiling;
+mod window_rules;
+mod workspaces;
+
+use self::client::TestClient;
+
+/// Upper bound of dispatch rounds to wait for the compositor to settle
+const MAX_ROUNDS: usize = 100;
+
+static ENVIRONMENT: Once = Once::new();
+
+fn setup_environment() {
+    ENVIRONMENT.call_once(|| {
+        // never pick up the configuration of the user running the tests, each compositor
+        // gets its own config and state directories on top of that
+        let dir = tempfile::tempdir()
+            .expect("Failed to create test directory")
+            .keep();
+        // SAFETY: Runs once, before any test spawns threads of its own.
+        unsafe {
+            std::env::set_var("XDG_CONFIG_HOME", dir.join("config"));
+            std::env::set_var("XDG_STATE_HOME", dir.join("state"));
+            std::env::set_var("XDG_RUNTIME_DIR", dir.join("runtime"));
+        }
+        let _ = std::fs::create_dir_all(dir.join("runtime"));
+        let _ = HOOKS.set(Hooks::default());
+    });
+}
+
+pub struct TestCompositor {
+    display: Display<State>,
+    event_loop: EventLoop<'static, State>,
+    pub state: State,
+    pub seat: Seat<State>,
+    client: TestClient,
+    _home: TempDir,
+}
+
+impl TestCompositor {
+    /// Creates a compositor with a single 1920x1080 output.
+    pub fn new() -> TestCompositor {
+        TestCompositor::with_outputs(vec![OutputSpec::default()])
+    }
+
+    pub fn with_outputs(specs: Vec<OutputSpec>) -> TestCompositor {
+        setup_environment();
+        let home = tempfile::tempdir().expect("Failed to create test directory");
+        TEST_HOME.set(Some(home.path().to_path_buf()));
+
+        let mut event_loop = EventLoop::try_new().expect("Failed to create event loop");
+        let display = Display::<State>::new().expect("Failed to create display");
+        let dh = display.handle();
+        let mut state = State::new(
+            &dh,
+            "wayland-test".into(),
+            event_loop.handle(),
+            event_loop.get_signal(),
+            false,
+            None,
+        );
+        headless::init_backend_with_outputs(&dh, &mut event_loop, &mut state, specs)
+            .expect("Failed to initialize headless backend");
+
+        let output = state
+            .common
+            .shell
+            .read()
+            .outputs()
+            .next()
+            .cloned()
+            .expect("Backend initialized without output");
+        let seat = create_seat(
+            &dh,
+            &mut state.common.seat_state,
+            &output,
+            &state.common.config,
+            "seat-0".into(),
+        );
+        state.common.shell.write().seats.add_seat(seat.clone());
+        state.common.startup_done.store(true, Ordering::SeqCst);
+
+        let (server, client) = UnixStream::pair().expect("Failed to create socket pair");
+        let client_state = Arc::new(state.new_client_state());
+        DisplayHandle::clone(&dh)
+            .insert_client(server, client_state)
+            .expect("Failed to insert test client");
+
+        let mut compositor = TestCompositor {
+            display,
+            event_loop,
+            state,
+            seat,
+            client: TestClient::new(client),
+            _home: home,
+        };
+        compositor.dispatch_until("client globals", |c| c.client.is_ready());
+        compositor
+    }
+
+    /// Creates a compositor with tiling enabled and `app_ids` mapped in order.
+    pub fn tiled(app_ids: &[&str]) -> TestCompositor {
+        let mut compositor = TestCompositor::new();
+        compositor.set_tiling(true);
+        for app_id in app_ids {
+            compositor.map_window(app_id);
+        }
+        compositor
+    }
+
+    /// Runs one round of client and server dispatching, mirroring the main loop.
+    pub fn dispatch(&mut self) {
+        self.client.flush();
+        self.display
+            .dispatch_clients(&mut self.state)
+            .expect("Failed to dispatch clients");
+        self.event_loop
+            .dispatch(Some(Duration::ZERO), &mut self.state)
+            .expect("Failed to dispatch event loop");
+
+        let clients = self.state.common.shell.write().update_animations();
+        let dh = self.state.common.display_handle.clone();
+        for client in clients.values() {
+            client_compositor_state(client).blocker_cleared(&mut self.state, &dh);
+        }
+        self.state.common.refresh();
+        Common::refresh_focus(&mut self.state);
+        IpcState::refresh(&mut self.state);
+
+        self.display
+            .flush_clients()
+            .expect("Failed to flush clients");
+        self.client.dispatch();
+    }
+
+    /// Dispatches until `condition` holds, panics if it never does.
+    pub fn dispatch_until(&mut self, what: &str, condition: impl Fn(&Self) -> bool) {
+        for _ in 0..MAX_ROUNDS {
+            self.dispatch();
+            if condition(self) {
+                return;
+            }
+        }
+        panic!("Timed out waiting for {what}");
+    }
+
+    /// Lets pending configures, commits and transactions settle.
+    pub fn settle(&mut self) {
+        for _ in 0..10 {
+            self.dispatch();
+        }
+    }
+
+    /// Maps a new toplevel and waits until the shell has placed it.
+    pub fn map_window(&mut self, app_id: &str) -> CosmicSurface {
+        self.client.create_window(app_id);
+        self.dispatch_until(&format!("{app_id:?} to map"), |c| {
+            c.try_surface(app_id).is_some()
+        });
+        self.settle();
+        self.surface(app_id)
+    }
+
+    /// Destroys a toplevel and waits until the shell has removed it.
+    pub fn unmap_window(&mut self, app_id: &str) {
+        self.client.destroy_window(app_id);
+        self.dispatch_until(&format!("{app_id:?} to unmap"), |c| {
+            c.try_surface(app_id).is_none()
+        });
+        self.settle();
+    }
+
+    /// Runs an action as if it was triggered by a key binding without modifiers.
+    pub fn action(&mut self, action: impl Into<Action>) {
+        let seat = self.seat.clone();
+        let time = self.state.common.clock.now().as_millis();
+        self.state.handle_action(
+            action.into(),
+            &seat,
+            SERIAL_COUNTER.next_serial(),
+            time,
+            shortcuts::Binding {
+                modifiers: Default::default(),
+                keycode: None,
+                key: None,
+                description: None,
+            },
+            None,
+        );
+        self.settle();
+    }
+
+    /// Enables or disables tiling on the active workspace of the active output.
+    pub fn set_tiling(&mut self, tiling: bool) {
+        let output = self.seat.active_output();
+        {
+            let mut shell = self.state.common.shell.write();
+            let workspace = shell.workspaces.active_mut(&output).unwrap();
+            let mut guard = self.state.common.workspace_state.update();
+            workspace.set_tiling(tiling, &self.seat, &mut guard);
+        }
+        self.settle();
+    }
+
+    pub fn output(&self, idx: usize) -> Output {
+        self.state
+            .common
+            .shell
+            .read()
+            .outputs()
+            .nth(idx)
+            .cloned()
+            .unwrap_or_else(|| panic!("No output {idx}"))
+    }
+
+    fn try_mapped(&self, app_id: &str) -> Option<CosmicMapped> {
+        self.state
+            .common
+            .shell
+            .read()
+            .mapped()
+            .find(|mapped| mapped.windows().any(|(w, _)| w.app_id() == app_id))
+            .cloned()
+    }
+
+    fn try_surface(&self, app_id: &str) -> Option<CosmicSurface> {
+        self.try_mapped(app_id).and_then(|mapped| {
+            mapped
+                .windows()
+                .map(|(w, _)| w)
+                .find(|w| w.app_id() == app_id)
+        })
+    }
+
+    pub fn surface(&self, app_id: &str) -> CosmicSurface {
+        self.try_surface(app_id)
+            .unwrap_or_else(|| panic!("No mapped window with app-id {app_id:?}"))
+    }
+
+    /// Global geometry of the element containing the window with `app_id`.
+    pub fn geometry(&self, app_id: &str) -> Rectangle<i32, Global> {
+        let mapped = self
+            .try_mapped(app_id)
+            .unwrap_or_else(|| panic!("No mapped window with app-id {app_id:?}"));
+        self.state
+            .common
+            .shell
+            .read()
+            .element_geometry(&mapped)
+            .unwrap_or_else(|| panic!("Window {app_id:?} has no geometry"))
+    }
+
+    /// App-id of the window holding keyboard focus of the seat.
+    pub fn focused_app_id(&self) -> Option<String> {
+        match self.seat.get_keyboard().unwrap().current_focus()? {
+            KeyboardFocusTarget::Element(mapped) => Some(mapped.active_window().app_id()),
+            KeyboardFocusTarget::Fullscreen(surface) => Some(surface.app_id()),
+            _ => None,
+        }
+    }
+
+    /// Index of the active workspace on the active output.
+    pub fn active_workspace(&self) -> usize {
+        let output = self.seat.active_output();
+        self.state
+            .common
+            .shell
+            .read()
+            .workspaces
+            .active_num(&output)
+            .1
+    }
+
+    /// App-ids of all windows on the given workspace of the active output.
+    pub fn workspace_windows(&self, idx: usize) -> Vec<String> {
+        let output = self.seat.active_output();
+        let shell = self.state.common.shell.read();
+        let Some(workspace) = shell.workspaces.get(idx, &output) else {
+            return Vec::new();
+        };
+        let mut app_ids = workspace
+            .mapped()
+            .flat_map(|mapped| mapped.windows().map(|(w, _)| w.app_id()))
+            .collect::<Vec<_>>();
+        app_ids.sort();
+        app_ids
+    }
+
+    /// Compact description of the tiling tree of the active workspace.
+    ///
+    /// Groups render as `H[..]` or `V[..]` after their orientation, stacks
+    /// as `S(..)`, windows by their app-id and placeholders as `_`.
+    /// An empty tree renders as an empty string.
+    pub fn tree_shape(&self) -> String {
+        let output = self.seat.active_output();
+        let shell = self.state.common.shell.read();
+        let workspace = shell.active_space(&output).unwrap();
+        let tree = workspace.tiling_layer.tree();
+        tree.root_node_id()
+            .map(|root| node_shape(tree, root))
+            .unwrap_or_default()
+    }
+}
+
+fn node_shape(tree: &Tree<Data>, id: &NodeId) -> String {
+    let node = tree.get(id).unwrap();
+    match node.data() {
+        Data::Group { orientation, .. } => {
+            let children = node
+                .children()
+                .iter()
+                .map(|child| node_shape(tree, child))
+                .collect::<Vec<_>>()
+                .join(",");
+            match orientation {
+                Orientation::Horizontal => format!("H[{children}]"),
+                Orientation::Vertical => format!("V[{children}]"),
+            }
+        }
+        Data::Mapped { mapped, .. } if mapped.is_stack() => {
+            let windows = mapped
+                .windows()
+                .map(|(w, _)| w.app_id())
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("S({windows})")
+        }
+        Data::Mapped { mapped, .. } => mapped.active_window().app_id(),
+        Data::Placeholder { .. } => String::from("_"),
+    }
+}
@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::utils::{Point, Rectangle, Size};
+
+use super::TestCompositor;
+use crate::shell::{Shell, focus::target::KeyboardFocusTarget, modal::centered};
+
+/// Maps a new modal dialog of the window with app-id `parent`.
+fn map_dialog(compositor: &mut TestCompositor, app_id: &str, parent: &str) {
+    compositor.client.create_dialog(app_id, parent);
+    compositor.dispatch_until(&format!("{app_id:?} to map"), |c| {
+        c.try_surface(app_id).is_some()
+    });
+    compositor.settle();
+}
+
+#[test]
+fn centered_over_parent() {
+    let parent = Rectangle::new(Point::from((100, 50)), Size::from((800, 600)));
+    assert_eq!(
+        centered(parent, Size::from((200, 100))),
+        Point::from((400, 300))
+    );
+    // larger dialogs overlap the parent evenly
+    assert_eq!(
+        centered(parent, Size::from((1000, 600))),
+        Point::from((0, 50))
+    );
+}
+
+#[test]
+fn dialog_floats_centered_over_tiled_parent() {
+    let mut compositor = TestCompositor::tiled(&["a", "parent"]);
+    let shape = compositor.tree_shape();
+
+    map_dialog(&mut compositor, "dialog", "parent");
+    assert_eq!(compositor.tree_shape(), shape);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("dialog"));
+
+    let parent = compositor.geometry("parent");
+    let dialog = compositor.geometry("dialog");
+    let center = |geometry: Rectangle<i32, _>| geometry.loc + geometry.size.downscale(2).to_point();
+    let (parent_center, dialog_center) = (center(parent), center(dialog));
+    assert!((parent_center.x - dialog_center.x).abs() <= 1);
+    assert!((parent_center.y - dialog_center.y).abs() <= 1);
+}
+
+#[test]
+fn modal_for_follows_nested_dialogs() {
+    let mut compositor = TestCompositor::tiled(&["a", "parent"]);
+    map_dialog(&mut compositor, "dialog", "parent");
+    map_dialog(&mut compositor, "nested", "dialog");
+
+    let modal_for = |app_id: &str| {
+        let mapped = compositor.try_mapped(app_id).unwrap();
+        compositor
+            .state
+            .common
+            .shell
+            .read()
+            .modal_for(&mapped)
+            .map(|mapped| mapped.active_window().app_id())
+    };
+    assert_eq!(modal_for("parent").as_deref(), Some("nested"));
+    assert_eq!(modal_for("dialog").as_deref(), Some("nested"));
+    assert_eq!(modal_for("nested"), None);
+    assert_eq!(modal_for("a"), None);
+}
+
+#[test]
+fn focusing_parent_focuses_dialog() {
+    let mut compositor = TestCompositor::tiled(&["a", "parent"]);
+    map_dialog(&mut compositor, "dialog", "parent");
+
+    let seat = compositor.seat.clone();
+    for app_id in ["a", "parent"] {
+        let target = KeyboardFocusTarget::Element(compositor.try_mapped(app_id).unwrap());
+        Shell::set_focus(&mut compositor.state, Some(&target), &seat, None, false);
+        compositor.settle();
+    }
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("dialog"));
+
+    compositor.unmap_window("dialog");
+    let target = KeyboardFocusTarget::Element(compositor.try_mapped("parent").unwrap());
+    Shell::set_focus(&mut compositor.state, Some(&target), &seat, None, false);
+    compositor.settle();
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("parent"));
+}
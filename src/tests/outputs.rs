@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::{
+    MirrorSource,
+    output::comp::{OutputConfig, OutputMatcher, OutputProfile, ProfileOutput},
+};
+use cosmic_settings_config::shortcuts::Action;
+use smithay::utils::{Physical, Rectangle};
+
+use super::{TestCompositor, client::CaptureResult};
+use crate::{
+    backend::{
+        headless::OutputSpec,
+        kms::{VirtualOutput, mirror_area},
+        render::PostprocessOutputConfig,
+    },
+    config::ScreenFilter,
+    state::BackendData,
+    utils::prelude::{OutputExt, RectGlobalExt, SeatExt},
+    wayland::protocols::workspace::State as WState,
+};
+
+#[test]
+fn virtual_output_joins_and_leaves_layout() {
+    let mut compositor = TestCompositor::new();
+    compositor.map_window("a");
+
+    let output = compositor
+        .state
+        .create_virtual_output(OutputSpec::default())
+        .expect("Failed to create virtual output");
+    compositor.settle();
+    assert_eq!(output.name(), "HEADLESS-2");
+    assert_eq!(compositor.output(1), output);
+    assert!(!output.geometry().overlaps(compositor.output(0).geometry()));
+
+    compositor
+        .state
+        .remove_virtual_output(&output)
+        .expect("Failed to remove virtual output");
+    compositor.settle();
+    assert_eq!(compositor.state.common.shell.read().outputs().count(), 1);
+    assert_eq!(compositor.workspace_windows(0), vec!["a"]);
+}
+
+#[test]
+fn capture_without_software_renderer_is_an_error() {
+    let mut compositor = TestCompositor::new();
+    let BackendData::Headless(headless) = &mut compositor.state.backend else {
+        unreachable!("Test compositor runs headless");
+    };
+    headless.software_renderer = None;
+
+    assert!(
+        compositor
+            .state
+            .backend
+            .offscreen_renderer(|kms| *kms.primary_node.read().unwrap())
+            .is_err()
+    );
+}
+
+#[test]
+fn kms_virtual_output_finishes_captures() {
+    let mut compositor = TestCompositor::new();
+    let mut virtual_output = VirtualOutput::new(
+        String::from("VIRTUAL-1"),
+        OutputSpec::default(),
+        &ScreenFilter::default(),
+        &compositor.state.common.event_loop_handle,
+    )
+    .expect("Failed to create virtual output");
+    let output = virtual_output.output.clone();
+    compositor
+        .state
+        .common
+        .output_configuration_state
+        .add_heads(std::iter::once(&output));
+    compositor.state.common.add_output(&output);
+    compositor
+        .state
+        .refresh_output_config()
+        .expect("Failed to configure virtual output");
+    compositor.dispatch_until("virtual output global", |c| {
+        c.client.has_output("VIRTUAL-1")
+    });
+
+    let capture = compositor.client.capture_output("VIRTUAL-1");
+    compositor.settle();
+    assert_eq!(compositor.client.capture_result(capture), None);
+
+    // render like the kms backend does, just with the software renderer instead of a gpu
+    let BackendData::Headless(headless) = &mut compositor.state.backend else {
+        unreachable!("Test compositor runs headless");
+    };
+    let renderer = headless
+        .software_renderer
+        .as_mut()
+        .expect("Test requires a software renderer");
+    virtual_output
+        .render(None, renderer, &mut compositor.state.common)
+        .expect("Failed to render virtual output");
+    compositor.dispatch_until("capture to finish", |c| {
+        c.client.capture_result(capture).is_some()
+    });
+    assert_eq!(
+        compositor.client.capture_result(capture),
+        Some(CaptureResult::Ready)
+    );
+}
+
+#[test]
+fn profile_arranges_matching_outputs() {
+    let mut compositor = TestCompositor::new();
+    let profile_output = |model: &str, position, primary| ProfileOutput {
+        matcher: OutputMatcher {
+            make: Some("COSMIC".into()),
+            model: Some(model.into()),
+            ..Default::default()
+        },
+        config: OutputConfig {
+            mode: ((1920, 1080), Some(60_000)),
+            position,
+            ..Default::default()
+        },
+        primary,
+    };
+    compositor
+        .state
+        .common
+        .config
+        .dynamic_conf
+        .outputs_mut()
+        .profiles
+        .push(OutputProfile {
+            name: String::from("desk"),
+            outputs: vec![
+                profile_output("HEADLESS-1", (1920, 0), false),
+                profile_output("HEADLESS-2", (3840, 0), false),
+                profile_output("HEADLESS-*", (0, 0), true),
+            ],
+        });
+
+    let outputs = (0..2)
+        .map(|_| {
+            compositor
+                .state
+                .create_virtual_output(OutputSpec::default())
+                .expect("Failed to create virtual output")
+        })
+        .collect::<Vec<_>>();
+    compositor.settle();
+    assert_eq!(compositor.output(0).geometry().loc, (1920, 0).into());
+    assert_eq!(outputs[0].geometry().loc, (3840, 0).into());
+    assert_eq!(outputs[1].geometry().loc, (0, 0).into());
+    assert_eq!(compositor.seat.active_output(), outputs[1]);
+    // the profile isn't stored as a config for exactly these outputs
+    let dynamic_conf = &compositor.state.common.config.dynamic_conf;
+    assert!(
+        !dynamic_conf
+            .outputs()
+            .config
+            .keys()
+            .any(|infos| infos.len() == 3)
+    );
+}
+
+#[test]
+fn workspaces_return_to_reconnected_output() {
+    let mut compositor = TestCompositor::new();
+    let spec = OutputSpec {
+        size: (1280, 720).into(),
+        ..Default::default()
+    };
+    let output = compositor
+        .state
+        .create_virtual_output(spec)
+        .expect("Failed to create virtual output");
+    compositor.settle();
+    compositor.seat.set_active_output(&output);
+
+    compositor.map_window("a");
+    let geometry = compositor.geometry("a");
+    compositor.action(Action::Workspace(2));
+    let b = compositor.map_window("b");
+    compositor.action(Action::Fullscreen);
+
+    compositor
+        .state
+        .remove_virtual_output(&output)
+        .expect("Failed to remove virtual output");
+    compositor.settle();
+    let output = compositor
+        .state
+        .create_virtual_output(spec)
+        .expect("Failed to create virtual output");
+    compositor.settle();
+    compositor.seat.set_active_output(&output);
+
+    assert_eq!(compositor.active_workspace(), 1);
+    assert_eq!(compositor.workspace_windows(0), vec!["a"]);
+    assert_eq!(compositor.geometry("a"), geometry);
+    let shell = compositor.state.common.shell.read();
+    let workspace = shell.workspaces.get(1, &output).unwrap();
+    assert!(
+        workspace
+            .fullscreen_surfaces
+            .iter()
+            .any(|f| f.surface == b && f.ended_at.is_none())
+    );
+    let workspace_state = &compositor.state.common.workspace_state;
+    let active = shell
+        .workspaces
+        .spaces_for_output(&output)
+        .filter(|workspace| {
+            workspace_state
+                .workspace_states(&workspace.handle)
+                .is_some_and(|states| states.contains(WState::Active))
+        })
+        .count();
+    assert_eq!(active, 1);
+}
+
+#[test]
+fn mirror_area_clamps_regions_and_follows_windows() {
+    let compositor = TestCompositor::tiled(&["a", "b"]);
+    let output = compositor.output(0);
+    let window = compositor.geometry("b").as_logical().to_f64();
+    let config = PostprocessOutputConfig::for_output_untransformed(&output);
+    let full = Rectangle::from_size(config.size);
+
+    let shell = compositor.state.common.shell.read();
+    let area = |source: MirrorSource| mirror_area(&shell, &output, &source, &config);
+    let region = |x, y, width, height| MirrorSource::Region {
+        x,
+        y,
+        width,
+        height,
+    };
+    assert_eq!(area(MirrorSource::Output), full);
+    assert_eq!(
+        area(region(1800, 1000, 400, 200)),
+        Rectangle::new((1800, 1000).into(), (120, 80).into())
+    );
+    // empty regions and regions beside the output show all of it
+    assert_eq!(area(region(100, 100, 0, 0)), full);
+    assert_eq!(area(region(2000, 0, 400, 200)), full);
+
+    let expected: Rectangle<i32, Physical> =
+        window.to_physical_precise_round(config.fractional_scale);
+    assert_eq!(area(MirrorSource::Window { app_id: "b".into() }), expected);
+    assert_eq!(
+        area(MirrorSource::Window {
+            app_id: "missing".into()
+        }),
+        full
+    );
+}
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::{
+    actions::{CompAction, TilingStrategy},
+    workspace::OutputMatch,
+};
+use cosmic_settings_config::shortcuts::action::Orientation;
+
+use super::TestCompositor;
+use crate::{
+    shell::layout::restore::{
+        LayoutSnapshot, NodeSnapshot, PendingRestore, WindowKey, WorkspaceSnapshot,
+    },
+    utils::prelude::*,
+};
+
+fn windows(app_ids: &[&str]) -> NodeSnapshot {
+    NodeSnapshot::Windows(
+        app_ids
+            .iter()
+            .map(|app_id| WindowKey {
+                app_id: app_id.to_string(),
+                title: app_id.to_string(),
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn saved_preset_is_reapplied() {
+    let mut compositor = TestCompositor::new();
+    compositor.action(CompAction::SetTilingStrategy(TilingStrategy::MasterStack));
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,c]]");
+    compositor.action(CompAction::SaveLayoutPreset("dev".into()));
+
+    compositor.action(CompAction::SetTilingStrategy(TilingStrategy::Grid));
+    assert_eq!(compositor.tree_shape(), "H[V[a,b],c]");
+
+    compositor.action(CompAction::ApplyLayoutPreset("dev".into()));
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,c]]");
+}
+
+#[test]
+fn new_windows_fill_empty_slots() {
+    let mut compositor = TestCompositor::new();
+    compositor.action(CompAction::SetTilingStrategy(TilingStrategy::MasterStack));
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    compositor.action(CompAction::SaveLayoutPreset("dev".into()));
+    compositor.action(CompAction::ApplyLayoutPreset("dev".into()));
+
+    compositor.unmap_window("b");
+    assert_eq!(compositor.tree_shape(), "V[a,c]");
+
+    compositor.map_window("b");
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,c]]");
+}
+
+#[test]
+fn stacks_keep_their_slot() {
+    // stacks are only created interactively, restore one instead
+    let mut compositor = TestCompositor::tiled(&[]);
+    let output = compositor.output(0);
+    let snapshot = LayoutSnapshot {
+        workspaces: vec![WorkspaceSnapshot {
+            output: OutputMatch {
+                name: output.name(),
+                edid: output.edid().cloned(),
+            },
+            idx: 0,
+            tiling: Some(NodeSnapshot::Group {
+                orientation: Orientation::Vertical,
+                ratios: vec![0.5, 0.5],
+                children: vec![windows(&["a", "b"]), windows(&["c"])],
+            }),
+            floating: Vec::new(),
+            scrolling: false,
+            strategy: TilingStrategy::Split,
+        }],
+    };
+    compositor.state.common.shell.write().pending_restore = PendingRestore::new(snapshot);
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    assert_eq!(compositor.tree_shape(), "V[S(a,b),c]");
+
+    compositor.action(CompAction::SaveLayoutPreset("stacked".into()));
+    compositor.action(CompAction::ApplyLayoutPreset("stacked".into()));
+    assert_eq!(compositor.tree_shape(), "V[S(a,b),c]");
+
+    compositor.unmap_window("a");
+    assert_eq!(compositor.tree_shape(), "V[S(b),c]");
+}
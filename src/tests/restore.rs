@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::{actions::TilingStrategy, workspace::OutputMatch};
+use cosmic_settings_config::shortcuts::action::Orientation;
+use smithay::utils::Rectangle;
+
+use super::TestCompositor;
+use crate::{
+    shell::{
+        FloatingRestoreData, WorkspaceRestoreData,
+        layout::restore::{
+            FloatingSnapshot, LayoutSnapshot, NodeSnapshot, PendingRestore, PlacementLayer,
+            ToplevelSnapshot, WindowKey, WorkspaceSnapshot,
+        },
+    },
+    utils::prelude::*,
+};
+
+fn key(app_id: &str) -> WindowKey {
+    WindowKey {
+        app_id: app_id.into(),
+        title: app_id.into(),
+    }
+}
+
+fn window(app_id: &str) -> NodeSnapshot {
+    NodeSnapshot::Windows(vec![key(app_id)])
+}
+
+/// Creates a tiling compositor about to restore the first workspace of its output.
+fn with_saved_layout(tiling: NodeSnapshot, floating: Vec<FloatingSnapshot>) -> TestCompositor {
+    let mut compositor = TestCompositor::tiled(&[]);
+    let output = compositor.output(0);
+    let snapshot = LayoutSnapshot {
+        workspaces: vec![WorkspaceSnapshot {
+            output: OutputMatch {
+                name: output.name(),
+                edid: output.edid().cloned(),
+            },
+            idx: 0,
+            tiling: Some(tiling),
+            floating,
+            scrolling: false,
+            strategy: TilingStrategy::Split,
+        }],
+    };
+    compositor.state.common.shell.write().pending_restore = PendingRestore::new(snapshot);
+    compositor
+}
+
+#[test]
+fn restores_tiling_tree_in_any_order() {
+    let mut compositor = with_saved_layout(
+        NodeSnapshot::Group {
+            orientation: Orientation::Vertical,
+            ratios: vec![0.5, 0.5],
+            children: vec![
+                window("a"),
+                NodeSnapshot::Group {
+                    orientation: Orientation::Horizontal,
+                    ratios: vec![0.5, 0.5],
+                    children: vec![window("b"), window("c")],
+                },
+            ],
+        },
+        Vec::new(),
+    );
+
+    compositor.map_window("c");
+    compositor.map_window("b");
+    assert_eq!(compositor.tree_shape(), "H[b,c]");
+    compositor.map_window("a");
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,c]]");
+}
+
+#[test]
+fn restores_stacks_and_floating_geometry() {
+    let mut compositor = with_saved_layout(
+        NodeSnapshot::Windows(vec![key("a"), key("b")]),
+        vec![FloatingSnapshot {
+            windows: vec![key("d")],
+            position: (100, 50),
+            size: (400, 300),
+        }],
+    );
+
+    compositor.map_window("a");
+    compositor.map_window("d");
+    compositor.map_window("b");
+    assert_eq!(compositor.tree_shape(), "S(a,b)");
+
+    let geometry = compositor.geometry("d");
+    assert_eq!((geometry.loc.x, geometry.loc.y), (100, 50));
+    assert_eq!((geometry.size.w, geometry.size.h), (400, 300));
+}
+
+#[test]
+fn scales_session_toplevels_to_resized_outputs() {
+    let compositor = TestCompositor::new();
+    let output = compositor.output(0);
+    let size = output.geometry().size;
+    let snapshot = ToplevelSnapshot {
+        output: OutputMatch {
+            name: output.name(),
+            edid: output.edid().cloned(),
+        },
+        workspace: 0,
+        previous: Some(WorkspaceRestoreData::Floating(FloatingRestoreData {
+            geometry: Rectangle::new((200, 100).into(), (400, 300).into()),
+            output_size: (size.w * 2, size.h * 2).into(),
+            was_maximized: false,
+            was_snapped: None,
+        })),
+        tiling: None,
+        fullscreen: false,
+        captured_at: 0,
+    };
+
+    let placement = snapshot
+        .place(&compositor.state.common.shell.read().workspaces)
+        .unwrap();
+    let Some(PlacementLayer::Floating(geometry)) = placement.layer else {
+        panic!("expected a floating placement, got {:?}", placement.layer);
+    };
+    assert_eq!((geometry.loc.x, geometry.loc.y), (100, 50));
+    assert_eq!((geometry.size.w, geometry.size.h), (400, 300));
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::actions::CompAction;
+use cosmic_settings_config::shortcuts::Action;
+
+use super::TestCompositor;
+
+#[test]
+fn toggled_window_is_centered_and_focused() {
+    let mut compositor = TestCompositor::new();
+    compositor.set_tiling(true);
+    compositor.map_window("a");
+    compositor.map_window("term");
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("term"));
+
+    compositor.action(CompAction::SendToScratchpad);
+    assert_eq!(compositor.workspace_windows(0), ["a"]);
+    assert_eq!(compositor.tree_shape(), "a");
+
+    compositor.action(CompAction::ToggleScratchpad("term".into()));
+    assert_eq!(compositor.workspace_windows(0), ["a", "term"]);
+    assert_eq!(compositor.tree_shape(), "a");
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("term"));
+    let geometry = compositor.geometry("term");
+    let center = geometry.loc + geometry.size.downscale(2).to_point();
+    assert!((center.x - 960).abs() <= 1 && (center.y - 540).abs() <= 1);
+
+    compositor.action(CompAction::ToggleScratchpad("term".into()));
+    assert_eq!(compositor.workspace_windows(0), ["a"]);
+}
+
+#[test]
+fn cycle_replaces_shown_window() {
+    let mut compositor = TestCompositor::new();
+    for app_id in ["a", "b"] {
+        compositor.map_window(app_id);
+        compositor.action(CompAction::SendToScratchpad);
+    }
+    assert!(compositor.workspace_windows(0).is_empty());
+
+    compositor.action(CompAction::CycleScratchpad);
+    assert_eq!(compositor.workspace_windows(0), ["a"]);
+    compositor.action(CompAction::CycleScratchpad);
+    assert_eq!(compositor.workspace_windows(0), ["b"]);
+    compositor.action(CompAction::CycleScratchpad);
+    assert_eq!(compositor.workspace_windows(0), ["a"]);
+}
+
+#[test]
+fn fullscreen_window_is_sent_to_scratchpad() {
+    let mut compositor = TestCompositor::new();
+    compositor.map_window("a");
+    compositor.map_window("video");
+    compositor.action(Action::Fullscreen);
+
+    compositor.action(CompAction::SendToScratchpad);
+    assert_eq!(compositor.workspace_windows(0), ["a"]);
+    {
+        let output = compositor.output(0);
+        let shell = compositor.state.common.shell.read();
+        let (_, workspace) = shell.workspaces.active(&output).unwrap();
+        assert!(workspace.get_fullscreen_surfaces().next().is_none());
+    }
+
+    compositor.action(CompAction::ToggleScratchpad("video".into()));
+    assert_eq!(compositor.workspace_windows(0), ["a", "video"]);
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::actions::CompAction;
+use cosmic_settings_config::shortcuts::{
+    Action,
+    action::{FocusDirection, ResizeDirection},
+};
+
+use super::TestCompositor;
+use crate::shell::{
+    grabs::ResizeEdge,
+    layout::restore::{LayoutSnapshot, PendingRestore},
+};
+
+#[test]
+fn new_windows_open_as_columns() {
+    let mut compositor = TestCompositor::new();
+    compositor.action(CompAction::ToggleScrolling);
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    assert_eq!(compositor.tree_shape(), "V[a,b,c]");
+
+    // columns share a width, independent of their count
+    let a = compositor.geometry("a");
+    let c = compositor.geometry("c");
+    assert_eq!(a.size, c.size);
+}
+
+#[test]
+fn viewport_follows_focus() {
+    let mut compositor = TestCompositor::new();
+    compositor.action(CompAction::ToggleScrolling);
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    let output = compositor.output(0).geometry();
+
+    // the focused column is scrolled into view, the first one scrolled out
+    let c = compositor.geometry("c");
+    assert!(output.contains_rect(c));
+    assert!(compositor.geometry("a").loc.x < output.loc.x);
+
+    compositor.action(Action::Focus(FocusDirection::Left));
+    compositor.action(Action::Focus(FocusDirection::Left));
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("a"));
+    assert!(output.contains_rect(compositor.geometry("a")));
+}
+
+#[test]
+fn toggle_action_switches_to_columns() {
+    let mut compositor = TestCompositor::new();
+    compositor.action(CompAction::ToggleScrolling);
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    assert_eq!(compositor.tree_shape(), "V[a,b,c]");
+    let output = compositor.output(0);
+    assert!(compositor.state.common.shell.read().is_scrolling(&output));
+
+    compositor.action(CompAction::ToggleScrolling);
+    assert!(!compositor.state.common.shell.read().is_scrolling(&output));
+}
+
+#[test]
+fn columns_resize_on_their_own() {
+    let mut compositor = TestCompositor::new();
+    compositor.action(CompAction::ToggleScrolling);
+    for app_id in ["a", "b", "c"] {
+        compositor.map_window(app_id);
+    }
+    let a = compositor.geometry("a").size.w;
+    let c = compositor.geometry("c").size.w;
+
+    let focus = compositor
+        .seat
+        .get_keyboard()
+        .unwrap()
+        .current_focus()
+        .unwrap();
+    {
+        let output = compositor.output(0);
+        let mut shell = compositor.state.common.shell.write();
+        let workspace = shell.workspaces.active_mut(&output).unwrap();
+        assert!(workspace.tiling_layer.resize(
+            &focus,
+            ResizeDirection::Outwards,
+            ResizeEdge::LEFT,
+            100
+        ));
+    }
+    compositor.settle();
+
+    // the column grows instead of taking space from its neighbour
+    assert_eq!(compositor.geometry("c").size.w, c + 100);
+    assert_eq!(compositor.geometry("b").size.w, a);
+    assert_eq!(compositor.geometry("a").size.w, a);
+}
+
+#[test]
+fn scrolling_is_restored_with_the_layout() {
+    let snapshot = {
+        let mut compositor = TestCompositor::new();
+        compositor.action(CompAction::ToggleScrolling);
+        compositor.map_window("a");
+        compositor.map_window("b");
+        LayoutSnapshot::capture(&compositor.state.common.shell.read().workspaces)
+    };
+    assert!(snapshot.workspaces[0].scrolling);
+
+    let mut compositor = TestCompositor::new();
+    compositor.set_tiling(true);
+    compositor.state.common.shell.write().pending_restore = PendingRestore::new(snapshot);
+    compositor.map_window("a");
+
+    let output = compositor.output(0);
+    assert!(compositor.state.common.shell.read().is_scrolling(&output));
+}
@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::actions::{CompAction, TilingStrategy};
+use cosmic_settings_config::shortcuts::{Action, action::Direction};
+
+use super::TestCompositor;
+use crate::shell::layout::restore::{LayoutSnapshot, PendingRestore};
+
+/// Creates a compositor with `strategy` set and `app_ids` mapped in order.
+fn arranged(strategy: TilingStrategy, app_ids: &[&str]) -> TestCompositor {
+    let mut compositor = TestCompositor::new();
+    compositor.action(CompAction::SetTilingStrategy(strategy));
+    for app_id in app_ids {
+        compositor.map_window(app_id);
+    }
+    compositor
+}
+
+#[test]
+fn master_stack() {
+    let mut compositor = arranged(TilingStrategy::MasterStack, &["a", "b", "c"]);
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,c]]");
+    assert!(compositor.geometry("a").size.w > compositor.geometry("b").size.w);
+
+    let master_width = compositor.geometry("a").size.w;
+    compositor.action(CompAction::GrowMaster);
+    assert!(compositor.geometry("a").size.w > master_width);
+
+    compositor.action(CompAction::IncreaseMasterCount);
+    assert_eq!(compositor.tree_shape(), "V[H[a,b],c]");
+
+    compositor.unmap_window("a");
+    assert_eq!(compositor.tree_shape(), "H[b,c]");
+}
+
+#[test]
+fn dwindle_spiral_and_grid() {
+    let mut compositor = arranged(TilingStrategy::Dwindle, &["a", "b", "c", "d", "e"]);
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,V[c,H[d,e]]]]");
+
+    compositor.action(CompAction::SetTilingStrategy(TilingStrategy::Spiral));
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,V[H[c,d],e]]]");
+
+    compositor.action(CompAction::SetTilingStrategy(TilingStrategy::Grid));
+    assert_eq!(compositor.tree_shape(), "H[V[a,b,c],V[d,e]]");
+}
+
+#[test]
+fn moved_windows_are_rearranged() {
+    let mut compositor = arranged(TilingStrategy::MasterStack, &["a", "b", "c"]);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("c"));
+
+    // moving out of the stack only changes the order of windows, not the shape
+    compositor.action(Action::Move(Direction::Left));
+    assert_eq!(compositor.tree_shape(), "V[a,H[c,b]]");
+}
+
+#[test]
+fn strategy_is_restored_with_the_layout() {
+    let snapshot = {
+        let compositor = arranged(TilingStrategy::Grid, &["a", "b"]);
+        LayoutSnapshot::capture(&compositor.state.common.shell.read().workspaces)
+    };
+    assert_eq!(snapshot.workspaces[0].strategy, TilingStrategy::Grid);
+
+    let mut compositor = TestCompositor::new();
+    compositor.set_tiling(true);
+    compositor.state.common.shell.write().pending_restore = PendingRestore::new(snapshot);
+    compositor.map_window("a");
+
+    let output = compositor.output(0);
+    let shell = compositor.state.common.shell.read();
+    let (_, workspace) = shell.workspaces.active(&output).unwrap();
+    assert_eq!(workspace.tiling_layer.strategy(), TilingStrategy::Grid);
+}
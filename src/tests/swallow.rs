@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::process::{self, Command};
+
+use super::TestCompositor;
+use crate::shell::{CosmicSurface, TEST_PIDS};
+
+/// Lets the window `app_id` take the tile of `terminal`, as if it was launched from it.
+fn swallow(compositor: &mut TestCompositor, terminal: &CosmicSurface, app_id: &str) {
+    let output = compositor.output(0);
+    let mut guard = compositor.state.common.shell.write();
+    let shell = &mut *guard;
+    let terminal = shell.element_for_surface(terminal).unwrap().clone();
+    let window = shell
+        .mapped()
+        .find(|m| m.active_window().app_id() == app_id)
+        .unwrap()
+        .clone();
+    let workspace = shell.workspaces.active_mut(&output).unwrap();
+    workspace.tiling_layer.unmap(&window, None).unwrap();
+    shell.swallowing.take_tile(workspace, &terminal, &window);
+}
+
+#[test]
+fn terminal_is_minimized_and_restored() {
+    let mut compositor = TestCompositor::tiled(&["a"]);
+    let terminal = compositor.map_window("term");
+    let shape = compositor.tree_shape();
+    compositor.map_window("vim");
+
+    swallow(&mut compositor, &terminal, "vim");
+    compositor.settle();
+    assert_eq!(compositor.tree_shape(), shape.replace("term", "vim"));
+    assert!(terminal.is_minimized());
+    assert_eq!(compositor.workspace_windows(0), ["a", "vim"]);
+
+    compositor.unmap_window("vim");
+    assert_eq!(compositor.tree_shape(), shape);
+    assert!(!terminal.is_minimized());
+    assert_eq!(compositor.workspace_windows(0), ["a", "term"]);
+}
+
+#[test]
+fn closed_terminal_is_forgotten() {
+    let mut compositor = TestCompositor::tiled(&["a"]);
+    let terminal = compositor.map_window("term");
+    compositor.map_window("vim");
+    swallow(&mut compositor, &terminal, "vim");
+    compositor.settle();
+
+    compositor.unmap_window("term");
+    compositor.unmap_window("vim");
+    assert_eq!(compositor.tree_shape(), "a");
+    assert_eq!(compositor.workspace_windows(0), ["a"]);
+}
+
+#[test]
+fn child_of_terminal_takes_its_tile() {
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+    TEST_PIDS.with_borrow_mut(|pids| {
+        pids.insert("term".into(), process::id());
+        pids.insert("vim".into(), child.id());
+    });
+    let mut compositor = TestCompositor::tiled(&["a"]);
+    compositor
+        .state
+        .common
+        .shell
+        .write()
+        .update_swallow_terminals(vec!["term".into()]);
+    let terminal = compositor.map_window("term");
+    let shape = compositor.tree_shape();
+
+    // the test client is no child of itself, so its other windows get new tiles
+    compositor.map_window("b");
+    assert!(!terminal.is_minimized());
+    compositor.unmap_window("b");
+
+    compositor.map_window("vim");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(compositor.tree_shape(), shape.replace("term", "vim"));
+    assert!(terminal.is_minimized());
+
+    compositor.unmap_window("vim");
+    assert_eq!(compositor.tree_shape(), shape);
+    assert!(!terminal.is_minimized());
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_settings_config::shortcuts::{
+    Action,
+    action::{Direction, FocusDirection},
+};
+
+use super::TestCompositor;
+
+#[test]
+fn new_windows_split_the_focused_window() {
+    let compositor = TestCompositor::tiled(&["a", "b", "c"]);
+
+    // wide windows are split side by side, tall ones top to bottom
+    assert_eq!(compositor.tree_shape(), "V[a,H[b,c]]");
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("c"));
+}
+
+#[test]
+fn move_swaps_with_sibling() {
+    let mut compositor = TestCompositor::tiled(&["a", "b"]);
+    let a = compositor.geometry("a");
+    let b = compositor.geometry("b");
+    assert!(a.loc.x < b.loc.x);
+
+    compositor.action(Action::Move(Direction::Left));
+    assert_eq!(compositor.tree_shape(), "V[b,a]");
+    assert_eq!(compositor.geometry("b"), a);
+    assert_eq!(compositor.geometry("a"), b);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+}
+
+#[test]
+fn toggle_orientation() {
+    let mut compositor = TestCompositor::tiled(&["a", "b"]);
+
+    compositor.action(Action::ToggleOrientation);
+    assert_eq!(compositor.tree_shape(), "H[a,b]");
+    assert!(compositor.geometry("a").loc.y < compositor.geometry("b").loc.y);
+}
+
+#[test]
+fn toggle_stacking() {
+    let mut compositor = TestCompositor::tiled(&["a", "b"]);
+
+    compositor.action(Action::ToggleStacking);
+    assert_eq!(compositor.tree_shape(), "V[a,S(b)]");
+
+    // moving a window into a stack joins it
+    compositor.action(Action::Focus(FocusDirection::Left));
+    compositor.action(Action::Move(Direction::Right));
+    assert_eq!(compositor.tree_shape(), "S(a,b)");
+}
+
+#[test]
+fn unmap_collapses_group() {
+    let mut compositor = TestCompositor::tiled(&["a", "b", "c"]);
+
+    compositor.unmap_window("b");
+    assert_eq!(compositor.tree_shape(), "V[a,c]");
+    compositor.unmap_window("a");
+    assert_eq!(compositor.tree_shape(), "c");
+}
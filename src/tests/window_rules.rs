@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::window_rules::{WindowMatch, WindowRule};
+
+use super::TestCompositor;
+
+fn app_id(pattern: &str) -> WindowMatch {
+    WindowMatch {
+        app_id: Some(pattern.into()),
+        ..Default::default()
+    }
+}
+
+/// Creates a tiling compositor applying `rules` to new windows.
+fn with_rules(rules: Vec<WindowRule>) -> TestCompositor {
+    let compositor = TestCompositor::tiled(&[]);
+    compositor
+        .state
+        .common
+        .shell
+        .write()
+        .update_window_rules(rules.iter());
+    compositor
+}
+
+#[test]
+fn floating_rule_overrides_tiling() {
+    let mut compositor = with_rules(vec![WindowRule {
+        matches: app_id("^float"),
+        floating: Some(true),
+        size: Some((400, 300)),
+        position: Some((100, 50)),
+        ..Default::default()
+    }]);
+
+    compositor.map_window("a");
+    compositor.map_window("floating-b");
+    assert_eq!(compositor.tree_shape(), "a");
+
+    let geometry = compositor.geometry("floating-b");
+    assert_eq!((geometry.loc.x, geometry.loc.y), (100, 50));
+    assert_eq!((geometry.size.w, geometry.size.h), (400, 300));
+}
+
+#[test]
+fn workspace_rule_places_window() {
+    let mut compositor = with_rules(vec![WindowRule {
+        matches: app_id("^b$"),
+        workspace: Some(1),
+        ..Default::default()
+    }]);
+
+    compositor.map_window("a");
+    compositor.map_window("b");
+    assert_eq!(compositor.active_workspace(), 0);
+    assert_eq!(compositor.workspace_windows(0), vec!["a"]);
+    assert_eq!(compositor.workspace_windows(1), vec!["b"]);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("a"));
+}
+
+#[test]
+fn later_rules_take_precedence() {
+    let mut compositor = with_rules(vec![
+        WindowRule {
+            matches: app_id(".*"),
+            floating: Some(true),
+            ..Default::default()
+        },
+        WindowRule {
+            matches: app_id("^tiled$"),
+            floating: Some(false),
+            ..Default::default()
+        },
+    ]);
+
+    compositor.map_window("tiled");
+    compositor.map_window("other");
+    assert_eq!(compositor.tree_shape(), "tiled");
+}
+
+#[test]
+fn missing_workspace_is_ignored() {
+    let mut compositor = with_rules(vec![WindowRule {
+        matches: app_id("^b$"),
+        workspace: Some(5),
+        ..Default::default()
+    }]);
+
+    compositor.map_window("a");
+    compositor.map_window("b");
+    assert_eq!(compositor.workspace_windows(0), vec!["a", "b"]);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+}
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_settings_config::shortcuts::Action;
+
+use super::TestCompositor;
+
+#[test]
+fn move_to_workspace_follows_window() {
+    let mut compositor = TestCompositor::tiled(&["a", "b"]);
+
+    compositor.action(Action::MoveToWorkspace(2));
+    assert_eq!(compositor.active_workspace(), 1);
+    assert_eq!(compositor.workspace_windows(0), vec!["a"]);
+    assert_eq!(compositor.workspace_windows(1), vec!["b"]);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+}
+
+#[test]
+fn send_to_workspace_keeps_workspace() {
+    let mut compositor = TestCompositor::tiled(&["a", "b"]);
+
+    compositor.action(Action::SendToWorkspace(2));
+    assert_eq!(compositor.active_workspace(), 0);
+    assert_eq!(compositor.workspace_windows(0), vec!["a"]);
+    assert_eq!(compositor.workspace_windows(1), vec!["b"]);
+    assert_eq!(compositor.tree_shape(), "a");
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("a"));
+}
+
+#[test]
+fn switching_workspaces_restores_focus() {
+    let mut compositor = TestCompositor::tiled(&["a", "b"]);
+    compositor.action(Action::SendToWorkspace(2));
+
+    compositor.action(Action::Workspace(2));
+    assert_eq!(compositor.active_workspace(), 1);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("b"));
+
+    compositor.action(Action::PreviousWorkspace);
+    assert_eq!(compositor.active_workspace(), 0);
+    assert_eq!(compositor.focused_app_id().as_deref(), Some("a"));
+}
@@ -172,3 +172,43 @@ impl<C: Coordinate> RectLocalExt<C> for Rectangle<C, Local> {
         Rectangle::new(self.loc.as_logical(), self.size.as_logical())
     }
 }
+
+/// (De)serializes a rectangle as `(x, y, width, height)`, for use with `#[serde(with)]`
+pub mod serde_rectangle {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use smithay::utils::Rectangle;
+
+    pub fn serialize<N, S: Serializer>(
+        rect: &Rectangle<i32, N>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (rect.loc.x, rect.loc.y, rect.size.w, rect.size.h).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, N, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Rectangle<i32, N>, D::Error> {
+        let (x, y, w, h) = <(i32, i32, i32, i32)>::deserialize(deserializer)?;
+        Ok(Rectangle::new((x, y).into(), (w, h).into()))
+    }
+}
+
+/// (De)serializes a size as `(width, height)`, for use with `#[serde(with)]`
+pub mod serde_size {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use smithay::utils::Size;
+
+    pub fn serialize<N, S: Serializer>(
+        size: &Size<i32, N>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (size.w, size.h).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, N, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Size<i32, N>, D::Error> {
+        let (w, h) = <(i32, i32)>::deserialize(deserializer)?;
+        Ok((w, h).into())
+    }
+}
@@ -82,6 +82,7 @@ impl OutputExt for Output {
         self.user_data()
             .get::<Vrr>()
             .map(|vrr| match vrr.0.load(Ordering::SeqCst) {
+                3 => AdaptiveSync::Automatic,
                 2 => AdaptiveSync::Force,
                 1 => AdaptiveSync::Enabled,
                 _ => AdaptiveSync::Disabled,
@@ -96,6 +97,7 @@ impl OutputExt for Output {
                 AdaptiveSync::Disabled => 0,
                 AdaptiveSync::Enabled => 1,
                 AdaptiveSync::Force => 2,
+                AdaptiveSync::Automatic => 3,
             },
             Ordering::SeqCst,
         );
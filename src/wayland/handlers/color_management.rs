@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    state::State,
+    wayland::protocols::color_management::{
+        ColorManagementHandler, ColorManagementState, delegate_color_management,
+    },
+};
+
+impl ColorManagementHandler for State {
+    fn color_management_state(&mut self) -> &mut ColorManagementState {
+        &mut self.common.color_management_state
+    }
+}
+
+delegate_color_management!(State);
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    reexports::{
+        wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::Type as ContentType,
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    wayland::{compositor::with_states, content_type::ContentTypeSurfaceCachedState},
+};
+
+/// The kind of content the client marked `surface` with.
+pub fn surface_content_type(surface: &WlSurface) -> ContentType {
+    with_states(surface, |states| {
+        *states
+            .cached_state
+            .get::<ContentTypeSurfaceCachedState>()
+            .current()
+            .content_type()
+    })
+}
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::output::Output;
+
+use crate::{
+    backend::render::gamma::{self, GammaRamp},
+    state::State,
+    wayland::protocols::gamma_control::{
+        GammaControlHandler, GammaControlState, delegate_gamma_control,
+    },
+};
+
+impl GammaControlHandler for State {
+    fn gamma_control_state(&mut self) -> &mut GammaControlState {
+        &mut self.common.gamma_control_state
+    }
+
+    fn gamma_size(&mut self, output: &Output) -> Option<usize> {
+        gamma::gamma_size(output)
+    }
+
+    fn set_gamma(&mut self, output: &Output, ramp: Option<GammaRamp>) {
+        gamma::set_client_gamma(output, ramp);
+        self.backend.update_gamma(output);
+    }
+}
+
+delegate_gamma_control!(State);
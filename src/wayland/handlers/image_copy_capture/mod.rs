@@ -378,7 +378,7 @@ fn constraints_for_output(output: &Output, backend: &mut BackendData) -> Option<
             kms.target_node_for_output(output)
                 .or(*kms.primary_node.read().unwrap())
         })
-        .unwrap();
+        .ok()?;
     Some(constraints_for_renderer(mode, renderer.as_mut()))
 }
 
@@ -400,7 +400,7 @@ fn constraints_for_toplevel(
 
             dma_node.or(*kms.primary_node.read().unwrap())
         })
-        .unwrap();
+        .ok()?;
 
     Some(constraints_for_renderer(size, renderer.as_mut()))
 }
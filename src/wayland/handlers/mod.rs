@@ -3,7 +3,9 @@
 pub mod a11y;
 pub mod background_effect;
 pub mod buffer;
+pub mod color_management;
 pub mod compositor;
+pub mod content_type;
 pub mod corner_radius;
 pub mod data_control;
 pub mod data_device;
@@ -14,6 +16,7 @@ pub mod drm_lease;
 pub mod drm_syncobj;
 pub mod foreign_toplevel_list;
 pub mod fractional_scale;
+pub mod gamma_control;
 pub mod idle_inhibit;
 pub mod idle_notify;
 pub mod image_capture_source;
@@ -33,13 +36,20 @@ pub mod seat;
 pub mod security_context;
 pub mod selection;
 pub mod session_lock;
+pub mod session_management;
 pub mod shm;
 pub mod tablet_manager;
+pub mod tearing_control;
 pub mod toplevel_info;
 pub mod toplevel_management;
+pub mod transient_seat;
+pub mod virtual_pointer;
 pub mod workspace;
 pub mod xdg_activation;
+pub mod xdg_dialog;
 pub mod xdg_foreign;
 pub mod xdg_shell;
+pub mod xdg_system_bell;
+pub mod xdg_toplevel_icon;
 pub mod xwayland_keyboard_grab;
 pub mod xwayland_shell;
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
-use cosmic_comp_config::output::comp::{OutputConfig, OutputState, TransformDef};
+use cosmic_comp_config::output::comp::{AdaptiveSync, OutputConfig, OutputState, TransformDef};
 use smithay::{output::Output, utils::Point};
 use tracing::{error, warn};
 
@@ -138,7 +138,13 @@ impl State {
                         current_config.position = (position.x as u32, position.y as u32);
                     }
                     if let Some(vrr) = adaptive_sync {
-                        current_config.vrr = *vrr;
+                        // the protocol reports content-type based vrr as automatic as well,
+                        // so keep it, unless a different mode was chosen
+                        if !(*vrr == AdaptiveSync::Enabled
+                            && current_config.vrr == AdaptiveSync::Automatic)
+                        {
+                            current_config.vrr = *vrr;
+                        }
                     }
                     if let Some(mirror) = mirroring {
                         current_config.enabled = OutputState::Mirroring(mirror.name());
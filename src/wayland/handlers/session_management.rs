@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::sync::Mutex;
+
+use smithay::{
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    wayland::{compositor::with_states, shell::xdg::ToplevelSurface},
+};
+
+use crate::{
+    config::DynamicConfig,
+    shell::layout::restore::ToplevelSnapshot,
+    state::State,
+    wayland::protocols::session_management::{
+        SessionManagementHandler, SessionManagementState, delegate_session_management,
+    },
+};
+
+#[derive(Debug, Default)]
+struct ToplevelSession(Mutex<Option<TrackedToplevel>>);
+
+#[derive(Debug)]
+struct TrackedToplevel {
+    session: String,
+    name: String,
+    /// State to apply, once the toplevel is mapped
+    restore: Option<ToplevelSnapshot>,
+}
+
+fn with_session<T>(surface: &WlSurface, f: impl FnOnce(&mut Option<TrackedToplevel>) -> T) -> T {
+    with_states(surface, |states| {
+        let session = states
+            .data_map
+            .get_or_insert_threadsafe(ToplevelSession::default);
+        f(&mut session.0.lock().unwrap())
+    })
+}
+
+/// Session id and name `surface` is tracked as, if any
+pub fn toplevel_session(surface: &WlSurface) -> Option<(String, String)> {
+    with_session(surface, |tracked| {
+        tracked
+            .as_ref()
+            .map(|tracked| (tracked.session.clone(), tracked.name.clone()))
+    })
+}
+
+/// Takes the state `surface` should be restored to, when it is mapped for the first time
+pub fn take_restore_data(surface: &WlSurface) -> Option<ToplevelSnapshot> {
+    with_session(surface, |tracked| {
+        tracked.as_mut().and_then(|tracked| tracked.restore.take())
+    })
+}
+
+/// Persists the state of session toplevels, that changed or are due to be marked as seen
+pub fn save_toplevels(
+    config: &mut DynamicConfig,
+    toplevels: Vec<((String, String), ToplevelSnapshot)>,
+) {
+    let toplevels = toplevels
+        .into_iter()
+        .filter(|((session, name), snapshot)| {
+            config.sessions().needs_update(session, name, snapshot)
+        })
+        .collect::<Vec<_>>();
+    if !toplevels.is_empty() {
+        let mut sessions = config.sessions_mut();
+        for ((session, name), snapshot) in toplevels {
+            sessions.insert(session, name, snapshot);
+        }
+    }
+}
+
+impl SessionManagementHandler for State {
+    fn session_management_state(&mut self) -> &mut SessionManagementState {
+        &mut self.common.session_management_state
+    }
+
+    fn session_exists(&mut self, session: &str) -> bool {
+        self.common.config.dynamic_conf.sessions().contains(session)
+    }
+
+    fn remove_session(&mut self, session: &str) {
+        if self.common.config.dynamic_conf.sessions().contains(session) {
+            self.common
+                .config
+                .dynamic_conf
+                .sessions_mut()
+                .remove_session(session);
+        }
+    }
+
+    fn add_toplevel(
+        &mut self,
+        session: &str,
+        name: &str,
+        toplevel: &ToplevelSurface,
+        restore: bool,
+    ) -> bool {
+        let restore = restore
+            .then(|| {
+                self.common
+                    .config
+                    .dynamic_conf
+                    .sessions()
+                    .get(session, name)
+                    .cloned()
+            })
+            .flatten();
+        let restored = restore.is_some();
+
+        // maximized and fullscreen state is part of the initial configure
+        if let Some(snapshot) = restore.as_ref() {
+            let mut shell = self.common.shell.write();
+            let output = snapshot.output(&shell.workspaces).cloned();
+            if let Some(pending) = shell.pending_windows.iter_mut().find(|pending| {
+                pending.surface.wl_surface().as_deref() == Some(toplevel.wl_surface())
+            }) {
+                pending.maximized = snapshot.maximized();
+                if snapshot.fullscreen {
+                    pending.fullscreen = output.or_else(|| Some(pending.seat.active_output()));
+                }
+            }
+        }
+
+        with_session(toplevel.wl_surface(), |tracked| {
+            *tracked = Some(TrackedToplevel {
+                session: session.to_string(),
+                name: name.to_string(),
+                restore,
+            })
+        });
+        restored
+    }
+
+    fn untrack_toplevel(&mut self, toplevel: &ToplevelSurface) {
+        with_session(toplevel.wl_surface(), |tracked| *tracked = None);
+    }
+
+    fn remove_toplevel(&mut self, session: &str, name: &str) {
+        if self
+            .common
+            .config
+            .dynamic_conf
+            .sessions()
+            .get(session, name)
+            .is_some()
+        {
+            self.common
+                .config
+                .dynamic_conf
+                .sessions_mut()
+                .remove_toplevel(session, name);
+        }
+    }
+}
+
+delegate_session_management!(State);
@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    reexports::{
+        wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_v1::PresentationHint,
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    wayland::{compositor::with_states, tearing_control::TearingControlSurfaceCachedState},
+};
+
+/// Whether the client asked for `surface` to be presented without waiting for vblank.
+pub fn wants_tearing(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        matches!(
+            states
+                .cached_state
+                .get::<TearingControlSurfaceCachedState>()
+                .current()
+                .presentation_hint(),
+            PresentationHint::Async
+        )
+    })
+}
@@ -7,7 +7,7 @@ use crate::{
     state::State,
     utils::prelude::Global,
     wayland::protocols::toplevel_info::{
-        ToplevelInfoHandler, ToplevelInfoState, Window, delegate_toplevel_info,
+        Icon, ToplevelInfoHandler, ToplevelInfoState, Window, delegate_toplevel_info,
     },
 };
 
@@ -31,6 +31,10 @@ impl Window for CosmicSurface {
         CosmicSurface::app_id(self)
     }
 
+    fn icon(&self) -> Option<Icon> {
+        CosmicSurface::toplevel_icon(self)
+    }
+
     fn is_activated(&self) -> bool {
         !self.is_minimized() && CosmicSurface::is_activated(self, true)
     }
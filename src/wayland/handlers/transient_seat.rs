@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    input::pointer::MotionEvent, reexports::wayland_server::backend::GlobalId,
+    utils::SERIAL_COUNTER,
+};
+
+use crate::{
+    shell::create_seat,
+    utils::prelude::*,
+    wayland::protocols::{
+        output_configuration::remove_global_with_timer,
+        transient_seat::{TransientSeatHandler, delegate_transient_seat},
+    },
+};
+
+impl TransientSeatHandler for State {
+    fn add_transient_seat(&mut self, name: &str) -> Option<GlobalId> {
+        let mut shell = self.common.shell.write();
+        let output = shell.seats.last_active().active_output();
+        let seat = create_seat(
+            &self.common.display_handle,
+            &mut self.common.seat_state,
+            &output,
+            &self.common.config,
+            name.to_string(),
+        );
+        shell.seats.add_seat(seat.clone());
+        seat.global()
+    }
+
+    fn remove_transient_seat(&mut self, name: &str) {
+        let Some(seat) = self
+            .common
+            .shell
+            .read()
+            .seats
+            .iter()
+            .find(|seat| seat.name() == name)
+            .cloned()
+        else {
+            return;
+        };
+
+        // leave the surfaces focused by the seat
+        let serial = SERIAL_COUNTER.next_serial();
+        if let Some(keyboard) = seat.get_keyboard() {
+            keyboard.set_focus(self, None, serial);
+        }
+        if let Some(pointer) = seat.get_pointer() {
+            pointer.unset_grab(self, serial, 0);
+            pointer.motion(
+                self,
+                None,
+                &MotionEvent {
+                    location: pointer.current_location(),
+                    serial,
+                    time: 0,
+                },
+            );
+            pointer.frame(self);
+        }
+
+        self.common.shell.write().remove_seat(&seat);
+        if let Some(global) = seat.global() {
+            remove_global_with_timer::<State>(
+                &self.common.display_handle,
+                &self.common.event_loop_handle,
+                global,
+            );
+        }
+    }
+}
+
+delegate_transient_seat!(State);
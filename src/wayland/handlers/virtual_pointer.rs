@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{backend::input::InputEvent, input::Seat, utils::Point};
+
+use crate::{
+    utils::prelude::*,
+    wayland::protocols::virtual_pointer::{
+        VirtualPointer, VirtualPointerHandler, VirtualPointerInput,
+        VirtualPointerMotionAbsoluteEvent, delegate_virtual_pointer,
+    },
+};
+
+impl VirtualPointerHandler for State {
+    fn add_virtual_pointer(&mut self, device: &VirtualPointer, seat: Option<Seat<State>>) {
+        let seat = seat.unwrap_or_else(|| self.common.shell.read().seats.last_active().clone());
+        let led_state = seat.get_keyboard().unwrap().led_state();
+        seat.devices().add_device(device, led_state);
+    }
+
+    fn remove_virtual_pointer(&mut self, device: &VirtualPointer) {
+        self.process_input_event(InputEvent::<VirtualPointerInput>::DeviceRemoved {
+            device: device.clone(),
+        });
+    }
+
+    fn virtual_pointer_event(&mut self, event: InputEvent<VirtualPointerInput>) {
+        let event = match event {
+            InputEvent::PointerMotionAbsolute { event } => {
+                let Some(event) = self.map_virtual_pointer_position(event) else {
+                    return;
+                };
+                InputEvent::PointerMotionAbsolute { event }
+            }
+            event => event,
+        };
+        self.process_input_event(event);
+    }
+}
+
+impl State {
+    /// Absolute motion is relative to the active output of the seat, so this activates the
+    /// output the device is mapped to, or the one under the position in the whole global
+    /// space, and makes the position relative to it.
+    fn map_virtual_pointer_position(
+        &self,
+        mut event: VirtualPointerMotionAbsoluteEvent,
+    ) -> Option<VirtualPointerMotionAbsoluteEvent> {
+        let shell = self.common.shell.read();
+        let seat = shell.seats.for_device(&event.device)?;
+
+        let mapped_output = event
+            .output
+            .take()
+            .and_then(|output| output.upgrade())
+            .filter(|output| shell.outputs().any(|o| o == output));
+        let output = match mapped_output {
+            Some(output) => output,
+            None => {
+                let space = shell.global_space().to_f64();
+                let position =
+                    space.loc + Point::from((event.x * space.size.w, event.y * space.size.h));
+                let output = shell
+                    .outputs()
+                    .find(|output| output.geometry().to_f64().contains(position))
+                    .cloned()
+                    .unwrap_or_else(|| seat.active_output());
+                let geometry = output.geometry().to_f64();
+                event.x = (position.x - geometry.loc.x) / geometry.size.w;
+                event.y = (position.y - geometry.loc.y) / geometry.size.h;
+                output
+            }
+        };
+
+        seat.set_active_output(&output);
+        Some(event)
+    }
+}
+
+delegate_virtual_pointer!(State);
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use smithay::{
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    wayland::{
+        compositor::with_states,
+        shell::xdg::{ToplevelSurface, dialog::XdgDialogHandler},
+    },
+};
+
+use crate::state::State;
+
+#[derive(Debug, Default)]
+struct ModalHint(AtomicBool);
+
+/// Whether the client marked `surface` as a modal dialog.
+pub fn is_modal(surface: &WlSurface) -> bool {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<ModalHint>()
+            .is_some_and(|hint| hint.0.load(Ordering::SeqCst))
+    })
+}
+
+impl XdgDialogHandler for State {
+    fn modal_changed(&mut self, toplevel: ToplevelSurface, is_modal: bool) {
+        with_states(toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get_or_insert_threadsafe(ModalHint::default)
+                .0
+                .store(is_modal, Ordering::SeqCst);
+        });
+
+        // windows mapped before the hint was set become modal from here on
+        let mut shell = self.common.shell.write();
+        let window = shell
+            .element_for_surface(&toplevel)
+            .and_then(|mapped| mapped.windows().find(|(w, _)| w == &toplevel))
+            .map(|(w, _)| w);
+        if let Some(window) = window {
+            shell.update_modal(&window);
+        }
+    }
+}
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::{
-    shell::{CosmicSurface, PendingWindow, focus::target::KeyboardFocusTarget, grabs::ReleaseMode},
+    shell::{
+        CosmicSurface, PendingWindow, focus::target::KeyboardFocusTarget, grabs::ReleaseMode,
+        layout::restore::ToplevelSnapshot,
+    },
     utils::prelude::*,
 };
 use smithay::desktop::layer_map_for_output;
@@ -29,7 +32,10 @@ use smithay::{
 use std::cell::Cell;
 use tracing::warn;
 
-use super::compositor::client_compositor_state;
+use super::{
+    compositor::client_compositor_state,
+    session_management::{save_toplevels, toplevel_session},
+};
 
 pub mod popup;
 
@@ -317,7 +323,7 @@ impl XdgShellHandler for State {
     }
 
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
-        let (output, clients) = {
+        let (output, clients, session) = {
             let mut shell = self.common.shell.write();
             let seat = shell.seats.last_active().clone();
 
@@ -329,6 +335,10 @@ impl XdgShellHandler for State {
             let output = shell
                 .visible_output_for_surface(surface.wl_surface())
                 .cloned();
+            let session = toplevel_session(surface.wl_surface()).zip(ToplevelSnapshot::capture(
+                &shell.workspaces,
+                surface.wl_surface(),
+            ));
             let _ = shell.unmap_surface(
                 surface.wl_surface(),
                 &seat,
@@ -339,9 +349,14 @@ impl XdgShellHandler for State {
             }
 
             // animations might be unblocked now
-            (output, shell.update_animations())
+            (output, shell.update_animations(), session)
         };
 
+        save_toplevels(
+            &mut self.common.config.dynamic_conf,
+            session.into_iter().collect(),
+        );
+
         {
             let dh = self.common.display_handle.clone();
             for client in clients.values() {
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_comp_config::VisualBell;
+use smithay::{
+    desktop::WindowSurfaceType,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    wayland::{compositor::get_parent, seat::WaylandFocus, xdg_system_bell::XdgSystemBellHandler},
+};
+
+use crate::{state::State, utils::prelude::*, wayland::protocols::workspace::State as WState};
+
+impl XdgSystemBellHandler for State {
+    // Xwayland forwards the bells of X11 clients through this protocol as well
+    fn ring(&mut self, surface: Option<WlSurface>) {
+        let config = self.common.config.cosmic_conf.system_bell;
+        let mut shell = self.common.shell.write();
+
+        let mapped = surface.as_ref().and_then(|surface| {
+            shell
+                .mapped()
+                .find(|m| m.has_surface(surface, WindowSurfaceType::ALL))
+                .cloned()
+        });
+        let window = mapped.as_ref().map(|mapped| {
+            let mut root = surface.clone().unwrap();
+            while let Some(parent) = get_parent(&root) {
+                root = parent;
+            }
+            mapped
+                .windows()
+                .map(|(w, _)| w)
+                .find(|w| w == &root)
+                .unwrap_or_else(|| mapped.active_window())
+        });
+
+        if config.audible {
+            self.common
+                .dbus_state
+                .ring_bell(window.as_ref().map(|w| w.app_id()).unwrap_or_default());
+        }
+
+        match (config.visual, &mapped) {
+            (VisualBell::Window, Some(mapped)) => shell.flash_window(mapped),
+            (VisualBell::Window, None) | (VisualBell::Output, _) => {
+                let output = surface
+                    .as_ref()
+                    .and_then(|surface| shell.visible_output_for_surface(surface))
+                    .cloned()
+                    .unwrap_or_else(|| shell.seats.last_active().active_output());
+                shell.flash_output(&output);
+            }
+            (VisualBell::None, _) => {}
+        }
+
+        if config.urgent
+            && let Some((window, mapped)) = window.zip(mapped)
+            && !window.is_activated(false)
+        {
+            window.set_urgent(true);
+            mapped.force_update();
+            if let Some(surface) = window.wl_surface()
+                && let Some((workspace, _)) = shell.workspace_for_surface(&surface)
+            {
+                let mut workspace_guard = self.common.workspace_state.update();
+                workspace_guard.add_workspace_state(&workspace, WState::Urgent);
+            }
+        }
+    }
+}
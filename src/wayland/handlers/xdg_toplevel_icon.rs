@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::state::State;
+use smithay::{
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel::XdgToplevel,
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    wayland::{
+        compositor::{add_post_commit_hook, with_states},
+        xdg_toplevel_icon::XdgToplevelIconHandler,
+    },
+};
+
+/// Set when a new icon was requested, which only applies with the next commit of the surface
+#[derive(Default)]
+struct PendingIcon(AtomicBool);
+
+impl XdgToplevelIconHandler for State {
+    fn set_icon(&mut self, _toplevel: XdgToplevel, wl_surface: WlSurface) {
+        let needs_hook = with_states(&wl_surface, |states| {
+            let added = states
+                .data_map
+                .insert_if_missing_threadsafe(PendingIcon::default);
+            states
+                .data_map
+                .get::<PendingIcon>()
+                .unwrap()
+                .0
+                .store(true, Ordering::SeqCst);
+            added
+        });
+
+        if needs_hook {
+            add_post_commit_hook::<Self, _>(&wl_surface, |state, _dh, surface| {
+                let pending = with_states(surface, |states| {
+                    states
+                        .data_map
+                        .get::<PendingIcon>()
+                        .unwrap()
+                        .0
+                        .swap(false, Ordering::SeqCst)
+                });
+                // stack tabs and headers pick up the new icon when they are rebuilt,
+                // toplevel-info compares it on its next refresh.
+                if pending
+                    && let Some(mapped) = state.common.shell.read().element_for_surface(surface)
+                {
+                    mapped.force_update();
+                }
+            });
+        }
+    }
+}
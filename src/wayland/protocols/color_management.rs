@@ -0,0 +1,949 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Implementation of `wp_color_management_v1`.
+//!
+//! Clients describe the colorimetry of their surfaces parametrically, through
+//! named primaries and transfer functions plus optional luminance and mastering
+//! metadata. Outputs advertise the description of their signal, which is sRGB
+//! unless the backend drives them in HDR10, and surfaces prefer the description
+//! of their primary scanout output.
+
+use smithay::{
+    desktop::utils::surface_primary_scanout_output,
+    output::{Output, WeakOutput},
+    reexports::{
+        wayland_protocols::wp::color_management::v1::server::{
+            wp_color_management_output_v1::{self, WpColorManagementOutputV1},
+            wp_color_management_surface_feedback_v1::{self, WpColorManagementSurfaceFeedbackV1},
+            wp_color_management_surface_v1::{self, WpColorManagementSurfaceV1},
+            wp_color_manager_v1::{
+                self, Feature, Primaries, RenderIntent, TransferFunction, WpColorManagerV1,
+            },
+            wp_image_description_creator_icc_v1::{self, WpImageDescriptionCreatorIccV1},
+            wp_image_description_creator_params_v1::{self, WpImageDescriptionCreatorParamsV1},
+            wp_image_description_info_v1::{self, WpImageDescriptionInfoV1},
+            wp_image_description_v1::{self, WpImageDescriptionV1},
+        },
+        wayland_server::{
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+            backend::GlobalId, protocol::wl_surface::WlSurface,
+        },
+    },
+    wayland::compositor::{self, Cacheable},
+};
+use std::sync::{
+    Arc, LazyLock, Mutex,
+    atomic::{AtomicU32, Ordering},
+};
+use wayland_backend::{protocol::WEnum, server::ClientId};
+
+/// Chromaticity coordinates of red, green, blue and white, in units of 1/1000000
+pub type Chromaticities = [(i32, i32); 4];
+
+pub const SRGB_PRIMARIES: Chromaticities = [
+    (640_000, 330_000),
+    (300_000, 600_000),
+    (150_000, 60_000),
+    (312_700, 329_000),
+];
+const BT2020_PRIMARIES: Chromaticities = [
+    (708_000, 292_000),
+    (170_000, 797_000),
+    (131_000, 46_000),
+    (312_700, 329_000),
+];
+const DISPLAY_P3_PRIMARIES: Chromaticities = [
+    (680_000, 320_000),
+    (265_000, 690_000),
+    (150_000, 60_000),
+    (312_700, 329_000),
+];
+
+/// Luminance of sRGB white in cd/m², after ITU-R BT.2408
+const SDR_REFERENCE_LUMINANCE: u32 = 203;
+
+pub trait ColorManagementHandler {
+    fn color_management_state(&mut self) -> &mut ColorManagementState;
+}
+
+/// Luminance range, the minimum in units of 0.0001 cd/m², the others in cd/m²
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Luminances {
+    pub min: u32,
+    pub max: u32,
+    pub reference: u32,
+}
+
+/// Parametric description of a color volume
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorParams {
+    pub tf: TransferFunction,
+    pub primaries: Primaries,
+    pub luminances: Option<Luminances>,
+    pub mastering_primaries: Option<Chromaticities>,
+    /// Mastering luminance range, the minimum in units of 0.0001 cd/m²
+    pub mastering_luminance: Option<(u32, u32)>,
+    pub max_cll: Option<u32>,
+    pub max_fall: Option<u32>,
+}
+
+impl ColorParams {
+    /// Description of plain sRGB content
+    pub fn srgb() -> ColorParams {
+        ColorParams {
+            tf: TransferFunction::Gamma22,
+            primaries: Primaries::Srgb,
+            luminances: None,
+            mastering_primaries: None,
+            mastering_luminance: None,
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+
+    /// Description of a HDR10 signal for a display with the given luminance range in cd/m²
+    pub fn hdr10(min_luminance: f32, max_luminance: f32, max_frame_average: f32) -> ColorParams {
+        let min = (min_luminance * 10000.).round() as u32;
+        let max = max_luminance.round() as u32;
+        ColorParams {
+            tf: TransferFunction::St2084Pq,
+            primaries: Primaries::Bt2020,
+            luminances: Some(Luminances {
+                min: 50,
+                max: 10000,
+                reference: SDR_REFERENCE_LUMINANCE,
+            }),
+            mastering_primaries: Some(BT2020_PRIMARIES),
+            mastering_luminance: Some((min, max)),
+            max_cll: Some(max),
+            max_fall: Some(max_frame_average.round() as u32),
+        }
+    }
+
+    pub fn primaries(&self) -> Chromaticities {
+        match self.primaries {
+            Primaries::Bt2020 => BT2020_PRIMARIES,
+            Primaries::DisplayP3 => DISPLAY_P3_PRIMARIES,
+            _ => SRGB_PRIMARIES,
+        }
+    }
+
+    pub fn luminances(&self) -> Luminances {
+        self.luminances.unwrap_or(match self.tf {
+            TransferFunction::St2084Pq => Luminances {
+                min: 50,
+                max: 10000,
+                reference: SDR_REFERENCE_LUMINANCE,
+            },
+            _ => Luminances {
+                min: 2000,
+                max: 80,
+                reference: 80,
+            },
+        })
+    }
+}
+
+static NEXT_IDENTITY: AtomicU32 = AtomicU32::new(1);
+/// Shared by all outputs and surfaces without a more specific description
+static SRGB_DESCRIPTION: LazyLock<Arc<ImageDescription>> =
+    LazyLock::new(|| ImageDescription::new(ColorParams::srgb()));
+
+/// An immutable image description, identical parameters may have distinct identities
+#[derive(Debug)]
+pub struct ImageDescription {
+    identity: u32,
+    pub params: ColorParams,
+}
+
+impl ImageDescription {
+    fn new(params: ColorParams) -> Arc<ImageDescription> {
+        Arc::new(ImageDescription {
+            identity: NEXT_IDENTITY.fetch_add(1, Ordering::Relaxed),
+            params,
+        })
+    }
+
+    pub fn identity(&self) -> u32 {
+        self.identity
+    }
+}
+
+struct OutputImageDescription(Mutex<Arc<ImageDescription>>);
+
+/// Image description of the signal sent to `output`.
+pub fn output_image_description(output: &Output) -> Arc<ImageDescription> {
+    output
+        .user_data()
+        .get_or_insert_threadsafe(|| OutputImageDescription(Mutex::new(SRGB_DESCRIPTION.clone())))
+        .0
+        .lock()
+        .unwrap()
+        .clone()
+}
+
+/// Updates the image description of `output`, clients are notified on the next refresh.
+pub fn set_output_image_description(output: &Output, params: ColorParams) {
+    let data = output
+        .user_data()
+        .get_or_insert_threadsafe(|| OutputImageDescription(Mutex::new(SRGB_DESCRIPTION.clone())));
+    let mut description = data.0.lock().unwrap();
+    if description.params != params {
+        *description = if params == SRGB_DESCRIPTION.params {
+            SRGB_DESCRIPTION.clone()
+        } else {
+            ImageDescription::new(params)
+        };
+    }
+}
+
+/// Image description the client set for a surface, `None` means sRGB
+#[derive(Debug, Clone, Default)]
+pub struct ColorManagementCachedState {
+    pub image_description: Option<Arc<ImageDescription>>,
+}
+
+impl Cacheable for ColorManagementCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Image description committed for `surface`.
+pub fn surface_image_description(surface: &WlSurface) -> Option<Arc<ImageDescription>> {
+    compositor::with_states(surface, |states| {
+        states
+            .cached_state
+            .get::<ColorManagementCachedState>()
+            .current()
+            .image_description
+            .clone()
+    })
+}
+
+#[derive(Debug, Default)]
+struct ColorManagementSurfaceMarker(Mutex<Option<Weak<WpColorManagementSurfaceV1>>>);
+
+#[derive(Debug)]
+pub struct ColorManagementState {
+    global: GlobalId,
+    outputs: Vec<(WpColorManagementOutputV1, u32)>,
+    feedbacks: Vec<(WpColorManagementSurfaceFeedbackV1, u32)>,
+}
+
+impl ColorManagementState {
+    pub fn new<D>(dh: &DisplayHandle) -> ColorManagementState
+    where
+        D: GlobalDispatch<WpColorManagerV1, ()> + 'static,
+    {
+        let global = dh.create_global::<D, WpColorManagerV1, _>(1, ());
+        ColorManagementState {
+            global,
+            outputs: Vec::new(),
+            feedbacks: Vec::new(),
+        }
+    }
+
+    pub fn global_id(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Notifies clients about changed output descriptions and preferred surface descriptions.
+    pub fn refresh(&mut self) {
+        for (output_obj, identity) in self.outputs.iter_mut() {
+            let data = output_obj.data::<ColorManagementOutputData>().unwrap();
+            if let Some(output) = data.output.upgrade() {
+                let description = output_image_description(&output);
+                if description.identity != *identity {
+                    *identity = description.identity;
+                    output_obj.image_description_changed();
+                }
+            }
+        }
+
+        for (feedback, identity) in self.feedbacks.iter_mut() {
+            let data = feedback.data::<ColorManagementSurfaceData>().unwrap();
+            if let Ok(surface) = data.surface.upgrade() {
+                let description = preferred_description(&surface);
+                if description.identity != *identity {
+                    *identity = description.identity;
+                    feedback.preferred_changed(description.identity);
+                }
+            }
+        }
+    }
+}
+
+/// Description of the primary scanout output of `surface`, or sRGB.
+fn preferred_description(surface: &WlSurface) -> Arc<ImageDescription> {
+    compositor::with_states(surface, |states| {
+        surface_primary_scanout_output(surface, states)
+    })
+    .map(|output| output_image_description(&output))
+    .unwrap_or_else(|| SRGB_DESCRIPTION.clone())
+}
+
+pub struct ColorManagementOutputData {
+    output: WeakOutput,
+}
+
+pub struct ColorManagementSurfaceData {
+    surface: Weak<WlSurface>,
+}
+
+#[derive(Debug, Default)]
+pub struct ParamsCreatorData(Mutex<ParamsBuilder>);
+
+#[derive(Debug, Default)]
+struct ParamsBuilder {
+    tf: Option<TransferFunction>,
+    primaries: Option<Primaries>,
+    luminances: Option<Luminances>,
+    mastering_primaries: Option<Chromaticities>,
+    mastering_luminance: Option<(u32, u32)>,
+    max_cll: Option<u32>,
+    max_fall: Option<u32>,
+}
+
+pub struct ImageDescriptionData {
+    description: Option<Arc<ImageDescription>>,
+    /// Whether `get_information` is allowed
+    info: bool,
+}
+
+impl ImageDescriptionData {
+    pub fn description(&self) -> Option<&Arc<ImageDescription>> {
+        self.description.as_ref()
+    }
+}
+
+fn ready_description<D>(
+    data_init: &mut DataInit<'_, D>,
+    id: New<WpImageDescriptionV1>,
+    description: Arc<ImageDescription>,
+    info: bool,
+) where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData> + 'static,
+{
+    let identity = description.identity;
+    let obj = data_init.init(
+        id,
+        ImageDescriptionData {
+            description: Some(description),
+            info,
+        },
+    );
+    obj.ready(identity);
+}
+
+impl<D> GlobalDispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: GlobalDispatch<WpColorManagerV1, ()> + Dispatch<WpColorManagerV1, ()> + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<WpColorManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(resource, ());
+        manager.supported_intent(RenderIntent::Perceptual);
+        for feature in [
+            Feature::Parametric,
+            Feature::SetLuminances,
+            Feature::SetMasteringDisplayPrimaries,
+        ] {
+            manager.supported_feature(feature);
+        }
+        for tf in [
+            TransferFunction::Srgb,
+            TransferFunction::Gamma22,
+            TransferFunction::Bt1886,
+            TransferFunction::ExtLinear,
+            TransferFunction::St2084Pq,
+        ] {
+            manager.supported_tf_named(tf);
+        }
+        for primaries in [Primaries::Srgb, Primaries::Bt2020, Primaries::DisplayP3] {
+            manager.supported_primaries_named(primaries);
+        }
+        manager.done();
+    }
+}
+
+impl<D> Dispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagerV1, ()>
+        + Dispatch<WpColorManagementOutputV1, ColorManagementOutputData>
+        + Dispatch<WpColorManagementSurfaceV1, ColorManagementSurfaceData>
+        + Dispatch<WpColorManagementSurfaceFeedbackV1, ColorManagementSurfaceData>
+        + Dispatch<WpImageDescriptionCreatorIccV1, ()>
+        + Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        obj: &WpColorManagerV1,
+        request: wp_color_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_manager_v1::Request::GetOutput { id, output } => {
+                let output = Output::from_resource(&output);
+                let identity = output
+                    .as_ref()
+                    .map(|o| output_image_description(o).identity)
+                    .unwrap_or_default();
+                let obj = data_init.init(
+                    id,
+                    ColorManagementOutputData {
+                        output: output.as_ref().map(|o| o.downgrade()).unwrap_or_default(),
+                    },
+                );
+                state.color_management_state().outputs.push((obj, identity));
+            }
+            wp_color_manager_v1::Request::GetSurface { id, surface } => {
+                let exists = compositor::with_states(&surface, |states| {
+                    let marker = states
+                        .data_map
+                        .get_or_insert_threadsafe(ColorManagementSurfaceMarker::default);
+                    let marker = marker.0.lock().unwrap();
+                    marker.as_ref().is_some_and(|obj| obj.upgrade().is_ok())
+                });
+                if exists {
+                    obj.post_error(
+                        wp_color_manager_v1::Error::SurfaceExists,
+                        "wl_surface already has a color management surface",
+                    );
+                    return;
+                }
+
+                let obj = data_init.init(
+                    id,
+                    ColorManagementSurfaceData {
+                        surface: surface.downgrade(),
+                    },
+                );
+                compositor::with_states(&surface, |states| {
+                    let marker = states
+                        .data_map
+                        .get_or_insert_threadsafe(ColorManagementSurfaceMarker::default);
+                    *marker.0.lock().unwrap() = Some(obj.downgrade());
+                });
+            }
+            wp_color_manager_v1::Request::GetSurfaceFeedback { id, surface } => {
+                let identity = preferred_description(&surface).identity;
+                let obj = data_init.init(
+                    id,
+                    ColorManagementSurfaceData {
+                        surface: surface.downgrade(),
+                    },
+                );
+                state
+                    .color_management_state()
+                    .feedbacks
+                    .push((obj, identity));
+            }
+            wp_color_manager_v1::Request::CreateIccCreator { obj: creator } => {
+                data_init.init(creator, ());
+                obj.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature,
+                    "ICC profiles are not supported",
+                );
+            }
+            wp_color_manager_v1::Request::CreateParametricCreator { obj: creator } => {
+                data_init.init(creator, ParamsCreatorData::default());
+            }
+            wp_color_manager_v1::Request::CreateWindowsScrgb { image_description } => {
+                data_init.init(
+                    image_description,
+                    ImageDescriptionData {
+                        description: None,
+                        info: false,
+                    },
+                );
+                obj.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature,
+                    "scRGB is not supported",
+                );
+            }
+            wp_color_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpColorManagementOutputV1, ColorManagementOutputData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementOutputV1, ColorManagementOutputData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _obj: &WpColorManagementOutputV1,
+        request: wp_color_management_output_v1::Request,
+        data: &ColorManagementOutputData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_output_v1::Request::GetImageDescription { image_description } => {
+                match data.output.upgrade() {
+                    Some(output) => ready_description(
+                        data_init,
+                        image_description,
+                        output_image_description(&output),
+                        true,
+                    ),
+                    None => {
+                        let obj = data_init.init(
+                            image_description,
+                            ImageDescriptionData {
+                                description: None,
+                                info: false,
+                            },
+                        );
+                        obj.failed(
+                            wp_image_description_v1::Cause::NoOutput,
+                            "The output was removed".into(),
+                        );
+                    }
+                }
+            }
+            wp_color_management_output_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        obj: &WpColorManagementOutputV1,
+        _data: &ColorManagementOutputData,
+    ) {
+        state
+            .color_management_state()
+            .outputs
+            .retain(|(output, _)| output != obj);
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceV1, ColorManagementSurfaceData, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceV1, ColorManagementSurfaceData> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        obj: &WpColorManagementSurfaceV1,
+        request: wp_color_management_surface_v1::Request,
+        data: &ColorManagementSurfaceData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let description = match request {
+            wp_color_management_surface_v1::Request::SetImageDescription {
+                image_description,
+                render_intent,
+            } => {
+                if !matches!(render_intent, WEnum::Value(RenderIntent::Perceptual)) {
+                    obj.post_error(
+                        wp_color_management_surface_v1::Error::RenderIntent,
+                        "Unsupported rendering intent",
+                    );
+                    return;
+                }
+                let Some(description) = image_description
+                    .data::<ImageDescriptionData>()
+                    .and_then(|data| data.description.clone())
+                else {
+                    obj.post_error(
+                        wp_color_management_surface_v1::Error::ImageDescription,
+                        "Image description is not ready",
+                    );
+                    return;
+                };
+                Some(description)
+            }
+            wp_color_management_surface_v1::Request::UnsetImageDescription
+            | wp_color_management_surface_v1::Request::Destroy => None,
+            _ => unreachable!(),
+        };
+
+        let Ok(surface) = data.surface.upgrade() else {
+            if description.is_some() {
+                obj.post_error(
+                    wp_color_management_surface_v1::Error::Inert,
+                    "wl_surface was destroyed",
+                );
+            }
+            return;
+        };
+        compositor::with_states(&surface, |states| {
+            states
+                .cached_state
+                .get::<ColorManagementCachedState>()
+                .pending()
+                .image_description = description;
+        });
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceFeedbackV1, ColorManagementSurfaceData, D>
+    for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceFeedbackV1, ColorManagementSurfaceData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + ColorManagementHandler
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        obj: &WpColorManagementSurfaceFeedbackV1,
+        request: wp_color_management_surface_feedback_v1::Request,
+        data: &ColorManagementSurfaceData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_surface_feedback_v1::Request::GetPreferred {
+                image_description,
+            }
+            | wp_color_management_surface_feedback_v1::Request::GetPreferredParametric {
+                image_description,
+            } => {
+                let Ok(surface) = data.surface.upgrade() else {
+                    obj.post_error(
+                        wp_color_management_surface_feedback_v1::Error::Inert,
+                        "wl_surface was destroyed",
+                    );
+                    return;
+                };
+                ready_description(
+                    data_init,
+                    image_description,
+                    preferred_description(&surface),
+                    true,
+                );
+            }
+            wp_color_management_surface_feedback_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        obj: &WpColorManagementSurfaceFeedbackV1,
+        _data: &ColorManagementSurfaceData,
+    ) {
+        state
+            .color_management_state()
+            .feedbacks
+            .retain(|(feedback, _)| feedback != obj);
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorIccV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorIccV1, ()> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _obj: &WpImageDescriptionCreatorIccV1,
+        _request: wp_image_description_creator_icc_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // never created successfully, see `create_icc_creator`
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorParamsV1, ParamsCreatorData>
+        + Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        obj: &WpImageDescriptionCreatorParamsV1,
+        request: wp_image_description_creator_params_v1::Request,
+        data: &ParamsCreatorData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        use wp_image_description_creator_params_v1::Error;
+
+        let mut builder = data.0.lock().unwrap();
+        let already_set = |obj: &WpImageDescriptionCreatorParamsV1, what: &str| {
+            obj.post_error(Error::AlreadySet, format!("{what} was already set"));
+        };
+        match request {
+            wp_image_description_creator_params_v1::Request::SetTfNamed { tf } => {
+                let tf = match tf {
+                    WEnum::Value(
+                        tf @ (TransferFunction::Srgb
+                        | TransferFunction::Gamma22
+                        | TransferFunction::Bt1886
+                        | TransferFunction::ExtLinear
+                        | TransferFunction::St2084Pq),
+                    ) => tf,
+                    _ => {
+                        obj.post_error(Error::InvalidTf, "Unsupported transfer function");
+                        return;
+                    }
+                };
+                if builder.tf.replace(tf).is_some() {
+                    already_set(obj, "Transfer function");
+                }
+            }
+            wp_image_description_creator_params_v1::Request::SetTfPower { .. } => {
+                obj.post_error(
+                    Error::UnsupportedFeature,
+                    "Power transfer functions are not supported",
+                );
+            }
+            wp_image_description_creator_params_v1::Request::SetPrimariesNamed { primaries } => {
+                let primaries = match primaries {
+                    WEnum::Value(
+                        primaries @ (Primaries::Srgb | Primaries::Bt2020 | Primaries::DisplayP3),
+                    ) => primaries,
+                    _ => {
+                        obj.post_error(Error::InvalidPrimariesNamed, "Unsupported primaries");
+                        return;
+                    }
+                };
+                if builder.primaries.replace(primaries).is_some() {
+                    already_set(obj, "Primaries");
+                }
+            }
+            wp_image_description_creator_params_v1::Request::SetPrimaries { .. } => {
+                obj.post_error(
+                    Error::UnsupportedFeature,
+                    "Custom primaries are not supported",
+                );
+            }
+            wp_image_description_creator_params_v1::Request::SetLuminances {
+                min_lum,
+                max_lum,
+                reference_lum,
+            } => {
+                // the minimum is in units of 0.0001 cd/m²
+                if max_lum as u64 * 10000 <= min_lum as u64
+                    || reference_lum as u64 * 10000 <= min_lum as u64
+                {
+                    obj.post_error(Error::InvalidLuminance, "Invalid luminance range");
+                    return;
+                }
+                let luminances = Luminances {
+                    min: min_lum,
+                    max: max_lum,
+                    reference: reference_lum,
+                };
+                if builder.luminances.replace(luminances).is_some() {
+                    already_set(obj, "Luminances");
+                }
+            }
+            wp_image_description_creator_params_v1::Request::SetMasteringDisplayPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                let primaries = [(r_x, r_y), (g_x, g_y), (b_x, b_y), (w_x, w_y)];
+                if builder.mastering_primaries.replace(primaries).is_some() {
+                    already_set(obj, "Mastering display primaries");
+                }
+            }
+            wp_image_description_creator_params_v1::Request::SetMasteringLuminance {
+                min_lum,
+                max_lum,
+            } => {
+                if max_lum as u64 * 10000 <= min_lum as u64 {
+                    obj.post_error(Error::InvalidLuminance, "Invalid mastering luminance");
+                    return;
+                }
+                if builder
+                    .mastering_luminance
+                    .replace((min_lum, max_lum))
+                    .is_some()
+                {
+                    already_set(obj, "Mastering luminance");
+                }
+            }
+            wp_image_description_creator_params_v1::Request::SetMaxCll { max_cll } => {
+                if builder.max_cll.replace(max_cll).is_some() {
+                    already_set(obj, "Maximum content light level");
+                }
+            }
+            wp_image_description_creator_params_v1::Request::SetMaxFall { max_fall } => {
+                if builder.max_fall.replace(max_fall).is_some() {
+                    already_set(obj, "Maximum frame-average light level");
+                }
+            }
+            wp_image_description_creator_params_v1::Request::Create { image_description } => {
+                let (Some(tf), Some(primaries)) = (builder.tf, builder.primaries) else {
+                    obj.post_error(
+                        Error::IncompleteSet,
+                        "Transfer function and primaries are required",
+                    );
+                    return;
+                };
+                let params = ColorParams {
+                    tf,
+                    primaries,
+                    luminances: builder.luminances,
+                    mastering_primaries: builder.mastering_primaries,
+                    mastering_luminance: builder.mastering_luminance,
+                    max_cll: builder.max_cll,
+                    max_fall: builder.max_fall,
+                };
+                ready_description(
+                    data_init,
+                    image_description,
+                    ImageDescription::new(params),
+                    false,
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionV1, ImageDescriptionData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>
+        + Dispatch<WpImageDescriptionInfoV1, ()>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        obj: &WpImageDescriptionV1,
+        request: wp_image_description_v1::Request,
+        data: &ImageDescriptionData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_image_description_v1::Request::GetInformation { information } => {
+                let Some(description) = data.description.as_ref() else {
+                    obj.post_error(
+                        wp_image_description_v1::Error::NotReady,
+                        "Image description failed",
+                    );
+                    return;
+                };
+                if !data.info {
+                    obj.post_error(
+                        wp_image_description_v1::Error::NoInformation,
+                        "Image description was created by the client",
+                    );
+                    return;
+                }
+
+                let info = data_init.init(information, ());
+                send_information(&info, &description.params);
+            }
+            wp_image_description_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionInfoV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionInfoV1, ()> + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _obj: &WpImageDescriptionInfoV1,
+        _request: wp_image_description_info_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+    }
+}
+
+fn send_information(info: &WpImageDescriptionInfoV1, params: &ColorParams) {
+    let [(r_x, r_y), (g_x, g_y), (b_x, b_y), (w_x, w_y)] = params.primaries();
+    info.primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+    info.primaries_named(params.primaries);
+    info.tf_named(params.tf);
+    let luminances = params.luminances();
+    info.luminances(luminances.min, luminances.max, luminances.reference);
+
+    let [(r_x, r_y), (g_x, g_y), (b_x, b_y), (w_x, w_y)] = params
+        .mastering_primaries
+        .unwrap_or_else(|| params.primaries());
+    info.target_primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+    let (min, max) = params
+        .mastering_luminance
+        .unwrap_or((luminances.min, luminances.max));
+    info.target_luminance(min, max);
+    if let Some(max_cll) = params.max_cll {
+        info.target_max_cll(max_cll);
+    }
+    if let Some(max_fall) = params.max_fall {
+        info.target_max_fall(max_fall);
+    }
+    info.done();
+}
+
+macro_rules! delegate_color_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_output_v1::WpColorManagementOutputV1: $crate::wayland::protocols::color_management::ColorManagementOutputData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_surface_v1::WpColorManagementSurfaceV1: $crate::wayland::protocols::color_management::ColorManagementSurfaceData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1: $crate::wayland::protocols::color_management::ColorManagementSurfaceData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_creator_icc_v1::WpImageDescriptionCreatorIccV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1: $crate::wayland::protocols::color_management::ParamsCreatorData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_v1::WpImageDescriptionV1: $crate::wayland::protocols::color_management::ImageDescriptionData
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_info_v1::WpImageDescriptionInfoV1: ()
+        ] => $crate::wayland::protocols::color_management::ColorManagementState);
+    };
+}
+pub(crate) use delegate_color_management;
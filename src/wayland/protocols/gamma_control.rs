@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    output::{Output, WeakOutput},
+    reexports::{
+        wayland_protocols_wlr::gamma_control::v1::server::{
+            zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+            zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+        },
+        wayland_server::{
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+            backend::GlobalId,
+        },
+    },
+};
+use std::{fs::File, os::unix::fs::FileExt};
+use wayland_backend::server::ClientId;
+
+use crate::backend::render::gamma::GammaRamp;
+
+pub trait GammaControlHandler {
+    fn gamma_control_state(&mut self) -> &mut GammaControlState;
+    /// Number of entries per channel of gamma ramps for `output`, if it supports them
+    fn gamma_size(&mut self, output: &Output) -> Option<usize>;
+    /// Applies `ramp` to `output`, or restores its original gamma, if `None`
+    fn set_gamma(&mut self, output: &Output, ramp: Option<GammaRamp>);
+}
+
+#[derive(Debug)]
+pub struct GammaControlState {
+    global: GlobalId,
+    /// Controls currently in charge of the gamma of their output, at most one per output
+    controls: Vec<ZwlrGammaControlV1>,
+}
+
+impl GammaControlState {
+    pub fn new<D, F>(dh: &DisplayHandle, client_filter: F) -> GammaControlState
+    where
+        D: GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData> + 'static,
+        F: for<'a> Fn(&'a Client) -> bool + Clone + Send + Sync + 'static,
+    {
+        let global = dh.create_global::<D, ZwlrGammaControlManagerV1, _>(
+            1,
+            GammaControlManagerGlobalData {
+                filter: Box::new(client_filter.clone()),
+            },
+        );
+
+        GammaControlState {
+            global,
+            controls: Vec::new(),
+        }
+    }
+
+    pub fn global_id(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Outputs that currently have a gamma control
+    pub fn outputs(&self) -> impl Iterator<Item = Output> + '_ {
+        self.controls
+            .iter()
+            .filter_map(|control| control.data::<GammaControlData>().unwrap().output.upgrade())
+    }
+
+    /// Send `failed` to the control of `output`, as it is going away.
+    pub fn output_removed(&mut self, output: &Output) {
+        self.controls.retain(|control| {
+            let removed = control.data::<GammaControlData>().unwrap().output == *output;
+            if removed {
+                control.failed();
+            }
+            !removed
+        });
+    }
+}
+
+pub struct GammaControlManagerGlobalData {
+    filter: Box<dyn for<'a> Fn(&'a Client) -> bool + Send + Sync>,
+}
+
+pub struct GammaControlData {
+    output: WeakOutput,
+    size: usize,
+}
+
+impl<D> GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData, D>
+    for GammaControlState
+where
+    D: GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData>
+        + Dispatch<ZwlrGammaControlManagerV1, ()>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrGammaControlManagerV1>,
+        _global_data: &GammaControlManagerGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &GammaControlManagerGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwlrGammaControlManagerV1, (), D> for GammaControlState
+where
+    D: GlobalDispatch<ZwlrGammaControlManagerV1, GammaControlManagerGlobalData>
+        + Dispatch<ZwlrGammaControlManagerV1, ()>
+        + Dispatch<ZwlrGammaControlV1, GammaControlData>
+        + GammaControlHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _obj: &ZwlrGammaControlManagerV1,
+        request: zwlr_gamma_control_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_gamma_control_manager_v1::Request::GetGammaControl { id, output } => {
+                let output = Output::from_resource(&output);
+                let size = output.as_ref().and_then(|o| state.gamma_size(o));
+                let control = data_init.init(
+                    id,
+                    GammaControlData {
+                        output: output.as_ref().map(|o| o.downgrade()).unwrap_or_default(),
+                        size: size.unwrap_or(0),
+                    },
+                );
+
+                let in_use =
+                    output.as_ref().is_some_and(|output| {
+                        state.gamma_control_state().controls.iter().any(|other| {
+                            other.data::<GammaControlData>().unwrap().output == *output
+                        })
+                    });
+                match size {
+                    Some(size) if !in_use => {
+                        control.gamma_size(size as u32);
+                        state.gamma_control_state().controls.push(control);
+                    }
+                    _ => control.failed(),
+                }
+            }
+            zwlr_gamma_control_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwlrGammaControlV1, GammaControlData, D> for GammaControlState
+where
+    D: Dispatch<ZwlrGammaControlV1, GammaControlData> + GammaControlHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        obj: &ZwlrGammaControlV1,
+        request: zwlr_gamma_control_v1::Request,
+        data: &GammaControlData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_gamma_control_v1::Request::SetGamma { fd } => {
+                if !state.gamma_control_state().controls.contains(obj) {
+                    return;
+                }
+                let Some(output) = data.output.upgrade() else {
+                    return;
+                };
+
+                let mut bytes = vec![0; data.size * 3 * 2];
+                match File::from(fd)
+                    .read_exact_at(&mut bytes, 0)
+                    .ok()
+                    .and_then(|_| GammaRamp::from_bytes(&bytes, data.size))
+                {
+                    Some(ramp) => state.set_gamma(&output, Some(ramp)),
+                    None => {
+                        obj.failed();
+                        state.gamma_control_state().controls.retain(|c| c != obj);
+                        state.set_gamma(&output, None);
+                    }
+                }
+            }
+            zwlr_gamma_control_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        obj: &ZwlrGammaControlV1,
+        data: &GammaControlData,
+    ) {
+        let controls = &mut state.gamma_control_state().controls;
+        if controls.contains(obj) {
+            controls.retain(|c| c != obj);
+            if let Some(output) = data.output.upgrade() {
+                state.set_gamma(&output, None);
+            }
+        }
+    }
+}
+
+macro_rules! delegate_gamma_control {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1: $crate::wayland::protocols::gamma_control::GammaControlManagerGlobalData
+        ] => $crate::wayland::protocols::gamma_control::GammaControlState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1: ()
+        ] => $crate::wayland::protocols::gamma_control::GammaControlState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::gamma_control::v1::server::zwlr_gamma_control_v1::ZwlrGammaControlV1: $crate::wayland::protocols::gamma_control::GammaControlData
+        ] => $crate::wayland::protocols::gamma_control::GammaControlState);
+    };
+}
+pub(crate) use delegate_gamma_control;
@@ -1,13 +1,18 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 pub mod a11y;
+pub mod color_management;
 pub mod corner_radius;
 pub mod drm;
+pub mod gamma_control;
 pub mod image_capture_source;
 pub mod keyboard_layout;
 pub mod output_configuration;
 pub mod output_power;
 pub mod overlap_notify;
+pub mod session_management;
 pub mod toplevel_info;
 pub mod toplevel_management;
+pub mod transient_seat;
+pub mod virtual_pointer;
 pub mod workspace;
@@ -473,7 +473,9 @@ where
                     AdaptiveSync::Disabled => {
                         zcosmic_output_head_v1::AdaptiveSyncStateExt::Disabled
                     }
-                    AdaptiveSync::Enabled => {
+                    // the protocol has no distinct state for content-type based vrr,
+                    // it is only configurable through cosmic-config
+                    AdaptiveSync::Enabled | AdaptiveSync::Automatic => {
                         zcosmic_output_head_v1::AdaptiveSyncStateExt::Automatic
                     }
                     AdaptiveSync::Force => zcosmic_output_head_v1::AdaptiveSyncStateExt::Always,
@@ -519,7 +521,7 @@ where
     }
 }
 
-fn remove_global_with_timer<D: 'static>(
+pub(crate) fn remove_global_with_timer<D: 'static>(
     dh: &DisplayHandle,
     event_loop_handle: &LoopHandle<D>,
     id: GlobalId,
@@ -532,10 +534,7 @@ fn remove_global_with_timer<D: 'static>(
         TimeoutAction::Drop
     });
     if let Err(err) = res {
-        tracing::error!(
-            "failed to insert timer source to destroy output global: {}",
-            err
-        );
+        tracing::error!("failed to insert timer source to destroy global: {}", err);
     }
 }
 
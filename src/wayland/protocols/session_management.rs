@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Implementation of `xdg_session_manager_v1`.
+//!
+//! Clients create or resume a session by id and add their toplevels to it under a
+//! name of their choosing. The compositor remembers the state of every named
+//! toplevel once it is gone, and reapplies it, when a toplevel of a later instance
+//! of the client is restored under the same name.
+//!
+//! Storing and applying the restore data is up to the [`SessionManagementHandler`],
+//! this module only tracks which session ids are in use.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use smithay::{
+    reexports::{
+        wayland_protocols::xdg::{
+            session_management::v1::server::{
+                xdg_session_manager_v1::{self, XdgSessionManagerV1},
+                xdg_session_v1::{self, XdgSessionV1},
+                xdg_toplevel_session_v1::{self, XdgToplevelSessionV1},
+            },
+            shell::server::xdg_toplevel::XdgToplevel,
+        },
+        wayland_server::{
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+            backend::GlobalId,
+        },
+    },
+    wayland::shell::xdg::{ToplevelSurface, XdgShellHandler},
+};
+use wayland_backend::server::ClientId;
+
+pub trait SessionManagementHandler: XdgShellHandler {
+    fn session_management_state(&mut self) -> &mut SessionManagementState;
+    /// Whether restore data of `session` is stored
+    fn session_exists(&mut self, session: &str) -> bool;
+    /// Forgets all restore data of `session`
+    fn remove_session(&mut self, session: &str);
+    /// Starts tracking the state of `toplevel` as `name` of `session`.
+    ///
+    /// If `restore` is set, the stored state is applied, once the toplevel is mapped.
+    /// Returns whether there was any state to restore.
+    fn add_toplevel(
+        &mut self,
+        session: &str,
+        name: &str,
+        toplevel: &ToplevelSurface,
+        restore: bool,
+    ) -> bool;
+    /// Stops tracking the state of `toplevel`, keeping what was stored so far
+    fn untrack_toplevel(&mut self, toplevel: &ToplevelSurface);
+    /// Forgets the restore data of `name` of `session`
+    fn remove_toplevel(&mut self, session: &str, name: &str);
+}
+
+#[derive(Debug)]
+pub struct SessionManagementState {
+    global: GlobalId,
+    /// Session objects by the id they are currently using
+    sessions: HashMap<String, Weak<XdgSessionV1>>,
+}
+
+impl SessionManagementState {
+    pub fn new<D>(dh: &DisplayHandle) -> SessionManagementState
+    where
+        D: GlobalDispatch<XdgSessionManagerV1, ()> + 'static,
+    {
+        let global = dh.create_global::<D, XdgSessionManagerV1, _>(1, ());
+        SessionManagementState {
+            global,
+            sessions: HashMap::new(),
+        }
+    }
+
+    pub fn global_id(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+#[derive(Debug)]
+pub struct SessionData {
+    /// Id of the session, unset once the session was replaced or removed
+    id: Mutex<Option<String>>,
+    /// Names in use by toplevels of this session object
+    names: Mutex<Vec<String>>,
+}
+
+impl SessionData {
+    fn id(&self) -> Option<String> {
+        self.id.lock().unwrap().clone()
+    }
+}
+
+#[derive(Debug)]
+pub struct ToplevelSessionData {
+    session: Weak<XdgSessionV1>,
+    name: String,
+    toplevel: Weak<XdgToplevel>,
+}
+
+fn new_session_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+impl<D> GlobalDispatch<XdgSessionManagerV1, (), D> for SessionManagementState
+where
+    D: GlobalDispatch<XdgSessionManagerV1, ()> + Dispatch<XdgSessionManagerV1, ()> + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<XdgSessionManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<XdgSessionManagerV1, (), D> for SessionManagementState
+where
+    D: Dispatch<XdgSessionManagerV1, ()>
+        + Dispatch<XdgSessionV1, SessionData>
+        + SessionManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        obj: &XdgSessionManagerV1,
+        request: xdg_session_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            xdg_session_manager_v1::Request::GetSession { id, session, .. } => {
+                let existing = session.filter(|session| state.session_exists(session));
+                let sessions = &mut state.session_management_state().sessions;
+                sessions.retain(|_, obj| obj.upgrade().is_ok());
+
+                if let Some(in_use) = existing
+                    .as_ref()
+                    .and_then(|session| sessions.get(session))
+                    .and_then(|obj| obj.upgrade().ok())
+                {
+                    if in_use.id().same_client_as(&obj.id()) {
+                        obj.post_error(
+                            xdg_session_manager_v1::Error::InUse,
+                            "session is already in use by this client",
+                        );
+                        return;
+                    }
+                    // the new instance of the application takes over
+                    *in_use.data::<SessionData>().unwrap().id.lock().unwrap() = None;
+                    in_use.replaced();
+                }
+
+                let restored = existing.is_some();
+                let session_id = existing.unwrap_or_else(new_session_id);
+                let session = data_init.init(
+                    id,
+                    SessionData {
+                        id: Mutex::new(Some(session_id.clone())),
+                        names: Mutex::new(Vec::new()),
+                    },
+                );
+                sessions.insert(session_id.clone(), session.downgrade());
+                if restored {
+                    session.restored();
+                } else {
+                    session.created(session_id);
+                }
+            }
+            xdg_session_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<XdgSessionV1, SessionData, D> for SessionManagementState
+where
+    D: Dispatch<XdgSessionV1, SessionData>
+        + Dispatch<XdgToplevelSessionV1, ToplevelSessionData>
+        + SessionManagementHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        obj: &XdgSessionV1,
+        request: xdg_session_v1::Request,
+        data: &SessionData,
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            xdg_session_v1::Request::AddToplevel { id, toplevel, name } => {
+                add_toplevel(state, obj, data, data_init, id, toplevel, name, false);
+            }
+            xdg_session_v1::Request::RestoreToplevel { id, toplevel, name } => {
+                add_toplevel(state, obj, data, data_init, id, toplevel, name, true);
+            }
+            xdg_session_v1::Request::Remove => {
+                if let Some(session) = data.id.lock().unwrap().take() {
+                    state.remove_session(&session);
+                    state.session_management_state().sessions.remove(&session);
+                }
+            }
+            xdg_session_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, obj: &XdgSessionV1, data: &SessionData) {
+        if let Some(session) = data.id() {
+            let sessions = &mut state.session_management_state().sessions;
+            if sessions
+                .get(&session)
+                .is_some_and(|weak| weak.id() == obj.id())
+            {
+                sessions.remove(&session);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn add_toplevel<D>(
+    state: &mut D,
+    obj: &XdgSessionV1,
+    data: &SessionData,
+    data_init: &mut DataInit<'_, D>,
+    id: New<XdgToplevelSessionV1>,
+    toplevel: XdgToplevel,
+    name: String,
+    restore: bool,
+) where
+    D: Dispatch<XdgToplevelSessionV1, ToplevelSessionData> + SessionManagementHandler + 'static,
+{
+    {
+        let mut names = data.names.lock().unwrap();
+        if names.contains(&name) {
+            obj.post_error(
+                xdg_session_v1::Error::NameInUse,
+                format!("toplevel name {name:?} is already in use"),
+            );
+            return;
+        }
+        names.push(name.clone());
+    }
+
+    let toplevel_session = data_init.init(
+        id,
+        ToplevelSessionData {
+            session: obj.downgrade(),
+            name: name.clone(),
+            toplevel: toplevel.downgrade(),
+        },
+    );
+    let (Some(session), Some(surface)) =
+        (data.id(), state.xdg_shell_state().get_toplevel(&toplevel))
+    else {
+        return;
+    };
+
+    if restore && surface.is_initial_configure_sent() {
+        obj.post_error(
+            xdg_session_v1::Error::AlreadyMapped,
+            "toplevel was restored after its initial configure",
+        );
+        return;
+    }
+    if state.add_toplevel(&session, &name, &surface, restore) {
+        toplevel_session.restored(&toplevel);
+    }
+}
+
+impl<D> Dispatch<XdgToplevelSessionV1, ToplevelSessionData, D> for SessionManagementState
+where
+    D: Dispatch<XdgToplevelSessionV1, ToplevelSessionData> + SessionManagementHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _obj: &XdgToplevelSessionV1,
+        request: xdg_toplevel_session_v1::Request,
+        data: &ToplevelSessionData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let remove = match request {
+            xdg_toplevel_session_v1::Request::Remove => true,
+            xdg_toplevel_session_v1::Request::Destroy => false,
+            _ => unreachable!(),
+        };
+
+        if let Some(surface) = data
+            .toplevel
+            .upgrade()
+            .ok()
+            .and_then(|toplevel| state.xdg_shell_state().get_toplevel(&toplevel))
+        {
+            state.untrack_toplevel(&surface);
+        }
+        if remove
+            && let Some(session) = data
+                .session
+                .upgrade()
+                .ok()
+                .and_then(|session| session.data::<SessionData>().unwrap().id())
+        {
+            state.remove_toplevel(&session, &data.name);
+        }
+    }
+
+    /// Toplevels stay tracked, if their client disconnects, so their state is stored
+    /// once they are gone.
+    fn destroyed(
+        _state: &mut D,
+        _client: ClientId,
+        _obj: &XdgToplevelSessionV1,
+        data: &ToplevelSessionData,
+    ) {
+        if let Ok(session) = data.session.upgrade() {
+            session
+                .data::<SessionData>()
+                .unwrap()
+                .names
+                .lock()
+                .unwrap()
+                .retain(|name| name != &data.name);
+        }
+    }
+}
+
+macro_rules! delegate_session_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::session_management::v1::server::xdg_session_manager_v1::XdgSessionManagerV1: ()
+        ] => $crate::wayland::protocols::session_management::SessionManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::session_management::v1::server::xdg_session_manager_v1::XdgSessionManagerV1: ()
+        ] => $crate::wayland::protocols::session_management::SessionManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::session_management::v1::server::xdg_session_v1::XdgSessionV1: $crate::wayland::protocols::session_management::SessionData
+        ] => $crate::wayland::protocols::session_management::SessionManagementState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::session_management::v1::server::xdg_toplevel_session_v1::XdgToplevelSessionV1: $crate::wayland::protocols::session_management::ToplevelSessionData
+        ] => $crate::wayland::protocols::session_management::SessionManagementState);
+    };
+}
+pub(crate) use delegate_session_management;
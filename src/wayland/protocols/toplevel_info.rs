@@ -1,6 +1,38 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::{collections::HashSet, sync::Mutex};
+pub use generated::{zcosmic_toplevel_icon_manager_v1, zcosmic_toplevel_icon_v1};
+
+#[allow(non_snake_case, non_upper_case_globals, non_camel_case_types)]
+mod generated {
+    use smithay::reexports::{
+        wayland_protocols::ext::foreign_toplevel_list::v1::server::*,
+        wayland_server::{self, protocol::*},
+    };
+
+    pub mod __interfaces {
+        use smithay::reexports::{
+            wayland_protocols::ext::foreign_toplevel_list::v1::server::__interfaces::*,
+            wayland_server::protocol::__interfaces::*,
+        };
+        use wayland_backend;
+        wayland_scanner::generate_interfaces!(
+            "resources/protocols/cosmic-toplevel-icon-unstable-v1.xml"
+        );
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_server_code!(
+        "resources/protocols/cosmic-toplevel-icon-unstable-v1.xml"
+    );
+}
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::Write,
+    os::fd::{AsFd, OwnedFd},
+    sync::{Arc, Mutex},
+};
 
 use smithay::{
     output::Output,
@@ -25,6 +57,9 @@ use smithay::{
 use crate::utils::prelude::{Global, OutputExt, RectGlobalExt};
 
 use super::workspace::{WorkspaceHandle, WorkspaceHandler, WorkspaceState};
+use rustix::fs::{MemfdFlags, SealFlags, fcntl_add_seals, memfd_create};
+use zcosmic_toplevel_icon_manager_v1::ZcosmicToplevelIconManagerV1;
+use zcosmic_toplevel_icon_v1::ZcosmicToplevelIconV1;
 
 use cosmic_protocols::toplevel_info::v1::server::{
     zcosmic_toplevel_handle_v1::{self, State as States, ZcosmicToplevelHandleV1},
@@ -35,6 +70,7 @@ use tracing::error;
 pub trait Window: IsAlive + Clone + PartialEq + Send {
     fn title(&self) -> String;
     fn app_id(&self) -> String;
+    fn icon(&self) -> Option<Icon>;
     fn is_activated(&self) -> bool;
     fn is_maximized(&self) -> bool;
     fn is_fullscreen(&self) -> bool;
@@ -45,6 +81,32 @@ pub trait Window: IsAlive + Clone + PartialEq + Send {
     fn user_data(&self) -> &UserDataMap;
 }
 
+/// Icon a toplevel supplied through `xdg_toplevel_icon_v1`
+#[derive(Debug, Clone)]
+pub enum Icon {
+    /// Name in the icon theme
+    Name(String),
+    /// Square image with straight rgba pixels
+    Pixels { size: u32, pixels: Arc<[u8]> },
+}
+
+// Pixels compare by identity, windows are expected to hold on to them while unchanged.
+impl PartialEq for Icon {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Icon::Name(a), Icon::Name(b)) => a == b,
+            (
+                Icon::Pixels { size, pixels },
+                Icon::Pixels {
+                    size: other_size,
+                    pixels: other_pixels,
+                },
+            ) => size == other_size && Arc::ptr_eq(pixels, other_pixels),
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ToplevelInfoState<D, W: Window> {
     dh: DisplayHandle,
@@ -74,6 +136,8 @@ pub(super) struct ToplevelStateInner {
     outputs: Vec<Output>,
     workspaces: Vec<WorkspaceHandle>,
     pub(super) rectangles: Vec<(Weak<WlSurface>, Rectangle<i32, Logical>)>,
+    icons: Vec<ZcosmicToplevelIconV1>,
+    icon: Option<Icon>,
 }
 pub(super) type ToplevelState = Mutex<ToplevelStateInner>;
 
@@ -277,6 +341,149 @@ where
     }
 }
 
+impl<D, W> GlobalDispatch<ZcosmicToplevelIconManagerV1, ToplevelInfoGlobalData, D>
+    for ToplevelInfoState<D, W>
+where
+    D: GlobalDispatch<ZcosmicToplevelIconManagerV1, ToplevelInfoGlobalData>
+        + Dispatch<ZcosmicToplevelIconManagerV1, ()>
+        + Dispatch<ZcosmicToplevelIconV1, ()>
+        + ToplevelInfoHandler<Window = W>
+        + 'static,
+    W: Window + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZcosmicToplevelIconManagerV1>,
+        _global_data: &ToplevelInfoGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &ToplevelInfoGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D, W> Dispatch<ZcosmicToplevelIconManagerV1, (), D> for ToplevelInfoState<D, W>
+where
+    D: GlobalDispatch<ZcosmicToplevelIconManagerV1, ToplevelInfoGlobalData>
+        + Dispatch<ZcosmicToplevelIconManagerV1, ()>
+        + Dispatch<ZcosmicToplevelIconV1, ()>
+        + ToplevelInfoHandler<Window = W>
+        + 'static,
+    W: Window + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _obj: &ZcosmicToplevelIconManagerV1,
+        request: zcosmic_toplevel_icon_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zcosmic_toplevel_icon_manager_v1::Request::GetToplevelIcon { id, toplevel } => {
+                let instance = data_init.init(id, ());
+                let Some(window) = window_from_ext_handle::<W, D>(state, &toplevel) else {
+                    // the toplevel is gone already, so it has no icon
+                    instance.done();
+                    return;
+                };
+
+                let mut toplevel_state = window
+                    .user_data()
+                    .get::<ToplevelState>()
+                    .unwrap()
+                    .lock()
+                    .unwrap();
+                let icon = window.icon();
+                if toplevel_state.icon != icon {
+                    send_icon(&toplevel_state.icons, icon.as_ref());
+                    toplevel_state.icon = icon;
+                }
+                send_icon(&[instance.clone()], toplevel_state.icon.as_ref());
+                toplevel_state.icons.push(instance);
+            }
+            zcosmic_toplevel_icon_manager_v1::Request::Destroy => {}
+            _ => {}
+        }
+    }
+}
+
+impl<D, W> Dispatch<ZcosmicToplevelIconV1, (), D> for ToplevelInfoState<D, W>
+where
+    D: GlobalDispatch<ZcosmicToplevelIconManagerV1, ToplevelInfoGlobalData>
+        + Dispatch<ZcosmicToplevelIconManagerV1, ()>
+        + Dispatch<ZcosmicToplevelIconV1, ()>
+        + ToplevelInfoHandler<Window = W>
+        + 'static,
+    W: Window + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _obj: &ZcosmicToplevelIconV1,
+        _request: zcosmic_toplevel_icon_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, resource: &ZcosmicToplevelIconV1, _data: &()) {
+        for toplevel in &state.toplevel_info_state_mut().toplevels {
+            if let Some(state) = toplevel.user_data().get::<ToplevelState>() {
+                state.lock().unwrap().icons.retain(|i| i != resource);
+            }
+        }
+    }
+}
+
+/// Sends `icon` to `instances`, shared through a sealed memfd if it is made of pixels.
+fn send_icon(instances: &[ZcosmicToplevelIconV1], icon: Option<&Icon>) {
+    if instances.is_empty() {
+        return;
+    }
+
+    match icon {
+        Some(Icon::Name(name)) => {
+            for instance in instances {
+                instance.name(name.clone());
+            }
+        }
+        Some(Icon::Pixels { size, pixels }) => match icon_fd(pixels) {
+            Ok(fd) => {
+                for instance in instances {
+                    instance.buffer(fd.as_fd(), *size);
+                }
+            }
+            Err(err) => error!(?err, "Failed to share toplevel icon."),
+        },
+        None => {}
+    }
+    for instance in instances {
+        instance.done();
+    }
+}
+
+fn icon_fd(pixels: &[u8]) -> std::io::Result<OwnedFd> {
+    let fd = memfd_create(
+        "cosmic-toplevel-icon",
+        MemfdFlags::CLOEXEC | MemfdFlags::ALLOW_SEALING,
+    )?;
+    let mut file = File::from(fd);
+    file.write_all(pixels)?;
+    fcntl_add_seals(
+        &file,
+        SealFlags::SHRINK | SealFlags::GROW | SealFlags::WRITE | SealFlags::SEAL,
+    )?;
+    Ok(file.into())
+}
+
 pub fn toplevel_enter_output(toplevel: &impl Window, output: &Output) {
     if let Some(state) = toplevel.user_data().get::<ToplevelState>() {
         state.lock().unwrap().outputs.push(output.clone());
@@ -308,6 +515,9 @@ where
         + Dispatch<ExtForeignToplevelHandleV1, ForeignToplevelHandle>
         + Dispatch<ZcosmicToplevelInfoV1, ()>
         + Dispatch<ZcosmicToplevelHandleV1, ToplevelHandleState<W>>
+        + GlobalDispatch<ZcosmicToplevelIconManagerV1, ToplevelInfoGlobalData>
+        + Dispatch<ZcosmicToplevelIconManagerV1, ()>
+        + Dispatch<ZcosmicToplevelIconV1, ()>
         + ForeignToplevelListHandler
         + ToplevelInfoHandler<Window = W>
         + 'static,
@@ -323,6 +533,13 @@ where
                 filter: Box::new(client_filter.clone()),
             },
         );
+        // icons are only sent to clients that may list toplevels
+        dh.create_global::<D, ZcosmicToplevelIconManagerV1, _>(
+            1,
+            ToplevelInfoGlobalData {
+                filter: Box::new(client_filter.clone()),
+            },
+        );
         let foreign_toplevel_list =
             ForeignToplevelListState::new_with_filter::<D>(dh, client_filter);
         ToplevelInfoState {
@@ -395,6 +612,13 @@ where
                 .rectangles
                 .retain(|(surface, _)| surface.upgrade().is_ok());
             if window.alive() {
+                if !state.icons.is_empty() {
+                    let icon = window.icon();
+                    if state.icon != icon {
+                        send_icon(&state.icons, icon.as_ref());
+                        state.icon = icon;
+                    }
+                }
                 std::mem::drop(state);
                 for instance in &self.instances {
                     let changed = send_toplevel_to_client::<D, W>(
@@ -713,6 +937,15 @@ macro_rules! delegate_toplevel_info {
         smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
             cosmic_protocols::toplevel_info::v1::server::zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1: $crate::wayland::protocols::toplevel_info::ToplevelHandleState<$window>
         ] => $crate::wayland::protocols::toplevel_info::ToplevelInfoState<Self, $window>);
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::toplevel_info::zcosmic_toplevel_icon_manager_v1::ZcosmicToplevelIconManagerV1: $crate::wayland::protocols::toplevel_info::ToplevelInfoGlobalData
+        ] => $crate::wayland::protocols::toplevel_info::ToplevelInfoState<Self, $window>);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::toplevel_info::zcosmic_toplevel_icon_manager_v1::ZcosmicToplevelIconManagerV1: ()
+        ] => $crate::wayland::protocols::toplevel_info::ToplevelInfoState<Self, $window>);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::protocols::toplevel_info::zcosmic_toplevel_icon_v1::ZcosmicToplevelIconV1: ()
+        ] => $crate::wayland::protocols::toplevel_info::ToplevelInfoState<Self, $window>);
     };
 }
 pub(crate) use delegate_toplevel_info;
@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Implementation of `ext_transient_seat_manager_v1`.
+//!
+//! Transient seats are additional seats for remote input, which live as long as the
+//! `ext_transient_seat_v1` object of the client, that created them.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use smithay::reexports::{
+    wayland_protocols::ext::transient_seat::v1::server::{
+        ext_transient_seat_manager_v1::{self, ExtTransientSeatManagerV1},
+        ext_transient_seat_v1::{self, ExtTransientSeatV1},
+    },
+    wayland_server::{
+        Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, backend::GlobalId,
+    },
+};
+use wayland_backend::server::ClientId;
+
+pub trait TransientSeatHandler {
+    /// Creates a seat named `name`, returning its `wl_seat` global, `None` to deny it
+    fn add_transient_seat(&mut self, name: &str) -> Option<GlobalId>;
+    fn remove_transient_seat(&mut self, name: &str);
+}
+
+#[derive(Debug)]
+pub struct TransientSeatState {
+    global: GlobalId,
+}
+
+impl TransientSeatState {
+    pub fn new<D, F>(dh: &DisplayHandle, client_filter: F) -> TransientSeatState
+    where
+        D: GlobalDispatch<ExtTransientSeatManagerV1, TransientSeatManagerGlobalData> + 'static,
+        F: for<'a> Fn(&'a Client) -> bool + Clone + Send + Sync + 'static,
+    {
+        let global = dh.create_global::<D, ExtTransientSeatManagerV1, _>(
+            1,
+            TransientSeatManagerGlobalData {
+                filter: Box::new(client_filter.clone()),
+            },
+        );
+
+        TransientSeatState { global }
+    }
+
+    pub fn global_id(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+pub struct TransientSeatManagerGlobalData {
+    filter: Box<dyn for<'a> Fn(&'a Client) -> bool + Send + Sync>,
+}
+
+pub struct TransientSeatData {
+    /// Name of the seat, `None` if it was denied
+    name: Option<String>,
+}
+
+static NEXT_SEAT: AtomicUsize = AtomicUsize::new(0);
+
+impl<D> GlobalDispatch<ExtTransientSeatManagerV1, TransientSeatManagerGlobalData, D>
+    for TransientSeatState
+where
+    D: GlobalDispatch<ExtTransientSeatManagerV1, TransientSeatManagerGlobalData>
+        + Dispatch<ExtTransientSeatManagerV1, ()>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtTransientSeatManagerV1>,
+        _global_data: &TransientSeatManagerGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &TransientSeatManagerGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ExtTransientSeatManagerV1, (), D> for TransientSeatState
+where
+    D: Dispatch<ExtTransientSeatManagerV1, ()>
+        + Dispatch<ExtTransientSeatV1, TransientSeatData>
+        + TransientSeatHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _obj: &ExtTransientSeatManagerV1,
+        request: ext_transient_seat_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_transient_seat_manager_v1::Request::Create { seat } => {
+                let name = format!(
+                    "transient-seat-{}",
+                    NEXT_SEAT.fetch_add(1, Ordering::Relaxed)
+                );
+                match state.add_transient_seat(&name) {
+                    Some(global) => {
+                        let seat = data_init.init(seat, TransientSeatData { name: Some(name) });
+                        seat.ready(global.protocol_id());
+                    }
+                    None => {
+                        let seat = data_init.init(seat, TransientSeatData { name: None });
+                        seat.denied();
+                    }
+                }
+            }
+            ext_transient_seat_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ExtTransientSeatV1, TransientSeatData, D> for TransientSeatState
+where
+    D: Dispatch<ExtTransientSeatV1, TransientSeatData> + TransientSeatHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _obj: &ExtTransientSeatV1,
+        request: ext_transient_seat_v1::Request,
+        _data: &TransientSeatData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_transient_seat_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    /// Also called, when the client disconnects
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        _obj: &ExtTransientSeatV1,
+        data: &TransientSeatData,
+    ) {
+        if let Some(name) = data.name.as_ref() {
+            state.remove_transient_seat(name);
+        }
+    }
+}
+
+macro_rules! delegate_transient_seat {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::transient_seat::v1::server::ext_transient_seat_manager_v1::ExtTransientSeatManagerV1: $crate::wayland::protocols::transient_seat::TransientSeatManagerGlobalData
+        ] => $crate::wayland::protocols::transient_seat::TransientSeatState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::transient_seat::v1::server::ext_transient_seat_manager_v1::ExtTransientSeatManagerV1: ()
+        ] => $crate::wayland::protocols::transient_seat::TransientSeatState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::transient_seat::v1::server::ext_transient_seat_v1::ExtTransientSeatV1: $crate::wayland::protocols::transient_seat::TransientSeatData
+        ] => $crate::wayland::protocols::transient_seat::TransientSeatState);
+    };
+}
+pub(crate) use delegate_transient_seat;
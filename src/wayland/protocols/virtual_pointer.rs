@@ -0,0 +1,555 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Implementation of `zwlr_virtual_pointer_manager_v1`.
+//!
+//! Every virtual pointer is an input device of its own, whose events are handed to the
+//! [`VirtualPointerHandler`] as [`InputEvent`]s of the [`VirtualPointerInput`] backend.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use smithay::{
+    backend::input::{
+        AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+        DeviceCapability, Event, InputBackend, InputEvent, PointerAxisEvent, PointerButtonEvent,
+        PointerMotionAbsoluteEvent, PointerMotionEvent, UnusedEvent,
+    },
+    input::{Seat, SeatHandler},
+    output::{Output, WeakOutput},
+    reexports::{
+        wayland_protocols_wlr::virtual_pointer::v1::server::{
+            zwlr_virtual_pointer_manager_v1::{self, ZwlrVirtualPointerManagerV1},
+            zwlr_virtual_pointer_v1::{self, ZwlrVirtualPointerV1},
+        },
+        wayland_server::{
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+            backend::GlobalId,
+            protocol::{wl_output::WlOutput, wl_pointer, wl_seat::WlSeat},
+        },
+    },
+};
+use wayland_backend::{protocol::WEnum, server::ClientId};
+
+pub trait VirtualPointerHandler: SeatHandler + Sized {
+    /// Adds `device` to `seat`, or to the default seat, if the client didn't choose one
+    fn add_virtual_pointer(&mut self, device: &VirtualPointer, seat: Option<Seat<Self>>);
+    fn remove_virtual_pointer(&mut self, device: &VirtualPointer);
+    fn virtual_pointer_event(&mut self, event: InputEvent<VirtualPointerInput>);
+}
+
+#[derive(Debug)]
+pub struct VirtualPointerManagerState {
+    global: GlobalId,
+}
+
+impl VirtualPointerManagerState {
+    pub fn new<D, F>(dh: &DisplayHandle, client_filter: F) -> VirtualPointerManagerState
+    where
+        D: GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData> + 'static,
+        F: for<'a> Fn(&'a Client) -> bool + Clone + Send + Sync + 'static,
+    {
+        let global = dh.create_global::<D, ZwlrVirtualPointerManagerV1, _>(
+            2,
+            VirtualPointerManagerGlobalData {
+                filter: Box::new(client_filter.clone()),
+            },
+        );
+
+        VirtualPointerManagerState { global }
+    }
+
+    pub fn global_id(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+pub struct VirtualPointerManagerGlobalData {
+    filter: Box<dyn for<'a> Fn(&'a Client) -> bool + Send + Sync>,
+}
+
+pub struct VirtualPointerData {
+    device: VirtualPointer,
+    output: Option<WeakOutput>,
+    axis: Mutex<PendingAxis>,
+}
+
+/// Axis events collected until the next `frame` request
+#[derive(Debug, Default)]
+struct PendingAxis {
+    time: u64,
+    source: Option<AxisSource>,
+    horizontal: Option<f64>,
+    vertical: Option<f64>,
+    horizontal_v120: Option<f64>,
+    vertical_v120: Option<f64>,
+}
+
+impl PendingAxis {
+    fn value(&mut self, axis: Axis) -> &mut Option<f64> {
+        match axis {
+            Axis::Horizontal => &mut self.horizontal,
+            Axis::Vertical => &mut self.vertical,
+        }
+    }
+
+    fn v120(&mut self, axis: Axis) -> &mut Option<f64> {
+        match axis {
+            Axis::Horizontal => &mut self.horizontal_v120,
+            Axis::Vertical => &mut self.vertical_v120,
+        }
+    }
+}
+
+static NEXT_POINTER_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Input device of a single virtual pointer
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VirtualPointer {
+    id: u32,
+}
+
+impl Device for VirtualPointer {
+    fn id(&self) -> String {
+        format!("virtual-pointer-{}", self.id)
+    }
+
+    fn name(&self) -> String {
+        String::from("virtual pointer")
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        capability == DeviceCapability::Pointer
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct VirtualPointerInput;
+
+#[derive(Debug, Clone)]
+pub struct VirtualPointerMotionEvent {
+    time: u64,
+    device: VirtualPointer,
+    dx: f64,
+    dy: f64,
+}
+
+/// Absolute motion, with the position normalized to `0.0..=1.0`
+#[derive(Debug, Clone)]
+pub struct VirtualPointerMotionAbsoluteEvent {
+    pub time: u64,
+    pub device: VirtualPointer,
+    pub x: f64,
+    pub y: f64,
+    /// Output the device is mapped to, if it was created for one
+    pub output: Option<WeakOutput>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VirtualPointerButtonEvent {
+    time: u64,
+    device: VirtualPointer,
+    button: u32,
+    state: ButtonState,
+}
+
+#[derive(Debug, Clone)]
+pub struct VirtualPointerAxisEvent {
+    time: u64,
+    device: VirtualPointer,
+    source: AxisSource,
+    horizontal: Option<f64>,
+    vertical: Option<f64>,
+    horizontal_v120: Option<f64>,
+    vertical_v120: Option<f64>,
+}
+
+macro_rules! virtual_pointer_event {
+    ($event:ty) => {
+        impl Event<VirtualPointerInput> for $event {
+            fn time(&self) -> u64 {
+                self.time
+            }
+
+            fn device(&self) -> VirtualPointer {
+                self.device.clone()
+            }
+        }
+    };
+}
+
+virtual_pointer_event!(VirtualPointerMotionEvent);
+virtual_pointer_event!(VirtualPointerMotionAbsoluteEvent);
+virtual_pointer_event!(VirtualPointerButtonEvent);
+virtual_pointer_event!(VirtualPointerAxisEvent);
+
+impl PointerMotionEvent<VirtualPointerInput> for VirtualPointerMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.dy
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.dx
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.dy
+    }
+}
+
+impl AbsolutePositionEvent<VirtualPointerInput> for VirtualPointerMotionAbsoluteEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        (self.x * width as f64).clamp(0.0, width as f64)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        (self.y * height as f64).clamp(0.0, height as f64)
+    }
+}
+
+impl PointerMotionAbsoluteEvent<VirtualPointerInput> for VirtualPointerMotionAbsoluteEvent {}
+
+impl PointerButtonEvent<VirtualPointerInput> for VirtualPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+impl PointerAxisEvent<VirtualPointerInput> for VirtualPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        match axis {
+            Axis::Horizontal => self.horizontal,
+            Axis::Vertical => self.vertical,
+        }
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        match axis {
+            Axis::Horizontal => self.horizontal_v120,
+            Axis::Vertical => self.vertical_v120,
+        }
+    }
+
+    fn source(&self) -> AxisSource {
+        self.source
+    }
+
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+}
+
+impl InputBackend for VirtualPointerInput {
+    type Device = VirtualPointer;
+    type KeyboardKeyEvent = UnusedEvent;
+    type PointerAxisEvent = VirtualPointerAxisEvent;
+    type PointerButtonEvent = VirtualPointerButtonEvent;
+    type PointerMotionEvent = VirtualPointerMotionEvent;
+    type PointerMotionAbsoluteEvent = VirtualPointerMotionAbsoluteEvent;
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
+    type SpecialEvent = UnusedEvent;
+}
+
+impl<D> GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData, D>
+    for VirtualPointerManagerState
+where
+    D: GlobalDispatch<ZwlrVirtualPointerManagerV1, VirtualPointerManagerGlobalData>
+        + Dispatch<ZwlrVirtualPointerManagerV1, ()>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrVirtualPointerManagerV1>,
+        _global_data: &VirtualPointerManagerGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, global_data: &VirtualPointerManagerGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwlrVirtualPointerManagerV1, (), D> for VirtualPointerManagerState
+where
+    D: Dispatch<ZwlrVirtualPointerManagerV1, ()>
+        + Dispatch<ZwlrVirtualPointerV1, VirtualPointerData>
+        + VirtualPointerHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _obj: &ZwlrVirtualPointerManagerV1,
+        request: zwlr_virtual_pointer_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_virtual_pointer_manager_v1::Request::CreateVirtualPointer { seat, id } => {
+                create_virtual_pointer(state, data_init, id, seat, None);
+            }
+            zwlr_virtual_pointer_manager_v1::Request::CreateVirtualPointerWithOutput {
+                seat,
+                output,
+                id,
+            } => {
+                create_virtual_pointer(state, data_init, id, seat, output);
+            }
+            zwlr_virtual_pointer_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn create_virtual_pointer<D>(
+    state: &mut D,
+    data_init: &mut DataInit<'_, D>,
+    id: New<ZwlrVirtualPointerV1>,
+    seat: Option<WlSeat>,
+    output: Option<WlOutput>,
+) where
+    D: Dispatch<ZwlrVirtualPointerV1, VirtualPointerData> + VirtualPointerHandler + 'static,
+{
+    let device = VirtualPointer {
+        id: NEXT_POINTER_ID.fetch_add(1, Ordering::Relaxed),
+    };
+    data_init.init(
+        id,
+        VirtualPointerData {
+            device: device.clone(),
+            output: output
+                .as_ref()
+                .and_then(Output::from_resource)
+                .map(|output| output.downgrade()),
+            axis: Mutex::new(PendingAxis::default()),
+        },
+    );
+    state.add_virtual_pointer(&device, seat.as_ref().and_then(Seat::from_resource));
+}
+
+impl<D> Dispatch<ZwlrVirtualPointerV1, VirtualPointerData, D> for VirtualPointerManagerState
+where
+    D: Dispatch<ZwlrVirtualPointerV1, VirtualPointerData> + VirtualPointerHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        obj: &ZwlrVirtualPointerV1,
+        request: zwlr_virtual_pointer_v1::Request,
+        data: &VirtualPointerData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let device = data.device.clone();
+        // the protocol uses milliseconds, input events microseconds
+        let micros = |time: u32| time as u64 * 1000;
+        let axis = |axis: WEnum<wl_pointer::Axis>| match axis {
+            WEnum::Value(wl_pointer::Axis::HorizontalScroll) => Some(Axis::Horizontal),
+            WEnum::Value(wl_pointer::Axis::VerticalScroll) => Some(Axis::Vertical),
+            _ => {
+                obj.post_error(zwlr_virtual_pointer_v1::Error::InvalidAxis, "invalid axis");
+                None
+            }
+        };
+
+        let event = match request {
+            zwlr_virtual_pointer_v1::Request::Motion { time, dx, dy } => {
+                InputEvent::PointerMotion {
+                    event: VirtualPointerMotionEvent {
+                        time: micros(time),
+                        device,
+                        dx,
+                        dy,
+                    },
+                }
+            }
+            zwlr_virtual_pointer_v1::Request::MotionAbsolute {
+                time,
+                x,
+                y,
+                x_extent,
+                y_extent,
+            } => {
+                if x_extent == 0 || y_extent == 0 {
+                    return;
+                }
+                InputEvent::PointerMotionAbsolute {
+                    event: VirtualPointerMotionAbsoluteEvent {
+                        time: micros(time),
+                        device,
+                        x: x as f64 / x_extent as f64,
+                        y: y as f64 / y_extent as f64,
+                        output: data.output.clone(),
+                    },
+                }
+            }
+            zwlr_virtual_pointer_v1::Request::Button {
+                time,
+                button,
+                state: button_state,
+            } => InputEvent::PointerButton {
+                event: VirtualPointerButtonEvent {
+                    time: micros(time),
+                    device,
+                    button,
+                    state: match button_state {
+                        WEnum::Value(wl_pointer::ButtonState::Pressed) => ButtonState::Pressed,
+                        _ => ButtonState::Released,
+                    },
+                },
+            },
+            zwlr_virtual_pointer_v1::Request::Axis {
+                time,
+                axis: wl_axis,
+                value,
+            } => {
+                let Some(axis) = axis(wl_axis) else {
+                    return;
+                };
+                let mut pending = data.axis.lock().unwrap();
+                pending.time = micros(time);
+                *pending.value(axis).get_or_insert(0.) += value;
+                return;
+            }
+            zwlr_virtual_pointer_v1::Request::AxisSource { axis_source } => {
+                let source = match axis_source {
+                    WEnum::Value(wl_pointer::AxisSource::Wheel) => AxisSource::Wheel,
+                    WEnum::Value(wl_pointer::AxisSource::Finger) => AxisSource::Finger,
+                    WEnum::Value(wl_pointer::AxisSource::Continuous) => AxisSource::Continuous,
+                    WEnum::Value(wl_pointer::AxisSource::WheelTilt) => AxisSource::WheelTilt,
+                    _ => {
+                        obj.post_error(
+                            zwlr_virtual_pointer_v1::Error::InvalidAxisSource,
+                            "invalid axis source",
+                        );
+                        return;
+                    }
+                };
+                data.axis.lock().unwrap().source = Some(source);
+                return;
+            }
+            zwlr_virtual_pointer_v1::Request::AxisStop {
+                time,
+                axis: wl_axis,
+            } => {
+                let Some(axis) = axis(wl_axis) else {
+                    return;
+                };
+                let mut pending = data.axis.lock().unwrap();
+                pending.time = micros(time);
+                pending.value(axis).get_or_insert(0.);
+                return;
+            }
+            zwlr_virtual_pointer_v1::Request::AxisDiscrete {
+                time,
+                axis: wl_axis,
+                value,
+                discrete,
+            } => {
+                let Some(axis) = axis(wl_axis) else {
+                    return;
+                };
+                let mut pending = data.axis.lock().unwrap();
+                pending.time = micros(time);
+                *pending.value(axis).get_or_insert(0.) += value;
+                *pending.v120(axis).get_or_insert(0.) += discrete as f64 * 120.;
+                return;
+            }
+            zwlr_virtual_pointer_v1::Request::Frame => {
+                let pending = std::mem::take(&mut *data.axis.lock().unwrap());
+                if pending.horizontal.is_none() && pending.vertical.is_none() {
+                    return;
+                }
+                InputEvent::PointerAxis {
+                    event: VirtualPointerAxisEvent {
+                        time: pending.time,
+                        device,
+                        source: pending.source.unwrap_or(AxisSource::Wheel),
+                        horizontal: pending.horizontal,
+                        vertical: pending.vertical,
+                        horizontal_v120: pending.horizontal_v120,
+                        vertical_v120: pending.vertical_v120,
+                    },
+                }
+            }
+            zwlr_virtual_pointer_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        state.virtual_pointer_event(event);
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        _obj: &ZwlrVirtualPointerV1,
+        data: &VirtualPointerData,
+    ) {
+        state.remove_virtual_pointer(&data.device);
+    }
+}
+
+macro_rules! delegate_virtual_pointer {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1: $crate::wayland::protocols::virtual_pointer::VirtualPointerManagerGlobalData
+        ] => $crate::wayland::protocols::virtual_pointer::VirtualPointerManagerState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1: ()
+        ] => $crate::wayland::protocols::virtual_pointer::VirtualPointerManagerState);
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::virtual_pointer::v1::server::zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1: $crate::wayland::protocols::virtual_pointer::VirtualPointerData
+        ] => $crate::wayland::protocols::virtual_pointer::VirtualPointerManagerState);
+    };
+}
+pub(crate) use delegate_virtual_pointer;
@@ -846,6 +846,7 @@ impl XwmHandler for State {
                 &mut self.common.toplevel_info_state,
                 &mut self.common.workspace_state,
                 &self.common.event_loop_handle,
+                &self.common.display_handle,
             );
             if let Some(target) = res {
                 let seat = shell.seats.last_active().clone();